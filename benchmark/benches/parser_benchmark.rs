@@ -16,6 +16,7 @@ pub type BenchmarkGroup<'a> = criterion::BenchmarkGroup<'a, measurement::WallTim
 #[cfg(feature = "codspeed")]
 pub use codspeed_criterion_compat::*;
 
+use self::incremental::{reparse, TextEdit};
 use self::parser::parser::Parser;
 
 fn try_download(path: &str, url: &str) -> String {
@@ -57,13 +58,31 @@ pub fn benchmark_parser(c: &mut Criterion) {
             |b, source| {
                 b.iter(|| {
                     let mut parser = Parser::new(source);
-                    parser.parse().unwrap();
+                    parser.parse();
 
                     0
                 });
             },
         );
 
+        // Typing a statement in the middle of the file
+        let (module, errors) = Parser::new(&source).parse();
+        let offset = source[source.len() / 2..]
+            .find('\n')
+            .map_or(source.len(), |i| source.len() / 2 + i + 1) as u32;
+        let edits = [TextEdit::new(offset, offset, "x = 1\n")];
+        group.bench_with_input(
+            BenchmarkId::new("reparse", path.to_string()),
+            &source,
+            |b, source| {
+                b.iter_batched(
+                    || (module.clone(), errors.clone()),
+                    |(module, errors)| reparse(source, module, errors, &edits),
+                    BatchSize::LargeInput,
+                );
+            },
+        );
+
         remove_file(path).expect("cannot delete file");
     }
     group.finish()
@@ -77,13 +77,10 @@ def ast2json(node):
     to_return = dict()
     to_return["_type"] = node.__class__.__name__
     for attr in dir(node):
-        if attr.startswith("_") or attr == "n" or attr == "s":
+        # Deprecated aliases, ast.dump does not print them either
+        if attr.startswith("_") or attr in ("n", "s", "dims"):
             continue
         to_return[attr] = get_value(getattr(node, attr))
-        to_return.pop("lineno", None)
-        to_return.pop("end_lineno", None)
-        to_return.pop("col_offset", None)
-        to_return.pop("end_col_offset", None)
     return to_return
 
 
@@ -119,5 +116,11 @@ tree = ast.parse(
     mode="exec",
     type_comments=args.type_comments,
 )
+# Nodes have type_params since Python 3.12. Add them on older versions so
+# that the output does not depend on the Python version.
+if sys.version_info < (3, 12):
+    for node in ast.walk(tree):
+        if isinstance(node, (ast.FunctionDef, ast.AsyncFunctionDef, ast.ClassDef)):
+            node.type_params = []
 tree_json = ast2json(tree)
 print(json.dumps(tree_json, indent=4))
@@ -161,11 +161,9 @@ pub fn assert_tokens_eq(
         } else {
             Some(enderpy_tokens[enderpy_index].clone())
         };
-        if python_token.is_none() || enderpy_token.is_none() {
-            mismatches.push(TokenMismatch::MissingToken(python_token, enderpy_token));
-        } else {
-            let mut python_token = python_token.unwrap();
-            let mut enderpy_token = enderpy_token.unwrap();
+        if let (Some(mut python_token), Some(mut enderpy_token)) =
+            (python_token.clone(), enderpy_token.clone())
+        {
             // (compat_fstrings) TODO: python fstring is a bit different than enderpy.
             // We merge multiple fstring middle tokens together and emit one token but python emits
             // multiple fstring middle tokens. Here we skip to the end and do not check fstrings.
@@ -202,6 +200,8 @@ pub fn assert_tokens_eq(
                     break;
                 }
             }
+        } else {
+            mismatches.push(TokenMismatch::MissingToken(python_token, enderpy_token));
         }
         python_index += 1;
         enderpy_index += 1;
@@ -19,7 +19,7 @@ use terminal_size::{terminal_size, Width as TerminalWidth};
 fn parse_python_source(source: &str) -> Result<Value> {
     let mut process = spawn_python_script_command(
         "compat/ast_python.py",
-        vec!["--stdin"],
+        vec!["--stdin", "--type-comments"],
         default_python_path()?,
     )?;
 
@@ -31,11 +31,11 @@ fn parse_python_source(source: &str) -> Result<Value> {
     }
     // Get process stdout and parse result.
     let output = process.wait_with_output().into_diagnostic()?;
-    let mut ast =
+    let ast =
         serde_json::from_str(String::from_utf8_lossy(&output.stdout).as_ref()).into_diagnostic()?;
-    remove_unimplemented_attributes(&mut ast);
     Ok(ast)
 }
+
 pub fn python_parser_test_ast(inputs: &[&str]) {
     for test_input in inputs.iter() {
         let enderpy_ast = parse_enderpy_source(test_input).unwrap();
@@ -89,29 +89,12 @@ fn assert_ast_eq(python_ast: &Value, enderpy_ast: &Value, source: &str) {
         );
     }
 }
-fn remove_unimplemented_attributes(value: &mut Value) {
-    match value {
-        Value::Object(map) => {
-            // TODO ast_python: Adjust these ignored values as Enderpy adds support.
-            map.retain(|key, _| !matches!(key.as_str(), "ctx" | "type_ignores" | "kind"));
-            for (_, v) in map.iter_mut() {
-                remove_unimplemented_attributes(v);
-            }
-        }
-        Value::Array(vec) => {
-            for v in vec.iter_mut() {
-                remove_unimplemented_attributes(v);
-            }
-        }
-        _ => {
-            // Nothing to do for other value types.
-        }
-    };
-}
-
 fn parse_enderpy_source(source: &str) -> Result<Value> {
     let mut parser = Parser::new(source);
-    let typed_ast = parser.parse().into_diagnostic()?;
+    let (typed_ast, errors) = parser.parse();
+    if let Some(err) = errors.into_iter().next() {
+        return Err(err.into());
+    }
     let ast = typed_ast.as_python_compat(&parser);
     Ok(ast)
 }
@@ -127,338 +110,354 @@ macro_rules! parser_test {
     };
 }
 
-// TODO: parser compatibility tests require work to get working.
-// Mostly matching the offsets and names
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use crate::runpython::python_version;
+
+    /// Whether the python binary is `version` or newer
+    fn python_supports(version: (u32, u32)) -> bool {
+        python_version(default_python_path().unwrap()).unwrap() >= version
+    }
+
+    #[test]
+    fn test_simple_compat() {
+        //         let source = r#"
+        // def x(a: int) -> int:
+        //     return 1 + 1
+        // b = x(1)
+        // print(b)
+        // "#;
+
+        let source = r#"(a
+, b, c)
+"#;
+
+        let enderpy_ast = parse_enderpy_source(source).unwrap();
+        let python_ast = parse_python_source(source).unwrap();
+        assert_ast_eq(&python_ast, &enderpy_ast, source);
+    }
+
+    #[test]
+    fn test_parse_assignment() {
+        python_parser_test_ast(&[
+            "a = 1",
+            "a = None",
+            "a = True",
+            "a = False",
+            "a = 1j",
+            // TODO ast_python: Python does not evaluate bytes.
+            // "a = b'1'",
+            // "a = rb'1'",
+            // "a = br'1'",
+            "a = \"a\"",
+            "a = '''a'''",
+            "a = \"\"\"a\"\"\"",
+            "a = 'a'",
+            "a = 1, 2",
+            "a = 1, 2, ",
+            "a = b = 1",
+            "a,b = c,d = 1,2",
+            // augmented assignment
+            "a += 1",
+            "a -= 1",
+            "a *= 1",
+            "a /= 1",
+            "a //= 1",
+            "a %= 1",
+            "a **= 1",
+            "a <<= 1",
+            "a >>= 1",
+            "a &= 1",
+            "a ^= 1",
+            "a |= 1",
+            // annotated assignment
+        ]);
+    }
+
+    #[test]
+    fn test_parse_assert_stmt() {
+        python_parser_test_ast(&["assert a", "assert a, b", "assert True, 'fancy message'"]);
+    }
 
-    //     #[test]
-    //     fn test_simple_compat() {
-    //         //         let source = r#"
-    //         // def x(a: int) -> int:
-    //         //     return 1 + 1
-    //         // b = x(1)
-    //         // print(b)
-    //         // "#;
-    //
-    //         let source = r#"(a
-    // , b, c)
-    // "#;
-    //
-    //         let enderpy_ast = parse_enderpy_source(source).unwrap();
-    //         let python_ast = parse_python_source(source).unwrap();
-    //         assert_ast_eq(&python_ast, &enderpy_ast, source);
-    //     }
-    //
-    //     #[test]
-    //     fn test_parse_assignment() {
-    //         python_parser_test_ast(&[
-    //             "a = 1",
-    //             "a = None",
-    //             "a = True",
-    //             "a = False",
-    //             "a = 1j",
-    //             // TODO ast_python: Python does not evaluate bytes.
-    //             // "a = b'1'",
-    //             // "a = rb'1'",
-    //             // "a = br'1'",
-    //             "a = \"a\"",
-    //             "a = '''a'''",
-    //             "a = \"\"\"a\"\"\"",
-    //             "a = 'a'",
-    //             "a = 1, 2",
-    //             "a = 1, 2, ",
-    //             "a = b = 1",
-    //             "a,b = c,d = 1,2",
-    //             // augmented assignment
-    //             "a += 1",
-    //             "a -= 1",
-    //             "a *= 1",
-    //             "a /= 1",
-    //             "a //= 1",
-    //             "a %= 1",
-    //             "a **= 1",
-    //             "a <<= 1",
-    //             "a >>= 1",
-    //             "a &= 1",
-    //             "a ^= 1",
-    //             "a |= 1",
-    //             // annotated assignment
-    //         ]);
-    //     }
-    //
-    //     #[test]
-    //     fn test_parse_assert_stmt() {
-    //         python_parser_test_ast(&["assert a", "assert a, b", "assert True, 'fancy message'"]);
-    //     }
-    //
-    //     #[test]
-    //     fn test_pass_stmt() {
-    //         python_parser_test_ast(&["pass", "pass ", "pass\n"]);
-    //     }
-    //
-    //     #[test]
-    //     fn test_parse_del_stmt() {
-    //         python_parser_test_ast(&["del a", "del a, b", "del a, b, "]);
-    //     }
-    //
-    //     #[test]
-    //     fn parse_yield_statement() {
-    //         python_parser_test_ast(&["yield", "yield a", "yield a, b", "yield a, b, "]);
-    //     }
-    //
-    //     #[test]
-    //     fn test_raise_statement() {
-    //         python_parser_test_ast(&["raise", "raise a", "raise a from c"]);
-    //     }
-    //
-    //     #[test]
-    //     fn test_parse_break_continue() {
-    //         python_parser_test_ast(&["break", "continue"]);
-    //     }
-    //
-    //     #[test]
-    //     fn test_parse_bool_op() {
-    //         python_parser_test_ast(&[
-    //             "a or b",
-    //             "a and b",
-    //             // TODO: Python parses this as a BoolOp with 3 values.
-    //             // i.e. {"op": "or", "values": ["a", "b", "c"]}
-    //             // Enderpy parses this as a nested set of BoolOps.
-    //             // i.e. {"op": "or", "values": ["a", {"op": "or", "values": ["b", "c"]}]}
-    //             // "a or b or c",
-    //             "a and b or c",
-    //         ]);
-    //     }
-    //
-    //     #[test]
-    //     fn test_parse_unary_op() {
-    //         python_parser_test_ast(&["not a", "+ a", "~ a", "-a"]);
-    //     }
-    //
-    //     #[test]
-    //     fn test_named_expression() {
-    //         python_parser_test_ast(&["(a := b)"]);
-    //     }
-    //
-    //     #[test]
-    //     fn test_tuple() {
-    //         python_parser_test_ast(&[
-    //             "(a, b, c)",
-    //             "(a,
-    //             b, c)",
-    //             "(a
-    //             , b, c)",
-    //             "(a,
-    //             b,
-    //                 c)",
-    //             "(a,
-    //             )",
-    //             "(a, b, c,)",
-    //         ]);
-    //     }
-    //
-    //     #[test]
-    //     fn test_yield_expression() {
-    //         python_parser_test_ast(&["yield", "yield a", "yield from a"]);
-    //     }
-    //
-    //     #[test]
-    //     fn test_await_expression() {
-    //         python_parser_test_ast(&["await a"]);
-    //     }
-    //
-    //     #[test]
-    //     fn test_attribute_ref() {
-    //         python_parser_test_ast(&["a.b", "a.b.c", "a.b_c", "a.b.c.d"]);
-    //     }
-    //     #[test]
-    //     fn test_subscript() {
-    //         python_parser_test_ast(&["a[1]", "a.b[1]"]);
-    //     }
-    //
-    //     #[test]
-    //     fn parse_call() {
-    //         python_parser_test_ast(&[
-    //             "a()",
-    //             "a(b)",
-    //             "a(b, c)",
-    //             "func(b=c)",
-    //             "func(a, b=c, d=e)",
-    //             "func(a, b=c, d=e, *f)",
-    //             "func(a, b=c, d=e, *f, **g)",
-    //             "func(a,)",
-    //         ]);
-    //     }
-    //
-    //     #[test]
-    //     fn test_lambda() {
-    //         python_parser_test_ast(&[
-    //             "lambda: a",
-    //             "lambda a: a",
-    //             "lambda a, b: a",
-    //             "lambda a, b, c: a",
-    //             "lambda a, *b: a",
-    //             "lambda a, *b, c: a",
-    //             "lambda a, *b, c, **d: a",
-    //             "lambda a=1 : a",
-    //             "lambda a=1 : a,",
-    //         ]);
-    //     }
-    //
-    //     #[test]
-    //     fn test_conditional_expression() {
-    //         python_parser_test_ast(&["a if b else c if d else e"]);
-    //     }
-    //
-    //     #[test]
-    //     fn test_string_literal_concatenation() {
-    //         python_parser_test_ast(&[
-    //             "'a' 'b'",
-    //             // TODO ast_python: Python evaluates this as "ab".
-    //             // "b'a' b'b'",
-    //             "'a'   'b'",
-    //             // TODO ast_python: Enderpy evaluates this as 'r"a"b'. This seems wrong.
-    //             // "r'a' 'b'",
-    //             "('a'
-    //             'b')",
-    //             "('a'
-    //             'b', 'c')",
-    //             "('a'
-    //                             'b'
-    //             'c')",
-    //             // TODO ast_python: Python evaluates this as "ac". Enderpy creates 2 constants.
-    //             // "f'a' 'c'",
-    //             // TODO ast_python: Python evaluates this as "abc". Enderpy creates 3 constants.
-    //             // "f'a' 'b' 'c'",
-    //             // TODO ast_python: Python evaluates this as "dab". Enderpy creates 3 constants.
-    //             // "'d' f'a' 'b'",
-    //             "f'a_{1}' 'b' ",
-    //         ]);
-    //     }
-    //
-    //     #[test]
-    //     fn test_fstring() {
-    //         python_parser_test_ast(&[
-    //             "f'a'",
-    //             "f'hello_{a}'",
-    //             "f'hello_{a} {b}'",
-    //             "f'hello_{a} {b} {c}'",
-    //             "f'hello_{f'''{a}'''}'",
-    //         ]);
-    //     }
-    //
-    //     #[test]
-    //     fn test_comparison() {
-    //         python_parser_test_ast(&[
-    //             "a == b",
-    //             "a != b",
-    //             "a > b",
-    //             "a < b",
-    //             "a >= b",
-    //             "a <= b",
-    //             "a is b",
-    //             "a is not b",
-    //             "a in b",
-    //             "a not in b",
-    //             "a < b < c",
-    //         ]);
-    //     }
-    //
-    //     #[test]
-    //     fn test_while_statement() {
-    //         python_parser_test_ast(&[
-    //             "while a: pass",
-    //             "while a:
-    //     pass",
-    //             "while a:
-    //         a = 1
-    // else:
-    //         b = 1
-    // ",
-    //         ]);
-    //     }
-    //
-    //     #[test]
-    //     fn test_try_statement() {
-    //         python_parser_test_ast(&[
-    //             "try:
-    //     pass
-    // except:
-    //     pass",
-    //             "try:
-    //                 pass
-    // except Exception:
-    //                 pass",
-    //             "try:
-    //                 pass
-    // except Exception as e:
-    //                 pass",
-    //             "try:
-    //                 pass
-    // except Exception as e:
-    //                 pass
-    // else:
-    //                 pass",
-    //             "try:
-    //                 pass
-    // except Exception as e:
-    //                 pass
-    // else:
-    //                 pass
-    // finally:
-    //                 pass",
-    //             "try:
-    //     pass
-    // except *Exception as e:
-    //     pass
-    // ",
-    //         ]);
-    //     }
-    //
-    //     #[test]
-    //     fn test_ellipsis_statement() {
-    //         python_parser_test_ast(&[
-    //             "def a(): ...",
-    //             "def a():
-    //     ...",
-    //             "a = ...",
-    //             "... + 1",
-    //         ]);
-    //     }
-    //
-    //     parser_test!(test_functions, "../parser/test_data/inputs/functions.py");
-    //     parser_test!(test_if, "../parser/test_data/inputs/if.py");
-    //     parser_test!(
-    //         test_indentation,
-    //         "../parser/test_data/inputs/indentation.py"
-    //     );
-    //     parser_test!(
-    //         test_separate_statements,
-    //         "../parser/test_data/inputs/separate_statements.py"
-    //     );
+    #[test]
+    fn test_pass_stmt() {
+        python_parser_test_ast(&["pass", "pass ", "pass\n"]);
+    }
+
+    #[test]
+    fn test_parse_del_stmt() {
+        python_parser_test_ast(&["del a", "del a, b", "del a, b, "]);
+    }
+
+    #[test]
+    fn parse_yield_statement() {
+        python_parser_test_ast(&["yield", "yield a", "yield a, b", "yield a, b, "]);
+    }
+
+    #[test]
+    fn test_raise_statement() {
+        python_parser_test_ast(&["raise", "raise a", "raise a from c"]);
+    }
+
+    #[test]
+    fn test_parse_break_continue() {
+        python_parser_test_ast(&["break", "continue"]);
+    }
+
+    #[test]
+    fn test_parse_bool_op() {
+        python_parser_test_ast(&[
+            "a or b",
+            "a and b",
+            // TODO: Python parses this as a BoolOp with 3 values.
+            // i.e. {"op": "or", "values": ["a", "b", "c"]}
+            // Enderpy parses this as a nested set of BoolOps.
+            // i.e. {"op": "or", "values": ["a", {"op": "or", "values": ["b", "c"]}]}
+            // "a or b or c",
+            "a and b or c",
+        ]);
+    }
+
+    #[test]
+    fn test_parse_unary_op() {
+        python_parser_test_ast(&["not a", "+ a", "~ a", "-a"]);
+    }
+
+    #[test]
+    fn test_named_expression() {
+        python_parser_test_ast(&["(a := b)"]);
+    }
+
+    #[test]
+    fn test_tuple() {
+        python_parser_test_ast(&[
+            "(a, b, c)",
+            "(a,
+            b, c)",
+            "(a
+            , b, c)",
+            "(a,
+            b,
+                c)",
+            "(a,
+            )",
+            "(a, b, c,)",
+        ]);
+    }
+
+    #[test]
+    fn test_yield_expression() {
+        python_parser_test_ast(&["yield", "yield a", "yield from a"]);
+    }
+
+    #[test]
+    fn test_await_expression() {
+        python_parser_test_ast(&["await a"]);
+    }
+
+    #[test]
+    fn test_attribute_ref() {
+        python_parser_test_ast(&["a.b", "a.b.c", "a.b_c", "a.b.c.d"]);
+    }
+    #[test]
+    fn test_subscript() {
+        python_parser_test_ast(&["a[1]", "a.b[1]"]);
+    }
+
+    #[test]
+    fn parse_call() {
+        python_parser_test_ast(&[
+            "a()",
+            "a(b)",
+            "a(b, c)",
+            "func(b=c)",
+            "func(a, b=c, d=e)",
+            "func(a, b=c, d=e, *f)",
+            "func(a, b=c, d=e, *f, **g)",
+            "func(a,)",
+        ]);
+    }
+
+    #[test]
+    fn test_lambda() {
+        python_parser_test_ast(&[
+            "lambda: a",
+            "lambda a: a",
+            "lambda a, b: a",
+            "lambda a, b, c: a",
+            "lambda a, *b: a",
+            "lambda a, *b, c: a",
+            "lambda a, *b, c, **d: a",
+            "lambda a=1 : a",
+            "lambda a=1 : a,",
+        ]);
+    }
+
+    #[test]
+    fn test_conditional_expression() {
+        python_parser_test_ast(&["a if b else c if d else e"]);
+    }
+
+    #[test]
+    fn test_string_literal_concatenation() {
+        python_parser_test_ast(&[
+            "'a' 'b'",
+            // TODO ast_python: Python evaluates this as "ab".
+            // "b'a' b'b'",
+            "'a'   'b'",
+            // TODO ast_python: Enderpy evaluates this as 'r"a"b'. This seems wrong.
+            // "r'a' 'b'",
+            "('a'
+            'b')",
+            "('a'
+            'b', 'c')",
+            "('a'
+                            'b'
+            'c')",
+        ]);
+    }
+
+    #[test]
+    fn test_fstring() {
+        // F-string parts are located per PEP 701 since Python 3.12
+        if !python_supports((3, 12)) {
+            eprintln!("skipping test_fstring, it requires Python 3.12 or newer");
+            return;
+        }
+        python_parser_test_ast(&[
+            "f'a'",
+            "f'hello_{a}'",
+            "f'hello_{a} {b}'",
+            "f'hello_{a} {b} {c}'",
+            "f'hello_{f'''{a}'''}'",
+            "f'{x!r:>{width}.3}'",
+            "f'{ {1} }'",
+            "f'{{a}}\\n'",
+            "rf'\\n{x}'",
+            "f'{x=}'",
+            "f'{x = !s:>3}'",
+            "f\"{f\"{x}\"}\"",
+            "f'a' 'c'",
+            "f'a' 'b' 'c'",
+            "'d' f'a' 'b'",
+            "f'a_{1}' 'b' ",
+        ]);
+    }
+
+    #[test]
+    fn test_comparison() {
+        python_parser_test_ast(&[
+            "a == b",
+            "a != b",
+            "a > b",
+            "a < b",
+            "a >= b",
+            "a <= b",
+            "a is b",
+            "a is not b",
+            "a in b",
+            "a not in b",
+            "a < b < c",
+        ]);
+    }
+
+    #[test]
+    fn test_while_statement() {
+        python_parser_test_ast(&[
+            "while a: pass",
+            "while a:
+    pass",
+            "while a:
+        a = 1
+else:
+        b = 1
+",
+        ]);
+    }
+
+    #[test]
+    fn test_try_statement() {
+        python_parser_test_ast(&[
+            "try:
+    pass
+except:
+    pass",
+            "try:
+                pass
+except Exception:
+                pass",
+            "try:
+                pass
+except Exception as e:
+                pass",
+            "try:
+                pass
+except Exception as e:
+                pass
+else:
+                pass",
+            "try:
+                pass
+except Exception as e:
+                pass
+else:
+                pass
+finally:
+                pass",
+            "try:
+    pass
+except *Exception as e:
+    pass
+",
+        ]);
+    }
+
+    #[test]
+    fn test_ellipsis_statement() {
+        python_parser_test_ast(&[
+            "def a(): ...",
+            "def a():
+    ...",
+            "a = ...",
+            "... + 1",
+        ]);
+    }
+
+    parser_test!(test_functions, "../parser/test_data/inputs/functions.py");
+    parser_test!(test_if, "../parser/test_data/inputs/if.py");
+    parser_test!(
+        test_indentation,
+        "../parser/test_data/inputs/indentation.py"
+    );
+    parser_test!(
+        test_separate_statements,
+        "../parser/test_data/inputs/separate_statements.py"
+    );
+    parser_test!(
+        annotated_assignment,
+        "../parser/test_data/inputs/annotated_assignment.py"
+    );
+    parser_test!(binary_op, "../parser/test_data/inputs/binary_op.py");
+    parser_test!(dict, "../parser/test_data/inputs/dict.py");
+    parser_test!(test_for, "../parser/test_data/inputs/for.py");
+    parser_test!(from_import, "../parser/test_data/inputs/from_import.py");
+    parser_test!(
+        generator_expressions,
+        "../parser/test_data/inputs/generator_expressions.py"
+    );
+    parser_test!(lists, "../parser/test_data/inputs/lists.py");
+    parser_test!(test_match, "../parser/test_data/inputs/match.py");
+    parser_test!(sets, "../parser/test_data/inputs/sets.py");
+    parser_test!(subscript, "../parser/test_data/inputs/subscript.py");
+    parser_test!(with, "../parser/test_data/inputs/with.py");
+    parser_test!(newlines, "../parser/test_data/inputs/newlines.py");
+    parser_test!(comments, "../parser/test_data/inputs/comments.py");
+    // TODO ast_python: These use f-strings, see `test_fstring`.
     // parser_test!(test_try, "../parser/test_data/inputs/try.py");
-    // parser_test!(
-    //     annotated_assignment,
-    //     "../parser/test_data/inputs/annotated_assignment.py"
-    // );
-    // parser_test!(binary_op, "../parser/test_data/inputs/binary_op.py");
+    // parser_test!(string, "../parser/test_data/inputs/string.py");
+    // TODO ast_python: These use type parameters which need Python 3.12.
     // parser_test!(class, "../parser/test_data/inputs/class.py");
-    // parser_test!(dict, "../parser/test_data/inputs/dict.py");
-    // parser_test!(test_for, "../parser/test_data/inputs/for.py");
-    // parser_test!(from_import, "../parser/test_data/inputs/from_import.py");
     // parser_test!(function_def, "../parser/test_data/inputs/function_def.py");
-    // parser_test!(
-    //     generator_expressions,
-    //     "../parser/test_data/inputs/generator_expressions.py"
-    // );
-    // parser_test!(lists, "../parser/test_data/inputs/lists.py");
-    // parser_test!(test_match, "../parser/test_data/inputs/match.py");
-    // parser_test!(sets, "../parser/test_data/inputs/sets.py");
-    // parser_test!(string, "../parser/test_data/inputs/string.py");
-    // parser_test!(subscript, "../parser/test_data/inputs/subscript.py");
-    // parser_test!(with, "../parser/test_data/inputs/with.py");
-    // parser_test!(newlines, "../parser/test_data/inputs/newlines.py");
-    // parser_test!(comments, "../parser/test_data/inputs/comments.py");
     // parser_test!(types_alias, "../parser/test_data/inputs/type_alias.py");
 }
@@ -29,6 +29,20 @@ where
         .into_diagnostic()
 }
 
+/// The major and minor version of the python binary
+pub fn python_version<P: AsRef<Path>>(python_path: P) -> Result<(u32, u32)> {
+    let output = Command::new(python_path.as_ref())
+        .args(["-c", "import sys; print(*sys.version_info[:2])"])
+        .output()
+        .into_diagnostic()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut parts = stdout.split_whitespace().map(str::parse::<u32>);
+    match (parts.next(), parts.next()) {
+        (Some(Ok(major)), Some(Ok(minor))) => Ok((major, minor)),
+        _ => bail!("Unable to find the version of {:?}.", python_path.as_ref()),
+    }
+}
+
 fn enderpy_root_path() -> Result<PathBuf> {
     let mut path = std::env::current_exe().into_diagnostic()?;
     let enderpy_dir = loop {
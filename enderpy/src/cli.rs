@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use enderpy_python_parser::python_version::PythonVersion;
 
 /// Enderpy CLI
 #[derive(Parser)]
@@ -20,9 +21,20 @@ pub enum Commands {
     Parse {
         /// Path to source file
         file: PathBuf,
+        /// Output format of the tree
+        #[arg(long, value_enum, default_value_t = ParseFormat::Debug)]
+        format: ParseFormat,
     },
     /// Type check
-    Check { path: PathBuf },
+    Check {
+        path: PathBuf,
+        /// Report `# type: ignore` comments that do not suppress any error
+        #[arg(long)]
+        warn_unused_ignores: bool,
+        /// Python version to check the file for, e.g. 3.10
+        #[arg(long, default_value_t = PythonVersion::default())]
+        python_version: PythonVersion,
+    },
     ///  Symbol table
     Symbols { path: PathBuf },
 
@@ -30,6 +42,14 @@ pub enum Commands {
     Watch,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ParseFormat {
+    /// Rust debug representation of the tree
+    Debug,
+    /// JSON compatible with the nodes of Python's `ast` module
+    PythonAst,
+}
+
 #[test]
 fn verify_cli() {
     use clap::CommandFactory;
@@ -5,8 +5,16 @@ use std::{
 };
 
 use clap::Parser as ClapParser;
-use cli::{Cli, Commands};
-use enderpy_python_parser::{get_row_col_position, parser::parser::Parser, Lexer};
+use cli::{Cli, Commands, ParseFormat};
+use enderpy_python_parser::{
+    encoding::decode_source,
+    error::ParsingError,
+    get_row_col_position,
+    parser::{compat::AsPythonCompat, parser::Parser},
+    python_version::PythonVersion,
+    semantic_errors::semantic_errors,
+    Lexer,
+};
 use enderpy_python_type_checker::{build::BuildManager, find_project_root, settings::Settings};
 use miette::{bail, IntoDiagnostic, Result};
 
@@ -16,8 +24,12 @@ fn main() -> Result<()> {
     let cli = Cli::parse();
     match &cli.command {
         Commands::Tokenize {} => tokenize(),
-        Commands::Parse { file } => parse(file),
-        Commands::Check { path } => check(path),
+        Commands::Parse { file, format } => parse(file, *format),
+        Commands::Check {
+            path,
+            warn_unused_ignores,
+            python_version,
+        } => check(path, *warn_unused_ignores, *python_version),
         Commands::Watch => watch(),
         Commands::Symbols { path } => symbols(path),
     }
@@ -73,19 +85,21 @@ fn get_typeshed_path() -> Result<PathBuf> {
 
 fn tokenize() -> Result<()> {
     let cli = Cli::parse();
-    let mut source = String::new();
+    let mut bytes = vec![];
     match cli.file {
         Some(path) => {
             File::open(path)
                 .into_diagnostic()?
-                .read_to_string(&mut source)
+                .read_to_end(&mut bytes)
                 .into_diagnostic()?;
         }
         None => {
-            io::stdin().read_to_string(&mut source).into_diagnostic()?;
+            io::stdin().read_to_end(&mut bytes).into_diagnostic()?;
         }
     }
-    let mut lexer = Lexer::new(&source);
+    let decoded = decode_source(&bytes);
+    let source = &decoded.text;
+    let mut lexer = Lexer::new(source);
     let tokens = lexer.lex();
     for token in tokens {
         let (start_line_num, start_line_column, end_line_num, end_line_column) =
@@ -97,22 +111,44 @@ fn tokenize() -> Result<()> {
             end_line_num,
             end_line_column,
             token.kind,
-            token.start,
-            token.end,
+            decoded.original_offset(token.start),
+            decoded.original_offset(token.end),
         );
     }
+    let errors = lexer.errors.into_iter().map(ParsingError::from);
+    for error in decoded.errors.into_iter().chain(errors) {
+        let report = miette::Report::new(error).with_source_code(source.clone());
+        eprintln!("{report:?}");
+    }
     Ok(())
 }
 
-fn parse(file: &PathBuf) -> Result<()> {
-    let source = fs::read_to_string(file).into_diagnostic()?;
+fn parse(file: &PathBuf, format: ParseFormat) -> Result<()> {
+    let decoded = decode_source(&fs::read(file).into_diagnostic()?);
+    let source = decoded.text;
     let mut parser = Parser::new(&source);
-    let ast = parser.parse();
-    println!("{:#?}", ast);
+    let (ast, errors) = parser.parse();
+    match format {
+        ParseFormat::Debug => println!("{:#?}", ast),
+        ParseFormat::PythonAst => {
+            let json = ast.as_python_compat(&parser);
+            println!("{}", serde_json::to_string_pretty(&json).into_diagnostic()?);
+        }
+    }
+    let semantic_errors = semantic_errors(&ast);
+    for err in decoded
+        .errors
+        .into_iter()
+        .chain(errors)
+        .chain(semantic_errors)
+    {
+        let report = miette::Report::new(err).with_source_code(source.clone());
+        eprintln!("{report:?}");
+    }
     Ok(())
 }
 
-fn check(path: &Path) -> Result<()> {
+fn check(path: &Path, report_unused_ignores: bool, target_version: PythonVersion) -> Result<()> {
     if path.is_dir() {
         bail!("Path must be a file");
     }
@@ -122,14 +158,20 @@ fn check(path: &Path) -> Result<()> {
     let settings = Settings {
         typeshed_path,
         python_executable,
+        report_unused_ignores,
+        target_version,
     };
     let build_manager = BuildManager::new(settings);
     build_manager.build(root);
     build_manager.build_one(root, path);
-    let id = build_manager.paths.get(path).unwrap();
-    let file = build_manager.files.get(&id).unwrap();
-    let checker = build_manager.type_check(path, &file);
-    print!("{}", checker.dump_types());
+    let diagnostics = build_manager.get_diagnostics(path);
+    for diagnostic in diagnostics.iter() {
+        println!("{}:{}", path.display(), diagnostic);
+    }
+    if !diagnostics.is_empty() {
+        bail!("Found {} errors", diagnostics.len());
+    }
+    println!("No errors found");
 
     Ok(())
 }
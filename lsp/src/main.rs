@@ -1,6 +1,15 @@
-use std::path::PathBuf;
+use std::{
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
 
-use enderpy_python_type_checker::{build::BuildManager, find_project_root, settings::Settings};
+use enderpy_python_parser::{
+    incremental::TextEdit,
+    line_index::{LineCol, LineIndex, PositionEncoding},
+};
+use enderpy_python_type_checker::{
+    build::BuildManager, diagnostic, find_project_root, settings::Settings,
+};
 use env_logger::Builder;
 use log::LevelFilter;
 use tower_lsp::{jsonrpc::Result, lsp_types::*, Client, LanguageServer, LspService, Server};
@@ -9,15 +18,139 @@ use tower_lsp::{jsonrpc::Result, lsp_types::*, Client, LanguageServer, LspServic
 struct Backend {
     client: Client,
     manager: BuildManager,
+    /// The encoding of the columns agreed with the client on initialize
+    position_encoding: OnceLock<PositionEncoding>,
 }
 
-impl<'a> Backend {
-    fn build(&self, path: PathBuf) {
+impl Backend {
+    fn build(&self, path: PathBuf) -> Vec<Diagnostic> {
         let root = find_project_root(&path);
         self.manager.build_one(root, &path);
-        let file_id = self.manager.paths.get(&path).unwrap();
-        let file = self.manager.files.get(&file_id).unwrap();
-        self.manager.type_check(&path, &file);
+        self.diagnostics(&path)
+    }
+
+    async fn build_and_publish(&self, uri: Url, version: Option<i32>) {
+        let Ok(path) = uri.to_file_path() else {
+            return;
+        };
+        let diagnostics = self.build(path);
+        self.publish(uri, diagnostics, version).await;
+    }
+
+    async fn publish(&self, uri: Url, diagnostics: Vec<Diagnostic>, version: Option<i32>) {
+        self.client
+            .publish_diagnostics(uri, diagnostics, version)
+            .await;
+    }
+
+    fn diagnostics(&self, path: &Path) -> Vec<Diagnostic> {
+        let Some(line_index) = self.manager.get_line_index(path) else {
+            return vec![];
+        };
+        let encoding = self.position_encoding();
+        self.manager
+            .get_diagnostics(path)
+            .into_iter()
+            .map(|d| to_lsp_diagnostic(d, &line_index, encoding))
+            .collect()
+    }
+
+    fn position_encoding(&self) -> PositionEncoding {
+        self.position_encoding.get().copied().unwrap_or_default()
+    }
+}
+
+/// Picks the encoding of positions from the ones the client supports. UTF-8
+/// is preferred because offsets are in bytes, clients that don't say anything
+/// only support UTF-16.
+fn negotiate_position_encoding(capabilities: &ClientCapabilities) -> PositionEncoding {
+    let supported = capabilities
+        .general
+        .as_ref()
+        .and_then(|general| general.position_encodings.as_deref())
+        .unwrap_or_default();
+    [PositionEncoding::Utf8, PositionEncoding::Utf32]
+        .into_iter()
+        .find(|encoding| supported.contains(&to_position_encoding_kind(*encoding)))
+        .unwrap_or(PositionEncoding::Utf16)
+}
+
+fn to_position_encoding_kind(encoding: PositionEncoding) -> PositionEncodingKind {
+    match encoding {
+        PositionEncoding::Utf8 => PositionEncodingKind::UTF8,
+        PositionEncoding::Utf16 => PositionEncodingKind::UTF16,
+        PositionEncoding::Utf32 => PositionEncodingKind::UTF32,
+    }
+}
+
+fn to_line_col(position: Position) -> LineCol {
+    LineCol {
+        line: position.line,
+        col: position.character,
+    }
+}
+
+/// Converts the changes sent by the editor to edits of `source`. Each change is
+/// relative to the source after the previous changes.
+fn to_text_edits(
+    mut source: String,
+    changes: Vec<TextDocumentContentChangeEvent>,
+    encoding: PositionEncoding,
+) -> Vec<TextEdit> {
+    let mut edits = vec![];
+    for change in changes {
+        let edit = match change.range {
+            Some(range) => {
+                let line_index = LineIndex::new(&source);
+                TextEdit::new(
+                    line_index.offset(to_line_col(range.start), encoding),
+                    line_index.offset(to_line_col(range.end), encoding),
+                    change.text,
+                )
+            }
+            None => TextEdit::new(0, source.len() as u32, change.text),
+        };
+        edit.apply(&mut source);
+        edits.push(edit);
+    }
+    edits
+}
+
+fn to_lsp_diagnostic(
+    d: diagnostic::Diagnostic,
+    line_index: &LineIndex,
+    encoding: PositionEncoding,
+) -> Diagnostic {
+    // Enderpy lines start from 1 and characters are in bytes but LSP lines
+    // start from 0 and characters are in the negotiated encoding
+    let position = |p: diagnostic::Position| {
+        let line_col = LineCol {
+            line: p.line.saturating_sub(1),
+            col: p.character,
+        };
+        let offset = line_index.offset(line_col, PositionEncoding::Utf8);
+        let line_col = line_index.line_col(offset, encoding);
+        Position {
+            line: line_col.line,
+            character: line_col.col,
+        }
+    };
+    let severity = match d.severity {
+        diagnostic::Severity::Error => DiagnosticSeverity::ERROR,
+        diagnostic::Severity::Warning => DiagnosticSeverity::WARNING,
+        diagnostic::Severity::Information => DiagnosticSeverity::INFORMATION,
+        diagnostic::Severity::Hint => DiagnosticSeverity::HINT,
+    };
+    Diagnostic {
+        range: Range {
+            start: position(d.range.start),
+            end: position(d.range.end),
+        },
+        severity: Some(severity),
+        code: Some(NumberOrString::String(d.code.to_string())),
+        source: Some("enderpy".to_string()),
+        message: d.body,
+        ..Diagnostic::default()
     }
 }
 
@@ -29,12 +162,15 @@ impl LanguageServer for Backend {
             None => PathBuf::from(""),
         };
         self.manager.build(&root);
+        let encoding = negotiate_position_encoding(&i.capabilities);
+        let _ = self.position_encoding.set(encoding);
         Ok(InitializeResult {
             server_info: None,
             offset_encoding: None,
             capabilities: ServerCapabilities {
+                position_encoding: Some(to_position_encoding_kind(encoding)),
                 text_document_sync: Some(TextDocumentSyncCapability::Kind(
-                    TextDocumentSyncKind::FULL,
+                    TextDocumentSyncKind::INCREMENTAL,
                 )),
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
                 ..ServerCapabilities::default()
@@ -53,33 +189,44 @@ impl LanguageServer for Backend {
         self.client
             .log_message(MessageType::INFO, "file saved!")
             .await;
-        let uri = params.text_document.uri;
-        let path = uri.to_file_path();
-        if let Ok(path) = path {
-            self.build(path);
-        }
+        self.build_and_publish(params.text_document.uri, None).await;
     }
 
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
         self.client
             .log_message(MessageType::INFO, "file opened!")
             .await;
-        let uri = params.text_document.uri;
-        let path = uri.to_file_path();
-        if let Ok(path) = path {
-            self.build(path);
-        }
+        let document = params.text_document;
+        let Ok(path) = document.uri.to_file_path() else {
+            return;
+        };
+        // The editor may have unsaved changes, use its text
+        let root = find_project_root(&path);
+        self.manager.build_source(root, &path, document.text);
+        let diagnostics = self.diagnostics(&path);
+        self.publish(document.uri, diagnostics, Some(document.version))
+            .await;
     }
 
     async fn did_change(&self, params: DidChangeTextDocumentParams) {
         self.client
             .log_message(MessageType::INFO, "file changed!")
             .await;
-        let uri = params.text_document.uri;
-        let path = uri.to_file_path();
-        if let Ok(path) = path {
-            self.build(path);
+        let document = params.text_document;
+        let Ok(path) = document.uri.to_file_path() else {
+            return;
+        };
+        let root = find_project_root(&path);
+        match self.manager.get_source(&path) {
+            Some(source) => {
+                let edits = to_text_edits(source, params.content_changes, self.position_encoding());
+                self.manager.apply_edits(root, &path, &edits);
+            }
+            None => self.manager.build_one(root, &path),
         }
+        let diagnostics = self.diagnostics(&path);
+        self.publish(document.uri, diagnostics, Some(document.version))
+            .await;
     }
 
     async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
@@ -100,15 +247,27 @@ impl LanguageServer for Backend {
             )
             .await;
 
-        // TODO: Implement real logic to find the symbol at the hover position
-        // For now, let's provide a sample hover message with placeholder values
-        let type_info =
-            self.manager
-                .get_hover_information(&path, position.line, position.character);
+        let Some(hover_information) = self.manager.get_hover_information(
+            &path,
+            to_line_col(position),
+            self.position_encoding(),
+        ) else {
+            return Ok(None);
+        };
 
+        let mut value = format!(
+            "**Hover Information**\n\n`{}`\n",
+            hover_information.type_info
+        );
+        if let Some(declared_in) = hover_information.declared_in {
+            value.push_str(&format!(
+                "\nInherited from `{}`\n",
+                declared_in.details.qual_name
+            ));
+        }
         let markup_content = MarkupContent {
             kind: MarkupKind::Markdown,
-            value: format!("**Hover Information**\n\n`{type_info}`\n",),
+            value,
         };
         let hover = Hover {
             contents: HoverContents::Markup(markup_content),
@@ -147,6 +306,10 @@ async fn main() {
         .join("typeshed");
     let settings = Settings::from_typeshed(typeshed_path);
     let manager = BuildManager::new(settings);
-    let (service, socket) = LspService::new(|client| Backend { client, manager });
+    let (service, socket) = LspService::new(|client| Backend {
+        client,
+        manager,
+        position_encoding: OnceLock::new(),
+    });
     Server::new(stdin, stdout, socket).serve(service).await;
 }
@@ -4,10 +4,12 @@ use std::fmt::{self};
 use std::sync::Arc;
 
 use miette::{SourceOffset, SourceSpan};
+use num_bigint::BigInt;
 
 use crate::intern::StrId;
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)] // #[serde(tag = "type")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Node {
     /// Start offset in source
     pub start: u32,
@@ -16,7 +18,8 @@ pub struct Node {
     pub end: u32,
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)] // #[serde(tag = "type")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TextRange {
     pub start: u32,
     pub end: u32,
@@ -34,7 +37,7 @@ impl Node {
     }
 
     pub fn len(&self) -> u32 {
-        self.end - self.start
+        self.end.saturating_sub(self.start)
     }
 
     pub fn is_empty(&self) -> bool {
@@ -55,13 +58,60 @@ impl From<Node> for SourceSpan {
 // The following structs are used to represent the AST
 // https://docs.python.org/3/library/ast.html#abstract-grammar
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Module {
     pub node: Node,
     pub body: Vec<Statement>,
 }
 
+/// Version of the serialized AST, it changes with the shape of the nodes so
+/// that modules cached by an older version are not read
+#[cfg(feature = "serde")]
+pub const SERIALIZATION_VERSION: u32 = 1;
+
+#[cfg(feature = "serde")]
+impl Module {
+    /// Every statement, expression, constant value, pattern and type param is
+    /// an object with the variant in `type` and the node in `value`, e.g.
+    /// `{"type": "Name", "value": {"node": {"start": 0, "end": 1}, ...}}`
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    /// Compact binary form in MessagePack that starts with the
+    /// [`SERIALIZATION_VERSION`]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+        let mut bytes = vec![];
+        rmp_serde::encode::write(&mut bytes, &SERIALIZATION_VERSION)?;
+        rmp_serde::encode::write_named(&mut bytes, self)?;
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, rmp_serde::decode::Error> {
+        use serde::{de::Error, Deserialize};
+
+        let mut deserializer = rmp_serde::Deserializer::new(bytes);
+        let version = u32::deserialize(&mut deserializer)?;
+        if version != SERIALIZATION_VERSION {
+            return Err(Error::custom(format!(
+                "expected AST version {SERIALIZATION_VERSION} but found {version}"
+            )));
+        }
+        Self::deserialize(&mut deserializer)
+    }
+}
+
 // Use box to reduce the enum size
 #[derive(Debug, Clone, Is)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", content = "value")
+)]
 pub enum Statement {
     AssignStatement(Box<Assign>),
     AnnAssignStatement(Box<AnnAssign>),
@@ -91,6 +141,7 @@ pub enum Statement {
     ClassDef(Arc<ClassDef>),
     MatchStmt(Box<Match>),
     TypeAlias(Box<TypeAlias>),
+    Invalid(Box<Invalid>),
 }
 
 impl GetNode for Statement {
@@ -124,18 +175,31 @@ impl GetNode for Statement {
             Statement::ClassDef(s) => s.node,
             Statement::MatchStmt(s) => s.node,
             Statement::TypeAlias(s) => s.node,
+            Statement::Invalid(s) => s.node,
         }
     }
 }
 
+// Placeholder for code that could not be parsed. The parser inserts it while
+// recovering from a syntax error so the valid parts of the tree stay usable.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Invalid {
+    pub node: Node,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Assign {
     pub node: Node,
     pub targets: Vec<Expression>,
     pub value: Expression,
+    /// The `int` in `x = [] # type: int`
+    pub type_comment: Option<String>,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AnnAssign {
     pub node: Node,
     pub target: Expression,
@@ -145,6 +209,7 @@ pub struct AnnAssign {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AugAssign {
     pub node: Node,
     pub target: Expression,
@@ -153,6 +218,7 @@ pub struct AugAssign {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AugAssignOp {
     Add,
     Sub,
@@ -169,7 +235,34 @@ pub enum AugAssignOp {
     FloorDiv,
 }
 
+impl From<&AugAssignOp> for BinaryOperator {
+    fn from(op: &AugAssignOp) -> Self {
+        match op {
+            AugAssignOp::Add => BinaryOperator::Add,
+            AugAssignOp::Sub => BinaryOperator::Sub,
+            AugAssignOp::Mult => BinaryOperator::Mult,
+            AugAssignOp::MatMult => BinaryOperator::MatMult,
+            AugAssignOp::Div => BinaryOperator::Div,
+            AugAssignOp::Mod => BinaryOperator::Mod,
+            AugAssignOp::Pow => BinaryOperator::Pow,
+            AugAssignOp::LShift => BinaryOperator::LShift,
+            AugAssignOp::RShift => BinaryOperator::RShift,
+            AugAssignOp::BitOr => BinaryOperator::BitOr,
+            AugAssignOp::BitXor => BinaryOperator::BitXor,
+            AugAssignOp::BitAnd => BinaryOperator::BitAnd,
+            AugAssignOp::FloorDiv => BinaryOperator::FloorDiv,
+        }
+    }
+}
+
+impl std::fmt::Display for AugAssignOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}=", BinaryOperator::from(self))
+    }
+}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Assert {
     pub node: Node,
     pub test: Expression,
@@ -177,17 +270,20 @@ pub struct Assert {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pass {
     pub node: Node,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Delete {
     pub node: Node,
     pub targets: Vec<Expression>,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Return {
     pub node: Node,
     pub value: Option<Expression>,
@@ -195,6 +291,7 @@ pub struct Return {
 
 // https://docs.python.org/3/library/ast.html#ast.Raise
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Raise {
     pub node: Node,
     pub exc: Option<Expression>,
@@ -203,18 +300,21 @@ pub struct Raise {
 
 // https://docs.python.org/3/library/ast.html#ast.Break
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Break {
     pub node: Node,
 }
 
 // https://docs.python.org/3/library/ast.html#ast.Continue
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Continue {
     pub node: Node,
 }
 
 // https://docs.python.org/3/library/ast.html#ast.Import
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Import {
     pub node: Node,
     pub names: Vec<Alias>,
@@ -222,6 +322,7 @@ pub struct Import {
 
 // https://docs.python.org/3/library/ast.html#ast.alias
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Alias {
     pub node: Node,
     pub name: String,
@@ -240,6 +341,7 @@ impl Alias {
 
 // https://docs.python.org/3/library/ast.html#ast.ImportFrom
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ImportFrom {
     pub node: Node,
     pub module: String,
@@ -249,6 +351,7 @@ pub struct ImportFrom {
 
 // https://docs.python.org/3/library/ast.html#ast.Global
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Global {
     pub node: Node,
     pub names: Vec<String>,
@@ -256,12 +359,18 @@ pub struct Global {
 
 // https://docs.python.org/3/library/ast.html#ast.Nonlocal
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Nonlocal {
     pub node: Node,
     pub names: Vec<String>,
 }
 
 #[derive(Debug, Clone, Is)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", content = "value")
+)]
 pub enum Expression {
     Constant(Box<Constant>),
     List(Box<List>),
@@ -292,6 +401,7 @@ pub enum Expression {
     IfExp(Box<IfExp>),
     JoinedStr(Box<JoinedStr>),
     FormattedValue(Box<FormattedValue>),
+    Invalid(Box<Invalid>),
 }
 
 impl GetNode for Expression {
@@ -324,12 +434,14 @@ impl GetNode for Expression {
             Expression::IfExp(i) => i.node,
             Expression::JoinedStr(j) => j.node,
             Expression::FormattedValue(f) => f.node,
+            Expression::Invalid(i) => i.node,
         }
     }
 }
 
 // https://docs.python.org/3/reference/expressions.html#atom-identifiers
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Name {
     pub node: Node,
     pub id: String,
@@ -352,73 +464,18 @@ impl fmt::Debug for Name {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Constant {
     pub node: Node,
     pub value: ConstantValue,
 }
 
 impl Constant {
-    pub fn get_value<'a>(&self, source: &'a str) -> Cow<'a, str> {
+    /// The decoded value of the constant as text. Strings are returned without
+    /// quotes and numbers are formatted the way Python prints them.
+    pub fn get_value(&self) -> Cow<'_, str> {
         match &self.value {
-            ConstantValue::Str(quote_type) => match quote_type {
-                QuoteType::Single => Cow::Borrowed(
-                    &source[(self.node.start + 1) as usize..(self.node.end - 1) as usize],
-                ),
-                QuoteType::Triple => Cow::Borrowed(
-                    &source[(self.node.start + 3) as usize..(self.node.end - 3) as usize],
-                ),
-                QuoteType::Concat => {
-                    let input = &source[(self.node.start) as usize..(self.node.end) as usize];
-                    let mut result = String::new();
-                    let mut chars = input.chars().peekable();
-
-                    while let Some(c) = chars.next() {
-                        let quote_type = match c {
-                            '\'' => {
-                                if chars.peek() == Some(&'\'') && chars.nth(1) == Some('\'') {
-                                    // Triple single quote
-                                    "'''"
-                                } else {
-                                    // Single quote
-                                    "'"
-                                }
-                            }
-                            '"' => {
-                                if chars.peek() == Some(&'"') && chars.nth(1) == Some('"') {
-                                    // Triple double quote
-                                    "\"\"\""
-                                } else {
-                                    // Double quote
-                                    "\""
-                                }
-                            }
-                            _ => continue, // Ignore any non-quote characters
-                        };
-
-                        // Extract content between quotes
-                        let mut content = String::new();
-                        let mut quote_ending = quote_type.chars().peekable();
-
-                        for next_char in chars.by_ref() {
-                            // Check for quote ending
-                            if Some(&next_char) == quote_ending.peek() {
-                                quote_ending.next();
-                                if quote_ending.peek().is_none() {
-                                    break; // End of the string literal
-                                }
-                            } else {
-                                content.push(next_char);
-                                quote_ending = quote_type.chars().peekable(); // Reset the ending check
-                            }
-                        }
-
-                        // Concatenate the cleaned-up string
-                        result.push_str(&content);
-                    }
-
-                    Cow::Owned(result)
-                }
-            },
+            ConstantValue::Str(value) => Cow::Borrowed(value),
             ConstantValue::Bool(b) => {
                 if *b {
                     Cow::Borrowed("true")
@@ -426,66 +483,103 @@ impl Constant {
                     Cow::Borrowed("false")
                 }
             }
-            _ => todo!("Call the parser and get the value"),
+            ConstantValue::None => Cow::Borrowed("None"),
+            ConstantValue::Ellipsis => Cow::Borrowed("..."),
+            ConstantValue::Tuple => Cow::Borrowed("()"),
+            ConstantValue::Bytes(value) => String::from_utf8_lossy(value),
+            ConstantValue::Int(value) => Cow::Owned(value.to_string()),
+            ConstantValue::Float(value) => Cow::Owned(float_repr(*value)),
+            ConstantValue::Complex { real, imag } => {
+                if *real == 0.0 && real.is_sign_positive() {
+                    Cow::Owned(format!("{}j", imag_repr(*imag)))
+                } else {
+                    let sign = if imag.is_sign_negative() { "-" } else { "+" };
+                    Cow::Owned(format!(
+                        "({}{}{}j)",
+                        imag_repr(*real),
+                        sign,
+                        imag_repr(imag.abs())
+                    ))
+                }
+            }
         }
     }
 }
 
+/// Formats the float like Python's `repr`
+fn float_repr(value: f64) -> String {
+    if value.is_nan() {
+        return "nan".to_string();
+    }
+    if value.is_infinite() {
+        return if value > 0.0 { "inf" } else { "-inf" }.to_string();
+    }
+    let abs = value.abs();
+    if abs != 0.0 && !(1e-4..1e16).contains(&abs) {
+        let formatted = format!("{value:e}");
+        let (mantissa, exponent) = formatted.split_once('e').unwrap_or((&formatted, "0"));
+        let (sign, digits) = match exponent.strip_prefix('-') {
+            Some(digits) => ('-', digits),
+            None => ('+', exponent),
+        };
+        return format!("{mantissa}e{sign}{digits:0>2}");
+    }
+    format!("{value:?}")
+}
+
+/// Parts of complex numbers are printed without a trailing `.0`
+fn imag_repr(value: f64) -> String {
+    let repr = float_repr(value);
+    repr.strip_suffix(".0").map(str::to_string).unwrap_or(repr)
+}
+
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", content = "value")
+)]
 pub enum ConstantValue {
     None,
     Ellipsis,
     Bool(bool),
-    // If the string start with triple quotes or single
-    // true => triple
-    // false => single
-    Str(QuoteType),
-    // Str,
-    Bytes,
+    /// Content of the string without its prefix and quotes
+    Str(String),
+    Bytes(Vec<u8>),
     Tuple,
-    // Numbers are string because we don't care about the value rn.
-    Int,
-    Float,
-    Complex,
-}
-
-#[derive(Clone, PartialEq, Debug)]
-pub enum QuoteType {
-    Single,
-    Triple,
-    // When this string was created because two strings were concatenated
-    Concat,
-}
-
-impl From<&str> for QuoteType {
-    fn from(value: &str) -> Self {
-        if value.starts_with("\"\"\"") || value.starts_with("'''") {
-            return Self::Triple;
-        }
-        Self::Single
-    }
+    Int(BigInt),
+    Float(f64),
+    Complex {
+        real: f64,
+        imag: f64,
+    },
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct List {
     pub node: Node,
     pub elements: Vec<Expression>,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Tuple {
     pub node: Node,
     pub elements: Vec<Expression>,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Dict {
     pub node: Node,
-    pub keys: Vec<Expression>,
+    /// The key is `None` for unpacked mappings, e.g. `{**a}`
+    pub keys: Vec<Option<Expression>>,
     pub values: Vec<Expression>,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Set {
     pub node: Node,
     pub elements: Vec<Expression>,
@@ -493,6 +587,7 @@ pub struct Set {
 
 // https://docs.python.org/3/library/ast.html#ast.BoolOp
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BoolOperation {
     pub node: Node,
     pub op: BooleanOperator,
@@ -500,6 +595,7 @@ pub struct BoolOperation {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BooleanOperator {
     And,
     Or,
@@ -507,6 +603,7 @@ pub enum BooleanOperator {
 
 // https://docs.python.org/3/library/ast.html#ast.UnaryOp
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UnaryOperation {
     pub node: Node,
     pub op: UnaryOperator,
@@ -514,6 +611,7 @@ pub struct UnaryOperation {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum UnaryOperator {
     Not,
     Invert,
@@ -521,8 +619,22 @@ pub enum UnaryOperator {
     USub,
 }
 
+impl std::fmt::Display for UnaryOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let op_str = match self {
+            UnaryOperator::Not => "not",
+            UnaryOperator::Invert => "~",
+            UnaryOperator::UAdd => "+",
+            UnaryOperator::USub => "-",
+        };
+
+        write!(f, "{}", op_str)
+    }
+}
+
 // https://docs.python.org/3/library/ast.html#ast.BinOp
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BinOp {
     pub node: Node,
     pub op: BinaryOperator,
@@ -531,6 +643,7 @@ pub struct BinOp {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BinaryOperator {
     Add,
     Sub,
@@ -571,6 +684,7 @@ impl std::fmt::Display for BinaryOperator {
 
 // https://docs.python.org/3/library/ast.html#ast.NamedExpr
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NamedExpression {
     pub node: Node,
     pub target: Expression,
@@ -579,6 +693,7 @@ pub struct NamedExpression {
 
 // https://docs.python.org/3/library/ast.html#ast.Yield
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Yield {
     pub node: Node,
     pub value: Option<Expression>,
@@ -586,6 +701,7 @@ pub struct Yield {
 
 // https://docs.python.org/3/library/ast.html#ast.YieldFrom
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct YieldFrom {
     pub node: Node,
     pub value: Expression,
@@ -593,6 +709,7 @@ pub struct YieldFrom {
 
 // https://docs.python.org/3/library/ast.html#ast.Starred
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Starred {
     pub node: Node,
     pub value: Expression,
@@ -600,6 +717,7 @@ pub struct Starred {
 
 // https://docs.python.org/3/library/ast.html#ast.GeneratorExp
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Generator {
     pub node: Node,
     pub element: Expression,
@@ -607,6 +725,7 @@ pub struct Generator {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ListComp {
     pub node: Node,
     pub element: Expression,
@@ -614,6 +733,7 @@ pub struct ListComp {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SetComp {
     pub node: Node,
     pub element: Expression,
@@ -621,6 +741,7 @@ pub struct SetComp {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DictComp {
     pub node: Node,
     pub key: Expression,
@@ -630,6 +751,7 @@ pub struct DictComp {
 
 // https://docs.python.org/3/library/ast.html#ast.comprehension
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Comprehension {
     pub node: Node,
     pub target: Expression,
@@ -640,6 +762,7 @@ pub struct Comprehension {
 
 // https://docs.python.org/3/library/ast.html#ast.Attribute
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Attribute {
     pub node: Node,
     /// The x in x.y
@@ -650,6 +773,7 @@ pub struct Attribute {
 
 // https://docs.python.org/3/library/ast.html#ast.Subscript
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Subscript {
     pub node: Node,
     pub value: Expression,
@@ -659,6 +783,7 @@ pub struct Subscript {
 // https://docs.python.org/3/library/ast.html#ast.Slice
 // can be used for Subscript
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Slice {
     pub node: Node,
     pub lower: Option<Expression>,
@@ -668,6 +793,7 @@ pub struct Slice {
 
 // https://docs.python.org/3/library/ast.html#ast.Call
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Call {
     pub node: Node,
     pub func: Expression,
@@ -678,6 +804,7 @@ pub struct Call {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Keyword {
     pub node: Node,
     pub arg: Option<String>,
@@ -686,6 +813,7 @@ pub struct Keyword {
 
 // https://docs.python.org/3/library/ast.html#ast.Await
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Await {
     pub node: Node,
     pub value: Expression,
@@ -693,6 +821,7 @@ pub struct Await {
 
 // https://docs.python.org/3/library/ast.html#ast.Compare
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Compare {
     pub node: Node,
     pub left: Expression,
@@ -701,6 +830,7 @@ pub struct Compare {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ComparisonOperator {
     Eq,
     NotEq,
@@ -714,8 +844,28 @@ pub enum ComparisonOperator {
     NotIn,
 }
 
+impl std::fmt::Display for ComparisonOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let op_str = match self {
+            ComparisonOperator::Eq => "==",
+            ComparisonOperator::NotEq => "!=",
+            ComparisonOperator::Lt => "<",
+            ComparisonOperator::LtE => "<=",
+            ComparisonOperator::Gt => ">",
+            ComparisonOperator::GtE => ">=",
+            ComparisonOperator::Is => "is",
+            ComparisonOperator::IsNot => "is not",
+            ComparisonOperator::In => "in",
+            ComparisonOperator::NotIn => "not in",
+        };
+
+        write!(f, "{}", op_str)
+    }
+}
+
 // https://docs.python.org/3/library/ast.html#ast.Lambda
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Lambda {
     pub node: Node,
     pub args: Arguments,
@@ -724,6 +874,7 @@ pub struct Lambda {
 
 // https://docs.python.org/3/library/ast.html#ast.arguments
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Arguments {
     pub node: Node,
     pub posonlyargs: Vec<Arg>,
@@ -790,6 +941,7 @@ impl std::fmt::Display for Arguments {
 
 // https://docs.python.org/3/library/ast.html#ast.arg
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Arg {
     pub node: Node,
     pub arg: String,
@@ -798,6 +950,7 @@ pub struct Arg {
 
 // https://docs.python.org/3/library/ast.html#ast.IfExp
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IfExp {
     pub node: Node,
     pub test: Expression,
@@ -807,6 +960,7 @@ pub struct IfExp {
 
 // https://docs.python.org/3/library/ast.html#ast.FormattedValue
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FormattedValue {
     pub node: Node,
     pub value: Expression,
@@ -816,6 +970,7 @@ pub struct FormattedValue {
 
 // https://docs.python.org/3/library/ast.html#ast.JoinedStr
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct JoinedStr {
     pub node: Node,
     pub values: Vec<Expression>,
@@ -823,6 +978,7 @@ pub struct JoinedStr {
 
 // https://docs.python.org/3/library/ast.html#ast.If
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct If {
     pub node: Node,
     pub test: Expression,
@@ -838,6 +994,7 @@ impl If {
 
 // https://docs.python.org/3/library/ast.html#ast.While
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct While {
     pub node: Node,
     pub test: Expression,
@@ -847,43 +1004,52 @@ pub struct While {
 
 // https://docs.python.org/3/library/ast.html#ast.For
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct For {
     pub node: Node,
     pub target: Expression,
     pub iter: Expression,
     pub body: Vec<Statement>,
     pub orelse: Vec<Statement>,
+    pub type_comment: Option<String>,
 }
 
 // https://docs.python.org/3/library/ast.html#ast.AsyncFor
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AsyncFor {
     pub node: Node,
     pub target: Expression,
     pub iter: Expression,
     pub body: Vec<Statement>,
     pub orelse: Vec<Statement>,
+    pub type_comment: Option<String>,
 }
 
 // https://docs.python.org/3/library/ast.html#ast.With
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct With {
     pub node: Node,
     pub items: Vec<WithItem>,
     pub body: Vec<Statement>,
+    pub type_comment: Option<String>,
 }
 
 // https://docs.python.org/3/library/ast.html#ast.AsyncWith
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AsyncWith {
     pub node: Node,
     pub items: Vec<WithItem>,
     pub body: Vec<Statement>,
+    pub type_comment: Option<String>,
 }
 
 // https://docs.python.org/3/library/ast.html#ast.withitem
 // can be used for With
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WithItem {
     pub node: Node,
     pub context_expr: Expression,
@@ -892,6 +1058,7 @@ pub struct WithItem {
 
 // https://docs.python.org/3/library/ast.html#ast.Try
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Try {
     pub node: Node,
     pub body: Vec<Statement>,
@@ -902,6 +1069,7 @@ pub struct Try {
 
 // https://docs.python.org/3/library/ast.html#ast.TryStar
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TryStar {
     pub node: Node,
     pub body: Vec<Statement>,
@@ -912,6 +1080,7 @@ pub struct TryStar {
 
 // https://docs.python.org/3/library/ast.html#ast.ExceptHandler
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ExceptHandler {
     pub node: Node,
     pub typ: Option<Expression>,
@@ -921,6 +1090,7 @@ pub struct ExceptHandler {
 
 // https://docs.python.org/3/library/ast.html#functiondef
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FunctionDef {
     pub node: Node,
     pub name: StrId,
@@ -934,6 +1104,7 @@ pub struct FunctionDef {
 
 // https://docs.python.org/3/library/ast.html#ast.AsyncFunctionDef
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AsyncFunctionDef {
     pub node: Node,
     pub name: StrId,
@@ -984,6 +1155,7 @@ impl AsyncFunctionDef {
 
 // https://docs.python.org/3/library/ast.html#ast.ClassDef
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ClassDef {
     pub node: Node,
     pub name: StrId,
@@ -996,6 +1168,7 @@ pub struct ClassDef {
 
 // https://docs.python.org/3/library/ast.html#ast.Match
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Match {
     pub node: Node,
     pub subject: Expression,
@@ -1004,6 +1177,7 @@ pub struct Match {
 
 // https://docs.python.org/3/library/ast.html#ast.match_case
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MatchCase {
     pub node: Node,
     pub pattern: MatchPattern,
@@ -1012,6 +1186,11 @@ pub struct MatchCase {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", content = "value")
+)]
 pub enum MatchPattern {
     MatchValue(MatchValue),
     MatchSingleton(Expression),
@@ -1024,12 +1203,14 @@ pub enum MatchPattern {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MatchValue {
     pub node: Node,
     pub value: Expression,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MatchAs {
     pub node: Node,
     pub name: Option<String>,
@@ -1037,6 +1218,7 @@ pub struct MatchAs {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MatchMapping {
     pub node: Node,
     pub keys: Vec<Expression>,
@@ -1045,6 +1227,7 @@ pub struct MatchMapping {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MatchClass {
     pub node: Node,
     pub cls: Expression,
@@ -1055,6 +1238,11 @@ pub struct MatchClass {
 
 // https://docs.python.org/3/library/ast.html#ast-type-params
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", content = "value")
+)]
 pub enum TypeParam {
     TypeVar(TypeVar),
     ParamSpec(ParamSpec),
@@ -1083,6 +1271,7 @@ impl TypeParam {
 
 // https://docs.python.org/3/library/ast.html#ast.TypeVar
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TypeVar {
     pub node: Node,
     pub name: String,
@@ -1091,6 +1280,7 @@ pub struct TypeVar {
 
 // https://docs.python.org/3/library/ast.html#ast.ParamSpec
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ParamSpec {
     pub node: Node,
     pub name: String,
@@ -1098,6 +1288,7 @@ pub struct ParamSpec {
 
 // https://docs.python.org/3/library/ast.html#ast.TypeVarTuple
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TypeVarTuple {
     pub node: Node,
     pub name: String,
@@ -1105,6 +1296,7 @@ pub struct TypeVarTuple {
 
 // https://docs.python.org/3/library/ast.html#ast.TypeAlias
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TypeAlias {
     pub node: Node,
     pub name: String,
@@ -1118,11 +1310,17 @@ impl Module {
     }
 }
 impl Assign {
-    pub fn new(node: Node, targets: Vec<Expression>, value: Expression) -> Self {
+    pub fn new(
+        node: Node,
+        targets: Vec<Expression>,
+        value: Expression,
+        type_comment: Option<&str>,
+    ) -> Self {
         Self {
             node,
             targets,
             value,
+            type_comment: type_comment.map(|s| s.to_owned()),
         }
     }
 }
@@ -1247,7 +1445,7 @@ impl Tuple {
     }
 }
 impl Dict {
-    pub fn new(node: Node, keys: Vec<Expression>, values: Vec<Expression>) -> Self {
+    pub fn new(node: Node, keys: Vec<Option<Expression>>, values: Vec<Expression>) -> Self {
         Self { node, keys, values }
     }
 }
@@ -1531,6 +1729,7 @@ impl For {
         iter: Expression,
         body: Vec<Statement>,
         orelse: Vec<Statement>,
+        type_comment: Option<&str>,
     ) -> Self {
         Self {
             node,
@@ -1538,6 +1737,7 @@ impl For {
             iter,
             body,
             orelse,
+            type_comment: type_comment.map(|s| s.to_owned()),
         }
     }
 }
@@ -1548,6 +1748,7 @@ impl AsyncFor {
         iter: Expression,
         body: Vec<Statement>,
         orelse: Vec<Statement>,
+        type_comment: Option<&str>,
     ) -> Self {
         Self {
             node,
@@ -1555,17 +1756,38 @@ impl AsyncFor {
             iter,
             body,
             orelse,
+            type_comment: type_comment.map(|s| s.to_owned()),
         }
     }
 }
 impl With {
-    pub fn new(node: Node, items: Vec<WithItem>, body: Vec<Statement>) -> Self {
-        Self { node, items, body }
+    pub fn new(
+        node: Node,
+        items: Vec<WithItem>,
+        body: Vec<Statement>,
+        type_comment: Option<&str>,
+    ) -> Self {
+        Self {
+            node,
+            items,
+            body,
+            type_comment: type_comment.map(|s| s.to_owned()),
+        }
     }
 }
 impl AsyncWith {
-    pub fn new(node: Node, items: Vec<WithItem>, body: Vec<Statement>) -> Self {
-        Self { node, items, body }
+    pub fn new(
+        node: Node,
+        items: Vec<WithItem>,
+        body: Vec<Statement>,
+        type_comment: Option<&str>,
+    ) -> Self {
+        Self {
+            node,
+            items,
+            body,
+            type_comment: type_comment.map(|s| s.to_owned()),
+        }
     }
 }
 impl WithItem {
@@ -1786,4 +2008,47 @@ mod tests {
         assert_eq!(size_of::<Statement>(), 16);
         assert_eq!(size_of::<Expression>(), 16);
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_roundtrip() {
+        use crate::{ast::Module, parser::parser::Parser, token::Token, Lexer};
+
+        insta::glob!("../test_data", "inputs/*.py", |path| {
+            let source = std::fs::read_to_string(path).unwrap();
+            let (module, _) = Parser::new(&source).parse();
+            let expected = format!("{module:#?}");
+
+            let json = module.to_json().unwrap();
+            let from_json = Module::from_json(&json).unwrap();
+            assert_eq!(format!("{from_json:#?}"), expected, "{}", path.display());
+
+            let bytes = module.to_bytes().unwrap();
+            let from_bytes = Module::from_bytes(&bytes).unwrap();
+            assert_eq!(format!("{from_bytes:#?}"), expected, "{}", path.display());
+
+            let tokens = Lexer::new(&source).lex();
+            let json = serde_json::to_string(&tokens).unwrap();
+            assert_eq!(serde_json::from_str::<Vec<Token>>(&json).unwrap(), tokens);
+        });
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_schema() {
+        use crate::{ast::Module, parser::parser::Parser};
+
+        let (module, _) = Parser::new("def f(): return {**a}").parse();
+        let json: serde_json::Value = serde_json::from_str(&module.to_json().unwrap()).unwrap();
+        let function = &json["body"][0];
+        assert_eq!(function["type"], "FunctionDef");
+        assert_eq!(function["value"]["name"], "f");
+        let dict = &function["value"]["body"][0]["value"]["value"];
+        assert_eq!(dict["type"], "Dict");
+        assert_eq!(dict["value"]["keys"], serde_json::json!([null]));
+
+        let mut bytes = module.to_bytes().unwrap();
+        bytes[0] += 1;
+        assert!(Module::from_bytes(&bytes).is_err());
+    }
 }
@@ -0,0 +1,351 @@
+//! Lossless view of the source code.
+//!
+//! The AST drops comments, whitespace and parentheses. The syntax tree keeps
+//! every token of the source together with the trivia in front of it so the
+//! source can be reproduced byte for byte. Nodes of the AST are mapped to the
+//! tokens by their range, which is used to find the comments and parentheses
+//! around a node.
+use crate::{ast::Node, token::Kind, Lexer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriviaKind {
+    /// Spaces, tabs and form feeds
+    Whitespace,
+    /// The comment including the `#` character
+    Comment,
+    /// Newlines that do not end a logical line, e.g. blank lines or newlines
+    /// inside brackets
+    Newline,
+    /// A backslash followed by a newline
+    LineContinuation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub start: u32,
+    pub end: u32,
+}
+
+impl Trivia {
+    pub fn as_str<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.start as usize..self.end as usize]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxToken {
+    pub kind: Kind,
+    pub start: u32,
+    pub end: u32,
+    /// Trivia between the previous token and this token
+    pub leading_trivia: Vec<Trivia>,
+}
+
+impl SyntaxToken {
+    pub fn as_str<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.start as usize..self.end as usize]
+    }
+
+    /// Indent and dedent tokens mark the structure of blocks and do not
+    /// belong to the nodes around them
+    fn is_structural(&self) -> bool {
+        matches!(self.kind, Kind::Indent | Kind::Dedent)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SyntaxTree<'a> {
+    source: &'a str,
+    /// All tokens of the source, the last token is `Eof`
+    tokens: Vec<SyntaxToken>,
+}
+
+impl<'a> SyntaxTree<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let mut lexer = Lexer::new(source);
+        let mut tokens = vec![];
+        let mut leading_trivia = vec![];
+        let mut offset = 0;
+        for token in lexer.lex() {
+            push_gap_trivia(source, offset, token.start, &mut leading_trivia);
+            offset = offset.max(token.end);
+            match token.kind {
+                Kind::Comment => leading_trivia.push(Trivia {
+                    kind: TriviaKind::Comment,
+                    start: token.start,
+                    end: token.end,
+                }),
+                Kind::NL => leading_trivia.push(Trivia {
+                    kind: TriviaKind::Newline,
+                    start: token.start,
+                    end: token.end,
+                }),
+                kind => tokens.push(SyntaxToken {
+                    kind,
+                    start: token.start,
+                    end: token.end,
+                    leading_trivia: std::mem::take(&mut leading_trivia),
+                }),
+            }
+        }
+        Self { source, tokens }
+    }
+
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    pub fn tokens(&self) -> &[SyntaxToken] {
+        &self.tokens
+    }
+
+    /// Writes the tokens and trivia back. The result is identical to the source.
+    pub fn to_source(&self) -> String {
+        let mut result = String::with_capacity(self.source.len());
+        for token in &self.tokens {
+            for trivia in &token.leading_trivia {
+                result.push_str(trivia.as_str(self.source));
+            }
+            result.push_str(token.as_str(self.source));
+        }
+        result
+    }
+
+    /// Tokens inside the range of the node
+    pub fn tokens_in(&self, node: Node) -> &[SyntaxToken] {
+        let (first, last) = self.token_range(node);
+        &self.tokens[first..last]
+    }
+
+    /// Comments on their own lines directly above the node
+    pub fn leading_comments(&self, node: Node) -> Vec<&Trivia> {
+        let (mut first, _) = self.token_range(node);
+        let mut comments = vec![];
+        // Comments above an indented block are placed before the indent token
+        loop {
+            let token = &self.tokens[first];
+            comments.splice(
+                0..0,
+                token
+                    .leading_trivia
+                    .iter()
+                    .filter(|t| t.kind == TriviaKind::Comment && self.is_own_line(t)),
+            );
+            if first == 0 || !self.tokens[first - 1].is_structural() {
+                break;
+            }
+            first -= 1;
+        }
+        comments
+    }
+
+    /// Comment on the same line right after the node
+    pub fn trailing_comment(&self, node: Node) -> Option<&Trivia> {
+        let (_, last) = self.token_range(node);
+        self.tokens
+            .get(last)?
+            .leading_trivia
+            .iter()
+            .take_while(|t| !matches!(t.kind, TriviaKind::Newline))
+            .find(|t| t.kind == TriviaKind::Comment)
+    }
+
+    /// The range of the outermost parentheses around the node. Parentheses of
+    /// calls, function definitions and class definitions are not included.
+    pub fn parentheses(&self, node: Node) -> Option<Node> {
+        let (mut first, mut last) = self.token_range(node);
+        let mut range = None;
+        while first > 0
+            && self.tokens[first - 1].kind == Kind::LeftParen
+            && self
+                .tokens
+                .get(last)
+                .is_some_and(|t| t.kind == Kind::RightParen)
+            && !(first > 1 && ends_operand(self.tokens[first - 2].kind))
+        {
+            first -= 1;
+            last += 1;
+            range = Some(Node::new(
+                self.tokens[first].start,
+                self.tokens[last - 1].end,
+            ));
+        }
+        range
+    }
+
+    pub fn is_parenthesized(&self, node: Node) -> bool {
+        self.parentheses(node).is_some()
+    }
+
+    /// Index of the first token of the node and the index after its last token
+    fn token_range(&self, node: Node) -> (usize, usize) {
+        let first = self.tokens.partition_point(|t| {
+            t.start < node.start || (t.start == node.start && t.is_structural())
+        });
+        let last = self
+            .tokens
+            .partition_point(|t| t.end <= node.end && t.start < node.end);
+        (first, last.max(first))
+    }
+
+    fn is_own_line(&self, trivia: &Trivia) -> bool {
+        let before = &self.source[..trivia.start as usize];
+        let line_start = before.rfind(['\n', '\r']).map_or(0, |i| i + 1);
+        before[line_start..].trim().is_empty()
+    }
+}
+
+/// Splits the text between two tokens into whitespace, newlines and line continuations
+fn push_gap_trivia(source: &str, start: u32, end: u32, trivia: &mut Vec<Trivia>) {
+    let mut offset = start as usize;
+    let end = end as usize;
+    while offset < end {
+        let rest = &source[offset..end];
+        let (kind, len) = if rest.starts_with("\\\r\n") {
+            (TriviaKind::LineContinuation, 3)
+        } else if rest.starts_with("\\\n") || rest.starts_with("\\\r") {
+            (TriviaKind::LineContinuation, 2)
+        } else if rest.starts_with("\r\n") {
+            (TriviaKind::Newline, 2)
+        } else if rest.starts_with(['\n', '\r']) {
+            (TriviaKind::Newline, 1)
+        } else {
+            let len = rest
+                .find(['\\', '\n', '\r'])
+                .filter(|len| *len > 0)
+                .unwrap_or(rest.len());
+            (TriviaKind::Whitespace, len)
+        };
+        trivia.push(Trivia {
+            kind,
+            start: offset as u32,
+            end: (offset + len) as u32,
+        });
+        offset += len;
+    }
+}
+
+/// A parenthesis after these tokens is a call or a definition
+fn ends_operand(kind: Kind) -> bool {
+    matches!(
+        kind,
+        Kind::Identifier
+            | Kind::RightParen
+            | Kind::RightBracket
+            | Kind::RightBrace
+            | Kind::StringLiteral
+            | Kind::Bytes
+            | Kind::RawBytes
+            | Kind::FStringEnd
+            | Kind::None
+            | Kind::True
+            | Kind::False
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use insta::glob;
+
+    use super::*;
+    use crate::{
+        ast::{GetNode, Statement},
+        parser::parser::Parser,
+    };
+
+    #[test]
+    fn test_round_trip() {
+        glob!("../test_data", "inputs/*.py", |path| {
+            let source = fs::read_to_string(path).unwrap();
+            let tree = SyntaxTree::new(&source);
+            assert_eq!(tree.to_source(), source, "{}", path.display());
+        });
+
+        for source in [
+            "x = 1 \\\n  + 2\r\n",
+            "if x:  # comment\n\n    pass\n\t\n",
+            "def f(\n    a,  # a\n    b,\n): ...",
+            "",
+        ] {
+            assert_eq!(SyntaxTree::new(source).to_source(), source);
+        }
+    }
+
+    #[test]
+    fn test_comments() {
+        let source = "# header
+import os
+
+class A:
+    # about x
+    # more about x
+    x = 1  # one
+
+    def f(self):
+        # inside f
+        return (self)  # done
+";
+        let mut parser = Parser::new(source);
+        let (module, _) = parser.parse();
+        let tree = SyntaxTree::new(source);
+        let text = |trivia: &[&Trivia]| -> Vec<String> {
+            trivia
+                .iter()
+                .map(|t| t.as_str(source).to_string())
+                .collect()
+        };
+
+        let import = module.body[0].get_node();
+        assert_eq!(text(&tree.leading_comments(import)), vec!["# header"]);
+
+        let Statement::ClassDef(class) = &module.body[1] else {
+            panic!("expected a class");
+        };
+        let x = class.body[0].get_node();
+        assert_eq!(
+            text(&tree.leading_comments(x)),
+            vec!["# about x", "# more about x"]
+        );
+        assert_eq!(tree.trailing_comment(x).unwrap().as_str(source), "# one");
+
+        let Statement::FunctionDef(f) = &class.body[1] else {
+            panic!("expected a function");
+        };
+        assert!(tree.leading_comments(f.node).is_empty());
+        let ret = f.body[0].get_node();
+        assert_eq!(text(&tree.leading_comments(ret)), vec!["# inside f"]);
+        assert_eq!(tree.trailing_comment(ret).unwrap().as_str(source), "# done");
+    }
+
+    #[test]
+    fn test_parentheses() {
+        let source = "a = ((b))\nc = f(d)\ne = (g) + h\n";
+        let mut parser = Parser::new(source);
+        let (module, _) = parser.parse();
+        let tree = SyntaxTree::new(source);
+        let value = |index: usize| match &module.body[index] {
+            Statement::AssignStatement(a) => a.value.clone(),
+            _ => panic!("expected an assignment"),
+        };
+
+        let b = value(0).get_node();
+        let parentheses = tree.parentheses(b).unwrap();
+        assert_eq!(
+            &source[parentheses.start as usize..parentheses.end as usize],
+            "((b))"
+        );
+
+        let call = value(1);
+        let d = &call.as_call().unwrap().args[0];
+        assert!(!tree.is_parenthesized(d.get_node()));
+
+        let bin_op = value(2);
+        let bin_op = bin_op.as_bin_op().unwrap();
+        assert!(tree.is_parenthesized(bin_op.left.get_node()));
+        assert!(!tree.is_parenthesized(bin_op.right.get_node()));
+        assert!(!tree.is_parenthesized(bin_op.node));
+    }
+}
@@ -0,0 +1,318 @@
+//! Decoding of source files.
+//!
+//! Files are UTF-8 unless they declare another encoding in a comment on one of
+//! the first two lines, see [PEP 263](https://peps.python.org/pep-0263/).
+//! A UTF-8 byte order mark is not part of the source. All offsets of the
+//! parser are in the decoded source, lines and columns of diagnostics are
+//! counted in it like editors do. [`DecodedSource::original_offset`] maps
+//! offsets back to bytes of the file, e.g. for the tokens the CLI prints.
+use encoding_rs::Encoding;
+
+use crate::error::ParsingError;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceEncoding {
+    Utf8,
+    /// ISO-8859-1, every byte is the code point with the same value
+    Latin1,
+    Other(&'static Encoding),
+}
+
+impl SourceEncoding {
+    /// Finds the encoding of a coding declaration the same way Python does,
+    /// e.g. `latin_1`, `ISO-8859-1` and `latin-1` are the same encoding
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase().replace('_', "-");
+        let is = |prefix: &str| name == prefix || name.starts_with(&format!("{prefix}-"));
+        if is("utf-8") || name == "utf8" {
+            Some(SourceEncoding::Utf8)
+        } else if [
+            "latin-1",
+            "latin1",
+            "iso-8859-1",
+            "iso8859-1",
+            "iso-latin-1",
+            "l1",
+        ]
+        .into_iter()
+        .any(is)
+        {
+            Some(SourceEncoding::Latin1)
+        } else {
+            Encoding::for_label(name.as_bytes()).map(|encoding| {
+                if encoding == encoding_rs::UTF_8 {
+                    SourceEncoding::Utf8
+                } else {
+                    SourceEncoding::Other(encoding)
+                }
+            })
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SourceEncoding::Utf8 => "utf-8",
+            SourceEncoding::Latin1 => "latin-1",
+            SourceEncoding::Other(encoding) => encoding.name(),
+        }
+    }
+
+    /// The length in the file of decoded text
+    fn encoded_len(&self, text: &str) -> usize {
+        match self {
+            SourceEncoding::Utf8 => text.len(),
+            SourceEncoding::Latin1 => text.chars().count(),
+            SourceEncoding::Other(encoding) => encoding.encode(text).0.len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DecodedSource {
+    pub text: String,
+    pub encoding: SourceEncoding,
+    /// The file starts with a UTF-8 byte order mark, it is not part of `text`
+    pub bom: bool,
+    /// Bytes that could not be decoded and invalid coding declarations. The
+    /// text is still decoded with replacement characters.
+    pub errors: Vec<ParsingError>,
+}
+
+impl DecodedSource {
+    /// The byte offset in the file of an offset in the decoded text
+    pub fn original_offset(&self, offset: u32) -> u32 {
+        let bom = if self.bom { UTF8_BOM.len() } else { 0 };
+        let prefix = &self.text[..(offset as usize).min(self.text.len())];
+        (bom + self.encoding.encoded_len(prefix)) as u32
+    }
+}
+
+/// Decodes the contents of a source file
+pub fn decode_source(bytes: &[u8]) -> DecodedSource {
+    let (bytes, bom) = match bytes.strip_prefix(UTF8_BOM) {
+        Some(bytes) => (bytes, true),
+        None => (bytes, false),
+    };
+    let mut errors = vec![];
+    let encoding = match coding_declaration(bytes) {
+        Some((name, span)) => match SourceEncoding::from_name(&name) {
+            Some(SourceEncoding::Utf8) => SourceEncoding::Utf8,
+            Some(_) if bom => {
+                errors.push(ParsingError::InvalidSyntax {
+                    msg: format!("encoding problem: {name} with BOM"),
+                    advice: "Remove the byte order mark or the coding declaration".to_string(),
+                    span,
+                });
+                SourceEncoding::Utf8
+            }
+            Some(encoding) => encoding,
+            None => {
+                errors.push(ParsingError::InvalidSyntax {
+                    msg: format!("unknown encoding: {name}"),
+                    advice: "Use an encoding that Python supports, e.g. utf-8 or latin-1"
+                        .to_string(),
+                    span,
+                });
+                SourceEncoding::Utf8
+            }
+        },
+        None => SourceEncoding::Utf8,
+    };
+
+    let text = match encoding {
+        SourceEncoding::Utf8 => match std::str::from_utf8(bytes) {
+            Ok(text) => text.to_string(),
+            Err(error) => {
+                let position = error.valid_up_to();
+                errors.push(ParsingError::InvalidSyntax {
+                    msg: format!(
+                        "'utf-8' codec can't decode byte {:#04x} in position {position}",
+                        bytes[position]
+                    ),
+                    advice: "Save the file as UTF-8 or declare its encoding on the first line, \
+                        e.g. `# -*- coding: latin-1 -*-`"
+                        .to_string(),
+                    span: (position, char::REPLACEMENT_CHARACTER.len_utf8()),
+                });
+                String::from_utf8_lossy(bytes).into_owned()
+            }
+        },
+        SourceEncoding::Latin1 => bytes.iter().map(|&byte| byte as char).collect(),
+        SourceEncoding::Other(other) => {
+            let (text, had_errors) = other.decode_without_bom_handling(bytes);
+            if had_errors {
+                let position = text.find(char::REPLACEMENT_CHARACTER).unwrap_or_default();
+                errors.push(ParsingError::InvalidSyntax {
+                    msg: format!("'{}' codec can't decode the source", other.name()),
+                    advice: "Check that the coding declaration matches the encoding of the file"
+                        .to_string(),
+                    span: (position, char::REPLACEMENT_CHARACTER.len_utf8()),
+                });
+            }
+            text.into_owned()
+        }
+    };
+
+    DecodedSource {
+        text,
+        encoding,
+        bom,
+        errors,
+    }
+}
+
+/// The encoding name of a `coding[:=]name` comment on the first two lines and
+/// its span. The second line is only checked when the first line is a comment
+/// or blank. Declarations are ASCII, so the span is the same in the decoded text.
+fn coding_declaration(bytes: &[u8]) -> Option<(String, (usize, usize))> {
+    let mut line_start = 0;
+    for _ in 0..2 {
+        let rest = &bytes[line_start..];
+        let line_end = rest
+            .iter()
+            .position(|&byte| byte == b'\n' || byte == b'\r')
+            .unwrap_or(rest.len());
+        let line = &rest[..line_end];
+        let indent = line
+            .iter()
+            .take_while(|&&byte| matches!(byte, b' ' | b'\t' | b'\x0c'))
+            .count();
+        let line = &line[indent..];
+        if line.first() != Some(&b'#') {
+            if !line.is_empty() {
+                return None;
+            }
+        } else if let Some((name, start)) = coding_name(line) {
+            let start = line_start + indent + start;
+            let len = name.len();
+            return Some((name, (start, len)));
+        }
+        line_start += line_end;
+        match bytes.get(line_start) {
+            Some(b'\r') if bytes.get(line_start + 1) == Some(&b'\n') => line_start += 2,
+            Some(_) => line_start += 1,
+            None => return None,
+        }
+    }
+    None
+}
+
+/// Matches `coding[:=][ \t]*([-\w.]+)` in a comment, returns the name and its
+/// offset in the comment
+fn coding_name(comment: &[u8]) -> Option<(String, usize)> {
+    let mut search_start = 0;
+    while let Some(position) = find(&comment[search_start..], b"coding") {
+        let after = search_start + position + b"coding".len();
+        search_start = after;
+        if !matches!(comment.get(after), Some(b':' | b'=')) {
+            continue;
+        }
+        let name_start = after
+            + 1
+            + comment[after + 1..]
+                .iter()
+                .take_while(|&&byte| byte == b' ' || byte == b'\t')
+                .count();
+        let name: String = comment[name_start..]
+            .iter()
+            .take_while(|&&byte| byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.'))
+            .map(|&byte| byte as char)
+            .collect();
+        if !name.is_empty() {
+            return Some((name, name_start));
+        }
+    }
+    None
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf8() {
+        let decoded = decode_source("x = 'é'\n".as_bytes());
+        assert_eq!(decoded.text, "x = 'é'\n");
+        assert_eq!(decoded.encoding, SourceEncoding::Utf8);
+        assert!(!decoded.bom);
+        assert!(decoded.errors.is_empty());
+    }
+
+    #[test]
+    fn test_bom() {
+        let decoded = decode_source(b"\xEF\xBB\xBFx = 1\n");
+        assert_eq!(decoded.text, "x = 1\n");
+        assert!(decoded.bom);
+        assert!(decoded.errors.is_empty());
+        assert_eq!(decoded.original_offset(0), 3);
+        assert_eq!(decoded.original_offset(4), 7);
+
+        let decoded = decode_source(b"\xEF\xBB\xBF# coding: latin-1\n");
+        assert_eq!(decoded.encoding, SourceEncoding::Utf8);
+        assert_eq!(decoded.errors.len(), 1);
+    }
+
+    #[test]
+    fn test_coding_declaration() {
+        for (source, encoding) in [
+            ("# -*- coding: latin-1 -*-\n", SourceEncoding::Latin1),
+            (
+                "# vim: set fileencoding=iso-8859-1 :\n",
+                SourceEncoding::Latin1,
+            ),
+            (
+                "#!/usr/bin/python\n# coding=Latin_1\n",
+                SourceEncoding::Latin1,
+            ),
+            ("\n# coding: utf-8\n", SourceEncoding::Utf8),
+            (
+                "# coding: cp1252\n",
+                SourceEncoding::Other(encoding_rs::WINDOWS_1252),
+            ),
+            // Only the first two lines are checked
+            ("#\n#\n# coding: latin-1\n", SourceEncoding::Utf8),
+            // The second line is only checked after a comment or blank line
+            ("x = 1\n# coding: latin-1\n", SourceEncoding::Utf8),
+            ("x = 1  # coding: latin-1\n", SourceEncoding::Utf8),
+        ] {
+            let decoded = decode_source(source.as_bytes());
+            assert_eq!(decoded.encoding, encoding, "{source}");
+            assert!(decoded.errors.is_empty(), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_latin1() {
+        let decoded = decode_source(b"# coding: latin-1\nx = '\xe9t\xe9'\n");
+        assert_eq!(decoded.text, "# coding: latin-1\nx = 'été'\n");
+        assert!(decoded.errors.is_empty());
+        // é is two bytes in the decoded text but one in the file
+        let t = decoded.text.rfind('t').unwrap() as u32;
+        assert_eq!(decoded.original_offset(t), 24);
+        assert_eq!(
+            decoded.original_offset(decoded.text.len() as u32),
+            b"# coding: latin-1\nx = '\xe9t\xe9'\n".len() as u32
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let decoded = decode_source(b"x = '\xe9'\n");
+        assert_eq!(decoded.text, "x = '\u{FFFD}'\n");
+        assert_eq!(decoded.errors.len(), 1);
+        assert_eq!(decoded.errors[0].span(), (5, 3));
+
+        let decoded = decode_source(b"# coding: klingon\nx = 1\n");
+        assert_eq!(decoded.encoding, SourceEncoding::Utf8);
+        assert_eq!(decoded.errors.len(), 1);
+        assert_eq!(decoded.errors[0].span(), (10, 7));
+    }
+}
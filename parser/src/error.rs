@@ -3,7 +3,7 @@ use thiserror::Error;
 
 #[derive(Error, Diagnostic, Debug, Clone)]
 pub enum ParsingError {
-    #[error("Invalid syntax")]
+    #[error("{msg}")]
     #[diagnostic(code(parser::invalid_syntax))]
     InvalidSyntax {
         msg: String,
@@ -14,7 +14,16 @@ pub enum ParsingError {
     },
 }
 
-#[derive(Error, Debug)]
+impl ParsingError {
+    /// The offset and length of the source the error is about
+    pub fn span(&self) -> (usize, usize) {
+        match self {
+            ParsingError::InvalidSyntax { span, .. } => *span,
+        }
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
 pub enum LexError {
     #[error("String not terminated")]
     StringNotTerminated,
@@ -31,3 +40,45 @@ pub enum LexError {
     #[error("Unindent does not match any outer indentation level")]
     UnindentDoesNotMatchAnyOuterIndentationLevel,
 }
+
+impl LexError {
+    pub fn advice(&self) -> &'static str {
+        match self {
+            LexError::StringNotTerminated => {
+                "Add the closing quote. Strings that are not triple quoted end at the end of the line"
+            }
+            LexError::InvalidDigitInBinaryLiteral(_)
+            | LexError::InvalidDigitInOctalLiteral(_)
+            | LexError::InvalidDigitInHexadecimalLiteral(_) => {
+                "Remove the invalid digit or write the number as a decimal literal"
+            }
+            LexError::InvalidDigitInDecimalLiteral => {
+                "Underscores in numbers must be between two digits, e.g. 1_000"
+            }
+            LexError::UnindentDoesNotMatchAnyOuterIndentationLevel => {
+                "Indent the line to the same level as the block it belongs to"
+            }
+        }
+    }
+}
+
+/// An error found by the lexer and the range of the source it covers
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexDiagnostic {
+    pub error: LexError,
+    pub start: u32,
+    pub end: u32,
+}
+
+impl From<LexDiagnostic> for ParsingError {
+    fn from(diagnostic: LexDiagnostic) -> Self {
+        ParsingError::InvalidSyntax {
+            msg: diagnostic.error.to_string(),
+            advice: diagnostic.error.advice().to_string(),
+            span: (
+                diagnostic.start as usize,
+                (diagnostic.end - diagnostic.start) as usize,
+            ),
+        }
+    }
+}
@@ -0,0 +1,791 @@
+//! Incremental parsing.
+//!
+//! After an edit only the statements touched by the edit are parsed again.
+//! The smallest block that contains the edit is found, the statements of that
+//! block around the edit are parsed from the new source, and the nodes after
+//! the edit are moved by the difference in length. When the edit changes the
+//! structure of the code around it, e.g. it opens a bracket or changes the
+//! indentation of the following lines, the whole source is parsed again. So is
+//! a source with syntax errors, unless all of them come after the change.
+use std::{ops::Range, sync::Arc};
+
+use crate::{
+    ast::*,
+    error::ParsingError,
+    parser::parser::{Parser, ParserOptions},
+};
+
+/// Replaces the source between the byte offsets `start` and `end` with `text`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub start: u32,
+    pub end: u32,
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn new(start: u32, end: u32, text: impl Into<String>) -> Self {
+        Self {
+            start,
+            end,
+            text: text.into(),
+        }
+    }
+
+    /// Applies the edit to `source`. Offsets outside of the source are clamped.
+    pub fn apply(&self, source: &mut String) {
+        let (start, end) = self.clamp(source);
+        source.replace_range(start..end, &self.text);
+    }
+
+    fn clamp(&self, source: &str) -> (usize, usize) {
+        let start = (self.start as usize).min(source.len());
+        let end = (self.end as usize).clamp(start, source.len());
+        (start, end)
+    }
+}
+
+/// Applies `edits` to `source` and updates `module` and `errors`, the result of
+/// parsing `source`, to match the new source. Each edit is relative to the
+/// source after the previous edits. Returns the new source, module and errors.
+pub fn reparse(
+    source: &str,
+    module: Module,
+    errors: Vec<ParsingError>,
+    edits: &[TextEdit],
+) -> (String, Module, Vec<ParsingError>) {
+    reparse_with_options(source, module, errors, edits, ParserOptions::default())
+}
+
+/// Same as `reparse` but parses with the given options, which must be the
+/// options `module` was parsed with.
+pub fn reparse_with_options(
+    source: &str,
+    mut module: Module,
+    mut errors: Vec<ParsingError>,
+    edits: &[TextEdit],
+    options: ParserOptions,
+) -> (String, Module, Vec<ParsingError>) {
+    let mut new_source = source.to_string();
+    let Some(changed) = apply_edits(&mut new_source, edits) else {
+        return (new_source, module, errors);
+    };
+    if reparse_region(
+        source,
+        &new_source,
+        &mut module,
+        &mut errors,
+        changed,
+        options,
+    ) {
+        return (new_source, module, errors);
+    }
+    let (module, errors) = Parser::with_options(&new_source, options).parse();
+    (new_source, module, errors)
+}
+
+/// The range of the old source that was replaced and its length in the new source
+#[derive(Debug, Clone, Copy)]
+struct Change {
+    start: u32,
+    old_end: u32,
+    new_end: u32,
+}
+
+impl Change {
+    fn delta(&self) -> i64 {
+        self.new_end as i64 - self.old_end as i64
+    }
+}
+
+/// Applies the edits and returns the range that covers all of them
+fn apply_edits(source: &mut String, edits: &[TextEdit]) -> Option<Change> {
+    let mut changed: Option<Change> = None;
+    let mut delta = 0i64;
+    for edit in edits {
+        let (start, end) = edit.clamp(source);
+        edit.apply(source);
+
+        let edit_delta = edit.text.len() as i64 - (end - start) as i64;
+        let (start, end) = (start as u32, end as u32);
+        // The changed range in the current source grows to cover this edit
+        let (changed_start, changed_end) = match changed {
+            Some(c) => (c.start.min(start), c.new_end.max(end)),
+            None => (start, end),
+        };
+        delta += edit_delta;
+        let new_end = (changed_end as i64 + edit_delta) as u32;
+        changed = Some(Change {
+            start: changed_start,
+            old_end: (new_end as i64 - delta) as u32,
+            new_end,
+        });
+    }
+    changed
+}
+
+/// Statements of a block that are parsed again
+#[derive(Debug)]
+struct Region {
+    /// Index of the statement and of its block for each block around the region
+    path: Vec<(usize, usize)>,
+    /// Indexes of the statements in the innermost block
+    statements: Range<usize>,
+    /// Start of the line of the first statement
+    start: u32,
+    /// Start of the statement after the region in the old source. `None` when
+    /// the region reaches the end of the module.
+    stop: Option<u32>,
+    /// Indentation of the blocks around the region
+    indent_stack: Vec<usize>,
+}
+
+/// Parses the region around the change. Syntax errors of the old source are
+/// kept only when they all come after the region: statements recovered from
+/// syntax errors do not end where the grammar says they end, so the region
+/// cannot start after one of them, and an error at the end of the region may
+/// be about the statements in it. Otherwise the whole source is parsed again.
+fn reparse_region(
+    source: &str,
+    new_source: &str,
+    module: &mut Module,
+    errors: &mut [ParsingError],
+    change: Change,
+    options: ParserOptions,
+) -> bool {
+    let Some(region) = find_region(source, &module.body, change, vec![], vec![0]) else {
+        return false;
+    };
+    let after_region = |error: &ParsingError| {
+        region
+            .stop
+            .is_some_and(|stop| error.span().0 > stop as usize)
+    };
+    if !errors.iter().all(after_region) {
+        return false;
+    }
+    let delta = change.delta();
+    let new_stop = region.stop.map(|stop| (stop as i64 + delta) as u32);
+    let mut parser = Parser::new_at(
+        new_source,
+        region.start,
+        region.indent_stack.clone(),
+        options,
+    );
+    let Some(Module {
+        node,
+        body: statements,
+    }) = parser.parse_region(new_stop)
+    else {
+        return false;
+    };
+
+    match region.stop {
+        Some(stop) => {
+            module.shift(stop, delta);
+            for error in errors.iter_mut() {
+                let ParsingError::InvalidSyntax { span, .. } = error;
+                span.0 = (span.0 as i64 + delta) as usize;
+            }
+        }
+        // The module ends at its last token, which is before the region when
+        // nothing but comments is left in it
+        None if region.start > 0 && node.end < region.start => return false,
+        None => module.node.end = node.end,
+    }
+    // The module starts at its first token
+    if region.start == 0 {
+        module.node.start = node.start;
+    }
+    let mut body = &mut module.body;
+    for &(statement, block) in &region.path {
+        body = blocks_mut(&mut body[statement]).swap_remove(block);
+    }
+    body.splice(region.statements, statements);
+    true
+}
+
+/// Finds the statements of the innermost block that must be parsed again.
+/// The statement after them must not be changed, it is where parsing stops.
+/// Only the statements of the module can be parsed until the end of the source.
+fn find_region(
+    source: &str,
+    body: &[Statement],
+    change: Change,
+    path: Vec<(usize, usize)>,
+    indent_stack: Vec<usize>,
+) -> Option<Region> {
+    let is_module = path.is_empty();
+    // The first statement that ends at or after the change
+    let mut first = body.partition_point(|s| s.get_node().end < change.start);
+
+    if let Some(statement) = body.get(first) {
+        for (index, block) in blocks(statement).into_iter().enumerate() {
+            let (Some(block_first), Some(block_last)) = (block.first(), block.last()) else {
+                continue;
+            };
+            if block_first.get_node().start > change.start
+                || block_last.get_node().end < change.old_end
+            {
+                continue;
+            }
+            let Some(indent) = indentation(source, block_first.get_node().start) else {
+                continue;
+            };
+            let mut path = path.clone();
+            path.push((first, index));
+            let mut indent_stack = indent_stack.clone();
+            indent_stack.push(indent);
+            if let Some(region) = find_region(source, block, change, path, indent_stack) {
+                return Some(region);
+            }
+        }
+    }
+
+    // Include the statements before the change until one starts a line. A
+    // compound statement ends at the next logical line, so the region cannot
+    // start right after one.
+    let start = loop {
+        if let Some(start) = body
+            .get(first)
+            .and_then(|s| line_start(source, s.get_node().start))
+            .filter(|start| *start <= change.start)
+        {
+            if first == 0 || blocks(&body[first - 1]).is_empty() {
+                break start;
+            }
+        }
+        if first > 0 {
+            first -= 1;
+        } else if is_module {
+            break 0;
+        } else {
+            return None;
+        }
+    };
+    // The statement after the region must start after the change
+    let last = body
+        .partition_point(|s| s.get_node().start <= change.old_end)
+        .max(first + 1)
+        .min(body.len());
+    let stop = match body.get(last) {
+        Some(statement) => Some(statement.get_node().start),
+        None if is_module => None,
+        None => return None,
+    };
+
+    Some(Region {
+        path,
+        statements: first..last,
+        start,
+        stop,
+        indent_stack,
+    })
+}
+
+/// The start of the line if only indentation is before `offset` on its line
+fn line_start(source: &str, offset: u32) -> Option<u32> {
+    let before = &source.as_bytes()[..offset as usize];
+    let indent = before
+        .iter()
+        .rev()
+        .take_while(|c| matches!(c, b' ' | b'\t' | b'\x0c'))
+        .count();
+    let start = before.len() - indent;
+    match before[..start] {
+        [] => Some(0),
+        // A backslash continues the previous line
+        [.., b'\\', b'\n' | b'\r'] | [.., b'\\', b'\r', b'\n'] => None,
+        [.., b'\n' | b'\r'] => Some(start as u32),
+        _ => None,
+    }
+}
+
+/// Width of the indentation before `offset`, counted like the lexer does
+fn indentation(source: &str, offset: u32) -> Option<usize> {
+    let start = line_start(source, offset)?;
+    let width = source[start as usize..offset as usize]
+        .chars()
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum();
+    Some(width)
+}
+
+/// The indented blocks of a compound statement
+fn blocks(statement: &Statement) -> Vec<&Vec<Statement>> {
+    match statement {
+        Statement::IfStatement(s) => vec![&s.body, &s.orelse],
+        Statement::WhileStatement(s) => vec![&s.body, &s.orelse],
+        Statement::ForStatement(s) => vec![&s.body, &s.orelse],
+        Statement::AsyncForStatement(s) => vec![&s.body, &s.orelse],
+        Statement::WithStatement(s) => vec![&s.body],
+        Statement::AsyncWithStatement(s) => vec![&s.body],
+        Statement::TryStatement(s) => try_blocks(&s.body, &s.handlers, &s.orelse, &s.finalbody),
+        Statement::TryStarStatement(s) => try_blocks(&s.body, &s.handlers, &s.orelse, &s.finalbody),
+        Statement::FunctionDef(s) => vec![&s.body],
+        Statement::AsyncFunctionDef(s) => vec![&s.body],
+        Statement::ClassDef(s) => vec![&s.body],
+        Statement::MatchStmt(s) => s.cases.iter().map(|c| &c.body).collect(),
+        _ => vec![],
+    }
+}
+
+fn try_blocks<'a>(
+    body: &'a Vec<Statement>,
+    handlers: &'a [ExceptHandler],
+    orelse: &'a Vec<Statement>,
+    finalbody: &'a Vec<Statement>,
+) -> Vec<&'a Vec<Statement>> {
+    let mut blocks = vec![body];
+    blocks.extend(handlers.iter().map(|h| &h.body));
+    blocks.extend([orelse, finalbody]);
+    blocks
+}
+
+/// Same as `blocks` but mutable
+fn blocks_mut(statement: &mut Statement) -> Vec<&mut Vec<Statement>> {
+    match statement {
+        Statement::IfStatement(s) => vec![&mut s.body, &mut s.orelse],
+        Statement::WhileStatement(s) => vec![&mut s.body, &mut s.orelse],
+        Statement::ForStatement(s) => vec![&mut s.body, &mut s.orelse],
+        Statement::AsyncForStatement(s) => vec![&mut s.body, &mut s.orelse],
+        Statement::WithStatement(s) => vec![&mut s.body],
+        Statement::AsyncWithStatement(s) => vec![&mut s.body],
+        Statement::TryStatement(s) => try_blocks_mut(
+            &mut s.body,
+            &mut s.handlers,
+            &mut s.orelse,
+            &mut s.finalbody,
+        ),
+        Statement::TryStarStatement(s) => try_blocks_mut(
+            &mut s.body,
+            &mut s.handlers,
+            &mut s.orelse,
+            &mut s.finalbody,
+        ),
+        Statement::FunctionDef(s) => vec![&mut Arc::make_mut(s).body],
+        Statement::AsyncFunctionDef(s) => vec![&mut Arc::make_mut(s).body],
+        Statement::ClassDef(s) => vec![&mut Arc::make_mut(s).body],
+        Statement::MatchStmt(s) => s.cases.iter_mut().map(|c| &mut c.body).collect(),
+        _ => vec![],
+    }
+}
+
+fn try_blocks_mut<'a>(
+    body: &'a mut Vec<Statement>,
+    handlers: &'a mut [ExceptHandler],
+    orelse: &'a mut Vec<Statement>,
+    finalbody: &'a mut Vec<Statement>,
+) -> Vec<&'a mut Vec<Statement>> {
+    let mut blocks = vec![body];
+    blocks.extend(handlers.iter_mut().map(|h| &mut h.body));
+    blocks.extend([orelse, finalbody]);
+    blocks
+}
+
+/// Moves the offsets at or after `from` by `delta`
+trait Shift {
+    fn shift(&mut self, from: u32, delta: i64);
+}
+
+impl Shift for Node {
+    fn shift(&mut self, from: u32, delta: i64) {
+        if self.start >= from {
+            self.start = (self.start as i64 + delta) as u32;
+        }
+        if self.end >= from {
+            self.end = (self.end as i64 + delta) as u32;
+        }
+    }
+}
+
+impl<T: Shift> Shift for Vec<T> {
+    fn shift(&mut self, from: u32, delta: i64) {
+        for item in self {
+            item.shift(from, delta);
+        }
+    }
+}
+
+impl<T: Shift> Shift for Option<T> {
+    fn shift(&mut self, from: u32, delta: i64) {
+        if let Some(item) = self {
+            item.shift(from, delta);
+        }
+    }
+}
+
+impl<T: Shift> Shift for Box<T> {
+    fn shift(&mut self, from: u32, delta: i64) {
+        self.as_mut().shift(from, delta);
+    }
+}
+
+impl<T: Shift + Clone> Shift for Arc<T> {
+    fn shift(&mut self, from: u32, delta: i64) {
+        Arc::make_mut(self).shift(from, delta);
+    }
+}
+
+macro_rules! shift_variants {
+    ($value:expr, $from:expr, $delta:expr, $ty:ident { $($variant:ident),* $(,)? }) => {
+        match $value {
+            $($ty::$variant(inner) => inner.shift($from, $delta),)*
+        }
+    };
+}
+
+macro_rules! impl_shift_enum {
+    ($($ty:ident $variants:tt)*) => {
+        $(
+            impl Shift for $ty {
+                fn shift(&mut self, from: u32, delta: i64) {
+                    shift_variants!(self, from, delta, $ty $variants)
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_shift_struct {
+    ($($ty:ident { $($field:ident),* $(,)? })*) => {
+        $(
+            impl Shift for $ty {
+                fn shift(&mut self, from: u32, delta: i64) {
+                    $(self.$field.shift(from, delta);)*
+                }
+            }
+        )*
+    };
+}
+
+impl Shift for Statement {
+    fn shift(&mut self, from: u32, delta: i64) {
+        // Nothing to move in statements before the change
+        if self.get_node().end < from {
+            return;
+        }
+        shift_variants!(
+            self,
+            from,
+            delta,
+            Statement {
+                AssignStatement,
+                AnnAssignStatement,
+                AugAssignStatement,
+                ExpressionStatement,
+                Assert,
+                Pass,
+                Delete,
+                ReturnStmt,
+                Raise,
+                BreakStmt,
+                ContinueStmt,
+                Import,
+                ImportFrom,
+                Global,
+                Nonlocal,
+                IfStatement,
+                WhileStatement,
+                ForStatement,
+                AsyncForStatement,
+                WithStatement,
+                AsyncWithStatement,
+                TryStatement,
+                TryStarStatement,
+                FunctionDef,
+                AsyncFunctionDef,
+                ClassDef,
+                MatchStmt,
+                TypeAlias,
+                Invalid,
+            }
+        )
+    }
+}
+
+impl_shift_enum! {
+    Expression {
+        Constant,
+        List,
+        Tuple,
+        Dict,
+        Set,
+        Name,
+        BoolOp,
+        UnaryOp,
+        BinOp,
+        NamedExpr,
+        Yield,
+        YieldFrom,
+        Starred,
+        Generator,
+        ListComp,
+        SetComp,
+        DictComp,
+        Attribute,
+        Subscript,
+        Slice,
+        Call,
+        Await,
+        Compare,
+        Lambda,
+        IfExp,
+        JoinedStr,
+        FormattedValue,
+        Invalid,
+    }
+    MatchPattern {
+        MatchValue,
+        MatchSingleton,
+        MatchSequence,
+        MatchStar,
+        MatchMapping,
+        MatchAs,
+        MatchClass,
+        MatchOr,
+    }
+    TypeParam {
+        TypeVar,
+        ParamSpec,
+        TypeVarTuple,
+    }
+}
+
+impl_shift_struct! {
+    Module { node, body }
+    Invalid { node }
+    Assign { node, targets, value }
+    AnnAssign { node, target, annotation, value }
+    AugAssign { node, target, value }
+    Assert { node, test, msg }
+    Pass { node }
+    Delete { node, targets }
+    Return { node, value }
+    Raise { node, exc, cause }
+    Break { node }
+    Continue { node }
+    Import { node, names }
+    Alias { node }
+    ImportFrom { node, names }
+    Global { node }
+    Nonlocal { node }
+    Name { node }
+    Constant { node }
+    List { node, elements }
+    Tuple { node, elements }
+    Dict { node, keys, values }
+    Set { node, elements }
+    BoolOperation { node, values }
+    UnaryOperation { node, operand }
+    BinOp { node, left, right }
+    NamedExpression { node, target, value }
+    Yield { node, value }
+    YieldFrom { node, value }
+    Starred { node, value }
+    Generator { node, element, generators }
+    ListComp { node, element, generators }
+    SetComp { node, element, generators }
+    DictComp { node, key, value, generators }
+    Comprehension { node, target, iter, ifs }
+    Attribute { node, value }
+    Subscript { node, value, slice }
+    Slice { node, lower, upper, step }
+    Call { node, func, args, keywords, starargs, kwargs }
+    Keyword { node, value }
+    Await { node, value }
+    Compare { node, left, comparators }
+    Lambda { node, args, body }
+    Arguments { node, posonlyargs, args, vararg, kwonlyargs, kw_defaults, kwarg, defaults }
+    Arg { node, annotation }
+    IfExp { node, test, body, orelse }
+    FormattedValue { node, value, format_spec }
+    JoinedStr { node, values }
+    If { node, test, body, orelse }
+    While { node, test, body, orelse }
+    For { node, target, iter, body, orelse }
+    AsyncFor { node, target, iter, body, orelse }
+    With { node, items, body }
+    AsyncWith { node, items, body }
+    WithItem { node, context_expr, optional_vars }
+    Try { node, body, handlers, orelse, finalbody }
+    TryStar { node, body, handlers, orelse, finalbody }
+    ExceptHandler { node, typ, body }
+    FunctionDef { node, args, body, decorator_list, returns, type_params }
+    AsyncFunctionDef { node, args, body, decorator_list, returns, type_params }
+    ClassDef { node, bases, keywords, body, decorator_list, type_params }
+    Match { node, subject, cases }
+    MatchCase { node, pattern, guard, body }
+    MatchValue { node, value }
+    MatchAs { node, pattern }
+    MatchMapping { node, keys, patterns }
+    MatchClass { node, cls, patterns, kwd_patterns }
+    TypeVar { node, bound }
+    ParamSpec { node }
+    TypeVarTuple { node }
+    TypeAlias { node, type_params, value }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, panic::catch_unwind};
+
+    use insta::glob;
+
+    use super::*;
+
+    fn parse(source: &str) -> Option<(Module, Vec<ParsingError>)> {
+        catch_unwind(|| Parser::new(source).parse()).ok()
+    }
+
+    /// Checks that the edits give the same tree as parsing the new source
+    fn assert_reparse(source: &str, edits: &[TextEdit]) {
+        let mut new_source = source.to_string();
+        for edit in edits {
+            edit.apply(&mut new_source);
+        }
+        // Reparsing falls back to parsing the whole source, so a panic of the
+        // full parse is a bug of the parser and not of reparsing. Any other
+        // panic fails the test.
+        let (Some((module, errors)), Some((expected_module, expected_errors))) =
+            (parse(source), parse(&new_source))
+        else {
+            return;
+        };
+        let (reparsed_source, module, errors) = reparse(source, module, errors, edits);
+        assert_eq!(reparsed_source, new_source);
+        assert_eq!(
+            format!("{module:#?}{errors:#?}"),
+            format!("{expected_module:#?}{expected_errors:#?}"),
+            "source: {source:?}\nedits: {edits:?}"
+        );
+    }
+
+    #[test]
+    fn test_reparse_matches_parse() {
+        glob!("../test_data", "inputs/*.py", |path| {
+            let source = fs::read_to_string(path).unwrap();
+            let boundaries: Vec<u32> = (0..=source.len())
+                .filter(|i| source.is_char_boundary(*i))
+                .map(|i| i as u32)
+                .collect();
+            for (i, &offset) in boundaries.iter().enumerate().step_by(17) {
+                let next = boundaries.get(i + 1).copied().unwrap_or(offset);
+                for text in ["x", "\n", "    ", "(", ":", "#", "\"\"\""] {
+                    assert_reparse(&source, &[TextEdit::new(offset, offset, text)]);
+                }
+                assert_reparse(&source, &[TextEdit::new(offset, next, "")]);
+            }
+        });
+    }
+
+    #[test]
+    fn test_reparse_changed_statements() {
+        let source = "def f():
+    return 1
+
+class A:
+    x = 1
+
+    def g(self):
+        a = 1
+        return a
+
+    y = 2
+";
+        let (module, errors) = Parser::new(source).parse();
+        let Statement::FunctionDef(f) = &module.body[0] else {
+            panic!("expected a function");
+        };
+        let f = f.clone();
+        let a = source.find("a = 1").unwrap() as u32;
+        let edits = [
+            TextEdit::new(a, a + 1, "value"),
+            TextEdit::new(a + 5, a + 5, "\n        b = a"),
+        ];
+        let (new_source, module, errors) = reparse(source, module, errors, &edits);
+        assert!(errors.is_empty());
+
+        let (expected, _) = Parser::new(&new_source).parse();
+        assert_eq!(format!("{module:?}"), format!("{expected:?}"));
+        // Statements before the change are not parsed again
+        let Statement::FunctionDef(new_f) = &module.body[0] else {
+            panic!("expected a function");
+        };
+        assert!(Arc::ptr_eq(&f, new_f));
+    }
+
+    #[test]
+    fn test_find_region() {
+        let source = "x = 1
+if x:
+    y = 2
+    z = 3
+w = 4
+";
+        let (module, _) = Parser::new(source).parse();
+        let region = |start: u32, end: u32| {
+            let change = Change {
+                start,
+                old_end: end,
+                new_end: end,
+            };
+            let region = find_region(source, &module.body, change, vec![], vec![0]).unwrap();
+            (region.path, region.statements, region.start, region.stop)
+        };
+        // Inside `y = 2`, the block of the if statement is parsed until `z`
+        assert_eq!(region(16, 17), (vec![(1, 0)], 0..1, 12, Some(26)));
+        // Inside `z = 3`, the last statement of the block
+        assert_eq!(region(26, 27), (vec![], 1..2, 6, Some(32)));
+        // Inside `w = 4`, the if statement before it ends at `w`
+        assert_eq!(region(32, 33), (vec![], 1..3, 6, None));
+        // At the start of `x = 1`
+        assert_eq!(region(0, 0), (vec![], 0..1, 0, Some(6)));
+    }
+
+    #[test]
+    fn test_reparse_with_syntax_errors() {
+        let source = "def f():
+    return 1
+
+w = 0
+x = 1
+y = (
+";
+        let (module, errors) = Parser::new(source).parse();
+        assert!(!errors.is_empty());
+        let x = source.find("x = 1").unwrap() as u32;
+        let edits = [TextEdit::new(x + 4, x + 5, "10")];
+        assert_reparse(source, &edits);
+
+        // The error comes after the change, the statements before it are kept
+        let Statement::FunctionDef(f) = &module.body[0] else {
+            panic!("expected a function");
+        };
+        let f = f.clone();
+        let (_, module, errors) = reparse(source, module, errors, &edits);
+        let Statement::FunctionDef(new_f) = &module.body[0] else {
+            panic!("expected a function");
+        };
+        assert!(Arc::ptr_eq(&f, new_f));
+        assert_eq!(errors.len(), 1);
+
+        // The change is after the error, the whole source is parsed again
+        let source = "y = (
+def f():
+    return 1
+";
+        let r = source.find("return 1").unwrap() as u32 + 7;
+        let edits = [TextEdit::new(r, r + 1, "2")];
+        assert_reparse(source, &edits);
+        let (module, errors) = Parser::new(source).parse();
+        let Statement::FunctionDef(f) = module.body[1].clone() else {
+            panic!("expected a function");
+        };
+        let (_, module, _) = reparse(source, module, errors, &edits);
+        let Statement::FunctionDef(new_f) = &module.body[1] else {
+            panic!("expected a function");
+        };
+        assert!(!Arc::ptr_eq(&f, new_f));
+    }
+}
@@ -1,14 +1,30 @@
 use std::fmt::{Debug, Display};
 
 use fxhash::FxHashMap;
-use serde::Serialize;
 use std::mem;
 
 use crate::parser::parser::intern_lookup;
 
-#[derive(Clone, Copy, Eq, PartialEq, Hash, Serialize)]
+#[derive(Clone, Copy, Eq, PartialEq, Hash)]
 pub struct StrId(pub u32);
 
+/// The ids are only valid in the process that interned them, so the string is
+/// serialized and interned again when it is deserialized
+#[cfg(feature = "serde")]
+impl serde::Serialize for StrId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(intern_lookup(*self))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for StrId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = <std::borrow::Cow<str>>::deserialize(deserializer)?;
+        Ok(crate::parser::parser::intern(&name))
+    }
+}
+
 impl Debug for StrId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", intern_lookup(*self))
@@ -1,8 +1,7 @@
 use unicode_id_start::{is_id_continue, is_id_start};
 
 use crate::{
-    error::LexError,
-    get_row_col_position,
+    error::{LexDiagnostic, LexError},
     token::{Kind, Token},
 };
 
@@ -35,7 +34,7 @@ impl StringQuotation {
 
 #[derive(Debug, Clone, Copy)]
 enum TokenizationMode {
-    Fstring((u8, StringQuotation)),
+    Fstring { quote: StringQuotation, raw: bool },
     FstringFormatSpecifier,
     PythonWithinFstring(u8),
 }
@@ -49,6 +48,8 @@ pub struct Lexer<'a> {
     current_line: u16,
     /// Array of all line starts offsets. Starts from line 0
     pub line_starts: Vec<u32>,
+    /// Errors found so far. Invalid tokens are returned as `Kind::Error`
+    pub errors: Vec<LexDiagnostic>,
 
     /// Keeps track of whether the lexer is at the start of a line
     start_of_line: bool,
@@ -78,11 +79,25 @@ impl<'a> Lexer<'a> {
             tokenization_mode_stack: vec![],
             next_token_is_dedent: 0,
             line_starts: vec![0],
+            errors: vec![],
             peek_mode: false,
             non_logical_line_state: true,
         }
     }
 
+    /// Creates a lexer that starts at `offset`, which must be the start of a
+    /// line outside of any brackets. `indent_stack` is the indentation of the
+    /// blocks enclosing that line. Line numbers are not known, so
+    /// `line_starts` only holds the lines from `offset` on.
+    pub(crate) fn new_at(source: &'a str, offset: u32, indent_stack: Vec<usize>) -> Self {
+        Self {
+            current: offset,
+            indent_stack,
+            line_starts: vec![offset],
+            ..Self::new(source)
+        }
+    }
+
     pub fn lex(&mut self) -> Vec<Token> {
         let mut tokens = vec![];
         loop {
@@ -111,12 +126,9 @@ impl<'a> Lexer<'a> {
 
         let kind = match self.next_kind() {
             Ok(kind) => kind,
-            Err(e) => {
-                println!(
-                    "position {:?}",
-                    get_row_col_position(start, self.current, &self.line_starts)
-                );
-                panic!("Invalid token {e}");
+            Err(error) => {
+                self.add_error(error, start, self.current);
+                Kind::Error
             }
         };
 
@@ -124,7 +136,7 @@ impl<'a> Lexer<'a> {
             kind,
             Kind::RightParen | Kind::RightBrace | Kind::RightBracket
         ) {
-            self.nesting -= 1;
+            self.nesting = self.nesting.saturating_sub(1);
         } else if matches!(kind, Kind::LeftParen | Kind::LeftBrace | Kind::LeftBracket) {
             self.nesting += 1;
         }
@@ -165,9 +177,36 @@ impl<'a> Lexer<'a> {
         token
     }
 
+    /// Moves the lexer back to the start of the line containing `offset` and
+    /// forgets any open brackets. Used by the parser to recover from unclosed
+    /// brackets so that indentation is tracked again from that line.
+    pub(crate) fn restart_line_at(&mut self, offset: u32) {
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(line) => line.saturating_sub(1),
+        };
+        self.line_starts.truncate(line + 1);
+        self.current = self.line_starts[line];
+        let current = self.current;
+        self.errors.retain(|error| error.start < current);
+        self.current_line = (line + 1) as u16;
+        self.nesting = 0;
+        self.start_of_line = true;
+        self.next_token_is_dedent = 0;
+        self.tokenization_mode_stack.clear();
+        self.non_logical_line_state = true;
+    }
+
+    /// Errors are only recorded once, not again when the token is peeked
+    fn add_error(&mut self, error: LexError, start: u32, end: u32) {
+        if !self.peek_mode {
+            self.errors.push(LexDiagnostic { error, start, end });
+        }
+    }
+
     fn next_kind(&mut self) -> Result<Kind, LexError> {
         if self.start_of_line && self.nesting == 0 {
-            if let Some(indent_kind) = self.match_indentation()? {
+            if let Some(indent_kind) = self.match_indentation() {
                 self.start_of_line = false;
                 return Ok(indent_kind);
             }
@@ -175,21 +214,28 @@ impl<'a> Lexer<'a> {
 
         if let Some(mode) = self.tokenization_mode_stack.last() {
             match mode {
-                TokenizationMode::Fstring((fstring_nesting, fstrin_ending)) => {
-                    return Ok(self.next_fstring_token(*fstrin_ending, *fstring_nesting))
+                TokenizationMode::Fstring { quote, raw } => {
+                    return self.next_fstring_token(*quote, *raw)
                 }
                 TokenizationMode::FstringFormatSpecifier => {
                     let mut read_chars = 0;
-                    while self.peek() != Some('}')
-                        && self.peek() != Some('{')
-                        && self.peek() != Some('\n')
-                    {
+                    while !matches!(self.peek(), Some('{' | '}' | '\n') | None) {
                         self.next();
                         read_chars += 1;
                     }
                     if read_chars > 0 {
                         return Ok(Kind::FStringMiddle);
-                    } else if !self.peek_mode {
+                    }
+                    // A replacement field nested in the format specifier, e.g. `{x:>{width}}`
+                    if self.peek() == Some('{') {
+                        self.next();
+                        if !self.peek_mode {
+                            self.tokenization_mode_stack
+                                .push(TokenizationMode::PythonWithinFstring(self.nesting + 1));
+                        }
+                        return Ok(Kind::LeftBracket);
+                    }
+                    if !self.peek_mode {
                         self.tokenization_mode_stack.pop();
                     }
                 }
@@ -340,18 +386,22 @@ impl<'a> Lexer<'a> {
                     return Ok(Kind::LeftBracket);
                 }
                 '}' => {
-                    if let Some(mode) = self.tokenization_mode_stack.last() {
-                        if matches!(mode, TokenizationMode::PythonWithinFstring(_)) {
-                            if !self.peek_mode {
-                                self.tokenization_mode_stack.pop();
-                            }
-                            return Ok(Kind::RightBracket);
+                    // Only the brace that closes the replacement field ends it,
+                    // not the one of a dict or set inside of it
+                    if let Some(TokenizationMode::PythonWithinFstring(i)) =
+                        self.tokenization_mode_stack.last()
+                    {
+                        if self.nesting == *i && !self.peek_mode {
+                            self.tokenization_mode_stack.pop();
                         }
                     }
                     return Ok(Kind::RightBracket);
                 }
                 ',' => return Ok(Kind::Comma),
                 '.' => {
+                    if let Some('0'..='9') = self.peek() {
+                        return Ok(self.match_fraction());
+                    }
                     if let Some('.') = self.peek() {
                         if let Some('.') = self.double_peek() {
                             self.double_next();
@@ -378,7 +428,20 @@ impl<'a> Lexer<'a> {
                     }
                     return Ok(Kind::Comment);
                 }
-                '\\' => return Ok(Kind::BackSlash),
+                '\\' => match self.peek() {
+                    // Explicit line joining, the next line continues the logical line
+                    Some('\n' | '\r') => {
+                        if self.peek() == Some('\r') {
+                            self.next();
+                        }
+                        if self.peek() == Some('\n') {
+                            self.next();
+                        }
+                        self.current_line += 1;
+                        return Ok(Kind::WhiteSpace);
+                    }
+                    _ => return Ok(Kind::BackSlash),
+                },
                 '$' => return Ok(Kind::Dollar),
                 '?' => return Ok(Kind::QuestionMark),
                 '`' => return Ok(Kind::BackTick),
@@ -434,118 +497,72 @@ impl<'a> Lexer<'a> {
     }
 
     // https://peps.python.org/pep-0701/#how-to-produce-these-new-tokens
-    fn next_fstring_token(&mut self, str_finisher: StringQuotation, _fstring_nesting: u8) -> Kind {
+    fn next_fstring_token(&mut self, quote: StringQuotation, raw: bool) -> Result<Kind, LexError> {
         let mut read_chars = false;
-        let mut last_read_char: Option<char> = None;
         loop {
-            let peeked_char = self.peek();
-            let double_peek = self.double_peek();
-            if peeked_char == Some('{') && peeked_char == double_peek {
-                self.next();
-                self.next();
-                read_chars = true;
-                continue;
-            }
-            if peeked_char == Some('{') && peeked_char != double_peek {
+            if self.at_fstring_end(quote) {
                 if read_chars {
-                    if !self.peek_mode {
-                        self.tokenization_mode_stack
-                            .push(TokenizationMode::PythonWithinFstring(self.nesting + 1));
-                    }
-                    return Kind::FStringMiddle;
-                } else {
-                    if !self.peek_mode {
-                        self.tokenization_mode_stack
-                            .push(TokenizationMode::PythonWithinFstring(self.nesting + 1));
+                    return Ok(Kind::FStringMiddle);
+                }
+                match quote {
+                    StringQuotation::Single | StringQuotation::Double => self.next(),
+                    StringQuotation::TripleSingle | StringQuotation::TripleDouble => {
+                        self.double_next();
+                        self.next()
                     }
-                    self.next();
-                    return Kind::LeftBracket;
+                };
+                if !self.peek_mode {
+                    let last = self.tokenization_mode_stack.pop();
+                    assert!(matches!(last, Some(TokenizationMode::Fstring { .. })))
                 }
-            }
-            if last_read_char != Some('\\')
-                && self.peek() == Some('\'')
-                && read_chars
-                && str_finisher == StringQuotation::Single
-            {
-                return Kind::FStringMiddle;
-            }
-            if last_read_char != Some('\\')
-                && self.peek() == Some('"')
-                && read_chars
-                && str_finisher == StringQuotation::Double
-            {
-                return Kind::FStringMiddle;
+                return Ok(Kind::FStringEnd);
             }
 
-            let Some(curr) = self.next() else {
-                panic!("eof while parsing fstring")
+            let Some(c) = self.peek() else {
+                if !self.peek_mode {
+                    self.tokenization_mode_stack.pop();
+                }
+                return Err(LexError::StringNotTerminated);
             };
-            read_chars = true;
-
-            match str_finisher {
-                StringQuotation::Single => {
-                    if curr != '\\' && self.peek() == Some('\'') {
-                        return Kind::FStringMiddle;
-                    }
-                    if last_read_char != Some('\\') && curr == '\'' {
-                        if !self.peek_mode {
-                            let last = self.tokenization_mode_stack.pop();
-                            assert!(matches!(last, Some(TokenizationMode::Fstring(_))))
-                        }
-                        return Kind::FStringEnd;
-                    }
+            match c {
+                // `{{` and `}}` are escaped braces
+                '{' | '}' if self.double_peek() == Some(c) => {
+                    self.double_next();
                 }
-                StringQuotation::Double => {
-                    if self.peek() == Some('"') {
-                        return Kind::FStringMiddle;
+                '{' => {
+                    if read_chars {
+                        return Ok(Kind::FStringMiddle);
                     }
-                    if curr == '"' {
-                        if !self.peek_mode {
-                            let last = self.tokenization_mode_stack.pop();
-                            assert!(matches!(last, Some(TokenizationMode::Fstring(_))))
-                        }
-                        return Kind::FStringEnd;
+                    self.next();
+                    if !self.peek_mode {
+                        self.tokenization_mode_stack
+                            .push(TokenizationMode::PythonWithinFstring(self.nesting + 1));
                     }
+                    return Ok(Kind::LeftBracket);
                 }
-                StringQuotation::TripleSingle => {
-                    if self.peek() == Some('\'')
-                        && self.peek() == self.double_peek()
-                        && self.peek() == self.triple_peek()
-                    {
-                        return Kind::FStringMiddle;
-                    }
-
-                    if curr == '\''
-                        && self.peek() == Some(curr)
-                        && self.peek() == self.double_peek()
-                    {
-                        if !self.peek_mode {
-                            let last = self.tokenization_mode_stack.pop();
-                            assert!(matches!(last, Some(TokenizationMode::Fstring(_))))
+                '\\' => {
+                    self.next();
+                    match self.peek() {
+                        // The braces of a named unicode character are not a replacement field
+                        Some('N') if !raw && self.double_peek() == Some('{') => {
+                            while let Some(c) = self.next() {
+                                if c == '}' {
+                                    break;
+                                }
+                            }
                         }
-                        self.double_next();
-                        return Kind::FStringEnd;
-                    }
-                }
-                StringQuotation::TripleDouble => {
-                    if self.peek() == Some('\"')
-                        && self.peek() == self.double_peek()
-                        && self.peek() == self.triple_peek()
-                    {
-                        return Kind::FStringMiddle;
-                    }
-                    if curr == '"' && self.peek() == Some(curr) && self.peek() == self.double_peek()
-                    {
-                        if !self.peek_mode {
-                            let last = self.tokenization_mode_stack.pop();
-                            assert!(matches!(last, Some(TokenizationMode::Fstring(_))))
+                        // A backslash does not escape the brace of a replacement field
+                        Some('{') | None => {}
+                        Some(_) => {
+                            self.next();
                         }
-                        self.double_next();
-                        return Kind::FStringEnd;
                     }
                 }
+                _ => {
+                    self.next();
+                }
             }
-            last_read_char = Some(curr);
+            read_chars = true;
         }
     }
 
@@ -596,14 +613,7 @@ impl<'a> Lexer<'a> {
                 Some('f') | Some('F') => match self.double_peek() {
                     Some(str_start @ '"') | Some(str_start @ '\'') => {
                         self.double_next();
-                        let fstring_start = self.f_string_quote_count(str_start);
-                        if !self.peek_mode {
-                            self.tokenization_mode_stack
-                                .push(TokenizationMode::Fstring((
-                                    self.nesting,
-                                    StringQuotation::new(str_start, fstring_start),
-                                )));
-                        }
+                        self.start_fstring(str_start, true);
                         return Ok(Some(Kind::RawFStringStart));
                     }
                     _ => {}
@@ -635,28 +645,14 @@ impl<'a> Lexer<'a> {
                 Some('r') | Some('R') => match self.double_peek() {
                     Some(str_start @ '"') | Some(str_start @ '\'') => {
                         self.double_next();
-                        let fstring_start = self.f_string_quote_count(str_start);
-                        if !self.peek_mode {
-                            self.tokenization_mode_stack
-                                .push(TokenizationMode::Fstring((
-                                    self.nesting,
-                                    StringQuotation::new(str_start, fstring_start),
-                                )));
-                        }
+                        self.start_fstring(str_start, true);
                         return Ok(Some(Kind::RawFStringStart));
                     }
                     _ => {}
                 },
                 Some(str_start @ '"') | Some(str_start @ '\'') => {
                     self.next();
-                    let fstring_start = self.f_string_quote_count(str_start);
-                    if !self.peek_mode {
-                        self.tokenization_mode_stack
-                            .push(TokenizationMode::Fstring((
-                                self.nesting,
-                                StringQuotation::new(str_start, fstring_start),
-                            )));
-                    }
+                    self.start_fstring(str_start, false);
                     return Ok(Some(Kind::FStringStart));
                 }
                 _ => {}
@@ -769,7 +765,16 @@ impl<'a> Lexer<'a> {
                 }
             }
         } else {
-            while let Some(c) = self.next() {
+            while let Some(c) = self.peek() {
+                // Strings that are not triple quoted end at the end of the line
+                if matches!(c, '\n' | '\r') && last_read_char != '\\' {
+                    break;
+                }
+                self.next();
+                // A backslash followed by \r\n escapes both of them
+                if c == '\r' && self.peek() == Some('\n') {
+                    continue;
+                }
                 // Two consecutive backslashes cancel out
                 if c == str_start && last_read_char != '\\' {
                     string_terminated = true;
@@ -804,6 +809,10 @@ impl<'a> Lexer<'a> {
                         '_' => {
                             self.next();
                         }
+                        '2'..='9' => {
+                            self.consume_invalid_literal();
+                            return Err(LexError::InvalidDigitInBinaryLiteral(c));
+                        }
                         _ => break,
                     }
                 }
@@ -819,6 +828,10 @@ impl<'a> Lexer<'a> {
                         '_' => {
                             self.next();
                         }
+                        '8' | '9' => {
+                            self.consume_invalid_literal();
+                            return Err(LexError::InvalidDigitInOctalLiteral(c));
+                        }
                         _ => break,
                     }
                 }
@@ -834,6 +847,10 @@ impl<'a> Lexer<'a> {
                         '_' => {
                             self.next();
                         }
+                        'g'..='z' | 'G'..='Z' => {
+                            self.consume_invalid_literal();
+                            return Err(LexError::InvalidDigitInHexadecimalLiteral(c));
+                        }
                         _ => break,
                     }
                 }
@@ -846,47 +863,8 @@ impl<'a> Lexer<'a> {
         while let Some(c) = self.peek() {
             match c {
                 '.' => {
-                    let mut has_exponent = false;
                     self.next();
-                    while let Some(c) = self.peek() {
-                        match c {
-                            '0'..='9' => {
-                                self.next();
-                            }
-                            '_' => {
-                                self.next();
-                            }
-                            'e' | 'E' => {
-                                has_exponent = true;
-                                self.next();
-                                match self.peek() {
-                                    Some('+') | Some('-') => {
-                                        self.next();
-                                    }
-                                    Some('0'..='9') => {
-                                        self.next();
-                                    }
-                                    _ => break,
-                                }
-                            }
-                            'j' | 'J' => {
-                                is_imaginary = true;
-                                self.next();
-                                break;
-                            }
-                            _ => break,
-                        }
-                    }
-                    if has_exponent {
-                        if is_imaginary {
-                            return Ok(Kind::ImaginaryExponentFloat);
-                        }
-                        return Ok(Kind::ExponentFloat);
-                    }
-                    if is_imaginary {
-                        return Ok(Kind::ImaginaryPointFloat);
-                    }
-                    return Ok(Kind::PointFloat);
+                    return Ok(self.match_fraction());
                 }
                 'e' | 'E' => {
                     self.next();
@@ -919,6 +897,10 @@ impl<'a> Lexer<'a> {
                 }
                 '_' => {
                     self.next();
+                    if !matches!(self.peek(), Some('0'..='9')) {
+                        self.consume_invalid_literal();
+                        return Err(LexError::InvalidDigitInDecimalLiteral);
+                    }
                 }
                 'j' | 'J' => {
                     is_imaginary = true;
@@ -936,8 +918,64 @@ impl<'a> Lexer<'a> {
         Ok(Kind::Integer)
     }
 
-    fn match_indentation(&mut self) -> Result<Option<Kind>, LexError> {
+    /// Skips the rest of an invalid number so it becomes a single error token
+    fn consume_invalid_literal(&mut self) {
+        while let Some(c) = self.peek() {
+            if !(c.is_ascii_alphanumeric() || c == '_') {
+                break;
+            }
+            self.next();
+        }
+    }
+
+    // Lexes the digits after the decimal point of a float, e.g. `1.5` or `.5e3`
+    fn match_fraction(&mut self) -> Kind {
+        let mut is_imaginary = false;
+        let mut has_exponent = false;
+        while let Some(c) = self.peek() {
+            match c {
+                '0'..='9' => {
+                    self.next();
+                }
+                '_' => {
+                    self.next();
+                }
+                'e' | 'E' => {
+                    has_exponent = true;
+                    self.next();
+                    match self.peek() {
+                        Some('+') | Some('-') => {
+                            self.next();
+                        }
+                        Some('0'..='9') => {
+                            self.next();
+                        }
+                        _ => break,
+                    }
+                }
+                'j' | 'J' => {
+                    is_imaginary = true;
+                    self.next();
+                    break;
+                }
+                _ => break,
+            }
+        }
+        if has_exponent {
+            if is_imaginary {
+                return Kind::ImaginaryExponentFloat;
+            }
+            return Kind::ExponentFloat;
+        }
+        if is_imaginary {
+            return Kind::ImaginaryPointFloat;
+        }
+        Kind::PointFloat
+    }
+
+    fn match_indentation(&mut self) -> Option<Kind> {
         use std::cmp::Ordering;
+        let start = self.current;
         let mut spaces_count = 0;
         while let Some(c) = self.peek() {
             match c {
@@ -965,9 +1003,9 @@ impl<'a> Lexer<'a> {
             // the indentation of "1" is completely inconsequential. To be technically correct,
             // we'll return a whiteSpace token if any amount of whitespace was found.
             if spaces_count > 0 {
-                return Ok(Some(Kind::WhiteSpace));
+                return Some(Kind::WhiteSpace);
             } else {
-                return Ok(None);
+                return None;
             }
         }
         if spaces_count == 0 {
@@ -983,11 +1021,11 @@ impl<'a> Lexer<'a> {
             // we should not consider it as dedent
             // Thanks python
             if self.peek() == Some('\n') && self.double_peek().is_some() {
-                return Ok(None);
+                return None;
             }
         }
         if self.peek() == Some('#') {
-            return Ok(Some(Kind::WhiteSpace));
+            return Some(Kind::WhiteSpace);
         }
         if let Some(top) = self.indent_stack.last() {
             match spaces_count.cmp(top) {
@@ -1002,8 +1040,13 @@ impl<'a> Lexer<'a> {
                             break;
                         }
                     }
+                    // Recover by closing the blocks that are indented more than this line
                     if !indentation_matches_outer_level {
-                        return Err(LexError::UnindentDoesNotMatchAnyOuterIndentationLevel);
+                        self.add_error(
+                            LexError::UnindentDoesNotMatchAnyOuterIndentationLevel,
+                            start,
+                            self.current,
+                        );
                     }
                     if !self.peek_mode {
                         let mut de_indents = 0;
@@ -1013,13 +1056,8 @@ impl<'a> Lexer<'a> {
                                     self.indent_stack.pop();
                                     de_indents += 1;
                                 }
-                                Ordering::Equal => break,
-                                // We only see a Kind::Dedent when the indentation level is less than the
-                                // top of the stack. So this should never happen and if it happens it's a
-                                // bug in code not an error for the user
-                                Ordering::Less => {
-                                    unreachable!()
-                                }
+                                // Less when the indentation does not match any outer level
+                                Ordering::Equal | Ordering::Less => break,
                             }
                         }
                         if de_indents > 1 {
@@ -1028,19 +1066,19 @@ impl<'a> Lexer<'a> {
                             self.next_token_is_dedent += de_indents - 1;
                         }
                     }
-                    Ok(Some(Kind::Dedent))
+                    Some(Kind::Dedent)
                 }
                 // Returning whitespace to ignore these spaces
-                Ordering::Equal => Ok(Some(Kind::WhiteSpace)),
+                Ordering::Equal => Some(Kind::WhiteSpace),
                 Ordering::Greater => {
                     if !self.peek_mode {
                         self.indent_stack.push(spaces_count);
                     }
-                    Ok(Some(Kind::Indent))
+                    Some(Kind::Indent)
                 }
             }
         } else {
-            Ok(None)
+            None
         }
     }
 
@@ -1052,6 +1090,35 @@ impl<'a> Lexer<'a> {
         }
         count
     }
+
+    /// Consumes the rest of the opening quotes after the first one
+    fn start_fstring(&mut self, str_start: char, raw: bool) {
+        let count = self.f_string_quote_count(str_start);
+        if !self.peek_mode {
+            self.tokenization_mode_stack
+                .push(TokenizationMode::Fstring {
+                    quote: StringQuotation::new(str_start, count),
+                    raw,
+                });
+        }
+    }
+
+    fn at_fstring_end(&self, quote: StringQuotation) -> bool {
+        match quote {
+            StringQuotation::Single => self.peek() == Some('\''),
+            StringQuotation::Double => self.peek() == Some('"'),
+            StringQuotation::TripleSingle => {
+                self.peek() == Some('\'')
+                    && self.double_peek() == Some('\'')
+                    && self.triple_peek() == Some('\'')
+            }
+            StringQuotation::TripleDouble => {
+                self.peek() == Some('"')
+                    && self.double_peek() == Some('"')
+                    && self.triple_peek() == Some('"')
+            }
+        }
+    }
 }
 
 fn match_whitespace(c: char) -> bool {
@@ -1416,15 +1483,102 @@ def",
     }
 
     #[test]
-    #[should_panic]
-    fn test_unterminated_string_double_quotes() {
+    fn test_fstring_replacement_fields() {
         snapshot_test_lexer(
-            "unterminated-string",
-            &["\"hello", "'hello", "'''hello''", "'''hello'"],
+            "fstring_replacement_fields",
+            &[
+                "f'{x!r:>{width}.{precision}}'",
+                "f'{a:{b:{c}}}'",
+                "f'{x = }'",
+                "f'{ {1} }'",
+                "f'{ {\"a\": 1}[\"a\"] }'",
+                "f\"{f\"{x}\"}\"",
+                "f'a\\\\' + b",
+                "f\"\\N{DASH} {x}\"",
+                "rf\"\\N{x}\"",
+            ],
         )
         .unwrap();
     }
 
+    #[test]
+    fn test_lex_errors() {
+        for (source, error) in [
+            ("\"hello", LexError::StringNotTerminated),
+            ("'hello", LexError::StringNotTerminated),
+            ("'''hello''", LexError::StringNotTerminated),
+            ("'''hello'", LexError::StringNotTerminated),
+            ("f'hello", LexError::StringNotTerminated),
+            ("0b102", LexError::InvalidDigitInBinaryLiteral('2')),
+            ("0o78", LexError::InvalidDigitInOctalLiteral('8')),
+            ("0x1g", LexError::InvalidDigitInHexadecimalLiteral('g')),
+            ("1__0", LexError::InvalidDigitInDecimalLiteral),
+            ("1_", LexError::InvalidDigitInDecimalLiteral),
+        ] {
+            let mut lexer = Lexer::new(source);
+            let tokens = lexer.lex();
+            let error_token = tokens.iter().find(|token| token.kind == Kind::Error);
+            let error_token = error_token.unwrap_or_else(|| panic!("{source}: {tokens:?}"));
+            assert_eq!(lexer.errors.len(), 1, "{source}");
+            assert_eq!(lexer.errors[0].error, error, "{source}");
+            assert_eq!(lexer.errors[0].start, error_token.start, "{source}");
+            assert_eq!(lexer.errors[0].end, source.len() as u32, "{source}");
+        }
+    }
+
+    #[test]
+    fn test_unterminated_string_ends_at_line_end() {
+        let source = "a = 'hello\nb";
+        let mut lexer = Lexer::new(source);
+        let kinds: Vec<Kind> = lexer.lex().iter().map(|token| token.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                Kind::Identifier,
+                Kind::Assign,
+                Kind::Error,
+                Kind::NewLine,
+                Kind::Identifier,
+                Kind::Eof
+            ]
+        );
+        assert_eq!(lexer.errors[0].end, source.find('\n').unwrap() as u32);
+    }
+
+    #[test]
+    fn test_unindent_does_not_match() {
+        let source = "if a:\n    b\n  c\n";
+        let mut lexer = Lexer::new(source);
+        let kinds: Vec<Kind> = lexer.lex().iter().map(|token| token.kind).collect();
+        // Recovers by closing the block
+        assert_eq!(
+            kinds,
+            [
+                Kind::If,
+                Kind::Identifier,
+                Kind::Colon,
+                Kind::NewLine,
+                Kind::Indent,
+                Kind::Identifier,
+                Kind::NewLine,
+                Kind::Dedent,
+                Kind::Identifier,
+                Kind::NewLine,
+                Kind::Eof
+            ]
+        );
+        assert_eq!(lexer.errors.len(), 1);
+        assert_eq!(
+            lexer.errors[0].error,
+            LexError::UnindentDoesNotMatchAnyOuterIndentationLevel
+        );
+        let line_start = source.find("  c").unwrap() as u32;
+        assert_eq!(
+            (lexer.errors[0].start, lexer.errors[0].end),
+            (line_start, line_start + 2)
+        );
+    }
+
     #[test]
     fn test_complete() {
         glob!("../../test_data", "inputs/*.py", |path| {
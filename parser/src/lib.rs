@@ -1,12 +1,22 @@
 pub mod ast;
+pub mod cst;
+pub mod encoding;
+pub mod incremental;
 mod lexer;
 pub mod parser;
+pub mod python_version;
+pub mod semantic_errors;
+pub mod unparse;
+pub mod visitor;
 
 pub use crate::lexer::Lexer;
 
 pub mod error;
 pub mod intern;
+pub mod line_index;
+pub mod locate;
 pub mod token;
+pub mod transformer;
 
 pub fn get_row_col_position(start: u32, end: u32, line_starts: &[u32]) -> (u32, u32, u32, u32) {
     let (start_line_num, start_line_offset) = match line_starts.binary_search(&start) {
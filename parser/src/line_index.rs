@@ -0,0 +1,201 @@
+//! Conversion between byte offsets and line and column positions.
+//!
+//! Offsets in the AST and tokens are in bytes of the UTF-8 source, but editors
+//! count columns in UTF-16 code units by default. [`LineIndex`] converts
+//! between the two in any of the encodings a language server can negotiate.
+use std::collections::HashMap;
+
+/// The unit columns are counted in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PositionEncoding {
+    /// Bytes
+    Utf8,
+    /// UTF-16 code units, the default of the language server protocol
+    #[default]
+    Utf16,
+    /// Unicode scalar values, i.e. `char`s
+    Utf32,
+}
+
+impl PositionEncoding {
+    fn len(self, c: char) -> u32 {
+        match self {
+            PositionEncoding::Utf8 => c.len_utf8() as u32,
+            PositionEncoding::Utf16 => c.len_utf16() as u32,
+            PositionEncoding::Utf32 => 1,
+        }
+    }
+}
+
+/// A position in the source, both line and column start from 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LineCol {
+    pub line: u32,
+    pub col: u32,
+}
+
+/// A character that takes more than one byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct WideChar {
+    /// Byte offset from the start of the line
+    start: u32,
+    c: char,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LineIndex {
+    /// Offsets of the line starts, lines are split on `\n` like the lexer does
+    line_starts: Vec<u32>,
+    /// Wide characters of each line, lines that are all ASCII are not stored
+    wide_chars: HashMap<u32, Vec<WideChar>>,
+    len: u32,
+}
+
+impl LineIndex {
+    pub fn new(source: &str) -> Self {
+        let mut line_starts = vec![0];
+        let mut wide_chars: HashMap<u32, Vec<WideChar>> = HashMap::new();
+        let mut line_start = 0;
+        for (offset, c) in source.char_indices() {
+            let offset = offset as u32;
+            if c == '\n' {
+                line_start = offset + 1;
+                line_starts.push(line_start);
+            } else if !c.is_ascii() {
+                let line = line_starts.len() as u32 - 1;
+                wide_chars.entry(line).or_default().push(WideChar {
+                    start: offset - line_start,
+                    c,
+                });
+            }
+        }
+        Self {
+            line_starts,
+            wide_chars,
+            len: source.len() as u32,
+        }
+    }
+
+    pub fn line_starts(&self) -> &[u32] {
+        &self.line_starts
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// The line and column of a byte offset. Offsets past the end of the
+    /// source are at the end of the last line.
+    pub fn line_col(&self, offset: u32, encoding: PositionEncoding) -> LineCol {
+        let offset = offset.min(self.len);
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let byte_col = offset - self.line_starts[line];
+        let line = line as u32;
+        let mut col = byte_col;
+        for wide in self.wide_chars_of(line) {
+            if wide.start >= byte_col {
+                break;
+            }
+            col -= wide.c.len_utf8() as u32 - encoding.len(wide.c);
+        }
+        LineCol { line, col }
+    }
+
+    /// The byte offset of a position. Columns past the end of the line are at
+    /// the end of the line and lines past the end of the source are at the
+    /// end of the source. A column in the middle of a character is moved to
+    /// the start of the character.
+    pub fn offset(&self, position: LineCol, encoding: PositionEncoding) -> u32 {
+        let Some(&line_start) = self.line_starts.get(position.line as usize) else {
+            return self.len;
+        };
+        let line_end = match self.line_starts.get(position.line as usize + 1) {
+            Some(next_line_start) => next_line_start - 1,
+            None => self.len,
+        };
+        // Bytes of the wide characters that are not counted in the column
+        let mut extra = 0;
+        for wide in self.wide_chars_of(position.line) {
+            let start = wide.start - extra;
+            if position.col <= start {
+                break;
+            }
+            if position.col < start + encoding.len(wide.c) {
+                return line_start + wide.start;
+            }
+            extra += wide.c.len_utf8() as u32 - encoding.len(wide.c);
+        }
+        (line_start + position.col + extra).min(line_end)
+    }
+
+    fn wide_chars_of(&self, line: u32) -> &[WideChar] {
+        self.wide_chars.get(&line).map_or(&[], Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line_col(line: u32, col: u32) -> LineCol {
+        LineCol { line, col }
+    }
+
+    #[test]
+    fn test_ascii() {
+        let index = LineIndex::new("a = 1\nb = 2\n");
+        assert_eq!(index.line_starts(), [0, 6, 12]);
+        for encoding in [
+            PositionEncoding::Utf8,
+            PositionEncoding::Utf16,
+            PositionEncoding::Utf32,
+        ] {
+            assert_eq!(index.line_col(0, encoding), line_col(0, 0));
+            assert_eq!(index.line_col(5, encoding), line_col(0, 5));
+            assert_eq!(index.line_col(8, encoding), line_col(1, 2));
+            assert_eq!(index.line_col(12, encoding), line_col(2, 0));
+            assert_eq!(index.offset(line_col(1, 2), encoding), 8);
+        }
+        // Clamped to the end of the line and of the source
+        assert_eq!(index.offset(line_col(0, 20), PositionEncoding::Utf16), 5);
+        assert_eq!(index.offset(line_col(5, 0), PositionEncoding::Utf16), 12);
+        assert_eq!(index.line_col(100, PositionEncoding::Utf16), line_col(2, 0));
+    }
+
+    #[test]
+    fn test_wide_chars() {
+        // é is 2 bytes and 1 UTF-16 unit, 𝕏 is 4 bytes and 2 UTF-16 units
+        let source = "x = 'é𝕏'\ny = x\n";
+        let index = LineIndex::new(source);
+        let closing_quote = source.rfind('\'').unwrap() as u32;
+        assert_eq!(closing_quote, 11);
+        let expected = [
+            (PositionEncoding::Utf8, 11),
+            (PositionEncoding::Utf16, 8),
+            (PositionEncoding::Utf32, 7),
+        ];
+        for (encoding, col) in expected {
+            assert_eq!(index.line_col(closing_quote, encoding), line_col(0, col));
+            assert_eq!(index.offset(line_col(0, col), encoding), closing_quote);
+        }
+        // Characters before the wide ones are not shifted
+        assert_eq!(index.line_col(4, PositionEncoding::Utf16), line_col(0, 4));
+        // Other lines are not affected
+        let y = source.find('y').unwrap() as u32;
+        assert_eq!(
+            index.line_col(y + 4, PositionEncoding::Utf16),
+            line_col(1, 4)
+        );
+        assert_eq!(index.offset(line_col(1, 4), PositionEncoding::Utf32), y + 4);
+    }
+
+    #[test]
+    fn test_middle_of_char() {
+        let source = "𝕏 = 1";
+        let index = LineIndex::new(source);
+        // The second half of the surrogate pair
+        assert_eq!(index.offset(line_col(0, 1), PositionEncoding::Utf16), 0);
+        assert_eq!(index.offset(line_col(0, 2), PositionEncoding::Utf16), 4);
+        assert_eq!(index.offset(line_col(0, 3), PositionEncoding::Utf16), 5);
+    }
+}
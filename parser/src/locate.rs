@@ -0,0 +1,201 @@
+//! Finding the nodes at an offset in the source.
+//!
+//! Editor features like hover, go to definition and completion start from a
+//! cursor position. [`node_at_offset`] gives the innermost node that contains
+//! the offset together with all the nodes around it, e.g. the call and the
+//! statement of an argument.
+use crate::{
+    ast::*,
+    visitor::{self, Visitor},
+};
+
+/// A node that can be found at an offset
+#[derive(Debug, Clone, Copy)]
+pub enum NodeRef<'a> {
+    Statement(&'a Statement),
+    Expression(&'a Expression),
+    Arg(&'a Arg),
+    Alias(&'a Alias),
+}
+
+impl GetNode for NodeRef<'_> {
+    fn get_node(&self) -> Node {
+        match self {
+            NodeRef::Statement(stmt) => stmt.get_node(),
+            NodeRef::Expression(expr) => expr.get_node(),
+            NodeRef::Arg(arg) => arg.node,
+            NodeRef::Alias(alias) => alias.node,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NodeAtOffset<'a> {
+    /// The innermost node that contains the offset
+    pub node: NodeRef<'a>,
+    /// The nodes that contain `node`, the first one is a statement of the module
+    pub ancestors: Vec<NodeRef<'a>>,
+}
+
+impl<'a> NodeAtOffset<'a> {
+    /// The innermost statement that contains the offset
+    pub fn statement(&self) -> Option<&'a Statement> {
+        std::iter::once(&self.node)
+            .chain(self.ancestors.iter().rev())
+            .find_map(|node| match node {
+                NodeRef::Statement(stmt) => Some(*stmt),
+                _ => None,
+            })
+    }
+}
+
+/// Finds the innermost node that contains the offset. The end of a node is
+/// exclusive, so the node right before a cursor is found with `offset - 1`.
+pub fn node_at_offset(module: &Module, offset: u32) -> Option<NodeAtOffset<'_>> {
+    let mut finder = NodeFinder {
+        offset,
+        path: vec![],
+    };
+    finder.visit_module(module);
+    let node = finder.path.pop()?;
+    Some(NodeAtOffset {
+        node,
+        ancestors: finder.path,
+    })
+}
+
+struct NodeFinder<'a> {
+    offset: u32,
+    /// Nodes that contain the offset from the outermost to the innermost
+    path: Vec<NodeRef<'a>>,
+}
+
+impl NodeFinder<'_> {
+    fn contains(&self, node: Node) -> bool {
+        node.start <= self.offset && self.offset < node.end
+    }
+}
+
+// Children are inside of their parent so only the nodes that contain the
+// offset are walked
+impl<'a> Visitor<'a> for NodeFinder<'a> {
+    fn visit_stmt(&mut self, stmt: &'a Statement) {
+        if self.contains(stmt.get_node()) {
+            self.path.push(NodeRef::Statement(stmt));
+            visitor::walk_stmt(self, stmt);
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &'a Expression) {
+        if self.contains(expr.get_node()) {
+            self.path.push(NodeRef::Expression(expr));
+            visitor::walk_expr(self, expr);
+        }
+    }
+
+    fn visit_arg(&mut self, arg: &'a Arg) {
+        if self.contains(arg.node) {
+            self.path.push(NodeRef::Arg(arg));
+            visitor::walk_arg(self, arg);
+        }
+    }
+
+    fn visit_alias(&mut self, alias: &'a Alias) {
+        if self.contains(alias.node) {
+            self.path.push(NodeRef::Alias(alias));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parser::parser::Parser,
+        unparse::{unparse_expression, unparse_statement},
+    };
+
+    fn describe(node: &NodeRef) -> String {
+        match node {
+            NodeRef::Statement(stmt) => {
+                let source = unparse_statement(stmt);
+                format!("stmt {}", source.lines().next().unwrap_or_default())
+            }
+            NodeRef::Expression(expr) => format!("expr {}", unparse_expression(expr)),
+            NodeRef::Arg(arg) => format!("arg {}", arg.arg),
+            NodeRef::Alias(alias) => format!("alias {}", alias.name),
+        }
+    }
+
+    /// The innermost node and its ancestors at the `|` in the source
+    fn nodes_at_cursor(source: &str) -> Vec<String> {
+        let offset = source.find('|').unwrap() as u32;
+        let source = source.replacen('|', "", 1);
+        let (module, errors) = Parser::new(&source).parse();
+        assert!(errors.is_empty(), "{errors:?}");
+        match node_at_offset(&module, offset) {
+            Some(found) => found
+                .ancestors
+                .iter()
+                .chain(std::iter::once(&found.node))
+                .map(describe)
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    #[test]
+    fn test_node_at_offset() {
+        assert_eq!(
+            nodes_at_cursor("x = foo(|bar.baz, 1)"),
+            [
+                "stmt x = foo(bar.baz, 1)",
+                "expr foo(bar.baz, 1)",
+                "expr bar.baz",
+                "expr bar",
+            ]
+        );
+        // The attribute name is not a node of its own
+        assert_eq!(
+            nodes_at_cursor("bar.b|az"),
+            ["stmt bar.baz", "expr bar.baz"]
+        );
+        assert_eq!(
+            nodes_at_cursor("def f(a: |int = 1):\n    return a"),
+            ["stmt def f(a: int=1):", "arg a", "expr int"]
+        );
+        assert_eq!(
+            nodes_at_cursor("def f(a):\n    return |a"),
+            ["stmt def f(a):", "stmt return a", "expr a"]
+        );
+        assert_eq!(
+            nodes_at_cursor("import os, sys.pa|th as p"),
+            ["stmt import os, sys.path as p", "alias sys.path"]
+        );
+        assert_eq!(
+            nodes_at_cursor("class C:\n    @dec|orator\n    def f(self): pass"),
+            ["stmt class C:", "stmt @decorator", "expr decorator"]
+        );
+    }
+
+    #[test]
+    fn test_node_at_offset_boundaries() {
+        // The end of a node is exclusive
+        assert_eq!(
+            nodes_at_cursor("abc| + d"),
+            ["stmt abc + d", "expr abc + d"]
+        );
+        assert_eq!(nodes_at_cursor("|abc"), ["stmt abc", "expr abc"]);
+        assert!(nodes_at_cursor("x = 1\n|\ny = 2").is_empty());
+    }
+
+    #[test]
+    fn test_innermost_statement() {
+        let source = "if a:\n    x = [b for b in c]";
+        let (module, _) = Parser::new(source).parse();
+        let offset = source.find("c]").unwrap() as u32;
+        let found = node_at_offset(&module, offset).unwrap();
+        let statement = found.statement().unwrap();
+        assert_eq!(unparse_statement(statement), "x = [b for b in c]");
+    }
+}
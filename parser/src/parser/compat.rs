@@ -1,6 +1,8 @@
 #![allow(unused_variables)]
 use crate::ast::*;
-use crate::parser::parser::Parser;
+use crate::get_row_col_position;
+use crate::parser::parser::{intern_lookup, Parser};
+use crate::token::Kind;
 use serde_json::{json, Value};
 
 pub trait AsPythonCompat {
@@ -24,23 +26,155 @@ impl<T: AsPythonCompat> AsNullablePythonCompat<T> for Option<T> {
 macro_rules! json_python_compat_node {
     ($name:literal, $instance:ident, $parser:ident, $other_fields:tt) => {{
         let mut node = json!($other_fields);
-        let (start_row, start_col, end_row, end_col) =
-            $parser.to_row_col($instance.node.start, $instance.node.end);
         node["_type"] = json!($name);
-        // TODO: (offset_compat)
-        // node["lineno"] = json!(start_row);
-        // node["col_offset"] = json!(start_col);
-        // node["end_lineno"] = json!(end_row);
-        // node["end_col_offset"] = json!(end_col);
+        set_location(&mut node, $parser, $instance.node);
         node
     }};
 }
 
+/// Sets the `lineno`, `col_offset`, `end_lineno` and `end_col_offset`
+/// attributes. Like CPython, columns are UTF-8 byte offsets and lines start at 1.
+fn set_location(value: &mut Value, parser: &Parser, node: Node) {
+    let (start_row, start_col, end_row, end_col) = parser.to_row_col(node.start, node.end);
+    value["lineno"] = json!(start_row);
+    value["col_offset"] = json!(start_col);
+    value["end_lineno"] = json!(end_row);
+    value["end_col_offset"] = json!(end_col);
+}
+
+/// Expression nodes do not include the parentheses around them, but the
+/// statements containing them do.
+fn parenthesized_location(mut node: Node, parser: &Parser) -> Node {
+    loop {
+        let before = parser.source[..node.start as usize].trim_end();
+        let after = &parser.source[node.end as usize..];
+        let closing = after.trim_start();
+        if !(before.ends_with('(') && closing.starts_with(')')) {
+            return node;
+        }
+        node.start = before.len() as u32 - 1;
+        node.end += (after.len() - closing.len()) as u32 + 1;
+    }
+}
+
+/// Compound statements end with their last statement, our nodes also
+/// include the line break and indentation after it.
+fn end_at_last_statement(value: &mut Value) {
+    let Some(mut last) = ["finalbody", "orelse", "handlers", "body", "cases"]
+        .iter()
+        .find_map(|block| value.get(*block)?.as_array()?.last())
+    else {
+        return;
+    };
+    // Match cases do not have a location
+    if last["_type"] == "match_case" {
+        let Some(statement) = last["body"].as_array().and_then(|body| body.last()) else {
+            return;
+        };
+        last = statement;
+    }
+    let (end_lineno, end_col_offset) = (last["end_lineno"].clone(), last["end_col_offset"].clone());
+    value["end_lineno"] = end_lineno;
+    value["end_col_offset"] = end_col_offset;
+}
+
+/// Definitions start at the `def` or `class` keyword, after their decorators.
+fn set_definition_start(value: &mut Value, parser: &Parser, node: Node, decorators: &[Expression]) {
+    let Some(decorator) = decorators.last() else {
+        return;
+    };
+    let mut offset = decorator.get_node().end as usize;
+    let Some(line_end) = parser.source[offset..].find('\n') else {
+        return;
+    };
+    offset += line_end + 1;
+    for line in parser.source[offset..].split_inclusive('\n') {
+        let code = line.trim_start();
+        if !code.is_empty() && !code.starts_with('#') {
+            offset += line.len() - code.len();
+            break;
+        }
+        offset += line.len();
+    }
+    let (lineno, col_offset, ..) = parser.to_row_col(offset as u32, node.end);
+    value["lineno"] = json!(lineno);
+    value["col_offset"] = json!(col_offset);
+}
+
+/// Replaces the `Load` context of an assignment or deletion target. The
+/// elements of tuples and lists and the value of starred expressions are
+/// targets too.
+fn set_ctx(value: &mut Value, ctx: &'static str) {
+    if value.get("ctx").is_none() {
+        return;
+    }
+    value["ctx"] = json!({"_type": ctx});
+    match value["_type"].as_str() {
+        Some("Tuple" | "List") => {
+            if let Some(Value::Array(elements)) = value.get_mut("elts") {
+                elements.iter_mut().for_each(|e| set_ctx(e, ctx));
+            }
+        }
+        Some("Starred") => set_ctx(&mut value["value"], ctx),
+        _ => {}
+    }
+}
+
+fn target_as_python_compat(target: &Expression, parser: &Parser, ctx: &'static str) -> Value {
+    let mut value = target.as_python_compat(parser);
+    set_ctx(&mut value, ctx);
+    value
+}
+
+fn load() -> Value {
+    json!({"_type": "Load"})
+}
+
+fn optional_str(value: &Option<String>) -> Value {
+    value.as_ref().map_or(json!(null), |s| json!(s))
+}
+
+/// `# type: ignore` comments are reported by CPython as `TypeIgnore` nodes
+/// with the text after `ignore` as their tag.
+fn type_ignores(parser: &Parser) -> Vec<Value> {
+    let mut lexer = crate::Lexer::new(parser.source);
+    let tokens = lexer.lex();
+    tokens
+        .iter()
+        .filter(|token| token.kind == Kind::Comment)
+        .filter_map(|token| {
+            let comment = token.as_str(parser.source);
+            let rest = comment.strip_prefix('#')?.trim_start();
+            let rest = rest.strip_prefix("type:")?.trim_start();
+            let tag = rest.strip_prefix("ignore")?;
+            if tag.starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+                return None;
+            }
+            let (lineno, ..) = get_row_col_position(token.start, token.end, &lexer.line_starts);
+            // CPython keeps the line break in the tag of comments on their own line
+            let line_start = lexer.line_starts[lineno as usize - 1] as usize;
+            let mut tag = tag.to_string();
+            let own_line = parser.source[line_start..token.start as usize]
+                .trim()
+                .is_empty();
+            if own_line && parser.source[token.end as usize..].starts_with(['\r', '\n']) {
+                tag.push('\n');
+            }
+            Some(json!({
+                "_type": "TypeIgnore",
+                "lineno": lineno,
+                "tag": tag,
+            }))
+        })
+        .collect()
+}
+
 impl AsPythonCompat for Module {
     fn as_python_compat(&self, parser: &Parser) -> Value {
         json!({
             "_type": "Module",
             "body": self.body.iter().map(|stmt| stmt.as_python_compat(parser)).collect::<Vec<_>>(),
+            "type_ignores": type_ignores(parser),
         })
     }
 }
@@ -49,17 +183,16 @@ impl AsPythonCompat for Statement {
     fn as_python_compat(&self, parser: &Parser) -> Value {
         match self {
             Statement::ExpressionStatement(e) => {
-                let expr = e.as_python_compat(parser);
-                json!({
+                let mut expr = json!({
                     "_type": "Expr",
-                    // TODO: (offset_compat) python parser and enderpy start/end offsets are different
-                    // Fix it when have some time
-                    // "lineno": expr["lineno"],
-                    // "col_offset": expr["col_offset"],
-                    // "end_lineno": expr["end_lineno"],
-                    // "end_col_offset": expr["end_col_offset"],
-                    "value": expr,
-                })
+                    "value": e.as_python_compat(parser),
+                });
+                set_location(
+                    &mut expr,
+                    parser,
+                    parenthesized_location(e.get_node(), parser),
+                );
+                expr
             }
             Statement::Import(i) => i.as_python_compat(parser),
             Statement::ImportFrom(i) => i.as_python_compat(parser),
@@ -88,28 +221,38 @@ impl AsPythonCompat for Statement {
             Statement::AsyncWithStatement(w) => w.as_python_compat(parser),
             Statement::AsyncFunctionDef(f) => f.as_python_compat(parser),
             Statement::TypeAlias(t) => t.as_python_compat(parser),
+            Statement::Invalid(i) => i.as_python_compat(parser),
         }
     }
 }
 
 impl AsPythonCompat for Assign {
     fn as_python_compat(&self, parser: &Parser) -> Value {
-        json_python_compat_node!("Assign", self, parser, {
-            "targets": self.targets.iter().map(|expr| expr.as_python_compat(parser)).collect::<Vec<_>>(),
+        let mut node = json_python_compat_node!("Assign", self, parser, {
+            "targets": self.targets.iter().map(|expr| target_as_python_compat(expr, parser, "Store")).collect::<Vec<_>>(),
             "value": self.value.as_python_compat(parser),
-            // TODO ast_python: Support for type_comment.
-            "type_comment": json!(null),
-        })
+            "type_comment": optional_str(&self.type_comment),
+        });
+        // The type comment is part of the statement
+        if self.type_comment.is_some() {
+            let rest = &parser.source[self.node.end as usize..];
+            let line = rest.split(['\r', '\n']).next().unwrap_or_default();
+            let end = self.node.end + line.trim_end().len() as u32;
+            let (.., end_lineno, end_col_offset) = parser.to_row_col(self.node.start, end);
+            node["end_lineno"] = json!(end_lineno);
+            node["end_col_offset"] = json!(end_col_offset);
+        }
+        node
     }
 }
 
 impl AsPythonCompat for AnnAssign {
     fn as_python_compat(&self, parser: &Parser) -> Value {
         json_python_compat_node!("AnnAssign", self, parser, {
-            "target": self.target.as_python_compat(parser),
+            "target": target_as_python_compat(&self.target, parser, "Store"),
             "annotation": self.annotation.as_python_compat(parser),
             "value": self.value.as_python_compat(parser),
-            "simple": self.simple,
+            "simple": u8::from(self.simple),
         })
     }
 }
@@ -117,7 +260,7 @@ impl AsPythonCompat for AnnAssign {
 impl AsPythonCompat for AugAssign {
     fn as_python_compat(&self, parser: &Parser) -> Value {
         json_python_compat_node!("AugAssign", self, parser, {
-            "target": self.target.as_python_compat(parser),
+            "target": target_as_python_compat(&self.target, parser, "Store"),
             "op": self.op.as_python_compat(parser),
             "value": self.value.as_python_compat(parser),
         })
@@ -162,7 +305,7 @@ impl AsPythonCompat for Pass {
 impl AsPythonCompat for Delete {
     fn as_python_compat(&self, parser: &Parser) -> Value {
         json_python_compat_node!("Delete", self, parser, {
-            "targets": self.targets.iter().map(|stmt| stmt.as_python_compat(parser)).collect::<Vec<_>>(),
+            "targets": self.targets.iter().map(|expr| target_as_python_compat(expr, parser, "Del")).collect::<Vec<_>>(),
         })
     }
 }
@@ -208,7 +351,7 @@ impl AsPythonCompat for Alias {
     fn as_python_compat(&self, parser: &Parser) -> Value {
         json_python_compat_node!("alias", self, parser, {
             "name": self.name,
-            "asname": self.asname,
+            "asname": optional_str(&self.asname),
         })
     }
 }
@@ -216,7 +359,7 @@ impl AsPythonCompat for Alias {
 impl AsPythonCompat for ImportFrom {
     fn as_python_compat(&self, parser: &Parser) -> Value {
         json_python_compat_node!("ImportFrom", self, parser, {
-            "module": self.module,
+            "module": if self.module.is_empty() { json!(null) } else { json!(self.module) },
             "names": self.names.iter().map(|alias| alias.as_python_compat(parser)).collect::<Vec<_>>(),
             "level": self.level,
         })
@@ -269,30 +412,75 @@ impl AsPythonCompat for Expression {
             Expression::IfExp(i) => i.as_python_compat(parser),
             Expression::JoinedStr(j) => j.as_python_compat(parser),
             Expression::FormattedValue(f) => f.as_python_compat(parser),
+            Expression::Invalid(i) => i.as_python_compat(parser),
         }
     }
 }
 
+impl AsPythonCompat for Invalid {
+    fn as_python_compat(&self, parser: &Parser) -> Value {
+        json_python_compat_node!("Invalid", self, parser, {})
+    }
+}
+
 impl AsPythonCompat for Name {
     fn as_python_compat(&self, parser: &Parser) -> Value {
         json_python_compat_node!("Name", self, parser, {
-            "id": "TODO",
+            "id": self.id,
+            "ctx": load(),
         })
     }
 }
 
 impl AsPythonCompat for Constant {
     fn as_python_compat(&self, parser: &Parser) -> Value {
+        let text = &parser.source[self.node.start as usize..];
+        let kind = if text.starts_with(['u', 'U']) && text[1..].starts_with(['\'', '"']) {
+            json!("u")
+        } else {
+            json!(null)
+        };
         json_python_compat_node!("Constant", self, parser, {
-            "value": json!(self.get_value(parser.source)),
+            "value": self.value.as_python_compat(parser),
+            "kind": kind,
         })
     }
 }
 
+impl AsPythonCompat for ConstantValue {
+    fn as_python_compat(&self, _: &Parser) -> Value {
+        match self {
+            ConstantValue::None => json!(null),
+            ConstantValue::Ellipsis => json!("..."),
+            ConstantValue::Bool(b) => json!(b),
+            ConstantValue::Str(s) => json!(s),
+            ConstantValue::Bytes(b) => json!(String::from_utf8_lossy(b)),
+            ConstantValue::Tuple => json!([]),
+            ConstantValue::Int(i) => match i64::try_from(i) {
+                Ok(i) => json!(i),
+                // JSON readers usually parse integers that do not fit in 64 bits as floats
+                Err(_) => i
+                    .to_string()
+                    .parse::<f64>()
+                    .map_or(json!(null), |f| json!(f)),
+            },
+            ConstantValue::Float(f) => json!(f),
+            ConstantValue::Complex { .. } => {
+                let constant = Constant {
+                    node: Node::default(),
+                    value: self.clone(),
+                };
+                json!(constant.get_value())
+            }
+        }
+    }
+}
+
 impl AsPythonCompat for List {
     fn as_python_compat(&self, parser: &Parser) -> Value {
         json_python_compat_node!("List", self, parser, {
             "elts": self.elements.iter().map(|expr| expr.as_python_compat(parser)).collect::<Vec<_>>(),
+            "ctx": load(),
         })
     }
 }
@@ -300,9 +488,8 @@ impl AsPythonCompat for List {
 impl AsPythonCompat for Tuple {
     fn as_python_compat(&self, parser: &Parser) -> Value {
         json_python_compat_node!("Tuple", self, parser, {
-            // Yes, these are repeated.
-            "dims": self.elements.iter().map(|expr| expr.as_python_compat(parser)).collect::<Vec<_>>(),
             "elts": self.elements.iter().map(|expr| expr.as_python_compat(parser)).collect::<Vec<_>>(),
+            "ctx": load(),
         })
     }
 }
@@ -310,7 +497,7 @@ impl AsPythonCompat for Tuple {
 impl AsPythonCompat for Dict {
     fn as_python_compat(&self, parser: &Parser) -> Value {
         json_python_compat_node!("Dict", self, parser, {
-            "keys": self.keys.iter().map(|expr| expr.as_python_compat(parser)).collect::<Vec<_>>(),
+            "keys": self.keys.iter().map(|key| key.as_ref().map_or(json!(null), |key| key.as_python_compat(parser))).collect::<Vec<_>>(),
             "values": self.values.iter().map(|expr| expr.as_python_compat(parser)).collect::<Vec<_>>(),
         })
     }
@@ -319,7 +506,7 @@ impl AsPythonCompat for Dict {
 impl AsPythonCompat for Set {
     fn as_python_compat(&self, parser: &Parser) -> Value {
         json_python_compat_node!("Set", self, parser, {
-            "elements": self.elements.iter().map(|expr| expr.as_python_compat(parser)).collect::<Vec<_>>(),
+            "elts": self.elements.iter().map(|expr| expr.as_python_compat(parser)).collect::<Vec<_>>(),
         })
     }
 }
@@ -395,7 +582,7 @@ impl AsPythonCompat for BinaryOperator {
 impl AsPythonCompat for NamedExpression {
     fn as_python_compat(&self, parser: &Parser) -> Value {
         json_python_compat_node!("NamedExpr", self, parser, {
-            "target": self.target.as_python_compat(parser),
+            "target": target_as_python_compat(&self.target, parser, "Store"),
             "value": self.value.as_python_compat(parser),
         })
     }
@@ -421,14 +608,15 @@ impl AsPythonCompat for Starred {
     fn as_python_compat(&self, parser: &Parser) -> Value {
         json_python_compat_node!("Starred", self, parser, {
             "value": self.value.as_python_compat(parser),
+            "ctx": load(),
         })
     }
 }
 
 impl AsPythonCompat for Generator {
     fn as_python_compat(&self, parser: &Parser) -> Value {
-        json_python_compat_node!("Generator", self, parser, {
-            "element": self.element.as_python_compat(parser),
+        json_python_compat_node!("GeneratorExp", self, parser, {
+            "elt": self.element.as_python_compat(parser),
             "generators": self.generators.iter().map(|gen| gen.as_python_compat(parser)).collect::<Vec<_>>(),
         })
     }
@@ -437,7 +625,7 @@ impl AsPythonCompat for Generator {
 impl AsPythonCompat for ListComp {
     fn as_python_compat(&self, parser: &Parser) -> Value {
         json_python_compat_node!("ListComp", self, parser, {
-            "element": self.element.as_python_compat(parser),
+            "elt": self.element.as_python_compat(parser),
             "generators": self.generators.iter().map(|gen| gen.as_python_compat(parser)).collect::<Vec<_>>(),
         })
     }
@@ -446,7 +634,7 @@ impl AsPythonCompat for ListComp {
 impl AsPythonCompat for SetComp {
     fn as_python_compat(&self, parser: &Parser) -> Value {
         json_python_compat_node!("SetComp", self, parser, {
-            "element": self.element.as_python_compat(parser),
+            "elt": self.element.as_python_compat(parser),
             "generators": self.generators.iter().map(|gen| gen.as_python_compat(parser)).collect::<Vec<_>>(),
         })
     }
@@ -464,11 +652,12 @@ impl AsPythonCompat for DictComp {
 
 impl AsPythonCompat for Comprehension {
     fn as_python_compat(&self, parser: &Parser) -> Value {
-        json_python_compat_node!("Comprehension", self, parser, {
-            "target": self.target.as_python_compat(parser),
+        json!({
+            "_type": "comprehension",
+            "target": target_as_python_compat(&self.target, parser, "Store"),
             "iter": self.iter.as_python_compat(parser),
             "ifs": self.ifs.iter().map(|expr| expr.as_python_compat(parser)).collect::<Vec<_>>(),
-            "is_async": self.is_async,
+            "is_async": u8::from(self.is_async),
         })
     }
 }
@@ -478,6 +667,7 @@ impl AsPythonCompat for Attribute {
         json_python_compat_node!("Attribute", self, parser, {
             "value": self.value.as_python_compat(parser),
             "attr": self.attr,
+            "ctx": load(),
         })
     }
 }
@@ -487,6 +677,7 @@ impl AsPythonCompat for Subscript {
         json_python_compat_node!("Subscript", self, parser, {
             "value": self.value.as_python_compat(parser),
             "slice": self.slice.as_python_compat(parser),
+            "ctx": load(),
         })
     }
 }
@@ -503,25 +694,18 @@ impl AsPythonCompat for Slice {
 
 impl AsPythonCompat for Call {
     fn as_python_compat(&self, parser: &Parser) -> Value {
-        let mut node = json_python_compat_node!("Call", self, parser, {
+        json_python_compat_node!("Call", self, parser, {
             "func": self.func.as_python_compat(parser),
             "args": self.args.iter().map(|expr| expr.as_python_compat(parser)).collect::<Vec<_>>(),
             "keywords": self.keywords.iter().map(|kw| kw.as_python_compat(parser)).collect::<Vec<_>>(),
-        });
-        if let Some(expr) = &self.starargs {
-            node["starargs"] = expr.as_python_compat(parser);
-        }
-        if let Some(expr) = &self.kwargs {
-            node["kwargs"] = expr.as_python_compat(parser);
-        }
-        node
+        })
     }
 }
 
 impl AsPythonCompat for Keyword {
     fn as_python_compat(&self, parser: &Parser) -> Value {
         json_python_compat_node!("keyword", self, parser, {
-            "arg": self.arg.as_ref().map_or(json!(null), |s| json!(s)),
+            "arg": optional_str(&self.arg),
             "value": self.value.as_python_compat(parser),
         })
     }
@@ -591,7 +775,6 @@ impl AsPythonCompat for Arg {
         json_python_compat_node!("arg", self, parser, {
             "arg": self.arg,
             "annotation": self.annotation.as_python_compat(parser),
-            // TODO ast_python: Support for type_comment.
             "type_comment": json!(null),
         })
     }
@@ -627,63 +810,77 @@ impl AsPythonCompat for JoinedStr {
 
 impl AsPythonCompat for If {
     fn as_python_compat(&self, parser: &Parser) -> Value {
-        json_python_compat_node!("If", self, parser, {
+        let mut node = json_python_compat_node!("If", self, parser, {
             "test": self.test.as_python_compat(parser),
             "body": self.body.iter().map(|stmt| stmt.as_python_compat(parser)).collect::<Vec<_>>(),
             "orelse": self.orelse.iter().map(|stmt| stmt.as_python_compat(parser)).collect::<Vec<_>>(),
-        })
+        });
+        end_at_last_statement(&mut node);
+        node
     }
 }
 
 impl AsPythonCompat for While {
     fn as_python_compat(&self, parser: &Parser) -> Value {
-        json_python_compat_node!("While", self, parser, {
+        let mut node = json_python_compat_node!("While", self, parser, {
             "test": self.test.as_python_compat(parser),
             "body": self.body.iter().map(|stmt| stmt.as_python_compat(parser)).collect::<Vec<_>>(),
             "orelse": self.orelse.iter().map(|stmt| stmt.as_python_compat(parser)).collect::<Vec<_>>(),
-        })
+        });
+        end_at_last_statement(&mut node);
+        node
     }
 }
 
 impl AsPythonCompat for For {
     fn as_python_compat(&self, parser: &Parser) -> Value {
-        json_python_compat_node!("For", self, parser, {
-            "target": self.target.as_python_compat(parser),
+        let mut node = json_python_compat_node!("For", self, parser, {
+            "target": target_as_python_compat(&self.target, parser, "Store"),
             "iter": self.iter.as_python_compat(parser),
             "body": self.body.iter().map(|stmt| stmt.as_python_compat(parser)).collect::<Vec<_>>(),
             "orelse": self.orelse.iter().map(|stmt| stmt.as_python_compat(parser)).collect::<Vec<_>>(),
-        })
+            "type_comment": optional_str(&self.type_comment),
+        });
+        end_at_last_statement(&mut node);
+        node
     }
 }
 
 impl AsPythonCompat for AsyncFor {
     fn as_python_compat(&self, parser: &Parser) -> Value {
-        json_python_compat_node!("AsyncFor", self, parser, {
-            "target": self.target.as_python_compat(parser),
+        let mut node = json_python_compat_node!("AsyncFor", self, parser, {
+            "target": target_as_python_compat(&self.target, parser, "Store"),
             "iter": self.iter.as_python_compat(parser),
             "body": self.body.iter().map(|stmt| stmt.as_python_compat(parser)).collect::<Vec<_>>(),
             "orelse": self.orelse.iter().map(|stmt| stmt.as_python_compat(parser)).collect::<Vec<_>>(),
-        })
+            "type_comment": optional_str(&self.type_comment),
+        });
+        end_at_last_statement(&mut node);
+        node
     }
 }
 
 impl AsPythonCompat for With {
     fn as_python_compat(&self, parser: &Parser) -> Value {
-        json_python_compat_node!("With", self, parser, {
+        let mut node = json_python_compat_node!("With", self, parser, {
             "items": self.items.iter().map(|wi| wi.as_python_compat(parser)).collect::<Vec<_>>(),
             "body": self.body.iter().map(|stmt| stmt.as_python_compat(parser)).collect::<Vec<_>>(),
-            // TODO ast_python: Support for type_comment.
-            "type_comment": json!(null),
-        })
+            "type_comment": optional_str(&self.type_comment),
+        });
+        end_at_last_statement(&mut node);
+        node
     }
 }
 
 impl AsPythonCompat for AsyncWith {
     fn as_python_compat(&self, parser: &Parser) -> Value {
-        json_python_compat_node!("AsyncWith", self, parser, {
+        let mut node = json_python_compat_node!("AsyncWith", self, parser, {
             "items": self.items.iter().map(|wi| wi.as_python_compat(parser)).collect::<Vec<_>>(),
             "body": self.body.iter().map(|stmt| stmt.as_python_compat(parser)).collect::<Vec<_>>(),
-        })
+            "type_comment": optional_str(&self.type_comment),
+        });
+        end_at_last_statement(&mut node);
+        node
     }
 }
 
@@ -692,96 +889,114 @@ impl AsPythonCompat for WithItem {
         json!({
             "_type": "withitem",
             "context_expr": self.context_expr.as_python_compat(parser),
-            "optional_vars": self.optional_vars.as_python_compat(parser),
+            "optional_vars": self.optional_vars.as_ref().map_or(json!(null), |expr| target_as_python_compat(expr, parser, "Store")),
         })
     }
 }
 
 impl AsPythonCompat for Try {
     fn as_python_compat(&self, parser: &Parser) -> Value {
-        json_python_compat_node!("Try", self, parser, {
+        let mut node = json_python_compat_node!("Try", self, parser, {
             "body": self.body.iter().map(|stmt| stmt.as_python_compat(parser)).collect::<Vec<_>>(),
             "handlers": self.handlers.iter().map(|hndl| hndl.as_python_compat(parser)).collect::<Vec<_>>(),
             "orelse": self.orelse.iter().map(|stmt| stmt.as_python_compat(parser)).collect::<Vec<_>>(),
             "finalbody": self.finalbody.iter().map(|stmt| stmt.as_python_compat(parser)).collect::<Vec<_>>(),
-        })
+        });
+        end_at_last_statement(&mut node);
+        node
     }
 }
 
 impl AsPythonCompat for TryStar {
     fn as_python_compat(&self, parser: &Parser) -> Value {
-        json_python_compat_node!("TryStar", self, parser, {
+        let mut node = json_python_compat_node!("TryStar", self, parser, {
             "body": self.body.iter().map(|stmt| stmt.as_python_compat(parser)).collect::<Vec<_>>(),
             "handlers": self.handlers.iter().map(|hndl| hndl.as_python_compat(parser)).collect::<Vec<_>>(),
             "orelse": self.orelse.iter().map(|stmt| stmt.as_python_compat(parser)).collect::<Vec<_>>(),
             "finalbody": self.finalbody.iter().map(|stmt| stmt.as_python_compat(parser)).collect::<Vec<_>>(),
-        })
+        });
+        end_at_last_statement(&mut node);
+        node
     }
 }
 
 impl AsPythonCompat for ExceptHandler {
     fn as_python_compat(&self, parser: &Parser) -> Value {
-        json_python_compat_node!("ExceptHandler", self, parser, {
+        let mut node = json_python_compat_node!("ExceptHandler", self, parser, {
             "type": self.typ.as_python_compat(parser),
-            "name": self.name.as_ref().map_or(json!(null), |s| json!(s)),
+            "name": optional_str(&self.name),
             "body": self.body.iter().map(|stmt| stmt.as_python_compat(parser)).collect::<Vec<_>>(),
-        })
+        });
+        end_at_last_statement(&mut node);
+        node
     }
 }
 
 impl AsPythonCompat for FunctionDef {
     fn as_python_compat(&self, parser: &Parser) -> Value {
-        json_python_compat_node!("FunctionDef", self, parser, {
-            // "name": self.name,
+        let mut node = json_python_compat_node!("FunctionDef", self, parser, {
+            "name": intern_lookup(self.name),
             "args": self.args.as_python_compat(parser),
             "body": self.body.iter().map(|stmt| stmt.as_python_compat(parser)).collect::<Vec<_>>(),
             "decorator_list": self.decorator_list.iter().map(|expr| expr.as_python_compat(parser)).collect::<Vec<_>>(),
             "returns": self.returns.as_python_compat(parser),
-            "type_comment": self.type_comment.as_ref().map_or(json!(null), |s| json!(s)),
+            "type_comment": optional_str(&self.type_comment),
             "type_params": self.type_params.iter().map(|tp| tp.as_python_compat(parser)).collect::<Vec<_>>(),
-        })
+        });
+        set_definition_start(&mut node, parser, self.node, &self.decorator_list);
+        end_at_last_statement(&mut node);
+        node
     }
 }
 
 impl AsPythonCompat for AsyncFunctionDef {
     fn as_python_compat(&self, parser: &Parser) -> Value {
-        json_python_compat_node!("AsyncFunctionDef", self, parser, {
-            "name": self.name,
+        let mut node = json_python_compat_node!("AsyncFunctionDef", self, parser, {
+            "name": intern_lookup(self.name),
             "args": self.args.as_python_compat(parser),
             "body": self.body.iter().map(|stmt| stmt.as_python_compat(parser)).collect::<Vec<_>>(),
             "decorator_list": self.decorator_list.iter().map(|expr| expr.as_python_compat(parser)).collect::<Vec<_>>(),
             "returns": self.returns.as_python_compat(parser),
-            "type_comment": self.type_comment.as_ref().map_or(json!(null), |s| json!(s)),
+            "type_comment": optional_str(&self.type_comment),
             "type_params": self.type_params.iter().map(|tp| tp.as_python_compat(parser)).collect::<Vec<_>>(),
-        })
+        });
+        set_definition_start(&mut node, parser, self.node, &self.decorator_list);
+        end_at_last_statement(&mut node);
+        node
     }
 }
 
 impl AsPythonCompat for ClassDef {
     fn as_python_compat(&self, parser: &Parser) -> Value {
-        json_python_compat_node!("ClassDef", self, parser, {
-            // "name": self.name,
+        let mut node = json_python_compat_node!("ClassDef", self, parser, {
+            "name": intern_lookup(self.name),
             "bases": self.bases.iter().map(|expr| expr.as_python_compat(parser)).collect::<Vec<_>>(),
             "keywords": self.keywords.iter().map(|kw| kw.as_python_compat(parser)).collect::<Vec<_>>(),
             "body": self.body.iter().map(|stmt| stmt.as_python_compat(parser)).collect::<Vec<_>>(),
             "decorator_list": self.decorator_list.iter().map(|expr| expr.as_python_compat(parser)).collect::<Vec<_>>(),
             "type_params": self.type_params.iter().map(|tp| tp.as_python_compat(parser)).collect::<Vec<_>>(),
-        })
+        });
+        set_definition_start(&mut node, parser, self.node, &self.decorator_list);
+        end_at_last_statement(&mut node);
+        node
     }
 }
 
 impl AsPythonCompat for Match {
     fn as_python_compat(&self, parser: &Parser) -> Value {
-        json_python_compat_node!("Match", self, parser, {
+        let mut node = json_python_compat_node!("Match", self, parser, {
             "subject": self.subject.as_python_compat(parser),
             "cases": self.cases.iter().map(|mc| mc.as_python_compat(parser)).collect::<Vec<_>>(),
-        })
+        });
+        end_at_last_statement(&mut node);
+        node
     }
 }
 
 impl AsPythonCompat for MatchCase {
     fn as_python_compat(&self, parser: &Parser) -> Value {
-        json_python_compat_node!("MatchCase", self, parser, {
+        json!({
+            "_type": "match_case",
             "pattern": self.pattern.as_python_compat(parser),
             "guard": self.guard.as_python_compat(parser),
             "body": self.body.iter().map(|stmt| stmt.as_python_compat(parser)).collect::<Vec<_>>(),
@@ -789,23 +1004,89 @@ impl AsPythonCompat for MatchCase {
     }
 }
 
+/// Sequence and or patterns do not store their location, it is taken from
+/// their first and last pattern and the brackets around them.
+fn patterns_location(patterns: &[MatchPattern], parser: &Parser, brackets: bool) -> Node {
+    let (Some(first), Some(last)) = (patterns.first(), patterns.last()) else {
+        return Node::default();
+    };
+    let mut node = Node::new(
+        pattern_location(first, parser).start,
+        pattern_location(last, parser).end,
+    );
+    if brackets {
+        let before = parser.source[..node.start as usize].trim_end();
+        if before.ends_with(['[', '(']) {
+            node.start = before.len() as u32 - 1;
+        }
+        let after = &parser.source[node.end as usize..];
+        let closing = after.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        if closing.starts_with([']', ')']) {
+            node.end += (after.len() - closing.len()) as u32 + 1;
+        }
+    }
+    node
+}
+
+fn pattern_location(pattern: &MatchPattern, parser: &Parser) -> Node {
+    match pattern {
+        MatchPattern::MatchValue(val) => val.node,
+        MatchPattern::MatchSingleton(expr) => expr.get_node(),
+        MatchPattern::MatchSequence(pats) => patterns_location(pats, parser, true),
+        MatchPattern::MatchStar(expr) => {
+            let node = expr.get_node();
+            Node::new(node.start.saturating_sub(1), node.end)
+        }
+        MatchPattern::MatchMapping(map) => map.node,
+        MatchPattern::MatchAs(mas) => mas.node,
+        MatchPattern::MatchClass(cls) => cls.node,
+        MatchPattern::MatchOr(pats) => patterns_location(pats, parser, false),
+    }
+}
+
 impl AsPythonCompat for MatchPattern {
     fn as_python_compat(&self, parser: &Parser) -> Value {
         match self {
             MatchPattern::MatchValue(val) => val.as_python_compat(parser),
-            MatchPattern::MatchSingleton(expr) => expr.as_python_compat(parser),
-            MatchPattern::MatchSequence(pats) => json!(pats
-                .iter()
-                .map(|pat| pat.as_python_compat(parser))
-                .collect::<Vec<_>>()),
-            MatchPattern::MatchStar(expr) => expr.as_python_compat(parser),
+            MatchPattern::MatchSingleton(expr) => {
+                let mut value = json!({
+                    "_type": "MatchSingleton",
+                    "value": expr.as_python_compat(parser)["value"],
+                });
+                set_location(&mut value, parser, expr.get_node());
+                value
+            }
+            MatchPattern::MatchSequence(pats) => {
+                let mut value = json!({
+                    "_type": "MatchSequence",
+                    "patterns": pats.iter().map(|pat| pat.as_python_compat(parser)).collect::<Vec<_>>(),
+                });
+                set_location(&mut value, parser, pattern_location(self, parser));
+                value
+            }
+            MatchPattern::MatchStar(expr) => {
+                let name = match expr {
+                    Expression::Name(name) if name.id != "_" => json!(name.id),
+                    _ => json!(null),
+                };
+                let mut value = json!({
+                    "_type": "MatchStar",
+                    "name": name,
+                });
+                set_location(&mut value, parser, pattern_location(self, parser));
+                value
+            }
             MatchPattern::MatchMapping(map) => map.as_python_compat(parser),
             MatchPattern::MatchAs(mas) => mas.as_python_compat(parser),
             MatchPattern::MatchClass(cls) => cls.as_python_compat(parser),
-            MatchPattern::MatchOr(pats) => json!(pats
-                .iter()
-                .map(|pat| pat.as_python_compat(parser))
-                .collect::<Vec<_>>()),
+            MatchPattern::MatchOr(pats) => {
+                let mut value = json!({
+                    "_type": "MatchOr",
+                    "patterns": pats.iter().map(|pat| pat.as_python_compat(parser)).collect::<Vec<_>>(),
+                });
+                set_location(&mut value, parser, pattern_location(self, parser));
+                value
+            }
         }
     }
 }
@@ -821,8 +1102,8 @@ impl AsPythonCompat for MatchValue {
 impl AsPythonCompat for MatchAs {
     fn as_python_compat(&self, parser: &Parser) -> Value {
         json_python_compat_node!("MatchAs", self, parser, {
-            "name": self.name.as_ref().map_or(json!(null), |s| json!(s)),
             "pattern": self.pattern.as_python_compat(parser),
+            "name": optional_str(&self.name),
         })
     }
 }
@@ -832,7 +1113,7 @@ impl AsPythonCompat for MatchMapping {
         json_python_compat_node!("MatchMapping", self, parser, {
             "keys": self.keys.iter().map(|expr| expr.as_python_compat(parser)).collect::<Vec<_>>(),
             "patterns": self.patterns.iter().map(|pat| pat.as_python_compat(parser)).collect::<Vec<_>>(),
-            "rest": self.rest.as_ref().map_or(json!(null), |s| json!(s)),
+            "rest": optional_str(&self.rest),
         })
     }
 }
@@ -885,8 +1166,18 @@ impl AsPythonCompat for TypeVarTuple {
 
 impl AsPythonCompat for TypeAlias {
     fn as_python_compat(&self, parser: &Parser) -> Value {
+        // The name follows the `type` keyword
+        let after_keyword = &parser.source[self.node.start as usize + "type".len()..];
+        let name_start = parser.source.len() - after_keyword.trim_start().len();
+        let mut name = json!({
+            "_type": "Name",
+            "id": self.name,
+            "ctx": {"_type": "Store"},
+        });
+        let name_node = Node::new(name_start as u32, (name_start + self.name.len()) as u32);
+        set_location(&mut name, parser, name_node);
         json_python_compat_node!("TypeAlias", self, parser, {
-            "name": self.name,
+            "name": name,
             "type_params": self.type_params.iter().map(|tp| tp.as_python_compat(parser)).collect::<Vec<_>>(),
             "value": self.value.as_python_compat(parser),
         })
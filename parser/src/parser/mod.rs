@@ -1,9 +1,12 @@
 pub mod compat;
-use crate::ast::{self, QuoteType};
+use crate::ast;
+#[allow(clippy::module_inception)]
 pub mod parser;
 use crate::token::{Kind, Token};
-use ast::{Node, UnaryOperator};
+use ast::{GetNode, Node, UnaryOperator};
 use miette::Result;
+use num_bigint::BigInt;
+use std::{iter::Peekable, str::Chars};
 
 use crate::{
     error::ParsingError,
@@ -27,33 +30,242 @@ pub fn is_at_compound_statement(token: &Token) -> bool {
     }
 }
 
-// TODO: performance
-pub fn extract_string_inside(val: String) -> String {
-    let delimiters = vec!["\"\"\"", "\"", "'''", "'"];
-    let mut result = String::new();
-    let is_raw = val.starts_with('r') || val.starts_with('R');
-    // drop first char if raw and put in val
-    let val = if is_raw {
-        val.chars().skip(1).collect::<String>()
-    } else {
-        val
+/// Decorators before Python 3.9 can only be a dotted name, optionally called
+/// https://peps.python.org/pep-0614/
+pub fn is_dotted_name_or_call(expr: &Expression) -> bool {
+    fn is_dotted_name(expr: &Expression) -> bool {
+        match expr {
+            Expression::Name(_) => true,
+            Expression::Attribute(attr) => is_dotted_name(&attr.value),
+            _ => false,
+        }
+    }
+    match expr {
+        Expression::Call(call) => is_dotted_name(&call.func),
+        expr => is_dotted_name(expr),
+    }
+}
+
+/// Splits a function signature type comment like `(int, *str) -> bool` into
+/// the argument types and the return type. The argument types are `["..."]`
+/// when only the return type is given.
+/// https://peps.python.org/pep-0484/#suggested-syntax-for-python-2-7-and-straddling-code
+pub fn split_signature_type_comment(comment: &str) -> Option<(Vec<&str>, &str)> {
+    let comment = comment.trim().strip_prefix('(')?;
+    let mut depth = 0;
+    let mut arg_start = 0;
+    let mut args = vec![];
+    for (i, c) in comment.char_indices() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                args.push(comment[arg_start..i].trim());
+                arg_start = i + 1;
+            }
+            ')' if depth == 0 => {
+                let last = comment[arg_start..i].trim();
+                if !last.is_empty() {
+                    args.push(last);
+                }
+                let returns = comment[i + 1..].trim_start().strip_prefix("->")?.trim();
+                return (!returns.is_empty()).then_some((args, returns));
+            }
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Value of an integer literal, e.g. `1_000`, `0x1f`, `0o17` or `0b101`
+pub fn parse_int(text: &str) -> BigInt {
+    let digits = text.replace('_', "");
+    let (radix, digits) = match digits.get(..2) {
+        Some("0x" | "0X") => (16, &digits[2..]),
+        Some("0o" | "0O") => (8, &digits[2..]),
+        Some("0b" | "0B") => (2, &digits[2..]),
+        _ => (10, digits.as_str()),
     };
+    BigInt::parse_bytes(digits.as_bytes(), radix).unwrap_or_default()
+}
+
+/// Value of a float literal, e.g. `1_000.5` or `1e-3`
+pub fn parse_float(text: &str) -> f64 {
+    text.replace('_', "").parse().unwrap_or_default()
+}
+
+/// Value of an imaginary literal, e.g. `1j` or `1.5e3J`
+pub fn parse_imaginary(text: &str) -> f64 {
+    parse_float(&text[..text.len() - 1])
+}
 
-    for delimiter in delimiters {
-        if let Some(val) = val.strip_prefix(delimiter) {
-            let message = format!("String must be enclosed with {}", delimiter);
-            result = val.strip_suffix(delimiter).expect(&message).to_string();
-            break;
+/// Prefix and content of a string or bytes literal without the quotes
+fn split_string_literal(text: &str) -> (&str, &str) {
+    let content_start = text
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(text.len());
+    let (prefix, text) = text.split_at(content_start);
+    for delimiter in ["\"\"\"", "'''", "\"", "'"] {
+        if let Some(content) = text
+            .strip_prefix(delimiter)
+            .and_then(|t| t.strip_suffix(delimiter))
+        {
+            return (prefix, content);
         }
     }
-    // add r back if raw ternary
-    if is_raw {
-        result = format!("r\"{}\"", result);
+    (prefix, text)
+}
+
+/// Prefix and quotes of an f-string starting at `text`, e.g. `("rf", "'''")`
+fn split_fstring_start(text: &str) -> (&str, &str) {
+    let quote_start = text
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(text.len());
+    let (prefix, text) = text.split_at(quote_start);
+    for delimiter in ["\"\"\"", "'''", "\"", "'"] {
+        if text.starts_with(delimiter) {
+            return (prefix, delimiter);
+        }
     }
+    (prefix, "")
+}
+
+fn is_raw_prefix(prefix: &str) -> bool {
+    prefix.contains(['r', 'R'])
+}
 
+/// Value of a string literal with its escape sequences decoded
+/// https://docs.python.org/3/reference/lexical_analysis.html#escape-sequences
+pub fn decode_string_literal(text: &str) -> String {
+    let (prefix, content) = split_string_literal(text);
+    if is_raw_prefix(prefix) {
+        return content.to_string();
+    }
+    decode_escapes(content)
+}
+
+/// Value of a literal part of an f-string, `{{` and `}}` are escaped braces
+fn decode_fstring_middle(text: &str, raw: bool) -> String {
+    let text = text.replace("{{", "{").replace("}}", "}");
+    if raw {
+        text
+    } else {
+        decode_escapes(&text)
+    }
+}
+
+fn decode_escapes(content: &str) -> String {
+    if !content.contains('\\') {
+        return content.to_string();
+    }
+    let mut result = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match decode_escape(&mut chars, false) {
+            Escape::Value(value) => match char::from_u32(value) {
+                Some(c) => result.push(c),
+                None => result.push(char::REPLACEMENT_CHARACTER),
+            },
+            Escape::LineContinuation => {}
+            Escape::Unknown(escape) => {
+                result.push('\\');
+                result.push_str(&escape);
+            }
+        }
+    }
     result
 }
 
+/// Value of a bytes literal with its escape sequences decoded
+pub fn decode_bytes_literal(text: &str) -> Vec<u8> {
+    let (prefix, content) = split_string_literal(text);
+    if is_raw_prefix(prefix) || !content.contains('\\') {
+        return content.as_bytes().to_vec();
+    }
+    let mut result = Vec::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    let mut buf = [0; 4];
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match decode_escape(&mut chars, true) {
+            Escape::Value(value) => result.push(value as u8),
+            Escape::LineContinuation => {}
+            Escape::Unknown(escape) => {
+                result.push(b'\\');
+                result.extend_from_slice(escape.as_bytes());
+            }
+        }
+    }
+    result
+}
+
+enum Escape {
+    Value(u32),
+    LineContinuation,
+    /// Unrecognized escape sequences are left in the string unchanged
+    Unknown(String),
+}
+
+/// Decodes the escape sequence after a backslash
+fn decode_escape(chars: &mut Peekable<Chars>, is_bytes: bool) -> Escape {
+    let Some(c) = chars.next() else {
+        return Escape::Unknown(String::new());
+    };
+    let value = match c {
+        '\n' => return Escape::LineContinuation,
+        '\r' => {
+            chars.next_if_eq(&'\n');
+            return Escape::LineContinuation;
+        }
+        '\\' | '\'' | '"' => c as u32,
+        'a' => 0x07,
+        'b' => 0x08,
+        'f' => 0x0c,
+        'n' => 0x0a,
+        'r' => 0x0d,
+        't' => 0x09,
+        'v' => 0x0b,
+        '0'..='7' => {
+            let mut digits = String::from(c);
+            while digits.len() < 3 {
+                match chars.next_if(|c| c.is_digit(8)) {
+                    Some(c) => digits.push(c),
+                    None => break,
+                }
+            }
+            u32::from_str_radix(&digits, 8).unwrap_or_default()
+        }
+        'x' => return decode_hex_escape(chars, c, 2),
+        'u' if !is_bytes => return decode_hex_escape(chars, c, 4),
+        'U' if !is_bytes => return decode_hex_escape(chars, c, 8),
+        // TODO: `\N{name}` needs the unicode character names
+        c => return Escape::Unknown(c.to_string()),
+    };
+    Escape::Value(value)
+}
+
+fn decode_hex_escape(chars: &mut Peekable<Chars>, kind: char, len: usize) -> Escape {
+    let mut digits = String::new();
+    while digits.len() < len {
+        match chars.next_if(|c| c.is_ascii_hexdigit()) {
+            Some(c) => digits.push(c),
+            None => break,
+        }
+    }
+    match u32::from_str_radix(&digits, 16) {
+        Ok(value) if digits.len() == len => Escape::Value(value),
+        _ => Escape::Unknown(format!("{kind}{digits}")),
+    }
+}
+
 pub fn concat_string_exprs(lhs: Expression, rhs: Expression) -> Result<Expression, ParsingError> {
     use crate::parser::ast::{Constant, ConstantValue};
     match (lhs, rhs) {
@@ -63,31 +275,31 @@ pub fn concat_string_exprs(lhs: Expression, rhs: Expression) -> Result<Expressio
                 end: rhs.node.end,
             };
             let concatnated_string = match (lhs.value, rhs.value) {
-                (ConstantValue::Str(_), ConstantValue::Str(_)) => {
+                (ConstantValue::Str(lhs), ConstantValue::Str(rhs)) => {
                     Expression::Constant(Box::new(Constant {
                         node,
-                        value: ConstantValue::Str(QuoteType::Concat),
+                        value: ConstantValue::Str(lhs + &rhs),
                     }))
                 }
-                (ConstantValue::Bytes, ConstantValue::Bytes) => {
+                (ConstantValue::Bytes(mut lhs), ConstantValue::Bytes(rhs)) => {
+                    lhs.extend(rhs);
                     Expression::Constant(Box::new(Constant {
                         node,
-                        value: ConstantValue::Bytes,
+                        value: ConstantValue::Bytes(lhs),
                     }))
                 }
-                (ConstantValue::Bytes, _) => {
-                    panic!("Cannot concat bytes and string");
+                (ConstantValue::Bytes(_), _) | (_, ConstantValue::Bytes(_)) => {
+                    return Err(mixed_bytes_error(node));
                 }
-                (_, ConstantValue::Bytes) => {
-                    panic!("Can only concat bytes with other bytes");
-                }
-                _ => panic!("Cannot concat string"),
+                _ => return Err(invalid_concat_error(node)),
             };
             Ok(concatnated_string)
         }
         (Expression::JoinedStr(fstring_lhs), Expression::JoinedStr(fstring_rhs)) => {
             let mut values = fstring_lhs.values;
-            values.extend(fstring_rhs.values);
+            for value in fstring_rhs.values {
+                push_fstring_value(&mut values, value);
+            }
             Ok(Expression::JoinedStr(Box::new(JoinedStr {
                 node: Node {
                     start: fstring_lhs.node.start,
@@ -97,55 +309,71 @@ pub fn concat_string_exprs(lhs: Expression, rhs: Expression) -> Result<Expressio
             })))
         }
         (Expression::JoinedStr(fstring_lhs), Expression::Constant(const_rhs)) => {
-            let mut values = fstring_lhs.values;
+            let node = Node::new(fstring_lhs.node.start, const_rhs.node.end);
             match const_rhs.value {
-                ConstantValue::Str(s) => {
-                    values.push(Expression::Constant(Box::new(Constant {
-                        node: Node {
-                            start: const_rhs.node.start,
-                            end: const_rhs.node.end,
-                        },
-                        value: ConstantValue::Str(s),
-                    })));
-                }
-                ConstantValue::Bytes => {
-                    panic!("Cannot concat string and bytes");
-                }
-                _ => panic!("Cannot concat string"),
+                ConstantValue::Str(_) => {}
+                ConstantValue::Bytes(_) => return Err(mixed_bytes_error(node)),
+                _ => return Err(invalid_concat_error(node)),
             }
-            Ok(Expression::JoinedStr(Box::new(JoinedStr {
-                node: Node {
-                    start: fstring_lhs.node.start,
-                    end: const_rhs.node.end,
-                },
-                values,
-            })))
+            let mut values = fstring_lhs.values;
+            push_fstring_value(&mut values, Expression::Constant(const_rhs));
+            Ok(Expression::JoinedStr(Box::new(JoinedStr { node, values })))
         }
         (Expression::Constant(const_lhs), Expression::JoinedStr(fstring_rhs)) => {
-            let const_expr = match const_lhs.value {
-                ConstantValue::Str(_) => Expression::Constant(Box::new(Constant {
-                    node: Node {
-                        start: const_lhs.node.start,
-                        end: fstring_rhs.node.end,
-                    },
-                    value: ConstantValue::Str(QuoteType::Concat),
-                })),
-                ConstantValue::Bytes => {
-                    panic!("Cannot concat string and bytes");
+            let node = Node::new(const_lhs.node.start, fstring_rhs.node.end);
+            match const_lhs.value {
+                ConstantValue::Str(_) => {}
+                ConstantValue::Bytes(_) => return Err(mixed_bytes_error(node)),
+                _ => return Err(invalid_concat_error(node)),
+            }
+            let mut values = vec![];
+            push_fstring_value(&mut values, Expression::Constant(const_lhs));
+            for value in fstring_rhs.values {
+                push_fstring_value(&mut values, value);
+            }
+            Ok(Expression::JoinedStr(Box::new(JoinedStr { node, values })))
+        }
+        (lhs, rhs) => Err(invalid_concat_error(Node::new(
+            lhs.get_node().start,
+            rhs.get_node().end,
+        ))),
+    }
+}
+
+/// Adds a part to the values of an f-string. Like in CPython adjacent string
+/// constants are merged into one and empty ones are left out.
+fn push_fstring_value(values: &mut Vec<Expression>, value: Expression) {
+    use crate::parser::ast::ConstantValue;
+    if let Expression::Constant(constant) = &value {
+        if let ConstantValue::Str(s) = &constant.value {
+            if s.is_empty() {
+                return;
+            }
+            if let Some(Expression::Constant(last)) = values.last_mut() {
+                if let ConstantValue::Str(last_value) = &mut last.value {
+                    last_value.push_str(s);
+                    last.node.end = constant.node.end;
+                    return;
                 }
-                _ => panic!("Cannot concat string"),
-            };
-            let mut values = vec![const_expr];
-            values.extend(fstring_rhs.values);
-            Ok(Expression::JoinedStr(Box::new(JoinedStr {
-                node: Node {
-                    start: const_lhs.node.start,
-                    end: fstring_rhs.node.end,
-                },
-                values,
-            })))
+            }
         }
-        _ => panic!("Cannot concat string"),
+    }
+    values.push(value);
+}
+
+fn mixed_bytes_error(node: Node) -> ParsingError {
+    ParsingError::InvalidSyntax {
+        msg: "cannot mix bytes and nonbytes literals".to_string(),
+        advice: String::new(),
+        span: (node.start as usize, node.len() as usize),
+    }
+}
+
+fn invalid_concat_error(node: Node) -> ParsingError {
+    ParsingError::InvalidSyntax {
+        msg: "invalid syntax".to_string(),
+        advice: String::new(),
+        span: (node.start as usize, node.len() as usize),
     }
 }
 
@@ -158,13 +386,3 @@ pub fn map_unary_operator(kind: &Kind) -> UnaryOperator {
         _ => panic!("Not a unary operator"),
     }
 }
-
-pub fn is_iterable(expr: &Expression) -> bool {
-    matches!(
-        expr,
-        Expression::List { .. }
-            | Expression::Tuple { .. }
-            | Expression::Set { .. }
-            | Expression::Name { .. }
-    )
-}
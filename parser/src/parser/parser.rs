@@ -1,5 +1,3 @@
-use core::panic;
-
 /// Some functions in this file have misleading names.
 /// For example star expressions are defined slightly differently in python grammar and references.
 /// So there might be duplicates of both. Try to migrate the wrong names to how they are called in:
@@ -11,13 +9,20 @@ use std::{
 
 use miette::Result;
 
-use super::{concat_string_exprs, is_at_compound_statement, is_iterable, map_unary_operator};
+use super::{
+    concat_string_exprs, decode_fstring_middle, is_at_compound_statement, is_dotted_name_or_call,
+    is_raw_prefix, map_unary_operator, push_fstring_value, split_fstring_start,
+};
 use crate::{
     error::ParsingError,
     get_row_col_position,
     intern::{Interner, StrId},
     lexer::Lexer,
-    parser::{ast::*, extract_string_inside},
+    parser::{
+        ast::*, decode_bytes_literal, decode_string_literal, parse_float, parse_imaginary,
+        parse_int,
+    },
+    python_version::PythonVersion,
     token::{Kind, Token},
 };
 static INTERNER: OnceLock<RwLock<Interner>> = OnceLock::new();
@@ -34,6 +39,13 @@ pub fn intern(s: &str) -> StrId {
     v2.intern(s)
 }
 
+/// Options that change what syntax the parser accepts
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParserOptions {
+    /// Syntax newer than this version is reported as an error
+    pub target_version: PythonVersion,
+}
+
 #[derive(Debug)]
 pub struct Parser<'a> {
     pub identifiers_start_offset: Vec<(u32, u32, String)>,
@@ -48,18 +60,50 @@ pub struct Parser<'a> {
     // This is incremented when we see an opening bracket and decremented when we
     // see a closing bracket.
     nested_expression_list: u32,
+    // Syntax errors collected while parsing. The parser recovers from most
+    // errors so that a single mistake does not hide the rest of the file.
+    errors: Vec<ParsingError>,
+    options: ParserOptions,
 }
 
 #[allow(unused)]
 impl<'a> Parser<'a> {
     pub fn new(source: &'a str) -> Self {
-        let mut lexer = Lexer::new(source);
+        Self::with_lexer(source, Lexer::new(source))
+    }
+
+    pub fn with_options(source: &'a str, options: ParserOptions) -> Self {
+        Self {
+            options,
+            ..Self::new(source)
+        }
+    }
+
+    /// Creates a parser for the statements starting at `offset`.
+    /// See `Lexer::new_at`.
+    pub(crate) fn new_at(
+        source: &'a str,
+        offset: u32,
+        indent_stack: Vec<usize>,
+        options: ParserOptions,
+    ) -> Self {
+        let mut parser = Self {
+            options,
+            ..Self::with_lexer(source, Lexer::new_at(source, offset, indent_stack))
+        };
+        // Skip comments as if the tokens before `offset` were parsed
+        if offset > 0 && matches!(parser.cur_kind(), Kind::Comment | Kind::NL) {
+            parser.advance(false);
+        }
+        parser
+    }
+
+    fn with_lexer(source: &'a str, mut lexer: Lexer<'a>) -> Self {
         let cur_token = lexer.next_token();
 
         let mut nested_expression_list = 0;
         match cur_token.kind {
             Kind::LeftParen | Kind::LeftBrace | Kind::LeftBracket => nested_expression_list += 1,
-            Kind::RightParen | Kind::RightBrace | Kind::RightBracket => nested_expression_list -= 1,
             _ => {}
         }
         let identifiers_offset =
@@ -78,24 +122,30 @@ impl<'a> Parser<'a> {
             prev_nonwhitespace_token_end: prev_token_end,
             nested_expression_list,
             identifiers_start_offset: identifiers_offset,
+            errors: vec![],
+            options: ParserOptions::default(),
         }
     }
 
-    pub fn parse(&mut self) -> Result<Module, ParsingError> {
+    /// Parses the whole source and returns the module together with the syntax
+    /// errors found in it. Statements that fail to parse are replaced with
+    /// `Statement::Invalid` so the rest of the tree is still available.
+    pub fn parse(&mut self) -> (Module, Vec<ParsingError>) {
         let node = self.start_node();
         let mut body = vec![];
         while self.cur_kind() != Kind::Eof {
             if self.consume_whitespace_and_comments() {
                 continue;
             }
-            let stmt = if is_at_compound_statement(self.cur_token()) {
-                self.parse_compound_statement()
-            } else {
-                self.parse_simple_statement()
-            };
-            match stmt {
-                Ok(stmt) => body.push(stmt),
-                Err(err) => return Err(err),
+            // A dedent at module level can only be left over after recovering
+            // from an error inside an indented block.
+            if self.eat(Kind::Dedent) {
+                continue;
+            }
+            let stmt_node = self.start_node();
+            match self.parse_statement() {
+                Ok(stmts) => body.extend(stmts),
+                Err(err) => body.push(self.recover_statement(stmt_node, err)),
             }
         }
 
@@ -103,7 +153,180 @@ impl<'a> Parser<'a> {
         // Remove the EOF offset
         node.end.saturating_sub(1);
 
-        Ok(Module::new(node, body))
+        (Module::new(node, body), self.take_errors())
+    }
+
+    /// The errors of the parser together with the errors of the lexer in
+    /// source order. Parser errors about an invalid token only repeat the
+    /// lexer error of that token, they are dropped.
+    fn take_errors(&mut self) -> Vec<ParsingError> {
+        let mut errors = std::mem::take(&mut self.errors);
+        for lex_error in self.lexer.errors.drain(..).map(ParsingError::from) {
+            let span = lex_error.span();
+            errors.retain(|error| error.span() != span);
+            let index = errors.partition_point(|error| error.span().0 <= span.0);
+            errors.insert(index, lex_error);
+        }
+        errors
+    }
+
+    /// Parses the statements of a block again after an edit. The parser must
+    /// start at the first statement. Parsing stops at `stop`, the start of the
+    /// statement after the edited statements, or at the end of the module.
+    /// Returns `None` when the edited statements have syntax errors or do not
+    /// end at `stop`, in that case the whole module has to be parsed again.
+    pub(crate) fn parse_region(&mut self, stop: Option<u32>) -> Option<Module> {
+        let node = self.start_node();
+        let mut body = vec![];
+        loop {
+            if self.consume_whitespace_and_comments() {
+                continue;
+            }
+            if stop.is_some_and(|stop| self.cur_token.start >= stop)
+                || matches!(self.cur_kind(), Kind::Eof | Kind::Indent | Kind::Dedent)
+            {
+                break;
+            }
+            body.extend(self.parse_statement().ok()?);
+        }
+        let at_stop = match stop {
+            Some(stop) => {
+                self.cur_token.start == stop
+                    && !matches!(self.cur_kind(), Kind::Eof | Kind::Indent | Kind::Dedent)
+            }
+            None => self.at(Kind::Eof),
+        };
+        if !at_stop || !self.errors.is_empty() || !self.lexer.errors.is_empty() {
+            return None;
+        }
+        Some(Module::new(self.finish_node(node), body))
+    }
+
+    /// Records `err` and skips tokens until the start of the next statement.
+    /// Returns a placeholder statement covering the skipped source.
+    fn recover_statement(&mut self, node: Node, err: ParsingError) -> Statement {
+        self.errors.push(err);
+        self.synchronize();
+        let end = self.prev_token_end.max(node.start);
+        Statement::Invalid(Box::new(Invalid {
+            node: Node::new(node.start, end),
+        }))
+    }
+
+    /// Skips tokens until the parser is at a statement boundary.
+    /// If the broken statement opens an indented block, the block is skipped as well.
+    /// Unclosed brackets are abandoned when a statement keyword starts a new line.
+    fn synchronize(&mut self) {
+        let mut indent_level = 0u32;
+        loop {
+            match self.cur_kind() {
+                Kind::Eof => break,
+                Kind::Indent => {
+                    indent_level += 1;
+                    self.bump_any();
+                }
+                Kind::Dedent => {
+                    if indent_level == 0 {
+                        break;
+                    }
+                    indent_level -= 1;
+                    self.bump_any();
+                    if indent_level == 0 && !self.at(Kind::Indent) {
+                        break;
+                    }
+                }
+                Kind::NewLine | Kind::SemiColon if self.nested_expression_list == 0 => {
+                    self.bump_any();
+                    if indent_level == 0 && !self.at(Kind::Indent) {
+                        break;
+                    }
+                }
+                kind => {
+                    if self.nested_expression_list > 0
+                        && matches!(
+                            kind,
+                            Kind::Def
+                                | Kind::Class
+                                | Kind::Import
+                                | Kind::Return
+                                | Kind::Pass
+                                | Kind::Raise
+                                | Kind::While
+                                | Kind::With
+                                | Kind::Try
+                        )
+                        && self.is_first_on_line()
+                    {
+                        self.abandon_brackets();
+                        if indent_level == 0 && !self.at(Kind::Indent) {
+                            break;
+                        }
+                        continue;
+                    }
+                    self.bump_any();
+                }
+            }
+        }
+    }
+
+    fn is_first_on_line(&self) -> bool {
+        let start = self.prev_token_end as usize;
+        let end = self.cur_token.start as usize;
+        start < end && self.source[start..end].contains('\n')
+    }
+
+    /// Treats all open brackets as closed and lexes the current line again
+    /// so that indentation is tracked correctly from here on.
+    fn abandon_brackets(&mut self) {
+        self.nested_expression_list = 0;
+        self.lexer.restart_line_at(self.cur_token.start);
+        self.cur_token = self.lexer.next_token();
+        if matches!(self.cur_kind(), Kind::Comment | Kind::NL) {
+            self.advance(false);
+        }
+    }
+
+    fn at_expression_terminator(&self) -> bool {
+        matches!(
+            self.cur_kind(),
+            Kind::NewLine
+                | Kind::Eof
+                | Kind::SemiColon
+                | Kind::Colon
+                | Kind::Comma
+                | Kind::Assign
+                | Kind::RightParen
+                | Kind::RightBrace
+                | Kind::RightBracket
+                | Kind::Indent
+                | Kind::Dedent
+        )
+    }
+
+    fn unexpected_token_error(&self, msg: String) -> ParsingError {
+        let token = self.cur_token();
+        ParsingError::InvalidSyntax {
+            msg,
+            advice: String::new(),
+            span: (token.start as usize, (token.end - token.start) as usize),
+        }
+    }
+
+    fn syntax_error(&self, msg: &str, node: Node) -> ParsingError {
+        ParsingError::InvalidSyntax {
+            msg: msg.to_string(),
+            advice: String::new(),
+            span: (node.start as usize, node.len() as usize),
+        }
+    }
+
+    /// Reports `feature` when the target version is older than `version`.
+    /// Parsing continues as if the feature was supported.
+    fn require_version(&mut self, feature: &str, version: PythonVersion, node: Node) {
+        if self.options.target_version < version {
+            let msg = format!("{feature} requires Python {version}");
+            self.errors.push(self.syntax_error(&msg, node));
+        }
     }
 
     fn start_node(&self) -> Node {
@@ -130,7 +353,11 @@ impl<'a> Parser<'a> {
     fn peek_token(&mut self) -> Result<Token, ParsingError> {
         let token = self.lexer.peek_token();
         if matches!(token.kind, Kind::Error) {
-            panic!("Next token is Error {token:?}");
+            return Err(ParsingError::InvalidSyntax {
+                msg: "invalid token".to_string(),
+                advice: String::new(),
+                span: (token.start as usize, (token.end - token.start) as usize),
+            });
         }
         Ok(token)
     }
@@ -186,7 +413,7 @@ impl<'a> Parser<'a> {
                 self.nested_expression_list += 1
             }
             Kind::RightParen | Kind::RightBrace | Kind::RightBracket => {
-                self.nested_expression_list -= 1
+                self.nested_expression_list = self.nested_expression_list.saturating_sub(1)
             }
             _ => {}
         }
@@ -212,9 +439,8 @@ impl<'a> Parser<'a> {
     /// Expect a `Kind` or return error
     pub fn expect(&mut self, kind: Kind) -> Result<(), ParsingError> {
         if !self.at(kind) {
-            let found = &self.cur_token;
-            let line = &self.get_offset_line_number(found.start);
-            panic!("Expected {:?} but found {:?} line: {line:}", kind, found);
+            let found = self.cur_kind();
+            return Err(self.unexpected_token_error(format!("Expected {kind} but found {found}")));
         }
         self.bump_any();
         Ok(())
@@ -223,17 +449,49 @@ impl<'a> Parser<'a> {
     /// Expect any of `Kinds` or return error
     pub fn expect_any(&mut self, kind: Vec<Kind>) -> Result<(), ParsingError> {
         if !kind.contains(&self.cur_token.kind) {
-            let mut expected = String::new();
-            for kind in kind {
-                expected.push_str(&format!("{:?}, ", kind));
-            }
-            let found = self.cur_token.kind;
-            panic!("Expected one of {:?} but found {:?}", expected, found);
+            let expected = kind
+                .iter()
+                .map(|k| k.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let found = self.cur_kind();
+            return Err(self
+                .unexpected_token_error(format!("Expected one of {expected} but found {found}")));
         }
         self.bump_any();
         Ok(())
     }
 
+    /// Returns the type of a `# type: ...` comment that follows `offset` on
+    /// the same line. `# type: ignore` is not a type comment.
+    /// https://peps.python.org/pep-0484/#type-comments
+    fn type_comment_after(&self, offset: u32) -> Option<String> {
+        let rest = &self.source[offset as usize..];
+        let line = &rest[..rest.find(['\n', '\r']).unwrap_or(rest.len())];
+        let comment = line
+            .trim_start_matches([' ', '\t', '\x0c'])
+            .strip_prefix('#')?;
+        let typ = comment.trim_start().strip_prefix("type:")?.trim();
+        let is_ignore = typ
+            .strip_prefix("ignore")
+            .is_some_and(|rest| !rest.starts_with(|c: char| c.is_alphanumeric() || c == '_'));
+        if typ.is_empty() || is_ignore {
+            return None;
+        }
+        Some(typ.to_string())
+    }
+
+    /// The signature type comment of a function can also be on the first line
+    /// of its body
+    fn signature_type_comment_after(&self, offset: u32) -> Option<String> {
+        let rest = &self.source[offset as usize..];
+        let line_end = rest.find('\n')?;
+        if !rest[..line_end].trim().is_empty() {
+            return self.type_comment_after(offset);
+        }
+        self.type_comment_after(offset + line_end as u32 + 1)
+    }
+
     fn get_offset_line_number(&self, pos: u32) -> u32 {
         match self.lexer.line_starts.binary_search(&pos) {
             Ok(line) => line as u32 + 1,
@@ -260,14 +518,14 @@ impl<'a> Parser<'a> {
             Kind::Nonlocal => self.parse_nonlocal_statement(),
             other => {
                 if self.cur_kind() == Kind::Indent {
-                    panic!("Unexpected token {:?}", self.cur_token());
+                    return Err(self.unexpected_token_error("unexpected indent".to_string()));
                 } else {
                     self.parse_assignment_or_expression_statement()
                 }
             }
         }?;
 
-        self.err_if_statement_not_ending_in_new_line_or_semicolon(stmt.get_node(), &stmt);
+        self.err_if_statement_not_ending_in_new_line_or_semicolon();
 
         Ok(stmt)
     }
@@ -297,7 +555,11 @@ impl<'a> Parser<'a> {
                 } else if matches!(self.peek_kind(), Ok(Kind::With)) {
                     self.parse_with_statement()
                 } else {
-                    panic!("");
+                    self.bump_any();
+                    let found = self.cur_kind();
+                    Err(self.unexpected_token_error(format!(
+                        "Expected def, for or with after async but found {found}"
+                    )))
                 }
             }
             other => {
@@ -315,18 +577,27 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn err_if_statement_not_ending_in_new_line_or_semicolon(
-        &mut self,
-        node: Node,
-        stmt: &Statement,
-    ) {
+    /// Reports tokens left over after a simple statement and skips them up to
+    /// the end of the logical line. The statement itself is kept.
+    fn err_if_statement_not_ending_in_new_line_or_semicolon(&mut self) {
         while self.eat(Kind::WhiteSpace) || self.eat(Kind::Comment) {}
 
         if !matches!(
             self.cur_kind(),
             Kind::NewLine | Kind::NL | Kind::SemiColon | Kind::Eof
         ) {
-            panic!("Statement does not end in new line or semicolon {:?}", stmt);
+            self.errors
+                .push(self.unexpected_token_error("invalid syntax".to_string()));
+            while !matches!(
+                self.cur_kind(),
+                Kind::NewLine | Kind::SemiColon | Kind::Eof | Kind::Dedent
+            ) || self.nested_expression_list > 0
+            {
+                if self.at(Kind::Eof) {
+                    break;
+                }
+                self.bump_any();
+            }
         }
     }
 
@@ -336,44 +607,32 @@ impl<'a> Parser<'a> {
         let test = self.parse_named_expression()?;
         self.expect(Kind::Colon)?;
         let body = self.parse_suite()?;
-        let mut orelse: Option<If> = None;
+        let mut elifs = vec![];
         while self.at(Kind::Elif) {
             let elif_node = self.start_node();
             self.bump(Kind::Elif);
             let elif_test = self.parse_named_expression()?;
             self.expect(Kind::Colon)?;
             let body = self.parse_suite()?;
-            let if_value = If {
+            elifs.push(If {
                 node: self.finish_node(elif_node),
                 test: elif_test,
                 body,
                 orelse: vec![],
-            };
-            if let Some(val) = &mut orelse {
-                val.update_orelse(vec![Statement::IfStatement(Box::new(if_value))]);
-            } else {
-                orelse = Some(if_value);
-            }
+            });
         }
 
-        let mut single_else_body: Option<Vec<Statement>> = None;
-        if self.eat(Kind::Else) {
+        let mut or_else_vec = if self.eat(Kind::Else) {
             self.expect(Kind::Colon)?;
-            let else_body = self.parse_suite()?;
-            if let Some(val) = &mut orelse {
-                val.update_orelse(else_body);
-            } else {
-                single_else_body = Some(else_body);
-            }
-        };
-
-        // if we had any else or elif statements, we need to wrap them in a vec
-        // otherwise we just return an empty vec as the else block of the if statement
-        let or_else_vec = if let Some(val) = orelse {
-            vec![Statement::IfStatement(Box::new(val))]
+            self.parse_suite()?
         } else {
-            single_else_body.unwrap_or_default()
+            vec![]
         };
+        // Each elif is an if statement in the else block of the previous one
+        for mut elif in elifs.into_iter().rev() {
+            elif.update_orelse(or_else_vec);
+            or_else_vec = vec![Statement::IfStatement(Box::new(elif))];
+        }
 
         Ok(Statement::IfStatement(Box::new(If {
             node: self.finish_node_chomped(node),
@@ -411,19 +670,21 @@ impl<'a> Parser<'a> {
         self.bump(Kind::For);
         let target = self.parse_target_list()?;
         self.expect(Kind::In)?;
+        let iter_node = self.start_node();
         let iter_list = self.parse_starred_list(Kind::Colon)?;
         let iter = match iter_list.len() {
             0 => {
-                panic!("Unexpected token {:?}", self.cur_token());
+                return Err(self.unexpected_token_error("Expected expression".to_string()));
             }
             1 => iter_list.into_iter().next().unwrap(),
             _ => Expression::Tuple(Box::new(Tuple {
-                node: self.finish_node(node),
+                node: self.finish_node(iter_node),
                 elements: iter_list,
             })),
         };
 
         self.expect(Kind::Colon)?;
+        let type_comment = self.type_comment_after(self.prev_token_end);
         let body = self.parse_suite()?;
         let orelse = if self.eat(Kind::Else) {
             self.expect(Kind::Colon)?;
@@ -439,6 +700,7 @@ impl<'a> Parser<'a> {
                 iter,
                 body,
                 orelse,
+                type_comment,
             })))
         } else {
             Ok(Statement::ForStatement(Box::new(For {
@@ -447,6 +709,7 @@ impl<'a> Parser<'a> {
                 iter,
                 body,
                 orelse,
+                type_comment,
             })))
         }
     }
@@ -457,6 +720,7 @@ impl<'a> Parser<'a> {
         self.bump(Kind::With);
         let items = self.parse_with_items()?;
         self.expect(Kind::Colon)?;
+        let type_comment = self.type_comment_after(self.prev_token_end);
         let body = self.parse_suite()?;
 
         if is_async {
@@ -464,12 +728,14 @@ impl<'a> Parser<'a> {
                 node: self.finish_node_chomped(node),
                 items,
                 body,
+                type_comment,
             })))
         } else {
             Ok(Statement::WithStatement(Box::new(With {
                 node: self.finish_node_chomped(node),
                 items,
                 body,
+                type_comment,
             })))
         }
     }
@@ -477,12 +743,52 @@ impl<'a> Parser<'a> {
     fn parse_with_items(&mut self) -> Result<Vec<WithItem>, ParsingError> {
         let mut items = vec![];
 
-        if self.eat(Kind::LeftParen) {
+        if self.at(Kind::LeftParen) {
+            let node = self.start_node();
+            self.bump(Kind::LeftParen);
             items.push(self.parse_with_item()?);
-            while self.eat(Kind::Comma) & !self.at(Kind::RightParen) {
+            let mut has_comma = false;
+            while self.eat(Kind::Comma) {
+                has_comma = true;
+                if self.at(Kind::RightParen) {
+                    break;
+                }
                 items.push(self.parse_with_item()?);
             }
             self.expect(Kind::RightParen)?;
+            // The parentheses belong to the expression of the first item, e.g.
+            // `with (a if b else c) as f:` or `with (a, b) as f:`
+            if !self.at(Kind::Colon) && items.iter().all(|item| item.optional_vars.is_none()) {
+                let context_expr = if has_comma {
+                    Expression::Tuple(Box::new(Tuple {
+                        node: self.finish_node(node),
+                        elements: items.into_iter().map(|item| item.context_expr).collect(),
+                    }))
+                } else {
+                    items.pop().unwrap().context_expr
+                };
+                let optional_vars = if self.eat(Kind::As) {
+                    Some(self.parse_target()?)
+                } else {
+                    None
+                };
+                let mut items = vec![WithItem {
+                    node: self.finish_node(node),
+                    context_expr,
+                    optional_vars,
+                }];
+                while self.eat(Kind::Comma) & !self.at(Kind::Colon) {
+                    items.push(self.parse_with_item()?);
+                }
+                return Ok(items);
+            }
+            if items.len() > 1 || items.iter().any(|item| item.optional_vars.is_some()) {
+                self.require_version(
+                    "parenthesized context manager",
+                    PythonVersion::Py39,
+                    self.finish_node(node),
+                );
+            }
             return Ok(items);
         }
         items.push(self.parse_with_item()?);
@@ -512,7 +818,7 @@ impl<'a> Parser<'a> {
     fn parse_try_statement(&mut self) -> Result<Statement, ParsingError> {
         let node = self.start_node();
         let mut is_try_star = false;
-        self.expect(Kind::Try);
+        self.expect(Kind::Try)?;
         self.expect(Kind::Colon)?;
         let body = self.parse_suite()?;
         let handlers = if self.at(Kind::Except) {
@@ -561,7 +867,13 @@ impl<'a> Parser<'a> {
         while self.at(Kind::Except) {
             let node = self.start_node();
             self.bump(Kind::Except);
-            self.bump(Kind::Mul);
+            if self.eat(Kind::Mul) {
+                self.require_version(
+                    "except* clause",
+                    PythonVersion::Py311,
+                    self.finish_node(node),
+                );
+            }
             let typ = if !self.at(Kind::Colon) {
                 Some(self.parse_expression()?)
             } else {
@@ -613,6 +925,7 @@ impl<'a> Parser<'a> {
         };
 
         self.expect(Kind::Colon)?;
+        let type_comment = self.signature_type_comment_after(self.prev_token_end);
         let body = self.parse_suite()?;
         if is_async {
             Ok(Statement::AsyncFunctionDef(Arc::new(
@@ -623,7 +936,7 @@ impl<'a> Parser<'a> {
                     body,
                     decorators,
                     return_type,
-                    None,
+                    type_comment.as_deref(),
                     type_params,
                 ),
             )))
@@ -635,8 +948,7 @@ impl<'a> Parser<'a> {
                 body,
                 decorators,
                 return_type,
-                // TODO: type comment
-                None,
+                type_comment.as_deref(),
                 type_params,
             ))))
         }
@@ -647,6 +959,13 @@ impl<'a> Parser<'a> {
         let mut decorators = vec![];
         while self.eat(Kind::MatrixMul) {
             let name = self.parse_named_expression()?;
+            if !is_dotted_name_or_call(&name) {
+                self.require_version(
+                    "arbitrary decorator expression",
+                    PythonVersion::Py39,
+                    name.get_node(),
+                );
+            }
             decorators.push(name);
             self.consume_whitespace_and_comments();
         }
@@ -657,7 +976,7 @@ impl<'a> Parser<'a> {
             }
             Kind::Async => {
                 self.bump_any();
-                self.expect(Kind::Def);
+                self.expect(Kind::Def)?;
                 self.parse_function_definition(node, decorators, true)
             }
             _ => self.parse_class_definition(decorators, Some(node)),
@@ -709,6 +1028,11 @@ impl<'a> Parser<'a> {
         // This identifier is match word
         // match is a soft keyword
         self.bump(Kind::Identifier);
+        self.require_version(
+            "match statement",
+            PythonVersion::Py310,
+            self.finish_node(node),
+        );
         let subject = self.parse_subject()?;
         self.expect(Kind::Colon)?;
         self.expect(Kind::NewLine)?;
@@ -733,11 +1057,7 @@ impl<'a> Parser<'a> {
     // but it does not accept expression as a value
     // https://docs.python.org/3/reference/grammar.html
     fn parse_star_named_expression(&mut self) -> Result<Expression, ParsingError> {
-        if self.at(Kind::Mul) {
-            self.parse_or_expr()
-        } else {
-            self.parse_named_expression()
-        }
+        self.parse_starred_item()
     }
 
     fn parse_star_named_expressions(&mut self) -> Result<Expression, ParsingError> {
@@ -808,12 +1128,12 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_pattern(&mut self) -> Result<MatchPattern, ParsingError> {
+        let node = self.start_node();
         let or_pattern = self.parse_or_pattern()?;
 
-        if self.at(Kind::As) {
-            let node = self.start_node();
+        if self.eat(Kind::As) {
             let name = Some(self.cur_token().to_string(self.source));
-            self.bump(Kind::As);
+            self.expect(Kind::Identifier)?;
             Ok(MatchPattern::MatchAs(Box::new(MatchAs {
                 node: self.finish_node(node),
                 pattern: Some(or_pattern),
@@ -873,15 +1193,15 @@ impl<'a> Parser<'a> {
             | Kind::None
             | Kind::True
             | Kind::False
-            | Kind::StringLiteral | Kind::RawBytes | Kind::Bytes
+            | Kind::StringLiteral | Kind::Unicode | Kind::RawBytes | Kind::Bytes
             // The signed numbers are also allowed
             | Kind::Minus | Kind::Plus => {
                 self.parse_literal_pattern()
 
                 },
             _ => {
-                let node = self.start_node();
-                panic!("Unexpected token {:?}", self.cur_kind());
+                let found = self.cur_kind();
+                Err(self.unexpected_token_error(format!("Expected pattern but found {found}")))
             },
         }
     }
@@ -889,6 +1209,9 @@ impl<'a> Parser<'a> {
     // https://docs.python.org/3/reference/compound_stmts.html#literal-patterns
     fn parse_literal_pattern(&mut self) -> Result<MatchPattern, ParsingError> {
         let node = self.start_node();
+        if matches!(self.cur_kind(), Kind::None | Kind::True | Kind::False) {
+            return Ok(MatchPattern::MatchSingleton(self.parse_atom()?));
+        }
         let value = self.parse_binary_arithmetic_operation(0)?;
         Ok(MatchPattern::MatchValue(MatchValue {
             node: self.finish_node(node),
@@ -936,12 +1259,12 @@ impl<'a> Parser<'a> {
     fn parse_attr(&mut self) -> Result<Expression, ParsingError> {
         let node = self.start_node();
         let id = self.cur_token.to_string(self.source);
+        self.expect(Kind::Identifier)?;
         let mut expr = Ok(Expression::Name(Box::new(Name {
             node: self.finish_node(node),
             id,
             parenthesized: false,
         })));
-        self.expect(Kind::Identifier);
         while self.eat(Kind::Dot) {
             let attr_val = self.cur_token().to_string(self.source);
             self.expect(Kind::Identifier)?;
@@ -1017,7 +1340,7 @@ impl<'a> Parser<'a> {
         &mut self,
         class_name: Expression,
     ) -> Result<MatchPattern, ParsingError> {
-        let node = self.start_node();
+        let node = Node::new(class_name.get_node().start, class_name.get_node().start);
         let class = class_name;
         self.expect(Kind::LeftParen)?;
         let mut patterns = vec![];
@@ -1037,7 +1360,9 @@ impl<'a> Parser<'a> {
                 kwd_patterns.push(self.parse_pattern()?);
             } else {
                 if seen_keyword_pattern {
-                    panic!("Positional arguments cannot come after keyword arguments.");
+                    self.errors.push(self.unexpected_token_error(
+                        "positional patterns follow keyword patterns".to_string(),
+                    ));
                 }
                 patterns.push(self.parse_pattern()?);
             }
@@ -1061,11 +1386,23 @@ impl<'a> Parser<'a> {
             self.expect(Kind::RightBrace)?;
             Ok(MatchPattern::MatchSequence(pattern))
         } else if self.eat(Kind::LeftParen) {
-            let pattern = self.parse_open_sequence_pattern()?;
+            if self.eat(Kind::RightParen) {
+                return Ok(MatchPattern::MatchSequence(vec![]));
+            }
+            let first = self.parse_maybe_star_pattern()?;
+            // Without a comma the parentheses only group the pattern
+            if !matches!(first, MatchPattern::MatchStar(_)) && self.eat(Kind::RightParen) {
+                return Ok(first);
+            }
+            let mut patterns = vec![first];
+            while self.eat(Kind::Comma) && !self.at(Kind::RightParen) {
+                patterns.push(self.parse_maybe_star_pattern()?);
+            }
             self.expect(Kind::RightParen)?;
-            Ok(MatchPattern::MatchSequence(pattern))
+            Ok(MatchPattern::MatchSequence(patterns))
         } else {
-            panic!("Unexpected token {:?}", self.cur_token());
+            let found = self.cur_kind();
+            Err(self.unexpected_token_error(format!("Expected sequence pattern but found {found}")))
         }
     }
 
@@ -1096,7 +1433,14 @@ impl<'a> Parser<'a> {
     }
     fn parse_maybe_star_pattern(&mut self) -> Result<MatchPattern, ParsingError> {
         if self.eat(Kind::Mul) {
-            self.parse_capture_or_wildcard_pattern()
+            let node = self.start_node();
+            let id = self.cur_token().to_string(self.source);
+            self.expect(Kind::Identifier)?;
+            Ok(MatchPattern::MatchStar(Expression::Name(Box::new(Name {
+                node: self.finish_node(node),
+                id,
+                parenthesized: false,
+            }))))
         } else {
             self.parse_pattern()
         }
@@ -1127,12 +1471,25 @@ impl<'a> Parser<'a> {
                 if self.eat(Kind::Comment) || self.consume_whitespace_and_newline() {
                     continue;
                 }
-                let stmt = self.parse_statement()?;
-                stmts.extend(stmt);
+                let node = self.start_node();
+                match self.parse_statement() {
+                    Ok(stmt) => stmts.extend(stmt),
+                    Err(err) => stmts.push(self.recover_statement(node, err)),
+                }
             }
             Ok(stmts)
         } else {
             let stmt = self.parse_statement_list()?;
+            // The suite is on the same line as the header, e.g. `try: a`, and the
+            // next clause of the statement starts on the following line
+            if self.at(Kind::NewLine)
+                && matches!(
+                    self.peek_kind(),
+                    Ok(Kind::Elif | Kind::Else | Kind::Except | Kind::Finally)
+                )
+            {
+                self.bump(Kind::NewLine);
+            }
             Ok(stmt)
         }
     }
@@ -1153,6 +1510,9 @@ impl<'a> Parser<'a> {
         let stmt = self.parse_simple_statement()?;
         stmts.push(stmt);
         while self.eat(Kind::SemiColon) {
+            if matches!(self.cur_kind(), Kind::NewLine | Kind::Eof) {
+                break;
+            }
             let stmt = self.parse_simple_statement()?;
             stmts.push(stmt);
         }
@@ -1200,6 +1560,7 @@ impl<'a> Parser<'a> {
             node: self.finish_node(start),
             targets,
             value,
+            type_comment: self.type_comment_after(self.prev_token_end),
         })))
     }
 
@@ -1259,6 +1620,7 @@ impl<'a> Parser<'a> {
             Kind::AddAssign => AugAssignOp::Add,
             Kind::SubAssign => AugAssignOp::Sub,
             Kind::MulAssign => AugAssignOp::Mult,
+            Kind::MatrixMulAssign => AugAssignOp::MatMult,
             Kind::DivAssign => AugAssignOp::Div,
             Kind::IntDivAssign => AugAssignOp::FloorDiv,
             Kind::ModAssign => AugAssignOp::Mod,
@@ -1302,7 +1664,7 @@ impl<'a> Parser<'a> {
     fn parse_return_statement(&mut self) -> Result<Statement, ParsingError> {
         let node = self.start_node();
         self.bump(Kind::Return);
-        let value = if self.at(Kind::NewLine) {
+        let value = if matches!(self.cur_kind(), Kind::NewLine | Kind::SemiColon | Kind::Eof) {
             None
         } else {
             Some(self.parse_expression_list()?)
@@ -1446,7 +1808,10 @@ impl<'a> Parser<'a> {
             self.bump_any();
             aliases.push(self.parse_alias("*".to_string(), node));
         } else {
-            panic!("Unexpected token {:?}", self.cur_token());
+            let found = self.cur_kind();
+            return Err(
+                self.unexpected_token_error(format!("Expected import name but found {found}"))
+            );
         }
         Ok(Statement::ImportFrom(Box::new(ImportFrom {
             node: self.finish_node(import_node),
@@ -1481,9 +1846,7 @@ impl<'a> Parser<'a> {
                 Kind::Ellipsis => {
                     level += 3;
                 }
-                _ => {
-                    panic!("lol");
-                }
+                _ => unreachable!(),
             }
             self.bump_any();
         }
@@ -1494,16 +1857,26 @@ impl<'a> Parser<'a> {
             while self.eat(Kind::Dot) {
                 module.push('.');
                 module.push_str(self.cur_token().as_str(self.source));
-                self.expect(Kind::Identifier);
+                self.expect(Kind::Identifier)?;
             }
         }
         Ok((module, level))
     }
 
     // https://docs.python.org/3/library/ast.html#ast.Expr
+    // https://docs.python.org/3/reference/expressions.html#expression-lists
+    // Elements of an expression list can be unpacked, e.g. `a, *b = c`
+    fn parse_star_expression(&mut self) -> Result<Expression, ParsingError> {
+        if self.at(Kind::Mul) {
+            self.parse_starred_item()
+        } else {
+            self.parse_expression()
+        }
+    }
+
     fn parse_expressions(&mut self) -> Result<Expression, ParsingError> {
         let node = self.start_node();
-        let expr = self.parse_expression()?;
+        let expr = self.parse_star_expression()?;
 
         let mut exprs = vec![];
         if self.at(Kind::Comma) {
@@ -1514,7 +1887,7 @@ impl<'a> Parser<'a> {
                 if self.at(Kind::Eof) || !self.cur_kind().is_star_expression() {
                     break;
                 }
-                exprs.push(self.parse_expression()?);
+                exprs.push(self.parse_star_expression()?);
             }
         } else {
             return Ok(expr);
@@ -1556,8 +1929,10 @@ impl<'a> Parser<'a> {
             identifier_node = self.finish_node(identifier_node);
             if self.eat(Kind::Walrus) {
                 let value = self.parse_expression()?;
+                let node = self.finish_node(node);
+                self.require_version("assignment expression", PythonVersion::Py38, node);
                 return Ok(Expression::NamedExpr(Box::new(NamedExpression {
-                    node: self.finish_node(node),
+                    node,
                     target: Expression::Name(Box::new(Name {
                         node: identifier_node,
                         id: identifier,
@@ -1616,7 +1991,7 @@ impl<'a> Parser<'a> {
     fn parse_paren_form_or_generator(&mut self) -> Result<Expression, ParsingError> {
         let node = self.start_node();
         self.expect(Kind::LeftParen)?;
-        if self.at(Kind::RightParen) {
+        if self.eat(Kind::RightParen) {
             return Ok(Expression::Tuple(Box::new(Tuple {
                 node: self.finish_node(node),
                 elements: vec![],
@@ -1635,17 +2010,13 @@ impl<'a> Parser<'a> {
         let first_expr =
             if self.at(Kind::Identifier) && matches!(self.peek_kind(), Ok(Kind::Walrus)) {
                 self.parse_named_expression()?
-            } else if self.eat(Kind::Mul) {
-                let expr = self.parse_or_expr()?;
-                Expression::Starred(Box::new(Starred {
-                    node: self.finish_node(node),
-                    value: expr,
-                }))
+            } else if self.at(Kind::Mul) {
+                self.parse_starred_item()?
             } else {
                 self.parse_expression()?
             };
 
-        if matches!(self.cur_kind(), Kind::For) || matches!(self.peek_kind(), Ok(Kind::For)) {
+        if matches!(self.cur_kind(), Kind::For | Kind::Async) {
             let generators = self.parse_comp_for()?;
             self.expect(Kind::RightParen)?;
             return Ok(Expression::Generator(Box::new(Generator {
@@ -1720,17 +2091,10 @@ impl<'a> Parser<'a> {
     // https://docs.python.org/3/reference/simple_stmts.html#grammar-token-python-grammar-target
     fn parse_target(&mut self) -> Result<Expression, ParsingError> {
         let node = self.start_node();
-        let mut targets = vec![];
         let target = match self.cur_kind() {
             Kind::Type | Kind::Match | Kind::Identifier => match self.peek_kind() {
-                Ok(Kind::LeftBrace) => {
-                    let atom = self.parse_atom()?;
-                    self.parse_subscript(node, atom)?
-                }
-                Ok(Kind::Dot) => {
-                    let atom = self.parse_atom()?;
-                    self.parse_attribute_ref(node, atom)?
-                }
+                // Trailers can be chained, e.g. `self.items[i:]`
+                Ok(Kind::LeftBrace | Kind::Dot) => self.parse_primary(None)?,
                 _ => {
                     let identifier = self.cur_token().to_string(self.source);
                     let mut identifier_node = self.start_node();
@@ -1778,38 +2142,22 @@ impl<'a> Parser<'a> {
                     }))
                 }
             }
-            Kind::Mul => Expression::Starred(Box::new(Starred {
-                node: self.finish_node(node),
-                value: self.parse_target()?,
-            })),
-            _ => panic!("invalid target"),
-        };
-        targets.push(target);
-        while self.eat(Kind::Comma) {
-            // check if current kind can be start of a target
-            if !matches!(
-                self.cur_kind(),
-                Kind::Match
-                    | Kind::Type
-                    | Kind::Identifier
-                    | Kind::LeftBrace
-                    | Kind::LeftParen
-                    | Kind::Mul
-            ) {
-                break;
+            Kind::Mul => {
+                self.bump(Kind::Mul);
+                let value = self.parse_target()?;
+                Expression::Starred(Box::new(Starred {
+                    node: self.finish_node(node),
+                    value,
+                }))
             }
-            let next_target = self.parse_target()?;
-            targets.push(next_target);
-        }
-
-        if targets.len() == 1 {
-            Ok(targets.remove(0))
-        } else {
-            Ok(Expression::Tuple(Box::new(Tuple {
-                node: self.finish_node(node),
-                elements: targets,
-            })))
-        }
+            _ => {
+                let found = self.cur_kind();
+                return Err(
+                    self.unexpected_token_error(format!("Expected target but found {found}"))
+                );
+            }
+        };
+        Ok(target)
     }
 
     fn parse_dict_or_set(&mut self) -> Result<Expression, ParsingError> {
@@ -1876,7 +2224,9 @@ impl<'a> Parser<'a> {
     ) -> Result<Expression, ParsingError> {
         if self.at(Kind::For) || self.at(Kind::Async) && matches!(self.peek_kind(), Ok(Kind::For)) {
             let Some(key) = first_key else {
-                panic!("cannot use ** in dict comprehension");
+                return Err(
+                    self.syntax_error("dict unpacking cannot be used in dict comprehension", node)
+                );
             };
 
             // make sure the first key is some
@@ -1895,18 +2245,11 @@ impl<'a> Parser<'a> {
                 self.expect(Kind::Comma)?;
                 self.consume_whitespace_and_newline();
             }
-            let mut keys = match first_key {
-                Some(k) => vec![k],
-                None => vec![],
-            };
+            let mut keys = vec![first_key];
             let mut values = vec![first_value];
             while !self.eat(Kind::RightBracket) {
                 let (key, value) = self.parse_double_starred_kv_pair()?;
-
-                if let Some(k) = key {
-                    keys.push(k)
-                }
-
+                keys.push(key);
                 values.push(value);
                 if !self.at(Kind::RightBracket) {
                     self.expect(Kind::Comma)?;
@@ -1981,15 +2324,10 @@ impl<'a> Parser<'a> {
 
     // https://docs.python.org/3/reference/expressions.html#expression-lists
     fn parse_starred_item(&mut self) -> Result<Expression, ParsingError> {
-        let mut node = self.start_node();
+        let node = self.start_node();
         if self.eat(Kind::Mul) {
-            let starred_value_kind = self.cur_kind();
+            // Whether the value is iterable is only known at runtime
             let expr = self.parse_or_expr()?;
-            node = self.finish_node(node);
-            if !is_iterable(&expr) {
-                // self.unepxted_token(node, starred_value_kind);
-                panic!();
-            }
             return Ok(Expression::Starred(Box::new(Starred {
                 node: self.finish_node(node),
                 value: expr,
@@ -2011,7 +2349,7 @@ impl<'a> Parser<'a> {
             })));
         }
         if self.eat(Kind::Lambda) {
-            let params_list = self.parse_parameters(true).expect("lambda params");
+            let params_list = self.parse_parameters(true)?;
             self.expect(Kind::Colon)?;
             let expr = self.parse_expression()?;
 
@@ -2027,32 +2365,38 @@ impl<'a> Parser<'a> {
     // https://docs.python.org/3/reference/expressions.html#boolean-operations
     fn parse_or_test(&mut self) -> Result<Expression, ParsingError> {
         let node = self.start_node();
-        let mut expr = self.parse_and_test()?;
-        if self.eat(Kind::Or) {
-            let rhs = self.parse_or_test()?;
-            expr = Expression::BoolOp(Box::new(BoolOperation {
-                node: self.finish_node(node),
-                op: BooleanOperator::Or,
-                values: vec![expr, rhs],
-            }));
+        let expr = self.parse_and_test()?;
+        if !self.at(Kind::Or) {
+            return Ok(expr);
         }
-        Ok(expr)
+        // `a or b or c` is a single operation with three values
+        let mut values = vec![expr];
+        while self.eat(Kind::Or) {
+            values.push(self.parse_and_test()?);
+        }
+        Ok(Expression::BoolOp(Box::new(BoolOperation {
+            node: self.finish_node(node),
+            op: BooleanOperator::Or,
+            values,
+        })))
     }
 
     // https://docs.python.org/3/reference/expressions.html#boolean-operations
     fn parse_and_test(&mut self) -> Result<Expression, ParsingError> {
         let node = self.start_node();
-        let mut expr = self.parse_not_test()?;
-        while self.at(Kind::And) {
-            self.bump_any();
-            let rhs = self.parse_not_test()?;
-            expr = Expression::BoolOp(Box::new(BoolOperation {
-                node: self.finish_node(node),
-                op: BooleanOperator::And,
-                values: vec![expr, rhs],
-            }));
+        let expr = self.parse_not_test()?;
+        if !self.at(Kind::And) {
+            return Ok(expr);
         }
-        Ok(expr)
+        let mut values = vec![expr];
+        while self.eat(Kind::And) {
+            values.push(self.parse_not_test()?);
+        }
+        Ok(Expression::BoolOp(Box::new(BoolOperation {
+            node: self.finish_node(node),
+            op: BooleanOperator::And,
+            values,
+        })))
     }
 
     // https://docs.python.org/3/reference/expressions.html#boolean-operations
@@ -2114,7 +2458,7 @@ impl<'a> Parser<'a> {
     fn parse_xor_expr(&mut self) -> Result<Expression, ParsingError> {
         let node = self.start_node();
         let mut and_expr = self.parse_and_expr()?;
-        if self.eat(Kind::BitXor) {
+        while self.eat(Kind::BitXor) {
             let rhs = self.parse_and_expr()?;
             and_expr = Expression::BinOp(Box::new(BinOp {
                 node: self.finish_node(node),
@@ -2241,22 +2585,38 @@ impl<'a> Parser<'a> {
     // previous primary
     fn parse_primary(&mut self, base: Option<Expression>) -> Result<Expression, ParsingError> {
         let next = self.peek_token();
-        let node = self.start_node();
-        let call_node = self.start_node();
+        // A chained primary starts where its base starts
+        let node = match &base {
+            Some(base) => Node::new(base.get_node().start, base.get_node().start),
+            None => self.start_node(),
+        };
         let mut atom_or_primary = if let Some(base) = base {
             base
         } else if self.cur_kind().is_atom() {
             self.parse_atom()?
+        } else if self.at_expression_terminator() {
+            // The expression is missing, e.g. `x = ` or `foo(a, )`. Report it and
+            // continue with a placeholder so the rest of the statement is kept.
+            self.errors
+                .push(self.unexpected_token_error("Expected expression".to_string()));
+            return Ok(Expression::Invalid(Box::new(Invalid {
+                node: Node::new(self.cur_token.start, self.cur_token.start),
+            })));
         } else {
-            panic!("not a primary {:?}", self.cur_token());
+            let found = self.cur_kind();
+            return Err(
+                self.unexpected_token_error(format!("Expected expression but found {found}"))
+            );
         };
 
         let mut primary = if self.at(Kind::Dot) {
             self.parse_attribute_ref(node, atom_or_primary)
         } else if self.at(Kind::LeftBrace) {
             // https://docs.python.org/3/reference/expressions.html#slicings
-            self.parse_subscript(atom_or_primary.get_node(), atom_or_primary)
-        } else if self.eat(Kind::LeftParen) {
+            self.parse_subscript(node, atom_or_primary)
+        } else if self.at(Kind::LeftParen) {
+            let paren_node = self.start_node();
+            self.bump(Kind::LeftParen);
             // parse call
             // https://docs.python.org/3/reference/expressions.html#calls
             let mut positional_args = vec![];
@@ -2293,8 +2653,9 @@ impl<'a> Parser<'a> {
                     keyword_args.push(kwarg);
                 } else {
                     if seen_keyword {
-                        let node_end = self.finish_node(node);
-                        panic!("Positional arguments cannot come after keyword arguments.");
+                        self.errors.push(self.unexpected_token_error(
+                            "positional argument follows keyword argument".to_string(),
+                        ));
                     }
                     let arg = self.parse_named_expression()?;
                     positional_args.push(arg);
@@ -2306,19 +2667,19 @@ impl<'a> Parser<'a> {
 
             self.bump(Kind::Comma);
             if self.at(Kind::Async) || self.at(Kind::For) {
+                let Some(element) = positional_args.into_iter().next() else {
+                    return Err(self.unexpected_token_error("invalid syntax".to_string()));
+                };
                 let comprehension = self.parse_comp_for()?;
+                self.expect(Kind::RightParen)?;
                 let arg = Expression::Generator(Box::new(Generator {
-                    node: self.finish_node(node),
-                    element: positional_args
-                        .into_iter()
-                        .next()
-                        .expect("generator passed to function does not have an element"),
+                    node: self.finish_node(paren_node),
+                    element,
                     generators: comprehension,
                 }));
-                self.expect(Kind::RightParen)?;
 
                 Ok(Expression::Call(Box::new(Call {
-                    node,
+                    node: self.finish_node(node),
                     func: atom_or_primary,
                     args: vec![arg],
                     keywords: vec![],
@@ -2329,10 +2690,7 @@ impl<'a> Parser<'a> {
                 self.expect(Kind::RightParen)?;
 
                 Ok(Expression::Call(Box::new(Call {
-                    node: Node {
-                        start: atom_or_primary.get_node().start,
-                        end: self.finish_node(call_node).end,
-                    },
+                    node: self.finish_node(node),
                     func: atom_or_primary,
                     args: positional_args,
                     keywords: keyword_args,
@@ -2372,25 +2730,28 @@ impl<'a> Parser<'a> {
             } else if self.at(Kind::Mul) {
                 let star_arg_node = self.start_node();
                 self.bump(Kind::Mul);
+                let value = self.parse_expression()?;
                 let star_arg = Expression::Starred(Box::new(Starred {
                     node: self.finish_node(star_arg_node),
-                    value: self.parse_expression()?,
+                    value,
                 }));
                 positional_args.push(star_arg);
             } else if self.at(Kind::Pow) {
                 let kwarg_node = self.start_node();
                 self.bump(Kind::Pow);
                 seen_keyword = true;
+                let value = self.parse_expression()?;
                 let kwarg = Keyword {
                     node: self.finish_node(kwarg_node),
                     arg: None,
-                    value: self.parse_expression()?,
+                    value,
                 };
                 keyword_args.push(kwarg);
             } else {
                 if seen_keyword {
-                    let node_end = self.finish_node(node);
-                    panic!("Positional arguments cannot come after keyword arguments.");
+                    self.errors.push(self.unexpected_token_error(
+                        "positional argument follows keyword argument".to_string(),
+                    ));
                 }
                 let arg = self.parse_named_expression()?;
                 positional_args.push(arg);
@@ -2444,16 +2805,13 @@ impl<'a> Parser<'a> {
         if self.at(Kind::Yield) {
             self.parse_yield_expression()
         } else if self.at(Kind::LeftBrace) {
-            let list_expr = self.parse_list();
-            return list_expr;
+            self.parse_list()
         } else if self.at(Kind::LeftBracket) {
-            let dict_or_set_expr = self.parse_dict_or_set();
-            return dict_or_set_expr;
+            self.parse_dict_or_set()
         } else if self.at(Kind::LeftParen) {
-            let tuple_or_named_expr = self.parse_paren_form_or_generator();
-            return tuple_or_named_expr;
+            self.parse_paren_form_or_generator()
         } else if self.at(Kind::Identifier) {
-            return self.parse_identifier();
+            self.parse_identifier()
         // Try to map to one of atoms like: number, string, ...
         } else {
             // value must be cloned to be assigned to the node
@@ -2469,12 +2827,12 @@ impl<'a> Parser<'a> {
                         parenthesized: false,
                     }))
                 }
-                Kind::Integer => {
+                Kind::Integer | Kind::Binary => {
                     let val = self.cur_token.to_string(self.source);
                     self.bump_any();
                     Expression::Constant(Box::new(Constant {
                         node: self.finish_node(start),
-                        value: ConstantValue::Int,
+                        value: ConstantValue::Int(parse_int(&val)),
                     }))
                 }
                 Kind::None => {
@@ -2503,44 +2861,40 @@ impl<'a> Parser<'a> {
                     self.bump_any();
                     Expression::Constant(Box::new(Constant {
                         node: self.finish_node(start),
-                        value: ConstantValue::Complex,
+                        value: ConstantValue::Complex {
+                            real: 0.0,
+                            imag: parse_imaginary(&val),
+                        },
                     }))
                 }
                 Kind::Bytes => {
                     let val = self.cur_token.to_string(self.source);
-                    let bytes_val = extract_string_inside(
-                        val.strip_prefix('b')
-                            .expect("bytes literal must start with b")
-                            .to_string(),
-                    )
-                    .into_bytes();
+                    let bytes_val = decode_bytes_literal(&val);
                     self.bump_any();
                     Expression::Constant(Box::new(Constant {
                         node: self.finish_node(start),
-                        value: ConstantValue::Bytes,
+                        value: ConstantValue::Bytes(bytes_val),
                     }))
                 }
-                Kind::StringLiteral => {
+                Kind::StringLiteral | Kind::Unicode => {
                     let val = self.cur_token.as_str(self.source);
                     self.bump_any();
                     Expression::Constant(Box::new(Constant {
                         node: self.finish_node(start),
-                        value: ConstantValue::Str(QuoteType::from(val)),
+                        value: ConstantValue::Str(decode_string_literal(val)),
                     }))
                 }
 
                 Kind::RawBytes => {
                     let val = self.cur_token.to_string(self.source);
-                    // rb or br appear in the beginning of raw bytes
-                    let bytes_val =
-                        extract_string_inside(val.chars().skip(2).collect::<String>()).into_bytes();
+                    let bytes_val = decode_bytes_literal(&val);
                     self.bump_any();
                     Expression::Constant(Box::new(Constant {
                         node: self.finish_node(start),
-                        value: ConstantValue::Bytes,
+                        value: ConstantValue::Bytes(bytes_val),
                     }))
                 }
-                Kind::FStringStart => {
+                Kind::FStringStart | Kind::RawFStringStart => {
                     self.bump_any();
                     let fstring = self.parse_fstring(start)?;
                     Expression::JoinedStr(Box::new(JoinedStr {
@@ -2553,7 +2907,7 @@ impl<'a> Parser<'a> {
                     self.bump_any();
                     Expression::Constant(Box::new(Constant {
                         node: self.finish_node(start),
-                        value: ConstantValue::Float,
+                        value: ConstantValue::Float(parse_float(&val)),
                     }))
                 }
                 Kind::ExponentFloat => {
@@ -2561,7 +2915,7 @@ impl<'a> Parser<'a> {
                     self.bump_any();
                     Expression::Constant(Box::new(Constant {
                         node: self.finish_node(start),
-                        value: ConstantValue::Float,
+                        value: ConstantValue::Float(parse_float(&val)),
                     }))
                 }
                 Kind::ImaginaryPointFloat => {
@@ -2569,7 +2923,10 @@ impl<'a> Parser<'a> {
                     self.bump_any();
                     Expression::Constant(Box::new(Constant {
                         node: self.finish_node(start),
-                        value: ConstantValue::Complex,
+                        value: ConstantValue::Complex {
+                            real: 0.0,
+                            imag: parse_imaginary(&val),
+                        },
                     }))
                 }
                 Kind::ImaginaryExponentFloat => {
@@ -2577,7 +2934,10 @@ impl<'a> Parser<'a> {
                     self.bump_any();
                     Expression::Constant(Box::new(Constant {
                         node: self.finish_node(start),
-                        value: ConstantValue::Complex,
+                        value: ConstantValue::Complex {
+                            real: 0.0,
+                            imag: parse_imaginary(&val),
+                        },
                     }))
                 }
                 Kind::Ellipsis => {
@@ -2587,13 +2947,20 @@ impl<'a> Parser<'a> {
                         value: ConstantValue::Ellipsis,
                     }))
                 }
+                // The lexer already reported why the token is invalid
+                Kind::Error => {
+                    self.bump_any();
+                    Expression::Invalid(Box::new(Invalid {
+                        node: self.finish_node(start),
+                    }))
+                }
                 // TODO: is there something for octal and Hexadecimal?
                 Kind::Octal => {
                     let val = self.cur_token.to_string(self.source);
                     self.bump_any();
                     Expression::Constant(Box::new(Constant {
                         node: self.finish_node(start),
-                        value: ConstantValue::Int,
+                        value: ConstantValue::Int(parse_int(&val)),
                     }))
                 }
                 Kind::Hexadecimal => {
@@ -2601,14 +2968,13 @@ impl<'a> Parser<'a> {
                     self.bump_any();
                     Expression::Constant(Box::new(Constant {
                         node: self.finish_node(start),
-                        value: ConstantValue::Int,
+                        value: ConstantValue::Int(parse_int(&val)),
                     }))
                 }
                 _ => {
-                    let line = self.get_offset_line_number(start.start);
                     let kind = self.cur_kind();
-                    let value = self.cur_token().to_string(self.source);
-                    panic!("token {kind:?} {value:?} {start:?} is not atom");
+                    return Err(self
+                        .unexpected_token_error(format!("Expected expression but found {kind}")));
                 }
             };
 
@@ -2635,7 +3001,7 @@ impl<'a> Parser<'a> {
                                 self.bump_any();
                                 Expression::Constant(Box::new(Constant {
                                     node: self.finish_node(start),
-                                    value: ConstantValue::Int,
+                                    value: ConstantValue::Int(parse_int(&val)),
                                 }))
                             }
                             Kind::None => {
@@ -2664,45 +3030,40 @@ impl<'a> Parser<'a> {
                                 self.bump_any();
                                 Expression::Constant(Box::new(Constant {
                                     node: self.finish_node(start),
-                                    value: ConstantValue::Complex,
+                                    value: ConstantValue::Complex {
+                                        real: 0.0,
+                                        imag: parse_imaginary(&val),
+                                    },
                                 }))
                             }
                             Kind::Bytes => {
                                 let val = &self.cur_token().to_string(self.source);
-                                let bytes_val = extract_string_inside(
-                                    val.strip_prefix('b')
-                                        .expect("bytes literal must start with b")
-                                        .to_string(),
-                                )
-                                .into_bytes();
+                                let bytes_val = decode_bytes_literal(val);
                                 self.bump_any();
                                 Expression::Constant(Box::new(Constant {
                                     node: self.finish_node(start),
-                                    value: ConstantValue::Bytes,
+                                    value: ConstantValue::Bytes(bytes_val),
                                 }))
                             }
-                            Kind::StringLiteral => {
+                            Kind::StringLiteral | Kind::Unicode => {
                                 let val = self.cur_token().as_str(self.source);
                                 self.bump_any();
                                 Expression::Constant(Box::new(Constant {
                                     node: self.finish_node(start),
-                                    value: ConstantValue::Str(QuoteType::from(val)),
+                                    value: ConstantValue::Str(decode_string_literal(val)),
                                 }))
                             }
 
                             Kind::RawBytes => {
                                 let val = &self.cur_token().to_string(self.source);
-                                // rb or br appear in the beginning of raw bytes
-                                let bytes_val =
-                                    extract_string_inside(val.chars().skip(2).collect::<String>())
-                                        .into_bytes();
+                                let bytes_val = decode_bytes_literal(val);
                                 self.bump_any();
                                 Expression::Constant(Box::new(Constant {
                                     node: self.finish_node(start),
-                                    value: ConstantValue::Bytes,
+                                    value: ConstantValue::Bytes(bytes_val),
                                 }))
                             }
-                            Kind::FStringStart => {
+                            Kind::FStringStart | Kind::RawFStringStart => {
                                 self.bump_any();
                                 let fstring = self.parse_fstring(start)?;
                                 Expression::JoinedStr(Box::new(JoinedStr {
@@ -2715,7 +3076,7 @@ impl<'a> Parser<'a> {
                                 self.bump_any();
                                 Expression::Constant(Box::new(Constant {
                                     node: self.finish_node(start),
-                                    value: ConstantValue::Float,
+                                    value: ConstantValue::Float(parse_float(&val)),
                                 }))
                             }
                             Kind::ExponentFloat => {
@@ -2723,7 +3084,7 @@ impl<'a> Parser<'a> {
                                 self.bump_any();
                                 Expression::Constant(Box::new(Constant {
                                     node: self.finish_node(start),
-                                    value: ConstantValue::Float,
+                                    value: ConstantValue::Float(parse_float(&val)),
                                 }))
                             }
                             Kind::ImaginaryPointFloat => {
@@ -2731,7 +3092,10 @@ impl<'a> Parser<'a> {
                                 self.bump_any();
                                 Expression::Constant(Box::new(Constant {
                                     node: self.finish_node(start),
-                                    value: ConstantValue::Complex,
+                                    value: ConstantValue::Complex {
+                                        real: 0.0,
+                                        imag: parse_imaginary(&val),
+                                    },
                                 }))
                             }
                             Kind::ImaginaryExponentFloat => {
@@ -2739,7 +3103,10 @@ impl<'a> Parser<'a> {
                                 self.bump_any();
                                 Expression::Constant(Box::new(Constant {
                                     node: self.finish_node(start),
-                                    value: ConstantValue::Complex,
+                                    value: ConstantValue::Complex {
+                                        real: 0.0,
+                                        imag: parse_imaginary(&val),
+                                    },
                                 }))
                             }
                             Kind::Ellipsis => {
@@ -2755,7 +3122,7 @@ impl<'a> Parser<'a> {
                                 self.bump_any();
                                 Expression::Constant(Box::new(Constant {
                                     node: self.finish_node(start),
-                                    value: ConstantValue::Int,
+                                    value: ConstantValue::Int(parse_int(&val)),
                                 }))
                             }
                             Kind::Hexadecimal => {
@@ -2763,14 +3130,14 @@ impl<'a> Parser<'a> {
                                 self.bump_any();
                                 Expression::Constant(Box::new(Constant {
                                     node: self.finish_node(start),
-                                    value: ConstantValue::Int,
+                                    value: ConstantValue::Int(parse_int(&val)),
                                 }))
                             }
                             _ => {
-                                let line = self.get_offset_line_number(start.start);
                                 let kind = self.cur_kind();
-                                let value = self.cur_token().to_string(self.source);
-                                panic!("token {kind:?} {value:?} {start:?} is not atom");
+                                return Err(self.unexpected_token_error(format!(
+                                    "Expected string but found {kind}"
+                                )));
                             }
                         };
 
@@ -2795,7 +3162,7 @@ impl<'a> Parser<'a> {
                 }
             }
 
-            return Ok(expr);
+            Ok(expr)
         }
     }
 
@@ -2822,16 +3189,21 @@ impl<'a> Parser<'a> {
                 value,
             })));
         }
-        if self.at(Kind::NewLine) || self.at(Kind::Eof) {
+        if matches!(
+            self.cur_kind(),
+            Kind::NewLine
+                | Kind::Eof
+                | Kind::SemiColon
+                | Kind::RightParen
+                | Kind::RightBrace
+                | Kind::RightBracket
+        ) {
             return Ok(Expression::Yield(Box::new(Yield {
                 node: self.finish_node(yield_node),
                 value: None,
             })));
         }
-        let value = match self.parse_expression_list() {
-            Ok(expr) => Some(expr),
-            _ => None,
-        };
+        let value = Some(self.parse_expression_list()?);
         Ok(Expression::Yield(Box::new(Yield {
             node: self.finish_node(yield_node),
             value,
@@ -2843,14 +3215,17 @@ impl<'a> Parser<'a> {
         let node = self.start_node();
         let mut expressions = vec![];
         expressions.push(self.parse_expression()?);
-        while self.eat(Kind::Comma) && !self.at(Kind::Eof) {
-            if !self.cur_kind().is_star_expression() {
+        // A trailing comma makes a tuple, e.g. `return x,`
+        let mut is_tuple = false;
+        while self.eat(Kind::Comma) {
+            is_tuple = true;
+            if self.at(Kind::Eof) || !self.cur_kind().is_star_expression() {
                 break;
             }
             let expr = self.parse_expression()?;
             expressions.push(expr);
         }
-        if expressions.len() == 1 {
+        if !is_tuple {
             return Ok(expressions.pop().unwrap());
         }
         Ok(Expression::Tuple(Box::new(Tuple {
@@ -2861,7 +3236,7 @@ impl<'a> Parser<'a> {
 
     fn parse_starred_expression_real(&mut self) -> Result<Expression, ParsingError> {
         let node = self.start_node();
-        self.expect(Kind::Mul);
+        self.expect(Kind::Mul)?;
         let expr = self.parse_expression()?;
         Ok(Expression::Starred(Box::new(Starred {
             node: self.finish_node(node),
@@ -2915,24 +3290,26 @@ impl<'a> Parser<'a> {
         let node = self.start_node();
 
         let mut elements: Vec<Expression> = vec![];
+        // `a[b,]` and `a[*b]` are indexed with a tuple
+        let mut is_tuple = false;
         while !self.at(Kind::RightBrace) {
             if self.at(Kind::Mul) {
                 elements.push(self.parse_starred_expression_real()?);
-                continue;
+                is_tuple = true;
+            } else {
+                elements.push(self.parse_slice()?);
             }
-            elements.push(self.parse_slice()?);
             if !self.eat(Kind::Comma) {
                 break;
             }
+            is_tuple = true;
         }
+        let node = self.finish_node(node);
         self.expect(Kind::RightBrace)?;
-        if elements.len() == 1 {
+        if elements.len() == 1 && !is_tuple {
             return Ok(elements.pop().unwrap());
         }
-        Ok(Expression::Tuple(Box::new(Tuple {
-            node: self.finish_node(node),
-            elements,
-        })))
+        Ok(Expression::Tuple(Box::new(Tuple { node, elements })))
     }
 
     fn parse_slice(&mut self) -> Result<Expression, ParsingError> {
@@ -2951,7 +3328,7 @@ impl<'a> Parser<'a> {
                     Ok(n)
                 // [expr:
                 } else {
-                    self.expect(Kind::Colon);
+                    self.expect(Kind::Colon)?;
                     // [expr::
                     if self.eat(Kind::Colon) {
                         // [expr:: end
@@ -2975,12 +3352,12 @@ impl<'a> Parser<'a> {
 
                     // [expr: ,|] end
                     } else if self.at(Kind::RightBrace) || self.at(Kind::Comma) {
-                        return Ok(Expression::Slice(Box::new(Slice {
+                        Ok(Expression::Slice(Box::new(Slice {
                             node: self.finish_node(node),
                             lower: Some(n),
                             upper: None,
                             step: None,
-                        })));
+                        })))
                     // [expr:expr
                     } else {
                         let upper_or_step = Some(self.parse_expression()?);
@@ -2988,30 +3365,30 @@ impl<'a> Parser<'a> {
                         if self.eat(Kind::Colon) {
                             // [expr:expr:] end
                             if self.at(Kind::RightBrace) || self.at(Kind::Comma) {
-                                return Ok(Expression::Slice(Box::new(Slice {
+                                Ok(Expression::Slice(Box::new(Slice {
                                     node: self.finish_node(node),
                                     lower: Some(n),
-                                    upper: None,
-                                    step: upper_or_step,
-                                })));
+                                    upper: upper_or_step,
+                                    step: None,
+                                })))
                                 // [expr:expr:expr] end
                             } else {
                                 let step = Some(self.parse_expression()?);
-                                return Ok(Expression::Slice(Box::new(Slice {
+                                Ok(Expression::Slice(Box::new(Slice {
                                     node: self.finish_node(node),
                                     lower: Some(n),
                                     upper: upper_or_step,
                                     step,
-                                })));
+                                })))
                             }
                         // [expr:expr
                         } else {
-                            return Ok(Expression::Slice(Box::new(Slice {
+                            Ok(Expression::Slice(Box::new(Slice {
                                 node: self.finish_node(node),
                                 lower: Some(n),
                                 upper: upper_or_step,
                                 step: None,
-                            })));
+                            })))
                         }
                     }
                 }
@@ -3030,21 +3407,21 @@ impl<'a> Parser<'a> {
                 } else if self.eat(Kind::Colon) {
                     // [::] || [::,
                     if self.at(Kind::RightBrace) || self.at(Kind::Comma) {
-                        return Ok(Expression::Slice(Box::new(Slice {
+                        Ok(Expression::Slice(Box::new(Slice {
                             node: self.finish_node(node),
                             lower: None,
                             upper: None,
                             step: None,
-                        })));
+                        })))
                     // [::expr
                     } else {
-                        let upper = Some(self.parse_expression()?);
-                        return Ok(Expression::Slice(Box::new(Slice {
+                        let step = Some(self.parse_expression()?);
+                        Ok(Expression::Slice(Box::new(Slice {
                             node: self.finish_node(node),
                             lower: None,
-                            upper,
-                            step: None,
-                        })));
+                            upper: None,
+                            step,
+                        })))
                     }
                 // [:expr
                 } else {
@@ -3069,12 +3446,12 @@ impl<'a> Parser<'a> {
                             step,
                         })));
                     }
-                    return Ok(Expression::Slice(Box::new(Slice {
+                    Ok(Expression::Slice(Box::new(Slice {
                         node: self.finish_node(node),
                         lower: None,
-                        upper: None,
-                        step: first,
-                    })));
+                        upper: first,
+                        step: None,
+                    })))
                 }
             }
         }
@@ -3103,13 +3480,14 @@ impl<'a> Parser<'a> {
                     ComparisonOperator::NotIn
                 }
                 _ => {
-                    // return Err(self.unepxted_token(node, self.cur_kind()).err().unwrap());
-                    panic!();
+                    return Err(self.unexpected_token_error("invalid syntax".to_string()));
                 }
             },
             _ => {
-                // return Err(self.unepxted_token(node, self.cur_kind()).err().unwrap());
-                panic!();
+                let found = self.cur_kind();
+                return Err(self.unexpected_token_error(format!(
+                    "Expected comparison operator but found {found}"
+                )));
             }
         };
         self.bump_any();
@@ -3127,11 +3505,10 @@ impl<'a> Parser<'a> {
             Kind::Pow => Ok(BinaryOperator::Pow),
             Kind::MatrixMul => Ok(BinaryOperator::MatMult),
             _ => {
-                // Err(self
-                // .unepxted_token(self.start_node(), self.cur_kind())
-                // .err()
-                // .unwrap()),
-                panic!();
+                let found = self.cur_kind();
+                return Err(self.unexpected_token_error(format!(
+                    "Expected binary operator but found {found}"
+                )));
             }
         };
         self.bump_any();
@@ -3141,8 +3518,8 @@ impl<'a> Parser<'a> {
     fn parse_keyword_item(&mut self) -> Result<Keyword, ParsingError> {
         let node = self.start_node();
         let arg = self.cur_token().to_string(self.source);
-        self.expect(Kind::Identifier);
-        self.expect(Kind::Assign);
+        self.expect(Kind::Identifier)?;
+        self.expect(Kind::Assign)?;
         let value = self.parse_expression()?;
         Ok(Keyword {
             node: self.finish_node(node),
@@ -3171,7 +3548,13 @@ impl<'a> Parser<'a> {
                 if seen_vararg {
                     kwonlyargs.push(param);
                 } else if seen_kwarg {
-                    panic!("positional argument follows keyword argument");
+                    self.errors.push(
+                        self.syntax_error(
+                            "arguments cannot follow var-keyword argument",
+                            param.node,
+                        ),
+                    );
+                    continue;
                 } else {
                     args.push(param);
                 }
@@ -3183,7 +3566,17 @@ impl<'a> Parser<'a> {
                     must_have_default = true;
                     defaults.push(default_value);
                 } else if must_have_default {
-                    panic!("non-default argument follows default argument");
+                    let last_param = if seen_vararg {
+                        kwonlyargs.last()
+                    } else {
+                        args.last()
+                    };
+                    if let Some(param) = last_param {
+                        self.errors.push(self.syntax_error(
+                            "parameter without a default follows parameter with a default",
+                            param.node,
+                        ));
+                    }
                 }
             // If a parameter has a default value, all following parameters up
             // until the “*” must also have a default value — this
@@ -3201,7 +3594,10 @@ impl<'a> Parser<'a> {
                 let (param, default) = self.parse_parameter(is_lambda)?;
                 // default is not allowed for vararg
                 if default.is_some() {
-                    panic!("var-positional argument cannot have default value");
+                    self.errors.push(self.syntax_error(
+                        "var-positional argument cannot have default value",
+                        param.node,
+                    ));
                 }
                 vararg = Some(param);
             } else if self.eat(Kind::Pow) {
@@ -3209,12 +3605,19 @@ impl<'a> Parser<'a> {
                 let (param, default) = self.parse_parameter(is_lambda)?;
                 // default is not allowed for kwarg
                 if default.is_some() {
-                    panic!("var-keyword argument cannot have default value");
+                    self.errors.push(self.syntax_error(
+                        "var-keyword argument cannot have default value",
+                        param.node,
+                    ));
                 }
                 kwarg = Some(param);
             } else if self.eat(Kind::Comma) {
                 continue;
-            } else if self.eat(Kind::Div) {
+            } else if self.at(Kind::Div) {
+                let div_node = self.start_node();
+                self.bump(Kind::Div);
+                let div_node = self.finish_node(div_node);
+                self.require_version("positional-only parameter", PythonVersion::Py38, div_node);
                 // copy the current args to posonlyargs
                 posonlyargs = args;
                 args = vec![];
@@ -3268,12 +3671,44 @@ impl<'a> Parser<'a> {
 
     // the FStringStart token is consumed by the caller
     fn parse_fstring(&mut self, node: Node) -> Result<Vec<Expression>, ParsingError> {
-        let mut expressions = vec![];
+        let (prefix, quote) = split_fstring_start(&self.source[node.start as usize..]);
+        let raw = is_raw_prefix(prefix);
+        let mut values = vec![];
         while self.cur_kind() != Kind::FStringEnd {
-            expressions.push(self.parse_fstring_middle()?)
+            self.parse_fstring_middle(raw, &mut values)?;
         }
         self.bump(Kind::FStringEnd);
-        Ok(expressions)
+        if self.options.target_version < PythonVersion::Py312 {
+            self.check_fstring_expressions(&values, quote);
+        }
+        Ok(values)
+    }
+
+    /// Before PEP 701 the expressions in an f-string could not reuse its
+    /// quotes or contain backslashes and comments
+    fn check_fstring_expressions(&mut self, values: &[Expression], quote: &str) {
+        for value in values {
+            let Expression::FormattedValue(value) = value else {
+                continue;
+            };
+            let node = value.value.get_node();
+            let text = &self.source[node.start as usize..node.end as usize];
+            let feature = if !quote.is_empty() && text.contains(quote) {
+                Some("reusing the quotes of the f-string in its expression")
+            } else if text.contains('\\') {
+                Some("backslash in f-string expression")
+            } else if text.contains('#') {
+                Some("comment in f-string expression")
+            } else {
+                None
+            };
+            if let Some(feature) = feature {
+                self.require_version(feature, PythonVersion::Py312, node);
+            }
+            if let Some(Expression::JoinedStr(spec)) = &value.format_spec {
+                self.check_fstring_expressions(&spec.values, quote);
+            }
+        }
     }
 
     // This function is just here to make it easier to refactor the spans.
@@ -3327,7 +3762,10 @@ impl<'a> Parser<'a> {
                     }));
                 }
                 _ => {
-                    panic!("Unexpected token {:?}", self.cur_token());
+                    let found = self.cur_kind();
+                    return Err(self.unexpected_token_error(format!(
+                        "Expected type parameter but found {found}"
+                    )));
                 }
             }
             if !self.at(Kind::RightBrace) {
@@ -3335,20 +3773,30 @@ impl<'a> Parser<'a> {
             }
         }
         if type_params.is_empty() {
-            panic!("Unexpected token {:?}", self.cur_token());
+            return Err(
+                self.unexpected_token_error("Type parameter list cannot be empty".to_string())
+            );
         }
+        self.require_version(
+            "type parameter list",
+            PythonVersion::Py312,
+            self.finish_node(node),
+        );
         Ok(type_params)
     }
 
     fn parse_type_alias_statement(&mut self) -> std::result::Result<Statement, ParsingError> {
         let node = self.start_node();
         self.expect(Kind::Identifier)?;
+        self.require_version(
+            "type alias statement",
+            PythonVersion::Py312,
+            self.finish_node(node),
+        );
         let name = self.cur_token().to_string(self.source);
         self.expect(Kind::Identifier)?;
-        let type_params = if self.eat(Kind::LeftBrace) {
-            let type_params = self.parse_type_parameters()?;
-            self.expect(Kind::RightBrace)?;
-            type_params
+        let type_params = if self.at(Kind::LeftBrace) {
+            self.parse_type_parameters()?
         } else {
             vec![]
         };
@@ -3362,9 +3810,19 @@ impl<'a> Parser<'a> {
         })))
     }
 
-    fn parse_fstring_replacement_field(&mut self) -> Result<Expression, ParsingError> {
+    // https://docs.python.org/3/reference/lexical_analysis.html#formatted-string-literals
+    fn parse_fstring_replacement_field(
+        &mut self,
+        raw: bool,
+        values: &mut Vec<Expression>,
+    ) -> Result<(), ParsingError> {
         let node = self.start_node();
         self.bump(Kind::LeftBracket);
+        if self.at(Kind::RightBracket) {
+            return Err(self.unexpected_token_error(
+                "f-string: valid expression required before '}'".to_string(),
+            ));
+        }
 
         let expr = if self.at(Kind::Yield) {
             self.parse_yield_expression()?
@@ -3372,24 +3830,41 @@ impl<'a> Parser<'a> {
             self.parse_expressions()?
         };
 
-        let mut conversion = -1;
-        if self.eat(Kind::Assign) {
-            conversion = 114;
+        // `{x = }` writes the text of the expression before its value
+        let is_debug = self.eat(Kind::Assign);
+        if is_debug {
+            let text_node = Node::new(node.start + 1, self.cur_token().start);
+            let text = &self.source[text_node.start as usize..text_node.end as usize];
+            push_fstring_value(
+                values,
+                Expression::Constant(Box::new(Constant {
+                    node: text_node,
+                    value: ConstantValue::Str(text.to_string()),
+                })),
+            );
         }
+
+        let mut conversion = -1;
         if self.eat(Kind::Exclamation) {
             conversion = match self.cur_token.as_str(self.source) {
-                "s" => 115,
-                "r" => 114,
-                "a" => 97,
-                _ => panic!("should not happen"),
+                "s" => 's' as i32,
+                "r" => 'r' as i32,
+                "a" => 'a' as i32,
+                _ => {
+                    return Err(self.unexpected_token_error(
+                        "f-string: invalid conversion character: expected 's', 'r', or 'a'"
+                            .to_string(),
+                    ))
+                }
             };
             self.bump_any();
         }
+
         let format_spec_node = self.start_node();
         let format_spec = if self.eat(Kind::Colon) {
             let mut specs = vec![];
             while matches!(self.cur_kind(), Kind::FStringMiddle | Kind::LeftBracket) {
-                specs.push(self.parse_fstring_middle()?);
+                self.parse_fstring_middle(raw, &mut specs)?;
             }
             Some(Expression::JoinedStr(Box::new(JoinedStr {
                 node: self.finish_node(format_spec_node),
@@ -3398,30 +3873,48 @@ impl<'a> Parser<'a> {
         } else {
             None
         };
+        // The text of a self-documenting expression is followed by its repr
+        // unless a conversion or a format specifier is given
+        if is_debug && conversion == -1 && format_spec.is_none() {
+            conversion = 'r' as i32;
+        }
 
         self.expect(Kind::RightBracket)?;
-        Ok(Expression::FormattedValue(Box::new(FormattedValue {
+        values.push(Expression::FormattedValue(Box::new(FormattedValue {
             node: self.finish_node(node),
             value: expr,
             conversion,
             format_spec,
-        })))
+        })));
+        Ok(())
     }
 
-    fn parse_fstring_middle(&mut self) -> Result<Expression, ParsingError> {
-        let node = self.start_node();
+    /// Parses a literal part or a replacement field of an f-string or of a
+    /// format specifier and adds it to `values`
+    fn parse_fstring_middle(
+        &mut self,
+        raw: bool,
+        values: &mut Vec<Expression>,
+    ) -> Result<(), ParsingError> {
         match self.cur_kind() {
             Kind::FStringMiddle => {
-                let val = self.cur_token().as_str(self.source);
+                let node = self.start_node();
+                let value = decode_fstring_middle(self.cur_token().as_str(self.source), raw);
                 self.bump(Kind::FStringMiddle);
-                Ok(Expression::Constant(Box::new(Constant {
-                    node: self.finish_node(node),
-                    value: ConstantValue::Str(QuoteType::from(val)),
-                })))
+                push_fstring_value(
+                    values,
+                    Expression::Constant(Box::new(Constant {
+                        node: self.finish_node(node),
+                        value: ConstantValue::Str(value),
+                    })),
+                );
+                Ok(())
             }
-            Kind::LeftBracket => self.parse_fstring_replacement_field(),
+            Kind::LeftBracket => self.parse_fstring_replacement_field(raw, values),
             _ => {
-                panic!("kind is {}", self.cur_token().kind);
+                let found = self.cur_kind();
+                Err(self
+                    .unexpected_token_error(format!("Expected f-string content but found {found}")))
             }
         }
     }
@@ -3439,6 +3932,7 @@ mod tests {
     use insta::assert_snapshot;
 
     use super::*;
+    use crate::parser::split_signature_type_comment;
 
     #[test]
     fn test_parse_assignment() {
@@ -3475,7 +3969,8 @@ mod tests {
             // annotated assignment
         ] {
             let mut parser = Parser::new(test_case);
-            let program = parser.parse().expect("parsing failed");
+            let (program, errors) = parser.parse();
+            assert!(errors.is_empty(), "{errors:?}");
 
             insta::with_settings!({
                     description => test_case.to_string(), // the template source code
@@ -3491,7 +3986,8 @@ mod tests {
     fn test_parse_assert_stmt() {
         for test_case in &["assert a", "assert a, b", "assert True, 'fancy message'"] {
             let mut parser = Parser::new(test_case);
-            let program = parser.parse().expect("parsing failed");
+            let (program, errors) = parser.parse();
+            assert!(errors.is_empty(), "{errors:?}");
 
             insta::with_settings!({
                     description => test_case.to_string(), // the template source code
@@ -3507,7 +4003,8 @@ mod tests {
     fn test_pass_stmt() {
         for test_case in &["pass", "pass ", "pass\n"] {
             let mut parser = Parser::new(test_case);
-            let program = parser.parse().expect("parsing failed");
+            let (program, errors) = parser.parse();
+            assert!(errors.is_empty(), "{errors:?}");
 
             insta::with_settings!({
                     description => test_case.to_string(), // the template source code
@@ -3519,11 +4016,169 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_semicolon_separated_statements() {
+        for (test_case, statements) in [
+            ("a; b", 2),
+            ("a = 1; b = 2\nc", 3),
+            ("if a:\n    b; c\nd; e", 3),
+        ] {
+            let mut parser = Parser::new(test_case);
+            let (program, errors) = parser.parse();
+            assert!(errors.is_empty(), "{errors:?}");
+            assert_eq!(program.body.len(), statements, "{test_case}");
+        }
+    }
+
+    #[test]
+    fn test_target_version() {
+        for (test_case, version, expected) in [
+            (
+                "if (a := 1): pass",
+                PythonVersion::Py37,
+                "assignment expression requires Python 3.8",
+            ),
+            (
+                "def f(a, /): pass",
+                PythonVersion::Py37,
+                "positional-only parameter requires Python 3.8",
+            ),
+            (
+                "@a[0]\ndef f(): pass",
+                PythonVersion::Py38,
+                "arbitrary decorator expression requires Python 3.9",
+            ),
+            (
+                "with (a as b, c): pass",
+                PythonVersion::Py38,
+                "parenthesized context manager requires Python 3.9",
+            ),
+            (
+                "match a:\n    case 1: pass",
+                PythonVersion::Py39,
+                "match statement requires Python 3.10",
+            ),
+            (
+                "try:\n    pass\nexcept* E:\n    pass",
+                PythonVersion::Py310,
+                "except* clause requires Python 3.11",
+            ),
+            (
+                "type A = int",
+                PythonVersion::Py311,
+                "type alias statement requires Python 3.12",
+            ),
+            (
+                "def f[T](a: T): pass",
+                PythonVersion::Py311,
+                "type parameter list requires Python 3.12",
+            ),
+            (
+                "class A[T]: pass",
+                PythonVersion::Py311,
+                "type parameter list requires Python 3.12",
+            ),
+            (
+                "f\"{\"a\"}\"",
+                PythonVersion::Py311,
+                "reusing the quotes of the f-string in its expression requires Python 3.12",
+            ),
+            (
+                "f'{\"\\n\".join(a)}'",
+                PythonVersion::Py311,
+                "backslash in f-string expression requires Python 3.12",
+            ),
+        ] {
+            let options = ParserOptions {
+                target_version: version,
+            };
+            let (_, errors) = Parser::with_options(test_case, options).parse();
+            let messages: Vec<_> = errors.iter().map(|e| e.to_string()).collect();
+            assert_eq!(messages, vec![expected], "{test_case}");
+
+            let (_, errors) = Parser::new(test_case).parse();
+            assert!(errors.is_empty(), "{test_case}: {errors:?}");
+        }
+
+        for test_case in [
+            "@a.b(c)\ndef f(): pass",
+            "with (a): pass",
+            "f'{a!r:>10}'",
+            "f'{\"a\"}'",
+            "match = 1",
+            "type = 1",
+        ] {
+            let options = ParserOptions {
+                target_version: PythonVersion::Py37,
+            };
+            let (_, errors) = Parser::with_options(test_case, options).parse();
+            assert!(errors.is_empty(), "{test_case}: {errors:?}");
+        }
+    }
+
+    #[test]
+    fn test_type_comments() {
+        let source = "\
+a = []  # type: List[int]
+b = 1  # type: ignore
+c = 1  #type:int  # comment
+for x in y:  # type: int
+    pass
+with open(f) as f:  # type: IO[str]
+    pass
+def f(a, *b):  # type: (int, *str) -> bool
+    pass
+def g(a):
+    # type: (...) -> None
+    pass
+def h(a):
+    a = 1  # type: int
+";
+        let (module, errors) = Parser::new(source).parse();
+        assert!(errors.is_empty(), "{errors:?}");
+        let type_comments: Vec<_> = module
+            .body
+            .iter()
+            .map(|stmt| match stmt {
+                Statement::AssignStatement(s) => s.type_comment.clone(),
+                Statement::ForStatement(s) => s.type_comment.clone(),
+                Statement::WithStatement(s) => s.type_comment.clone(),
+                Statement::FunctionDef(s) => s.type_comment.clone(),
+                _ => panic!("unexpected statement {stmt:?}"),
+            })
+            .collect();
+        assert_eq!(
+            type_comments,
+            [
+                Some("List[int]"),
+                None,
+                Some("int  # comment"),
+                Some("int"),
+                Some("IO[str]"),
+                Some("(int, *str) -> bool"),
+                Some("(...) -> None"),
+                None,
+            ]
+            .map(|s| s.map(String::from))
+        );
+
+        assert_eq!(
+            split_signature_type_comment("(int, Dict[str, int], *str) -> bool"),
+            Some((vec!["int", "Dict[str, int]", "*str"], "bool"))
+        );
+        assert_eq!(
+            split_signature_type_comment("() -> Tuple[int, int]"),
+            Some((vec![], "Tuple[int, int]"))
+        );
+        assert_eq!(split_signature_type_comment("int"), None);
+    }
+
     #[test]
     fn test_parse_del_stmt() {
         for test_case in &["del a", "del a, b", "del a, b, "] {
             let mut parser = Parser::new(test_case);
-            let program = parser.parse().expect("parsing failed");
+            let (program, errors) = parser.parse();
+            assert!(errors.is_empty(), "{errors:?}");
 
             insta::with_settings!({
                     description => test_case.to_string(), // the template source code
@@ -3539,7 +4194,8 @@ mod tests {
     fn parse_yield_statement() {
         for test_case in &["yield", "yield a", "yield a, b", "yield a, b, "] {
             let mut parser = Parser::new(test_case);
-            let program = parser.parse().expect("parsing failed");
+            let (program, errors) = parser.parse();
+            assert!(errors.is_empty(), "{errors:?}");
 
             insta::with_settings!({
                     description => test_case.to_string(), // the template source code
@@ -3555,7 +4211,8 @@ mod tests {
     fn test_raise_statement() {
         for test_case in &["raise", "raise a", "raise a from c"] {
             let mut parser = Parser::new(test_case);
-            let program = parser.parse().expect("parsing failed");
+            let (program, errors) = parser.parse();
+            assert!(errors.is_empty(), "{errors:?}");
 
             insta::with_settings!({
                     description => test_case.to_string(), // the template source code
@@ -3571,7 +4228,8 @@ mod tests {
     fn test_parse_break_continue() {
         for test_case in &["break", "continue"] {
             let mut parser = Parser::new(test_case);
-            let program = parser.parse().expect("parsing failed");
+            let (program, errors) = parser.parse();
+            assert!(errors.is_empty(), "{errors:?}");
 
             insta::with_settings!({
                     description => test_case.to_string(), // the template source code
@@ -3587,7 +4245,8 @@ mod tests {
     fn test_parse_bool_op() {
         for test_case in &["a or b", "a and b", "a or b or c", "a and b or c"] {
             let mut parser = Parser::new(test_case);
-            let program = parser.parse().expect("parsing failed");
+            let (program, errors) = parser.parse();
+            assert!(errors.is_empty(), "{errors:?}");
 
             insta::with_settings!({
                     description => test_case.to_string(), // the template source code
@@ -3603,7 +4262,8 @@ mod tests {
     fn test_parse_unary_op() {
         for test_case in &["not a", "+ a", "~ a", "-a"] {
             let mut parser = Parser::new(test_case);
-            let program = parser.parse().expect("parsing failed");
+            let (program, errors) = parser.parse();
+            assert!(errors.is_empty(), "{errors:?}");
 
             insta::with_settings!({
                     description => test_case.to_string(), // the template source code
@@ -3620,7 +4280,8 @@ mod tests {
         {
             let test_case = &"(a := b)";
             let mut parser = Parser::new(test_case);
-            let program = parser.parse().expect("parsing failed");
+            let (program, errors) = parser.parse();
+            assert!(errors.is_empty(), "{errors:?}");
 
             insta::with_settings!({
                     description => test_case.to_string(), // the template source code
@@ -3648,7 +4309,8 @@ mod tests {
             "(a, b, c,)",
         ] {
             let mut parser = Parser::new(test_case);
-            let program = parser.parse().expect("parsing failed");
+            let (program, errors) = parser.parse();
+            assert!(errors.is_empty(), "{errors:?}");
 
             insta::with_settings!({
                     description => test_case.to_string(), // the template source code
@@ -3664,7 +4326,8 @@ mod tests {
     fn test_yield_expression() {
         for test_case in &["yield", "yield a", "yield from a"] {
             let mut parser = Parser::new(test_case);
-            let program = parser.parse().expect("parsing failed");
+            let (program, errors) = parser.parse();
+            assert!(errors.is_empty(), "{errors:?}");
 
             insta::with_settings!({
                     description => test_case.to_string(), // the template source code
@@ -3681,7 +4344,8 @@ mod tests {
         {
             let test_case = &"(*a)";
             let mut parser = Parser::new(test_case);
-            let program = parser.parse().expect("parsing failed");
+            let (program, errors) = parser.parse();
+            assert!(errors.is_empty(), "{errors:?}");
 
             insta::with_settings!({
                     description => test_case.to_string(), // the template source code
@@ -3698,7 +4362,8 @@ mod tests {
         {
             let test_case = &"await a";
             let mut parser = Parser::new(test_case);
-            let program = parser.parse().expect("parsing failed");
+            let (program, errors) = parser.parse();
+            assert!(errors.is_empty(), "{errors:?}");
 
             insta::with_settings!({
                     description => test_case.to_string(), // the template source code
@@ -3714,7 +4379,8 @@ mod tests {
     fn test_attribute_ref() {
         for test_case in &["a.b", "a.b.c", "a.b_c", "a.b.c.d"] {
             let mut parser = Parser::new(test_case);
-            let program = parser.parse().expect("parsing failed");
+            let (program, errors) = parser.parse();
+            assert!(errors.is_empty(), "{errors:?}");
 
             insta::with_settings!({
                     description => test_case.to_string(), // the template source code
@@ -3740,7 +4406,8 @@ mod tests {
             "eval(' '.join(map(chr, [105, 110, 116])))",
         ] {
             let mut parser = Parser::new(test_case);
-            let program = parser.parse().expect("parsing failed");
+            let (program, errors) = parser.parse();
+            assert!(errors.is_empty(), "{errors:?}");
 
             insta::with_settings!({
                     description => test_case.to_string(), // the template source code
@@ -3766,7 +4433,8 @@ mod tests {
             "lambda a=1 : a,",
         ] {
             let mut parser = Parser::new(test_case);
-            let program = parser.parse().expect("parsing failed");
+            let (program, errors) = parser.parse();
+            assert!(errors.is_empty(), "{errors:?}");
 
             insta::with_settings!({
                     description => test_case.to_string(), // the template source code
@@ -3783,7 +4451,8 @@ mod tests {
         {
             let test_case = &"a if b else c if d else e";
             let mut parser = Parser::new(test_case);
-            let program = parser.parse().expect("parsing failed");
+            let (program, errors) = parser.parse();
+            assert!(errors.is_empty(), "{errors:?}");
 
             insta::with_settings!({
                     description => test_case.to_string(), // the template source code
@@ -3815,7 +4484,8 @@ mod tests {
             "f'a_{1}' 'b' ",
         ] {
             let mut parser = Parser::new(test_case);
-            let program = parser.parse().expect("parsing failed");
+            let (program, errors) = parser.parse();
+            assert!(errors.is_empty(), "{errors:?}");
 
             insta::with_settings!({
                     description => test_case.to_string(), // the template source code
@@ -3834,11 +4504,19 @@ mod tests {
             "f'hello_{a}'",
             "f'hello_{a} {b}'",
             "f'hello_{a} {b} {c}'",
-            // unsupported
-            // "f'hello_{f'''{a}'''}'",
+            "f'hello_{f'''{a}'''}'",
+            "f'{a!r} {b!s:>10} {c!a}'",
+            "f'{x:>{width}.{precision}f}'",
+            "f'{x = }'",
+            "f'a{x=:>3} {y=!s}'",
+            "f\"{d[\"key\"]} {f\"{x}\"}\"",
+            "f'{ {1, 2} }'",
+            "f'{{x}} \\t {x}\\n'",
+            "rf'\\d{x}\\n'",
         ] {
             let mut parser = Parser::new(test_case);
-            let program = parser.parse().expect("parsing failed");
+            let (program, errors) = parser.parse();
+            assert!(errors.is_empty(), "{errors:?}");
 
             insta::with_settings!({
                     description => test_case.to_string(), // the template source code
@@ -3850,6 +4528,15 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_fstring_errors() {
+        for test_case in &["f'{}'", "f'{x!z}'", "f'{x'", "f'{x:{}}'"] {
+            let mut parser = Parser::new(test_case);
+            let (_, errors) = parser.parse();
+            assert!(!errors.is_empty(), "{test_case}");
+        }
+    }
+
     #[test]
     fn test_comparison() {
         for test_case in &[
@@ -3866,7 +4553,8 @@ mod tests {
             "a < b < c",
         ] {
             let mut parser = Parser::new(test_case);
-            let program = parser.parse().expect("parsing failed");
+            let (program, errors) = parser.parse();
+            assert!(errors.is_empty(), "{errors:?}");
 
             insta::with_settings!({
                 description => test_case.to_string(), // the template source code
@@ -3891,7 +4579,8 @@ else:
 ",
         ] {
             let mut parser = Parser::new(test_case);
-            let program = parser.parse().expect("parsing failed");
+            let (program, errors) = parser.parse();
+            assert!(errors.is_empty(), "{errors:?}");
 
             insta::with_settings!({
                     description => test_case.to_string(), // the template source code
@@ -3939,7 +4628,8 @@ except *Exception as e:
 ",
         ] {
             let mut parser = Parser::new(test_case);
-            let program = parser.parse().expect("parsing failed");
+            let (program, errors) = parser.parse();
+            assert!(errors.is_empty(), "{errors:?}");
 
             insta::with_settings!({
                     description => test_case.to_string(), // the template source code
@@ -3961,7 +4651,8 @@ except *Exception as e:
             "... + 1",
         ] {
             let mut parser = Parser::new(test_case);
-            let program = parser.parse().expect("parsing failed");
+            let (program, errors) = parser.parse();
+            assert!(errors.is_empty(), "{errors:?}");
 
             insta::with_settings!({
                     description => test_case.to_string(), // the template source code
@@ -3973,6 +4664,58 @@ except *Exception as e:
         }
     }
 
+    #[test]
+    fn test_error_recovery() {
+        for test_case in &[
+            "a = \nb = 1",
+            "foo(a, *)\nx = 1",
+            "def f(:\n    pass\nx = 1",
+            "if a\n    b = 1\n    c = 2\nd = 3",
+            "class A:\n    def f(self):\n        return +\n    def g(self):\n        pass\n",
+            "x = [1, 2\ndef f():\n    pass\n",
+            "x = 1 2\ny = 3",
+            "def f(a=1, b, **c, d):\n    pass",
+            "f(a=1, b)",
+            "x = 'a' b'b'",
+            "  x = 1\ny = 2",
+        ] {
+            let mut parser = Parser::new(test_case);
+            let (program, errors) = parser.parse();
+            assert!(!errors.is_empty(), "{test_case}");
+
+            insta::with_settings!({
+                    description => test_case.to_string(), // the template source code
+                    snapshot_path => "../../test_data/output/",
+                    omit_expression => true // do not include the default expression
+                }, {
+                    assert_debug_snapshot!((program, errors));
+            });
+        }
+    }
+
+    #[test]
+    fn test_lex_errors() {
+        for test_case in &[
+            "x = 'abc\ny = 1",
+            "x = 0b12 + 0o8 + 0xg + 1__0",
+            "if a:\n    b = 1\n  c = 2\nd = 3",
+            "x = f'abc",
+            "def 0b2():\n    pass",
+        ] {
+            let mut parser = Parser::new(test_case);
+            let (program, errors) = parser.parse();
+            assert!(!errors.is_empty(), "{test_case}");
+
+            insta::with_settings!({
+                    description => test_case.to_string(), // the template source code
+                    snapshot_path => "../../test_data/output/",
+                    omit_expression => true // do not include the default expression
+                }, {
+                    assert_debug_snapshot!((program, errors));
+            });
+        }
+    }
+
     #[test]
     fn test_constant_value_get_source() {
         for source in &[
@@ -3983,7 +4726,7 @@ except *Exception as e:
             "'he' \"\"\"l\"\"\" \"l\" 'o'",
         ] {
             let mut parser = Parser::new(source);
-            let module = parser.parse().unwrap();
+            let (module, _) = parser.parse();
 
             let constant_value = module
                 .body
@@ -3993,12 +4736,105 @@ except *Exception as e:
                 .expect_expression_statement()
                 .expect_constant();
 
-            let value = constant_value.get_value(source);
+            let value = constant_value.get_value();
             dbg!(&value);
             assert!(value.eq("hello"));
         }
     }
 
+    #[test]
+    fn test_number_constant_values() {
+        for (source, expected) in &[
+            ("1_000", ConstantValue::Int(1000.into())),
+            ("0x1F", ConstantValue::Int(31.into())),
+            ("0o17", ConstantValue::Int(15.into())),
+            ("0b101", ConstantValue::Int(5.into())),
+            (
+                "123456789012345678901234567890",
+                ConstantValue::Int("123456789012345678901234567890".parse().unwrap()),
+            ),
+            ("1_000.5", ConstantValue::Float(1000.5)),
+            ("1e-3", ConstantValue::Float(0.001)),
+        ] {
+            let mut parser = Parser::new(source);
+            let (module, _) = parser.parse();
+
+            let constant_value = module
+                .body
+                .first()
+                .unwrap()
+                .clone()
+                .expect_expression_statement()
+                .expect_constant();
+
+            assert_eq!(&constant_value.value, expected, "{source}");
+        }
+    }
+
+    #[test]
+    fn test_decoded_constant_values() {
+        for (source, expected) in &[
+            (r"'a\nb'", ConstantValue::Str("a\nb".to_string())),
+            (r"r'a\nb'", ConstantValue::Str(r"a\nb".to_string())),
+            (
+                r"'\x41é\101\U0001F600'",
+                ConstantValue::Str("AéA😀".to_string()),
+            ),
+            (r"'\q'", ConstantValue::Str(r"\q".to_string())),
+            ("'a\\\nb'", ConstantValue::Str("ab".to_string())),
+            (r#"'\'' "\"""#, ConstantValue::Str("'\"".to_string())),
+            (r"b'\x00\nA'", ConstantValue::Bytes(vec![0, b'\n', b'A'])),
+            (r"rb'\x00'", ConstantValue::Bytes(br"\x00".to_vec())),
+            (
+                "1.5j",
+                ConstantValue::Complex {
+                    real: 0.0,
+                    imag: 1.5,
+                },
+            ),
+        ] {
+            let mut parser = Parser::new(source);
+            let (module, _) = parser.parse();
+
+            let constant_value = module
+                .body
+                .first()
+                .unwrap()
+                .clone()
+                .expect_expression_statement()
+                .expect_constant();
+
+            assert_eq!(&constant_value.value, expected, "{source}");
+        }
+    }
+
+    #[test]
+    fn test_constant_get_value() {
+        for (source, expected) in &[
+            ("0x10", "16"),
+            ("1.0", "1.0"),
+            ("1e20", "1e+20"),
+            ("0.00001", "1e-05"),
+            ("2j", "2j"),
+            ("1.5J", "1.5j"),
+            ("b'ab'", "ab"),
+            ("None", "None"),
+        ] {
+            let mut parser = Parser::new(source);
+            let (module, _) = parser.parse();
+
+            let constant_value = module
+                .body
+                .first()
+                .unwrap()
+                .clone()
+                .expect_expression_statement()
+                .expect_constant();
+
+            assert_eq!(constant_value.get_value(), *expected, "{source}");
+        }
+    }
+
     macro_rules! parser_test {
         ($test_name:ident, $test_file:expr) => {
             #[test]
@@ -4007,7 +4843,8 @@ except *Exception as e:
                 let mut parser = Parser::new(
                     &test_case,
                 );
-                let program = parser.parse().expect("parsing failed");
+                let (program, errors) = parser.parse();
+                assert!(errors.is_empty(), "{errors:?}");
                 let snapshot = format!("{program:#?}");
 
                 insta::with_settings!({
@@ -0,0 +1,51 @@
+use std::{fmt, str::FromStr};
+
+/// Python version the parsed source targets. Syntax added in a newer version
+/// is reported as an error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum PythonVersion {
+    Py37,
+    Py38,
+    Py39,
+    Py310,
+    Py311,
+    #[default]
+    Py312,
+}
+
+impl PythonVersion {
+    /// The minor version, e.g. `10` for Python 3.10
+    pub fn minor(self) -> u8 {
+        match self {
+            PythonVersion::Py37 => 7,
+            PythonVersion::Py38 => 8,
+            PythonVersion::Py39 => 9,
+            PythonVersion::Py310 => 10,
+            PythonVersion::Py311 => 11,
+            PythonVersion::Py312 => 12,
+        }
+    }
+}
+
+impl fmt::Display for PythonVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "3.{}", self.minor())
+    }
+}
+
+impl FromStr for PythonVersion {
+    type Err = String;
+
+    /// Parses versions written like `3.10`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "3.7" => Ok(PythonVersion::Py37),
+            "3.8" => Ok(PythonVersion::Py38),
+            "3.9" => Ok(PythonVersion::Py39),
+            "3.10" => Ok(PythonVersion::Py310),
+            "3.11" => Ok(PythonVersion::Py311),
+            "3.12" => Ok(PythonVersion::Py312),
+            _ => Err(format!("unsupported Python version {s}")),
+        }
+    }
+}
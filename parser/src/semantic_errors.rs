@@ -0,0 +1,1133 @@
+//! Syntax errors that are not part of the grammar.
+//!
+//! CPython reports some errors when it compiles a module instead of when it
+//! parses it, e.g. `return` outside of a function or assigning to a literal.
+//! [`semantic_errors`] finds them in a parsed [`Module`] with the messages of
+//! CPython.
+use std::collections::HashSet;
+
+use crate::{
+    ast::*,
+    error::ParsingError,
+    intern::StrId,
+    parser::parser::intern_lookup,
+    visitor::{self, Visitor},
+};
+
+/// Finds the syntax errors that CPython reports when it compiles the module,
+/// ordered by their position
+pub fn semantic_errors(module: &Module) -> Vec<ParsingError> {
+    let mut checker = SemanticChecker {
+        scopes: vec![Scope::new(ScopeKind::Module)],
+        errors: vec![],
+    };
+    checker.visit_module(module);
+    checker.errors.sort_by_key(|error| error.span().0);
+    checker.errors
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScopeKind {
+    Module,
+    Class,
+    /// Functions and lambdas
+    Function {
+        is_async: bool,
+    },
+    /// Everything of a comprehension except its first iterable, `kind` is
+    /// e.g. "list comprehension"
+    Comprehension {
+        kind: &'static str,
+        node: Node,
+    },
+}
+
+#[derive(Debug)]
+struct Scope<'a> {
+    kind: ScopeKind,
+    /// In the body of a loop, but not in its `else` block
+    in_loop: bool,
+    parameters: HashSet<&'a str>,
+    /// Names read, assigned and annotated so far, only the ones of this scope
+    /// and not of the scopes inside of it
+    used: HashSet<&'a str>,
+    assigned: HashSet<&'a str>,
+    annotated: HashSet<&'a str>,
+    globals: HashSet<&'a str>,
+    nonlocals: HashSet<&'a str>,
+    has_yield: bool,
+    /// `return` statements with a value, an error if the function is an
+    /// async generator
+    returns_with_value: Vec<Node>,
+    /// Uses `await` or `async for`, an error for comprehensions other than
+    /// generator expressions outside of async functions
+    is_async: bool,
+}
+
+impl Scope<'_> {
+    fn new(kind: ScopeKind) -> Self {
+        Self {
+            kind,
+            in_loop: false,
+            parameters: HashSet::new(),
+            used: HashSet::new(),
+            assigned: HashSet::new(),
+            annotated: HashSet::new(),
+            globals: HashSet::new(),
+            nonlocals: HashSet::new(),
+            has_yield: false,
+            returns_with_value: vec![],
+            is_async: false,
+        }
+    }
+}
+
+struct SemanticChecker<'a> {
+    /// From the module to the innermost scope
+    scopes: Vec<Scope<'a>>,
+    errors: Vec<ParsingError>,
+}
+
+impl<'a> SemanticChecker<'a> {
+    fn error(&mut self, node: Node, msg: String, advice: &str) {
+        self.errors.push(ParsingError::InvalidSyntax {
+            msg,
+            advice: advice.to_string(),
+            span: (node.start as usize, node.len() as usize),
+        });
+    }
+
+    fn scope(&mut self) -> &mut Scope<'a> {
+        self.scopes
+            .last_mut()
+            .expect("the module scope is never popped")
+    }
+
+    /// The innermost scope that is not a comprehension
+    fn enclosing_scope(&mut self) -> &mut Scope<'a> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find(|scope| !matches!(scope.kind, ScopeKind::Comprehension { .. }))
+            .expect("the module scope is never popped")
+    }
+
+    fn in_async_function(&mut self) -> bool {
+        self.enclosing_scope().kind == ScopeKind::Function { is_async: true }
+    }
+
+    /// Records a name that is assigned in the current scope
+    fn bind(&mut self, name: &'a str, node: Node) {
+        self.check_debug(name, node);
+        self.scope().assigned.insert(name);
+    }
+
+    fn check_debug(&mut self, name: &str, node: Node) {
+        if name == "__debug__" {
+            self.error(
+                node,
+                "cannot assign to __debug__".to_string(),
+                "__debug__ is a constant, use another name",
+            );
+        }
+    }
+
+    /// Checks the target of an assignment, `for` loop, `with` item or
+    /// comprehension. `suggest_comparison` is set for the first target of an
+    /// assignment statement, where `=` may be a typo of `==`.
+    fn visit_target(&mut self, target: &'a Expression, suggest_comparison: bool) {
+        match target {
+            Expression::Name(name) => self.bind(&name.id, name.node),
+            Expression::Attribute(_) | Expression::Subscript(_) => self.visit_expr(target),
+            Expression::Tuple(_) | Expression::List(_) => {
+                let elements = match target {
+                    Expression::Tuple(tuple) => &tuple.elements,
+                    Expression::List(list) => &list.elements,
+                    _ => unreachable!(),
+                };
+                let starred = elements
+                    .iter()
+                    .filter(|element| element.is_starred())
+                    .count();
+                if starred > 1 {
+                    self.error(
+                        target.get_node(),
+                        "multiple starred expressions in assignment".to_string(),
+                        "Only one of the targets can be starred",
+                    );
+                }
+                for element in elements {
+                    match element {
+                        Expression::Starred(starred) => self.visit_target(&starred.value, false),
+                        _ => self.visit_target(element, false),
+                    }
+                }
+            }
+            Expression::Starred(_) => self.error(
+                target.get_node(),
+                "starred assignment target must be in a list or tuple".to_string(),
+                "Add a comma after the target to make it a tuple",
+            ),
+            Expression::Invalid(_) => {}
+            _ => {
+                let kind = expression_kind(target);
+                let msg = if is_keyword_constant(target) || !suggest_comparison {
+                    format!("cannot assign to {kind}")
+                } else {
+                    format!("cannot assign to {kind} here. Maybe you meant '==' instead of '='?")
+                };
+                self.error(
+                    target.get_node(),
+                    msg,
+                    "Only names, attributes, subscripts and lists or tuples of them can be assigned to",
+                );
+            }
+        }
+    }
+
+    fn visit_delete_target(&mut self, target: &'a Expression) {
+        match target {
+            Expression::Name(name) => self.bind(&name.id, name.node),
+            Expression::Attribute(_) | Expression::Subscript(_) => self.visit_expr(target),
+            Expression::Tuple(tuple) => {
+                for element in &tuple.elements {
+                    self.visit_delete_target(element);
+                }
+            }
+            Expression::List(list) => {
+                for element in &list.elements {
+                    self.visit_delete_target(element);
+                }
+            }
+            Expression::Invalid(_) => {}
+            _ => self.error(
+                target.get_node(),
+                format!("cannot delete {}", expression_kind(target)),
+                "Only names, attributes, subscripts and lists or tuples of them can be deleted",
+            ),
+        }
+    }
+
+    /// Visits the elements of a list, tuple, set, call or class bases where
+    /// starred expressions are allowed
+    fn visit_elements(&mut self, elements: &'a [Expression]) {
+        for element in elements {
+            match element {
+                Expression::Starred(starred) => self.visit_expr(&starred.value),
+                _ => self.visit_expr(element),
+            }
+        }
+    }
+
+    /// Checks a `global` or `nonlocal` declaration against the uses of the
+    /// names before it
+    fn declare(&mut self, names: &'a [String], node: Node, keyword: &str) {
+        for name in names {
+            let name = name.as_str();
+            let scope = self.scope();
+            let other_declaration = match keyword {
+                "global" => &scope.nonlocals,
+                _ => &scope.globals,
+            };
+            let msg = if scope.parameters.contains(name) {
+                format!("name '{name}' is parameter and {keyword}")
+            } else if other_declaration.contains(name) {
+                format!("name '{name}' is nonlocal and global")
+            } else if scope.used.contains(name) {
+                format!("name '{name}' is used prior to {keyword} declaration")
+            } else if scope.annotated.contains(name) {
+                format!("annotated name '{name}' can't be {keyword}")
+            } else if scope.assigned.contains(name) {
+                format!("name '{name}' is assigned to before {keyword} declaration")
+            } else {
+                match keyword {
+                    "global" => scope.globals.insert(name),
+                    _ => scope.nonlocals.insert(name),
+                };
+                continue;
+            };
+            self.error(
+                node,
+                msg,
+                &format!("Move the {keyword} declaration to the start of the scope"),
+            );
+        }
+    }
+
+    /// Visits the parts of a function that are evaluated where it is defined
+    fn visit_function_header(&mut self, decorators: &'a [Expression], args: &'a Arguments) {
+        for decorator in decorators {
+            self.visit_expr(decorator);
+        }
+        for default in args
+            .defaults
+            .iter()
+            .chain(args.kw_defaults.iter().flatten())
+        {
+            self.visit_expr(default);
+        }
+        for arg in parameters(args) {
+            if let Some(annotation) = &arg.annotation {
+                self.visit_expr(annotation);
+            }
+        }
+    }
+
+    fn add_parameters(&mut self, args: &'a Arguments) {
+        for arg in parameters(args) {
+            self.check_debug(&arg.arg, arg.node);
+            if !self.scope().parameters.insert(&arg.arg) {
+                self.error(
+                    arg.node,
+                    format!("duplicate argument '{}' in function definition", arg.arg),
+                    "Rename one of the parameters",
+                );
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn visit_function(
+        &mut self,
+        node: Node,
+        name: StrId,
+        args: &'a Arguments,
+        body: &'a [Statement],
+        decorators: &'a [Expression],
+        returns: &'a Option<Expression>,
+        is_async: bool,
+    ) {
+        self.visit_function_header(decorators, args);
+        if let Some(returns) = returns {
+            self.visit_expr(returns);
+        }
+        self.bind(intern_lookup(name), node);
+
+        self.scopes
+            .push(Scope::new(ScopeKind::Function { is_async }));
+        self.add_parameters(args);
+        self.visit_body(body);
+        let scope = self.scopes.pop().expect("the function scope was pushed");
+        if is_async && scope.has_yield {
+            for node in scope.returns_with_value {
+                self.error(
+                    node,
+                    "'return' with value in async generator".to_string(),
+                    "Async generators can only return without a value",
+                );
+            }
+        }
+    }
+
+    fn visit_comprehension_scope(
+        &mut self,
+        kind: &'static str,
+        node: Node,
+        generators: &'a [Comprehension],
+        elements: &[&'a Expression],
+    ) {
+        // The first iterable is evaluated in the enclosing scope
+        let Some(first) = generators.first() else {
+            return;
+        };
+        self.visit_expr(&first.iter);
+
+        self.scopes
+            .push(Scope::new(ScopeKind::Comprehension { kind, node }));
+        for (index, generator) in generators.iter().enumerate() {
+            if generator.is_async {
+                self.scope().is_async = true;
+            }
+            if index > 0 {
+                self.visit_expr(&generator.iter);
+            }
+            self.visit_target(&generator.target, false);
+            for condition in &generator.ifs {
+                self.visit_expr(condition);
+            }
+        }
+        for element in elements {
+            self.visit_expr(element);
+        }
+        let scope = self
+            .scopes
+            .pop()
+            .expect("the comprehension scope was pushed");
+        if scope.is_async && kind != "generator expression" && !self.in_async_function() {
+            self.error(
+                node,
+                "asynchronous comprehension outside of an asynchronous function".to_string(),
+                "Make the enclosing function async",
+            );
+        }
+    }
+
+    fn visit_loop_body(&mut self, body: &'a [Statement], orelse: &'a [Statement]) {
+        let in_loop = std::mem::replace(&mut self.scope().in_loop, true);
+        self.visit_body(body);
+        self.scope().in_loop = in_loop;
+        self.visit_body(orelse);
+    }
+
+    /// Reports `async for` and `async with` outside of async functions
+    fn check_async_statement(&mut self, node: Node, statement: &str) {
+        if !self.in_async_function() {
+            self.error(
+                node,
+                format!("'{statement}' outside async function"),
+                "Make the enclosing function async",
+            );
+        }
+    }
+
+    fn check_yield(&mut self, node: Node, is_yield_from: bool) {
+        match self.scope().kind {
+            ScopeKind::Module | ScopeKind::Class => self.error(
+                node,
+                "'yield' outside function".to_string(),
+                "yield can only be used in functions",
+            ),
+            ScopeKind::Comprehension { kind, .. } => self.error(
+                node,
+                format!("'yield' inside {kind}"),
+                "Use a loop in a generator function instead",
+            ),
+            ScopeKind::Function { is_async } => {
+                self.scope().has_yield = true;
+                if is_async && is_yield_from {
+                    self.error(
+                        node,
+                        "'yield from' inside async function".to_string(),
+                        "Iterate with async for and yield the items",
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for SemanticChecker<'a> {
+    fn visit_assign(&mut self, node: &'a Assign) {
+        self.visit_expr(&node.value);
+        for (index, target) in node.targets.iter().enumerate() {
+            self.visit_target(target, index == 0);
+        }
+    }
+
+    fn visit_ann_assign(&mut self, node: &'a AnnAssign) {
+        self.visit_expr(&node.annotation);
+        if let Some(value) = &node.value {
+            self.visit_expr(value);
+        }
+        match &node.target {
+            Expression::Name(name) => {
+                let name_id = name.id.as_str();
+                self.check_debug(name_id, name.node);
+                let scope = self.scope();
+                let keyword = if scope.globals.contains(name_id) {
+                    Some("global")
+                } else if scope.nonlocals.contains(name_id) {
+                    Some("nonlocal")
+                } else {
+                    None
+                };
+                scope.annotated.insert(name_id);
+                if node.value.is_some() {
+                    scope.assigned.insert(name_id);
+                }
+                if let Some(keyword) = keyword {
+                    self.error(
+                        node.node,
+                        format!("annotated name '{name_id}' can't be {keyword}"),
+                        "Remove the annotation",
+                    );
+                }
+            }
+            Expression::Attribute(_) | Expression::Subscript(_) => self.visit_expr(&node.target),
+            Expression::Tuple(_) | Expression::List(_) => {
+                let kind = expression_kind(&node.target);
+                self.error(
+                    node.target.get_node(),
+                    format!("only single target (not {kind}) can be annotated"),
+                    "Annotate each name in its own statement",
+                );
+            }
+            Expression::Invalid(_) => {}
+            _ => self.error(
+                node.target.get_node(),
+                "illegal target for annotation".to_string(),
+                "Only names, attributes and subscripts can be annotated",
+            ),
+        }
+    }
+
+    fn visit_aug_assign(&mut self, node: &'a AugAssign) {
+        self.visit_expr(&node.value);
+        match &node.target {
+            Expression::Name(name) => self.bind(&name.id, name.node),
+            Expression::Attribute(_) | Expression::Subscript(_) => self.visit_expr(&node.target),
+            Expression::Invalid(_) => {}
+            target => self.error(
+                target.get_node(),
+                format!(
+                    "'{}' is an illegal expression for augmented assignment",
+                    expression_kind(target)
+                ),
+                "Only names, attributes and subscripts can be assigned with an operator",
+            ),
+        }
+    }
+
+    fn visit_delete(&mut self, node: &'a Delete) {
+        for target in &node.targets {
+            self.visit_delete_target(target);
+        }
+    }
+
+    fn visit_return(&mut self, node: &'a Return) {
+        match self.scope().kind {
+            ScopeKind::Function { .. } => {
+                if node.value.is_some() {
+                    self.scope().returns_with_value.push(node.node);
+                }
+            }
+            _ => self.error(
+                node.node,
+                "'return' outside function".to_string(),
+                "return can only be used in functions",
+            ),
+        }
+        visitor::walk_return(self, node);
+    }
+
+    fn visit_break(&mut self, node: &'a Break) {
+        if !self.scope().in_loop {
+            self.error(
+                node.node,
+                "'break' outside loop".to_string(),
+                "break can only be used in the body of a for or while loop",
+            );
+        }
+    }
+
+    fn visit_continue(&mut self, node: &'a Continue) {
+        if !self.scope().in_loop {
+            self.error(
+                node.node,
+                "'continue' not properly in loop".to_string(),
+                "continue can only be used in the body of a for or while loop",
+            );
+        }
+    }
+
+    fn visit_import(&mut self, node: &'a Import) {
+        for alias in &node.names {
+            self.visit_alias(alias);
+        }
+    }
+
+    fn visit_import_from(&mut self, node: &'a ImportFrom) {
+        for alias in &node.names {
+            self.visit_alias(alias);
+        }
+    }
+
+    fn visit_alias(&mut self, node: &'a Alias) {
+        let name = match &node.asname {
+            Some(asname) => asname.as_str(),
+            None => node.name.split('.').next().unwrap_or_default(),
+        };
+        // Imports bind the name, but CPython does not count them as
+        // assignments before a global or nonlocal declaration
+        self.check_debug(name, node.node);
+    }
+
+    fn visit_global(&mut self, node: &'a Global) {
+        self.declare(&node.names, node.node, "global");
+    }
+
+    fn visit_nonlocal(&mut self, node: &'a Nonlocal) {
+        if self.scope().kind == ScopeKind::Module {
+            self.error(
+                node.node,
+                "nonlocal declaration not allowed at module level".to_string(),
+                "Use global to assign to module level names",
+            );
+            return;
+        }
+        self.declare(&node.names, node.node, "nonlocal");
+    }
+
+    fn visit_while(&mut self, node: &'a While) {
+        self.visit_expr(&node.test);
+        self.visit_loop_body(&node.body, &node.orelse);
+    }
+
+    fn visit_for(&mut self, node: &'a For) {
+        self.visit_expr(&node.iter);
+        self.visit_target(&node.target, false);
+        self.visit_loop_body(&node.body, &node.orelse);
+    }
+
+    fn visit_async_for(&mut self, node: &'a AsyncFor) {
+        self.check_async_statement(node.node, "async for");
+        self.visit_expr(&node.iter);
+        self.visit_target(&node.target, false);
+        self.visit_loop_body(&node.body, &node.orelse);
+    }
+
+    fn visit_async_with(&mut self, node: &'a AsyncWith) {
+        self.check_async_statement(node.node, "async with");
+        visitor::walk_async_with(self, node);
+    }
+
+    fn visit_with_item(&mut self, node: &'a WithItem) {
+        self.visit_expr(&node.context_expr);
+        if let Some(target) = &node.optional_vars {
+            self.visit_target(target, false);
+        }
+    }
+
+    fn visit_except_handler(&mut self, node: &'a ExceptHandler) {
+        if let Some(typ) = &node.typ {
+            self.visit_expr(typ);
+        }
+        if let Some(name) = &node.name {
+            self.bind(name, node.node);
+        }
+        self.visit_body(&node.body);
+    }
+
+    fn visit_function_def(&mut self, node: &'a FunctionDef) {
+        self.visit_function(
+            node.node,
+            node.name,
+            &node.args,
+            &node.body,
+            &node.decorator_list,
+            &node.returns,
+            false,
+        );
+    }
+
+    fn visit_async_function_def(&mut self, node: &'a AsyncFunctionDef) {
+        self.visit_function(
+            node.node,
+            node.name,
+            &node.args,
+            &node.body,
+            &node.decorator_list,
+            &node.returns,
+            true,
+        );
+    }
+
+    fn visit_class_def(&mut self, node: &'a ClassDef) {
+        for decorator in &node.decorator_list {
+            self.visit_expr(decorator);
+        }
+        self.visit_elements(&node.bases);
+        for keyword in &node.keywords {
+            self.visit_keyword(keyword);
+        }
+        self.bind(intern_lookup(node.name), node.node);
+
+        self.scopes.push(Scope::new(ScopeKind::Class));
+        self.visit_body(&node.body);
+        self.scopes.pop();
+    }
+
+    fn visit_name(&mut self, node: &'a Name) {
+        self.scope().used.insert(&node.id);
+    }
+
+    fn visit_list(&mut self, node: &'a List) {
+        self.visit_elements(&node.elements);
+    }
+
+    fn visit_tuple(&mut self, node: &'a Tuple) {
+        self.visit_elements(&node.elements);
+    }
+
+    fn visit_set(&mut self, node: &'a Set) {
+        self.visit_elements(&node.elements);
+    }
+
+    fn visit_call(&mut self, node: &'a Call) {
+        self.visit_expr(&node.func);
+        self.visit_elements(&node.args);
+        for keyword in &node.keywords {
+            self.visit_keyword(keyword);
+        }
+    }
+
+    fn visit_keyword(&mut self, node: &'a Keyword) {
+        if let Some(arg) = &node.arg {
+            self.check_debug(arg, node.node);
+        }
+        visitor::walk_keyword(self, node);
+    }
+
+    fn visit_starred(&mut self, node: &'a Starred) {
+        self.error(
+            node.node,
+            "can't use starred expression here".to_string(),
+            "Starred expressions can only be used in calls, lists, tuples and sets",
+        );
+        visitor::walk_starred(self, node);
+    }
+
+    fn visit_named_expr(&mut self, node: &'a NamedExpression) {
+        self.visit_expr(&node.value);
+        match &node.target {
+            // Assignment expressions in comprehensions bind in the enclosing scope
+            Expression::Name(name) => {
+                self.check_debug(&name.id, name.node);
+                self.enclosing_scope().assigned.insert(&name.id);
+            }
+            Expression::Invalid(_) => {}
+            target => self.error(
+                target.get_node(),
+                format!(
+                    "cannot use assignment expressions with {}",
+                    expression_kind(target)
+                ),
+                "Only names can be assigned with :=",
+            ),
+        }
+    }
+
+    fn visit_yield(&mut self, node: &'a Yield) {
+        self.check_yield(node.node, false);
+        visitor::walk_yield(self, node);
+    }
+
+    fn visit_yield_from(&mut self, node: &'a YieldFrom) {
+        self.check_yield(node.node, true);
+        visitor::walk_yield_from(self, node);
+    }
+
+    fn visit_await(&mut self, node: &'a Await) {
+        match self.scope().kind {
+            ScopeKind::Comprehension { .. } => self.scope().is_async = true,
+            ScopeKind::Function { is_async: true } => {}
+            ScopeKind::Function { is_async: false } => self.error(
+                node.node,
+                "'await' outside async function".to_string(),
+                "Make the enclosing function async",
+            ),
+            ScopeKind::Module | ScopeKind::Class => self.error(
+                node.node,
+                "'await' outside function".to_string(),
+                "await can only be used in async functions",
+            ),
+        }
+        visitor::walk_await(self, node);
+    }
+
+    fn visit_lambda(&mut self, node: &'a Lambda) {
+        self.visit_function_header(&[], &node.args);
+        self.scopes
+            .push(Scope::new(ScopeKind::Function { is_async: false }));
+        self.add_parameters(&node.args);
+        self.visit_expr(&node.body);
+        self.scopes.pop();
+    }
+
+    fn visit_generator(&mut self, node: &'a Generator) {
+        self.visit_comprehension_scope(
+            "generator expression",
+            node.node,
+            &node.generators,
+            &[&node.element],
+        );
+    }
+
+    fn visit_list_comp(&mut self, node: &'a ListComp) {
+        self.visit_comprehension_scope(
+            "list comprehension",
+            node.node,
+            &node.generators,
+            &[&node.element],
+        );
+    }
+
+    fn visit_set_comp(&mut self, node: &'a SetComp) {
+        self.visit_comprehension_scope(
+            "set comprehension",
+            node.node,
+            &node.generators,
+            &[&node.element],
+        );
+    }
+
+    fn visit_dict_comp(&mut self, node: &'a DictComp) {
+        self.visit_comprehension_scope(
+            "dict comprehension",
+            node.node,
+            &node.generators,
+            &[&node.key, &node.value],
+        );
+    }
+}
+
+fn parameters(args: &Arguments) -> impl Iterator<Item = &Arg> {
+    args.posonlyargs
+        .iter()
+        .chain(args.args.iter())
+        .chain(args.vararg.iter())
+        .chain(args.kwonlyargs.iter())
+        .chain(args.kwarg.iter())
+}
+
+fn is_keyword_constant(expr: &Expression) -> bool {
+    matches!(
+        expr,
+        Expression::Constant(constant)
+            if matches!(constant.value, ConstantValue::None | ConstantValue::Bool(_))
+    )
+}
+
+/// How CPython calls an expression in error messages
+fn expression_kind(expr: &Expression) -> &'static str {
+    match expr {
+        Expression::Constant(constant) => match constant.value {
+            ConstantValue::None => "None",
+            ConstantValue::Bool(true) => "True",
+            ConstantValue::Bool(false) => "False",
+            ConstantValue::Ellipsis => "ellipsis",
+            _ => "literal",
+        },
+        Expression::List(_) => "list",
+        Expression::Tuple(_) => "tuple",
+        Expression::Dict(_) => "dict literal",
+        Expression::Set(_) => "set display",
+        Expression::Name(_) => "name",
+        Expression::BoolOp(_) | Expression::BinOp(_) | Expression::UnaryOp(_) => "expression",
+        Expression::NamedExpr(_) => "named expression",
+        Expression::Yield(_) | Expression::YieldFrom(_) => "yield expression",
+        Expression::Starred(_) => "starred",
+        Expression::Generator(_) => "generator expression",
+        Expression::ListComp(_) => "list comprehension",
+        Expression::SetComp(_) => "set comprehension",
+        Expression::DictComp(_) => "dict comprehension",
+        Expression::Attribute(_) => "attribute",
+        Expression::Subscript(_) => "subscript",
+        Expression::Slice(_) => "slice",
+        Expression::Call(_) => "function call",
+        Expression::Await(_) => "await expression",
+        Expression::Compare(_) => "comparison",
+        Expression::Lambda(_) => "lambda",
+        Expression::IfExp(_) => "conditional expression",
+        Expression::JoinedStr(_) | Expression::FormattedValue(_) => "f-string expression",
+        Expression::Invalid(_) => "invalid expression",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parser::Parser;
+
+    /// The messages of the errors and the source they are reported at
+    fn errors(source: &str) -> Vec<(String, String)> {
+        let (module, parse_errors) = Parser::new(source).parse();
+        assert!(parse_errors.is_empty(), "{source}: {parse_errors:?}");
+        semantic_errors(&module)
+            .into_iter()
+            .map(|error| {
+                let (start, len) = error.span();
+                (error.to_string(), source[start..start + len].to_string())
+            })
+            .collect()
+    }
+
+    fn assert_errors(cases: &[(&str, &str, &str)]) {
+        for (source, msg, at) in cases {
+            assert_eq!(
+                errors(source),
+                [(msg.to_string(), at.to_string())],
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_valid() {
+        for source in [
+            "def f():\n    return 1",
+            "def f():\n    yield 1\n    yield from g()",
+            "async def f():\n    await x\n    async for a in b:\n        pass\n    async with c:\n        pass",
+            "async def f():\n    yield 1\n    return",
+            "async def f():\n    return [await x for x in y]",
+            "(await x for x in y)",
+            "while a:\n    if b:\n        break\n    continue",
+            "for a in b:\n    try:\n        pass\n    finally:\n        continue",
+            "def f():\n    x = 1\n    def g():\n        nonlocal x\n        x = 2",
+            "def f():\n    global x\n    x = 1",
+            "a, *b = c",
+            "[*a, b] = *c, d",
+            "print(*a, *b, **c)",
+            "x = [*a], {*b}, (*c,)",
+            "class A(*bases):\n    pass",
+            "x[*a]",
+            "a.b = c[0] = d",
+            "del a, b.c, d[0], (e, [f])",
+            "x += 1\na.b -= 1\nc[0] *= 2",
+            "[y := 1 for x in z]",
+            "lambda x: (yield)",
+        ] {
+            assert_eq!(errors(source), [], "{source}");
+        }
+    }
+
+    #[test]
+    fn test_outside_function() {
+        assert_errors(&[
+            ("return 1", "'return' outside function", "return 1"),
+            (
+                "class A:\n    return",
+                "'return' outside function",
+                "return",
+            ),
+            ("yield 1", "'yield' outside function", "yield 1"),
+            (
+                "x = yield from a",
+                "'yield' outside function",
+                "yield from a",
+            ),
+            ("await x", "'await' outside function", "await x"),
+            (
+                "def f():\n    await x",
+                "'await' outside async function",
+                "await x",
+            ),
+            (
+                "async def f():\n    g = lambda: await x",
+                "'await' outside async function",
+                "await x",
+            ),
+            (
+                "def f():\n    [await x for x in y]",
+                "asynchronous comprehension outside of an asynchronous function",
+                "[await x for x in y]",
+            ),
+            (
+                "async for x in y:\n    pass",
+                "'async for' outside async function",
+                "async for x in y:\n    pass",
+            ),
+            (
+                "def f():\n    async with x:\n        pass",
+                "'async with' outside async function",
+                "async with x:\n        pass",
+            ),
+            (
+                "def f():\n    [(yield x) for x in y]",
+                "'yield' inside list comprehension",
+                "yield x",
+            ),
+            (
+                "def f():\n    {k: (yield) for k in y}",
+                "'yield' inside dict comprehension",
+                "yield",
+            ),
+            (
+                "async def f():\n    yield 1\n    return 2",
+                "'return' with value in async generator",
+                "return 2",
+            ),
+            (
+                "async def f():\n    yield from x",
+                "'yield from' inside async function",
+                "yield from x",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn test_outside_loop() {
+        assert_errors(&[
+            ("break", "'break' outside loop", "break"),
+            (
+                "if a:\n    continue",
+                "'continue' not properly in loop",
+                "continue",
+            ),
+            (
+                "while a:\n    pass\nelse:\n    break",
+                "'break' outside loop",
+                "break",
+            ),
+            (
+                "for a in b:\n    def f():\n        break",
+                "'break' outside loop",
+                "break",
+            ),
+            (
+                "while a:\n    class A:\n        continue",
+                "'continue' not properly in loop",
+                "continue",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn test_parameters() {
+        assert_errors(&[
+            (
+                "def f(a, b, a): pass",
+                "duplicate argument 'a' in function definition",
+                "a",
+            ),
+            (
+                "def f(a, *, b, **a): pass",
+                "duplicate argument 'a' in function definition",
+                "a",
+            ),
+            (
+                "f = lambda a, a: 1",
+                "duplicate argument 'a' in function definition",
+                "a",
+            ),
+            (
+                "def f(__debug__): pass",
+                "cannot assign to __debug__",
+                "__debug__",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn test_assignment_targets() {
+        assert_errors(&[
+            (
+                "1 = x",
+                "cannot assign to literal here. Maybe you meant '==' instead of '='?",
+                "1",
+            ),
+            (
+                "f() = x",
+                "cannot assign to function call here. Maybe you meant '==' instead of '='?",
+                "f()",
+            ),
+            ("x = a + b = c", "cannot assign to expression", "a + b"),
+            ("None = 1", "cannot assign to None", "None"),
+            ("a, True = b", "cannot assign to True", "True"),
+            ("(a, [b, 'c']) = d", "cannot assign to literal", "'c'"),
+            ("__debug__ = 1", "cannot assign to __debug__", "__debug__"),
+            (
+                "*a = b",
+                "starred assignment target must be in a list or tuple",
+                "*a",
+            ),
+            (
+                "*a, *b = c",
+                "multiple starred expressions in assignment",
+                "*a, *b",
+            ),
+            (
+                "for *a in b:\n    pass",
+                "starred assignment target must be in a list or tuple",
+                "*a",
+            ),
+            (
+                "f() += 1",
+                "'function call' is an illegal expression for augmented assignment",
+                "f()",
+            ),
+            (
+                "a, b += 1",
+                "'tuple' is an illegal expression for augmented assignment",
+                "a, b",
+            ),
+            (
+                "[a, b]: int",
+                "only single target (not list) can be annotated",
+                "[a, b]",
+            ),
+            ("f(): int = 1", "illegal target for annotation", "f()"),
+            (
+                "x = f(__debug__=1)",
+                "cannot assign to __debug__",
+                "__debug__=1",
+            ),
+            (
+                "import a as __debug__",
+                "cannot assign to __debug__",
+                "a as __debug__",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn test_starred() {
+        assert_errors(&[
+            ("x = *a", "can't use starred expression here", "*a"),
+            (
+                "for x in *a:\n    pass",
+                "can't use starred expression here",
+                "*a",
+            ),
+            ("x = (*a)", "can't use starred expression here", "*a"),
+        ]);
+    }
+
+    #[test]
+    fn test_global_and_nonlocal() {
+        assert_errors(&[
+            (
+                "nonlocal x",
+                "nonlocal declaration not allowed at module level",
+                "nonlocal x",
+            ),
+            (
+                "def f(x):\n    global x",
+                "name 'x' is parameter and global",
+                "global x",
+            ),
+            (
+                "def f():\n    print(x)\n    global x",
+                "name 'x' is used prior to global declaration",
+                "global x",
+            ),
+            (
+                "def f():\n    x = 1\n    global x",
+                "name 'x' is assigned to before global declaration",
+                "global x",
+            ),
+            (
+                "def f():\n    x: int\n    global x",
+                "annotated name 'x' can't be global",
+                "global x",
+            ),
+            (
+                "def f():\n    global x\n    x: int = 1",
+                "annotated name 'x' can't be global",
+                "x: int = 1",
+            ),
+            (
+                "def f():\n    [y := 1 for x in z]\n    global y",
+                "name 'y' is assigned to before global declaration",
+                "global y",
+            ),
+            (
+                "def f():\n    x = 1\n    def g():\n        nonlocal x\n        global x",
+                "name 'x' is nonlocal and global",
+                "global x",
+            ),
+            (
+                "def f():\n    x = 1\n    def g():\n        x += 1\n        nonlocal x",
+                "name 'x' is assigned to before nonlocal declaration",
+                "nonlocal x",
+            ),
+        ]);
+        // CPython accepts imports of a name before its global declaration
+        assert_eq!(errors("def f():\n    import os\n    global os"), []);
+        // Uses in nested scopes are not uses of the enclosing scope
+        assert_eq!(
+            errors("def f():\n    def g():\n        print(x)\n    global x"),
+            []
+        );
+    }
+}
@@ -3,6 +3,7 @@ use std::fmt::Display;
 use crate::ast::BinaryOperator;
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Token {
     pub kind: Kind,
     pub start: u32,
@@ -15,7 +16,7 @@ impl Token {
     }
 
     pub fn to_string(&self, source: &str) -> String {
-        return self.as_str(source).to_string();
+        self.as_str(source).to_string()
     }
 
     pub fn display_token(&self, source: &str) -> String {
@@ -58,6 +59,7 @@ impl Token {
 
 // https://docs.python.org/3/reference/lexical_analysis.html
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Kind {
     // Line structure
     NewLine, // \n
@@ -209,7 +211,12 @@ impl Kind {
     pub fn is_string(&self) -> bool {
         matches!(
             self,
-            Self::StringLiteral | Self::RawBytes | Self::Bytes | Self::FStringStart
+            Self::StringLiteral
+                | Self::Unicode
+                | Self::RawBytes
+                | Self::Bytes
+                | Self::FStringStart
+                | Self::RawFStringStart
         )
     }
     pub fn is_unary_op(&self) -> bool {
@@ -251,6 +258,7 @@ impl Kind {
         match self {
         Kind::Identifier
         | Kind::StringLiteral
+        | Kind::Unicode
         | Kind::RawBytes
         | Kind::Bytes
         | Kind::FStringStart
@@ -287,7 +295,13 @@ impl Kind {
         }
         matches!(
             self,
-            Kind::Await | Kind::BitNot | Kind::Minus | Kind::Plus | Kind::Mul
+            Kind::Await
+                | Kind::BitNot
+                | Kind::Minus
+                | Kind::Plus
+                | Kind::Mul
+                | Kind::Not
+                | Kind::Lambda
         )
     }
 }
@@ -0,0 +1,912 @@
+//! Rewriting of the AST.
+//!
+//! [`Transformer`] is the mutable counterpart of
+//! [`Visitor`](crate::visitor::Visitor). The methods get mutable references so
+//! nodes can be changed or replaced in place, e.g. by assigning a new
+//! expression to `*expr` in `visit_expr`. Statements are added or removed by
+//! overriding `visit_body`, which gets the whole list of statements of a
+//! block.
+//!
+//! ```
+//! use enderpy_python_parser::{
+//!     ast::Name, parser::parser::Parser, transformer::Transformer, unparse::unparse,
+//! };
+//!
+//! struct Rename;
+//!
+//! impl Transformer for Rename {
+//!     fn visit_name(&mut self, node: &mut Name) {
+//!         if node.id == "a" {
+//!             node.id = "renamed".to_string();
+//!         }
+//!     }
+//! }
+//!
+//! let (mut module, _) = Parser::new("a = a + 1").parse();
+//! Rename.visit_module(&mut module);
+//! assert_eq!(unparse(&module), "renamed = renamed + 1");
+//! ```
+//!
+//! Function and class definitions are shared with `Arc`, they are cloned
+//! before the first change if another reference to them exists.
+use std::sync::Arc;
+
+use crate::ast::*;
+
+/// Visits the nodes of the AST mutably, the default methods visit all the
+/// children
+#[allow(unused_variables)]
+pub trait Transformer {
+    /// Visits the statements of a block, statements can be inserted or
+    /// removed here
+    fn visit_body(&mut self, body: &mut Vec<Statement>) {
+        walk_body(self, body);
+    }
+
+    fn visit_stmt(&mut self, stmt: &mut Statement) {
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &mut Expression) {
+        walk_expr(self, expr);
+    }
+
+    fn visit_module(&mut self, node: &mut Module) {
+        walk_module(self, node);
+    }
+
+    fn visit_assign(&mut self, node: &mut Assign) {
+        walk_assign(self, node);
+    }
+
+    fn visit_ann_assign(&mut self, node: &mut AnnAssign) {
+        walk_ann_assign(self, node);
+    }
+
+    fn visit_aug_assign(&mut self, node: &mut AugAssign) {
+        walk_aug_assign(self, node);
+    }
+
+    fn visit_assert(&mut self, node: &mut Assert) {
+        walk_assert(self, node);
+    }
+
+    fn visit_pass(&mut self, node: &mut Pass) {}
+
+    fn visit_delete(&mut self, node: &mut Delete) {
+        walk_delete(self, node);
+    }
+
+    fn visit_return(&mut self, node: &mut Return) {
+        walk_return(self, node);
+    }
+
+    fn visit_raise(&mut self, node: &mut Raise) {
+        walk_raise(self, node);
+    }
+
+    fn visit_break(&mut self, node: &mut Break) {}
+
+    fn visit_continue(&mut self, node: &mut Continue) {}
+
+    fn visit_import(&mut self, node: &mut Import) {
+        walk_import(self, node);
+    }
+
+    fn visit_import_from(&mut self, node: &mut ImportFrom) {
+        walk_import_from(self, node);
+    }
+
+    fn visit_global(&mut self, node: &mut Global) {}
+
+    fn visit_nonlocal(&mut self, node: &mut Nonlocal) {}
+
+    fn visit_if(&mut self, node: &mut If) {
+        walk_if(self, node);
+    }
+
+    fn visit_while(&mut self, node: &mut While) {
+        walk_while(self, node);
+    }
+
+    fn visit_for(&mut self, node: &mut For) {
+        walk_for(self, node);
+    }
+
+    fn visit_async_for(&mut self, node: &mut AsyncFor) {
+        walk_async_for(self, node);
+    }
+
+    fn visit_with(&mut self, node: &mut With) {
+        walk_with(self, node);
+    }
+
+    fn visit_async_with(&mut self, node: &mut AsyncWith) {
+        walk_async_with(self, node);
+    }
+
+    fn visit_try(&mut self, node: &mut Try) {
+        walk_try(self, node);
+    }
+
+    fn visit_try_star(&mut self, node: &mut TryStar) {
+        walk_try_star(self, node);
+    }
+
+    fn visit_function_def(&mut self, node: &mut FunctionDef) {
+        walk_function_def(self, node);
+    }
+
+    fn visit_async_function_def(&mut self, node: &mut AsyncFunctionDef) {
+        walk_async_function_def(self, node);
+    }
+
+    fn visit_class_def(&mut self, node: &mut ClassDef) {
+        walk_class_def(self, node);
+    }
+
+    fn visit_match(&mut self, node: &mut Match) {
+        walk_match(self, node);
+    }
+
+    fn visit_type_alias(&mut self, node: &mut TypeAlias) {
+        walk_type_alias(self, node);
+    }
+
+    fn visit_invalid(&mut self, node: &mut Invalid) {}
+
+    fn visit_constant(&mut self, node: &mut Constant) {}
+
+    fn visit_list(&mut self, node: &mut List) {
+        walk_list(self, node);
+    }
+
+    fn visit_tuple(&mut self, node: &mut Tuple) {
+        walk_tuple(self, node);
+    }
+
+    fn visit_dict(&mut self, node: &mut Dict) {
+        walk_dict(self, node);
+    }
+
+    fn visit_set(&mut self, node: &mut Set) {
+        walk_set(self, node);
+    }
+
+    fn visit_name(&mut self, node: &mut Name) {}
+
+    fn visit_bool_op(&mut self, node: &mut BoolOperation) {
+        walk_bool_op(self, node);
+    }
+
+    fn visit_unary_op(&mut self, node: &mut UnaryOperation) {
+        walk_unary_op(self, node);
+    }
+
+    fn visit_bin_op(&mut self, node: &mut BinOp) {
+        walk_bin_op(self, node);
+    }
+
+    fn visit_named_expr(&mut self, node: &mut NamedExpression) {
+        walk_named_expr(self, node);
+    }
+
+    fn visit_yield(&mut self, node: &mut Yield) {
+        walk_yield(self, node);
+    }
+
+    fn visit_yield_from(&mut self, node: &mut YieldFrom) {
+        walk_yield_from(self, node);
+    }
+
+    fn visit_starred(&mut self, node: &mut Starred) {
+        walk_starred(self, node);
+    }
+
+    fn visit_generator(&mut self, node: &mut Generator) {
+        walk_generator(self, node);
+    }
+
+    fn visit_list_comp(&mut self, node: &mut ListComp) {
+        walk_list_comp(self, node);
+    }
+
+    fn visit_set_comp(&mut self, node: &mut SetComp) {
+        walk_set_comp(self, node);
+    }
+
+    fn visit_dict_comp(&mut self, node: &mut DictComp) {
+        walk_dict_comp(self, node);
+    }
+
+    fn visit_attribute(&mut self, node: &mut Attribute) {
+        walk_attribute(self, node);
+    }
+
+    fn visit_subscript(&mut self, node: &mut Subscript) {
+        walk_subscript(self, node);
+    }
+
+    fn visit_slice(&mut self, node: &mut Slice) {
+        walk_slice(self, node);
+    }
+
+    fn visit_call(&mut self, node: &mut Call) {
+        walk_call(self, node);
+    }
+
+    fn visit_await(&mut self, node: &mut Await) {
+        walk_await(self, node);
+    }
+
+    fn visit_compare(&mut self, node: &mut Compare) {
+        walk_compare(self, node);
+    }
+
+    fn visit_lambda(&mut self, node: &mut Lambda) {
+        walk_lambda(self, node);
+    }
+
+    fn visit_if_exp(&mut self, node: &mut IfExp) {
+        walk_if_exp(self, node);
+    }
+
+    fn visit_joined_str(&mut self, node: &mut JoinedStr) {
+        walk_joined_str(self, node);
+    }
+
+    fn visit_formatted_value(&mut self, node: &mut FormattedValue) {
+        walk_formatted_value(self, node);
+    }
+
+    fn visit_alias(&mut self, node: &mut Alias) {}
+
+    fn visit_arguments(&mut self, node: &mut Arguments) {
+        walk_arguments(self, node);
+    }
+
+    fn visit_arg(&mut self, node: &mut Arg) {
+        walk_arg(self, node);
+    }
+
+    fn visit_keyword(&mut self, node: &mut Keyword) {
+        walk_keyword(self, node);
+    }
+
+    fn visit_comprehension(&mut self, node: &mut Comprehension) {
+        walk_comprehension(self, node);
+    }
+
+    fn visit_with_item(&mut self, node: &mut WithItem) {
+        walk_with_item(self, node);
+    }
+
+    fn visit_except_handler(&mut self, node: &mut ExceptHandler) {
+        walk_except_handler(self, node);
+    }
+
+    fn visit_match_case(&mut self, node: &mut MatchCase) {
+        walk_match_case(self, node);
+    }
+
+    fn visit_match_pattern(&mut self, pattern: &mut MatchPattern) {
+        walk_match_pattern(self, pattern);
+    }
+
+    fn visit_type_param(&mut self, type_param: &mut TypeParam) {
+        walk_type_param(self, type_param);
+    }
+}
+
+pub fn walk_body<V: Transformer + ?Sized>(visitor: &mut V, body: &mut [Statement]) {
+    for stmt in body.iter_mut() {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_stmt<V: Transformer + ?Sized>(visitor: &mut V, stmt: &mut Statement) {
+    match stmt {
+        Statement::AssignStatement(node) => visitor.visit_assign(node),
+        Statement::AnnAssignStatement(node) => visitor.visit_ann_assign(node),
+        Statement::AugAssignStatement(node) => visitor.visit_aug_assign(node),
+        Statement::ExpressionStatement(node) => visitor.visit_expr(node),
+        Statement::Assert(node) => visitor.visit_assert(node),
+        Statement::Pass(node) => visitor.visit_pass(node),
+        Statement::Delete(node) => visitor.visit_delete(node),
+        Statement::ReturnStmt(node) => visitor.visit_return(node),
+        Statement::Raise(node) => visitor.visit_raise(node),
+        Statement::BreakStmt(node) => visitor.visit_break(node),
+        Statement::ContinueStmt(node) => visitor.visit_continue(node),
+        Statement::Import(node) => visitor.visit_import(node),
+        Statement::ImportFrom(node) => visitor.visit_import_from(node),
+        Statement::Global(node) => visitor.visit_global(node),
+        Statement::Nonlocal(node) => visitor.visit_nonlocal(node),
+        Statement::IfStatement(node) => visitor.visit_if(node),
+        Statement::WhileStatement(node) => visitor.visit_while(node),
+        Statement::ForStatement(node) => visitor.visit_for(node),
+        Statement::AsyncForStatement(node) => visitor.visit_async_for(node),
+        Statement::WithStatement(node) => visitor.visit_with(node),
+        Statement::AsyncWithStatement(node) => visitor.visit_async_with(node),
+        Statement::TryStatement(node) => visitor.visit_try(node),
+        Statement::TryStarStatement(node) => visitor.visit_try_star(node),
+        Statement::FunctionDef(node) => visitor.visit_function_def(Arc::make_mut(node)),
+        Statement::AsyncFunctionDef(node) => visitor.visit_async_function_def(Arc::make_mut(node)),
+        Statement::ClassDef(node) => visitor.visit_class_def(Arc::make_mut(node)),
+        Statement::MatchStmt(node) => visitor.visit_match(node),
+        Statement::TypeAlias(node) => visitor.visit_type_alias(node),
+        Statement::Invalid(node) => visitor.visit_invalid(node),
+    }
+}
+
+pub fn walk_expr<V: Transformer + ?Sized>(visitor: &mut V, expr: &mut Expression) {
+    match expr {
+        Expression::Constant(node) => visitor.visit_constant(node),
+        Expression::List(node) => visitor.visit_list(node),
+        Expression::Tuple(node) => visitor.visit_tuple(node),
+        Expression::Dict(node) => visitor.visit_dict(node),
+        Expression::Set(node) => visitor.visit_set(node),
+        Expression::Name(node) => visitor.visit_name(node),
+        Expression::BoolOp(node) => visitor.visit_bool_op(node),
+        Expression::UnaryOp(node) => visitor.visit_unary_op(node),
+        Expression::BinOp(node) => visitor.visit_bin_op(node),
+        Expression::NamedExpr(node) => visitor.visit_named_expr(node),
+        Expression::Yield(node) => visitor.visit_yield(node),
+        Expression::YieldFrom(node) => visitor.visit_yield_from(node),
+        Expression::Starred(node) => visitor.visit_starred(node),
+        Expression::Generator(node) => visitor.visit_generator(node),
+        Expression::ListComp(node) => visitor.visit_list_comp(node),
+        Expression::SetComp(node) => visitor.visit_set_comp(node),
+        Expression::DictComp(node) => visitor.visit_dict_comp(node),
+        Expression::Attribute(node) => visitor.visit_attribute(node),
+        Expression::Subscript(node) => visitor.visit_subscript(node),
+        Expression::Slice(node) => visitor.visit_slice(node),
+        Expression::Call(node) => visitor.visit_call(node),
+        Expression::Await(node) => visitor.visit_await(node),
+        Expression::Compare(node) => visitor.visit_compare(node),
+        Expression::Lambda(node) => visitor.visit_lambda(node),
+        Expression::IfExp(node) => visitor.visit_if_exp(node),
+        Expression::JoinedStr(node) => visitor.visit_joined_str(node),
+        Expression::FormattedValue(node) => visitor.visit_formatted_value(node),
+        Expression::Invalid(node) => visitor.visit_invalid(node),
+    }
+}
+
+pub fn walk_module<V: Transformer + ?Sized>(visitor: &mut V, node: &mut Module) {
+    visitor.visit_body(&mut node.body);
+}
+
+pub fn walk_assign<V: Transformer + ?Sized>(visitor: &mut V, node: &mut Assign) {
+    for expr in &mut node.targets {
+        visitor.visit_expr(expr);
+    }
+    visitor.visit_expr(&mut node.value);
+}
+
+pub fn walk_ann_assign<V: Transformer + ?Sized>(visitor: &mut V, node: &mut AnnAssign) {
+    visitor.visit_expr(&mut node.target);
+    visitor.visit_expr(&mut node.annotation);
+    if let Some(expr) = &mut node.value {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_aug_assign<V: Transformer + ?Sized>(visitor: &mut V, node: &mut AugAssign) {
+    visitor.visit_expr(&mut node.target);
+    visitor.visit_expr(&mut node.value);
+}
+
+pub fn walk_assert<V: Transformer + ?Sized>(visitor: &mut V, node: &mut Assert) {
+    visitor.visit_expr(&mut node.test);
+    if let Some(expr) = &mut node.msg {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_delete<V: Transformer + ?Sized>(visitor: &mut V, node: &mut Delete) {
+    for expr in &mut node.targets {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_return<V: Transformer + ?Sized>(visitor: &mut V, node: &mut Return) {
+    if let Some(expr) = &mut node.value {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_raise<V: Transformer + ?Sized>(visitor: &mut V, node: &mut Raise) {
+    if let Some(expr) = &mut node.exc {
+        visitor.visit_expr(expr);
+    }
+    if let Some(expr) = &mut node.cause {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_import<V: Transformer + ?Sized>(visitor: &mut V, node: &mut Import) {
+    for alias in &mut node.names {
+        visitor.visit_alias(alias);
+    }
+}
+
+pub fn walk_import_from<V: Transformer + ?Sized>(visitor: &mut V, node: &mut ImportFrom) {
+    for alias in &mut node.names {
+        visitor.visit_alias(alias);
+    }
+}
+
+pub fn walk_if<V: Transformer + ?Sized>(visitor: &mut V, node: &mut If) {
+    visitor.visit_expr(&mut node.test);
+    visitor.visit_body(&mut node.body);
+    visitor.visit_body(&mut node.orelse);
+}
+
+pub fn walk_while<V: Transformer + ?Sized>(visitor: &mut V, node: &mut While) {
+    visitor.visit_expr(&mut node.test);
+    visitor.visit_body(&mut node.body);
+    visitor.visit_body(&mut node.orelse);
+}
+
+pub fn walk_for<V: Transformer + ?Sized>(visitor: &mut V, node: &mut For) {
+    visitor.visit_expr(&mut node.target);
+    visitor.visit_expr(&mut node.iter);
+    visitor.visit_body(&mut node.body);
+    visitor.visit_body(&mut node.orelse);
+}
+
+pub fn walk_async_for<V: Transformer + ?Sized>(visitor: &mut V, node: &mut AsyncFor) {
+    visitor.visit_expr(&mut node.target);
+    visitor.visit_expr(&mut node.iter);
+    visitor.visit_body(&mut node.body);
+    visitor.visit_body(&mut node.orelse);
+}
+
+pub fn walk_with<V: Transformer + ?Sized>(visitor: &mut V, node: &mut With) {
+    for with_item in &mut node.items {
+        visitor.visit_with_item(with_item);
+    }
+    visitor.visit_body(&mut node.body);
+}
+
+pub fn walk_async_with<V: Transformer + ?Sized>(visitor: &mut V, node: &mut AsyncWith) {
+    for with_item in &mut node.items {
+        visitor.visit_with_item(with_item);
+    }
+    visitor.visit_body(&mut node.body);
+}
+
+pub fn walk_try<V: Transformer + ?Sized>(visitor: &mut V, node: &mut Try) {
+    visitor.visit_body(&mut node.body);
+    for except_handler in &mut node.handlers {
+        visitor.visit_except_handler(except_handler);
+    }
+    visitor.visit_body(&mut node.orelse);
+    visitor.visit_body(&mut node.finalbody);
+}
+
+pub fn walk_try_star<V: Transformer + ?Sized>(visitor: &mut V, node: &mut TryStar) {
+    visitor.visit_body(&mut node.body);
+    for except_handler in &mut node.handlers {
+        visitor.visit_except_handler(except_handler);
+    }
+    visitor.visit_body(&mut node.orelse);
+    visitor.visit_body(&mut node.finalbody);
+}
+
+pub fn walk_function_def<V: Transformer + ?Sized>(visitor: &mut V, node: &mut FunctionDef) {
+    for expr in &mut node.decorator_list {
+        visitor.visit_expr(expr);
+    }
+    for type_param in &mut node.type_params {
+        visitor.visit_type_param(type_param);
+    }
+    visitor.visit_arguments(&mut node.args);
+    if let Some(expr) = &mut node.returns {
+        visitor.visit_expr(expr);
+    }
+    visitor.visit_body(&mut node.body);
+}
+
+pub fn walk_async_function_def<V: Transformer + ?Sized>(
+    visitor: &mut V,
+    node: &mut AsyncFunctionDef,
+) {
+    for expr in &mut node.decorator_list {
+        visitor.visit_expr(expr);
+    }
+    for type_param in &mut node.type_params {
+        visitor.visit_type_param(type_param);
+    }
+    visitor.visit_arguments(&mut node.args);
+    if let Some(expr) = &mut node.returns {
+        visitor.visit_expr(expr);
+    }
+    visitor.visit_body(&mut node.body);
+}
+
+pub fn walk_class_def<V: Transformer + ?Sized>(visitor: &mut V, node: &mut ClassDef) {
+    for expr in &mut node.decorator_list {
+        visitor.visit_expr(expr);
+    }
+    for type_param in &mut node.type_params {
+        visitor.visit_type_param(type_param);
+    }
+    for expr in &mut node.bases {
+        visitor.visit_expr(expr);
+    }
+    for keyword in &mut node.keywords {
+        visitor.visit_keyword(keyword);
+    }
+    visitor.visit_body(&mut node.body);
+}
+
+pub fn walk_match<V: Transformer + ?Sized>(visitor: &mut V, node: &mut Match) {
+    visitor.visit_expr(&mut node.subject);
+    for match_case in &mut node.cases {
+        visitor.visit_match_case(match_case);
+    }
+}
+
+pub fn walk_type_alias<V: Transformer + ?Sized>(visitor: &mut V, node: &mut TypeAlias) {
+    for type_param in &mut node.type_params {
+        visitor.visit_type_param(type_param);
+    }
+    visitor.visit_expr(&mut node.value);
+}
+
+pub fn walk_list<V: Transformer + ?Sized>(visitor: &mut V, node: &mut List) {
+    for expr in &mut node.elements {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_tuple<V: Transformer + ?Sized>(visitor: &mut V, node: &mut Tuple) {
+    for expr in &mut node.elements {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_dict<V: Transformer + ?Sized>(visitor: &mut V, node: &mut Dict) {
+    for (key, value) in node.keys.iter_mut().zip(&mut node.values) {
+        if let Some(key) = key {
+            visitor.visit_expr(key);
+        }
+        visitor.visit_expr(value);
+    }
+}
+
+pub fn walk_set<V: Transformer + ?Sized>(visitor: &mut V, node: &mut Set) {
+    for expr in &mut node.elements {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_bool_op<V: Transformer + ?Sized>(visitor: &mut V, node: &mut BoolOperation) {
+    for expr in &mut node.values {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_unary_op<V: Transformer + ?Sized>(visitor: &mut V, node: &mut UnaryOperation) {
+    visitor.visit_expr(&mut node.operand);
+}
+
+pub fn walk_bin_op<V: Transformer + ?Sized>(visitor: &mut V, node: &mut BinOp) {
+    visitor.visit_expr(&mut node.left);
+    visitor.visit_expr(&mut node.right);
+}
+
+pub fn walk_named_expr<V: Transformer + ?Sized>(visitor: &mut V, node: &mut NamedExpression) {
+    visitor.visit_expr(&mut node.target);
+    visitor.visit_expr(&mut node.value);
+}
+
+pub fn walk_yield<V: Transformer + ?Sized>(visitor: &mut V, node: &mut Yield) {
+    if let Some(expr) = &mut node.value {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_yield_from<V: Transformer + ?Sized>(visitor: &mut V, node: &mut YieldFrom) {
+    visitor.visit_expr(&mut node.value);
+}
+
+pub fn walk_starred<V: Transformer + ?Sized>(visitor: &mut V, node: &mut Starred) {
+    visitor.visit_expr(&mut node.value);
+}
+
+pub fn walk_generator<V: Transformer + ?Sized>(visitor: &mut V, node: &mut Generator) {
+    visitor.visit_expr(&mut node.element);
+    for comprehension in &mut node.generators {
+        visitor.visit_comprehension(comprehension);
+    }
+}
+
+pub fn walk_list_comp<V: Transformer + ?Sized>(visitor: &mut V, node: &mut ListComp) {
+    visitor.visit_expr(&mut node.element);
+    for comprehension in &mut node.generators {
+        visitor.visit_comprehension(comprehension);
+    }
+}
+
+pub fn walk_set_comp<V: Transformer + ?Sized>(visitor: &mut V, node: &mut SetComp) {
+    visitor.visit_expr(&mut node.element);
+    for comprehension in &mut node.generators {
+        visitor.visit_comprehension(comprehension);
+    }
+}
+
+pub fn walk_dict_comp<V: Transformer + ?Sized>(visitor: &mut V, node: &mut DictComp) {
+    visitor.visit_expr(&mut node.key);
+    visitor.visit_expr(&mut node.value);
+    for comprehension in &mut node.generators {
+        visitor.visit_comprehension(comprehension);
+    }
+}
+
+pub fn walk_attribute<V: Transformer + ?Sized>(visitor: &mut V, node: &mut Attribute) {
+    visitor.visit_expr(&mut node.value);
+}
+
+pub fn walk_subscript<V: Transformer + ?Sized>(visitor: &mut V, node: &mut Subscript) {
+    visitor.visit_expr(&mut node.value);
+    visitor.visit_expr(&mut node.slice);
+}
+
+pub fn walk_slice<V: Transformer + ?Sized>(visitor: &mut V, node: &mut Slice) {
+    if let Some(expr) = &mut node.lower {
+        visitor.visit_expr(expr);
+    }
+    if let Some(expr) = &mut node.upper {
+        visitor.visit_expr(expr);
+    }
+    if let Some(expr) = &mut node.step {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_call<V: Transformer + ?Sized>(visitor: &mut V, node: &mut Call) {
+    visitor.visit_expr(&mut node.func);
+    for expr in &mut node.args {
+        visitor.visit_expr(expr);
+    }
+    for keyword in &mut node.keywords {
+        visitor.visit_keyword(keyword);
+    }
+}
+
+pub fn walk_await<V: Transformer + ?Sized>(visitor: &mut V, node: &mut Await) {
+    visitor.visit_expr(&mut node.value);
+}
+
+pub fn walk_compare<V: Transformer + ?Sized>(visitor: &mut V, node: &mut Compare) {
+    visitor.visit_expr(&mut node.left);
+    for expr in &mut node.comparators {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_lambda<V: Transformer + ?Sized>(visitor: &mut V, node: &mut Lambda) {
+    visitor.visit_arguments(&mut node.args);
+    visitor.visit_expr(&mut node.body);
+}
+
+pub fn walk_if_exp<V: Transformer + ?Sized>(visitor: &mut V, node: &mut IfExp) {
+    visitor.visit_expr(&mut node.body);
+    visitor.visit_expr(&mut node.test);
+    visitor.visit_expr(&mut node.orelse);
+}
+
+pub fn walk_joined_str<V: Transformer + ?Sized>(visitor: &mut V, node: &mut JoinedStr) {
+    for expr in &mut node.values {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_formatted_value<V: Transformer + ?Sized>(visitor: &mut V, node: &mut FormattedValue) {
+    visitor.visit_expr(&mut node.value);
+    if let Some(expr) = &mut node.format_spec {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_arguments<V: Transformer + ?Sized>(visitor: &mut V, node: &mut Arguments) {
+    // Defaults belong to the last positional arguments
+    let positional = node.posonlyargs.len() + node.args.len();
+    let without_default = positional.saturating_sub(node.defaults.len());
+    let mut defaults = node.defaults.iter_mut();
+    for (index, arg) in node
+        .posonlyargs
+        .iter_mut()
+        .chain(node.args.iter_mut())
+        .enumerate()
+    {
+        visitor.visit_arg(arg);
+        if index >= without_default {
+            if let Some(default) = defaults.next() {
+                visitor.visit_expr(default);
+            }
+        }
+    }
+    if let Some(vararg) = &mut node.vararg {
+        visitor.visit_arg(vararg);
+    }
+    for (arg, default) in node.kwonlyargs.iter_mut().zip(node.kw_defaults.iter_mut()) {
+        visitor.visit_arg(arg);
+        if let Some(default) = default {
+            visitor.visit_expr(default);
+        }
+    }
+    if let Some(kwarg) = &mut node.kwarg {
+        visitor.visit_arg(kwarg);
+    }
+}
+
+pub fn walk_arg<V: Transformer + ?Sized>(visitor: &mut V, node: &mut Arg) {
+    if let Some(expr) = &mut node.annotation {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_keyword<V: Transformer + ?Sized>(visitor: &mut V, node: &mut Keyword) {
+    visitor.visit_expr(&mut node.value);
+}
+
+pub fn walk_comprehension<V: Transformer + ?Sized>(visitor: &mut V, node: &mut Comprehension) {
+    visitor.visit_expr(&mut node.target);
+    visitor.visit_expr(&mut node.iter);
+    for expr in &mut node.ifs {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_with_item<V: Transformer + ?Sized>(visitor: &mut V, node: &mut WithItem) {
+    visitor.visit_expr(&mut node.context_expr);
+    if let Some(expr) = &mut node.optional_vars {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_except_handler<V: Transformer + ?Sized>(visitor: &mut V, node: &mut ExceptHandler) {
+    if let Some(expr) = &mut node.typ {
+        visitor.visit_expr(expr);
+    }
+    visitor.visit_body(&mut node.body);
+}
+
+pub fn walk_match_case<V: Transformer + ?Sized>(visitor: &mut V, node: &mut MatchCase) {
+    visitor.visit_match_pattern(&mut node.pattern);
+    if let Some(expr) = &mut node.guard {
+        visitor.visit_expr(expr);
+    }
+    visitor.visit_body(&mut node.body);
+}
+
+pub fn walk_match_pattern<V: Transformer + ?Sized>(visitor: &mut V, pattern: &mut MatchPattern) {
+    match pattern {
+        MatchPattern::MatchValue(node) => visitor.visit_expr(&mut node.value),
+        MatchPattern::MatchSingleton(expr) | MatchPattern::MatchStar(expr) => {
+            visitor.visit_expr(expr)
+        }
+        MatchPattern::MatchSequence(patterns) | MatchPattern::MatchOr(patterns) => {
+            for pattern in patterns {
+                visitor.visit_match_pattern(pattern);
+            }
+        }
+        MatchPattern::MatchMapping(node) => {
+            for (key, pattern) in node.keys.iter_mut().zip(&mut node.patterns) {
+                visitor.visit_expr(key);
+                visitor.visit_match_pattern(pattern);
+            }
+        }
+        MatchPattern::MatchAs(node) => {
+            if let Some(pattern) = &mut node.pattern {
+                visitor.visit_match_pattern(pattern);
+            }
+        }
+        MatchPattern::MatchClass(node) => {
+            visitor.visit_expr(&mut node.cls);
+            for pattern in &mut node.patterns {
+                visitor.visit_match_pattern(pattern);
+            }
+            for pattern in &mut node.kwd_patterns {
+                visitor.visit_match_pattern(pattern);
+            }
+        }
+    }
+}
+
+pub fn walk_type_param<V: Transformer + ?Sized>(visitor: &mut V, type_param: &mut TypeParam) {
+    match type_param {
+        TypeParam::TypeVar(node) => {
+            if let Some(bound) = &mut node.bound {
+                visitor.visit_expr(bound);
+            }
+        }
+        TypeParam::ParamSpec(_) | TypeParam::TypeVarTuple(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use insta::glob;
+
+    use super::*;
+    use crate::{parser::parser::Parser, unparse::unparse, visitor::Visitor};
+
+    struct RenameAll;
+
+    impl Transformer for RenameAll {
+        fn visit_name(&mut self, node: &mut Name) {
+            node.id = "renamed".to_string();
+        }
+    }
+
+    #[derive(Default)]
+    struct NameCollector {
+        names: Vec<String>,
+    }
+
+    impl Visitor<'_> for NameCollector {
+        fn visit_name(&mut self, node: &Name) {
+            self.names.push(node.id.clone());
+        }
+    }
+
+    #[test]
+    fn test_transformer_visits_all_names() {
+        glob!("../test_data", "inputs/*.py", |path| {
+            let source = fs::read_to_string(path).unwrap();
+            let (mut module, _) = Parser::new(&source).parse();
+            let mut before = NameCollector::default();
+            before.visit_module(&module);
+
+            RenameAll.visit_module(&mut module);
+            let mut after = NameCollector::default();
+            after.visit_module(&module);
+            assert_eq!(
+                after.names,
+                vec!["renamed"; before.names.len()],
+                "{}",
+                path.display()
+            );
+        });
+    }
+
+    struct RemovePass;
+
+    impl Transformer for RemovePass {
+        fn visit_body(&mut self, body: &mut Vec<Statement>) {
+            body.retain(|stmt| !matches!(stmt, Statement::Pass(_)));
+            walk_body(self, body);
+        }
+
+        fn visit_expr(&mut self, expr: &mut Expression) {
+            walk_expr(self, expr);
+            if let Expression::BinOp(bin_op) = expr {
+                if bin_op.op == BinaryOperator::Add {
+                    *expr = bin_op.left.clone();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_transformer_rewrites_nodes() {
+        let source = "def f():\n    pass\n    return a + b + c\n\nclass C:\n    pass\n    x = 1";
+        let (mut module, _) = Parser::new(source).parse();
+        let function = module.body[0].clone();
+        RemovePass.visit_module(&mut module);
+        assert_eq!(
+            unparse(&module),
+            "def f():\n    return a\n\nclass C:\n    x = 1"
+        );
+        // The definitions are copied on write, the original is not changed
+        assert_eq!(
+            crate::unparse::unparse_statement(&function),
+            "def f():\n    pass\n    return a + b + c"
+        );
+    }
+}
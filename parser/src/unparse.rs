@@ -0,0 +1,1376 @@
+//! Source code generation from the AST.
+//!
+//! The output follows Python's `ast.unparse`: the code is formatted in one
+//! canonical way, parentheses are only added where the precedence of the
+//! operators requires them, and comments and the original formatting are not
+//! kept. Parsing the generated code gives the same tree back.
+//! https://github.com/python/cpython/blob/3.12/Lib/ast.py
+use crate::{ast::*, parser::parser::intern_lookup};
+
+/// Source code of the module
+pub fn unparse(module: &Module) -> String {
+    let mut unparser = Unparser::default();
+    unparser.body_with_docstring(&module.body);
+    unparser.source
+}
+
+/// Source code of the statement, compound statements include their body
+pub fn unparse_statement(statement: &Statement) -> String {
+    let mut unparser = Unparser::default();
+    unparser.statement(statement);
+    unparser.source
+}
+
+/// Source code of the expression
+pub fn unparse_expression(expression: &Expression) -> String {
+    let mut unparser = Unparser::default();
+    unparser.expression(expression, Precedence::Test);
+    unparser.source
+}
+
+/// Precedence of the operators from the lowest to the highest. An expression
+/// is parenthesized when it is used in a place that requires a higher
+/// precedence than its own.
+/// https://docs.python.org/3/reference/expressions.html#operator-precedence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+    /// `a := b`
+    NamedExpr,
+    /// `a, b`
+    Tuple,
+    /// `yield a` and `yield from a`
+    Yield,
+    /// `a if b else c` and `lambda: a`
+    Test,
+    Or,
+    And,
+    Not,
+    /// Comparisons, membership and identity tests
+    Cmp,
+    /// Expressions that are not comparisons, starts with `|`
+    Expr,
+    BitXor,
+    BitAnd,
+    /// `<<` and `>>`
+    Shift,
+    /// `+` and `-`
+    Arith,
+    /// `*`, `@`, `/`, `//` and `%`
+    Term,
+    /// Unary `+`, `-` and `~`
+    Factor,
+    Power,
+    Await,
+    Atom,
+}
+
+impl Precedence {
+    const BIT_OR: Precedence = Precedence::Expr;
+
+    fn next(self) -> Self {
+        match self {
+            Precedence::NamedExpr => Precedence::Tuple,
+            Precedence::Tuple => Precedence::Yield,
+            Precedence::Yield => Precedence::Test,
+            Precedence::Test => Precedence::Or,
+            Precedence::Or => Precedence::And,
+            Precedence::And => Precedence::Not,
+            Precedence::Not => Precedence::Cmp,
+            Precedence::Cmp => Precedence::Expr,
+            Precedence::Expr => Precedence::BitXor,
+            Precedence::BitXor => Precedence::BitAnd,
+            Precedence::BitAnd => Precedence::Shift,
+            Precedence::Shift => Precedence::Arith,
+            Precedence::Arith => Precedence::Term,
+            Precedence::Term => Precedence::Factor,
+            Precedence::Factor => Precedence::Power,
+            Precedence::Power => Precedence::Await,
+            Precedence::Await | Precedence::Atom => Precedence::Atom,
+        }
+    }
+
+    fn of_binary_operator(op: &BinaryOperator) -> Self {
+        match op {
+            BinaryOperator::Add | BinaryOperator::Sub => Precedence::Arith,
+            BinaryOperator::Mult
+            | BinaryOperator::MatMult
+            | BinaryOperator::Div
+            | BinaryOperator::Mod
+            | BinaryOperator::FloorDiv => Precedence::Term,
+            BinaryOperator::LShift | BinaryOperator::RShift => Precedence::Shift,
+            BinaryOperator::BitOr => Precedence::BIT_OR,
+            BinaryOperator::BitXor => Precedence::BitXor,
+            BinaryOperator::BitAnd => Precedence::BitAnd,
+            BinaryOperator::Pow => Precedence::Power,
+        }
+    }
+}
+
+const ALL_QUOTES: [&str; 4] = ["'", "\"", "\"\"\"", "'''"];
+const MULTI_QUOTES: [&str; 2] = ["\"\"\"", "'''"];
+
+/// Infinity is not a literal, a number that overflows is used instead
+const INFINITY: &str = "1e309";
+
+#[derive(Default)]
+struct Unparser {
+    source: String,
+    indent: usize,
+    /// Handlers of `try` statements are written as `except*`
+    in_try_star: bool,
+}
+
+impl Unparser {
+    fn write(&mut self, text: &str) {
+        self.source.push_str(text);
+    }
+
+    /// Starts a new line with the current indentation
+    fn fill(&mut self, text: &str) {
+        self.maybe_newline();
+        for _ in 0..self.indent {
+            self.source.push_str("    ");
+        }
+        self.source.push_str(text);
+    }
+
+    fn maybe_newline(&mut self) {
+        if !self.source.is_empty() {
+            self.source.push('\n');
+        }
+    }
+
+    fn block(&mut self, body: &[Statement], extra: Option<&str>) {
+        self.write(":");
+        if let Some(extra) = extra {
+            self.write(extra);
+        }
+        self.indent += 1;
+        self.statements(body);
+        self.indent -= 1;
+    }
+
+    fn statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    fn type_comment(type_comment: &Option<String>) -> Option<String> {
+        type_comment
+            .as_ref()
+            .map(|comment| format!(" # type: {comment}"))
+    }
+
+    fn body_with_docstring(&mut self, body: &[Statement]) {
+        match body.split_first() {
+            Some((Statement::ExpressionStatement(expr), rest)) => match expr.as_ref() {
+                Expression::Constant(constant) => match &constant.value {
+                    ConstantValue::Str(docstring) => {
+                        self.fill("");
+                        let (docstring, quotes) =
+                            str_literal_helper(docstring, &MULTI_QUOTES, false);
+                        self.write(quotes[0]);
+                        self.write(&docstring);
+                        self.write(quotes[0]);
+                        self.statements(rest);
+                    }
+                    _ => self.statements(body),
+                },
+                _ => self.statements(body),
+            },
+            _ => self.statements(body),
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::ExpressionStatement(expr) => {
+                self.fill("");
+                self.expression(expr, Precedence::Yield);
+            }
+            Statement::AssignStatement(assign) => {
+                self.fill("");
+                for target in &assign.targets {
+                    self.expression(target, Precedence::Tuple);
+                    self.write(" = ");
+                }
+                self.expression(&assign.value, Precedence::Test);
+                if let Some(comment) = Self::type_comment(&assign.type_comment) {
+                    self.write(&comment);
+                }
+            }
+            Statement::AnnAssignStatement(assign) => {
+                self.fill("");
+                let parenthesize = !assign.simple && assign.target.is_name();
+                if parenthesize {
+                    self.write("(");
+                }
+                self.expression(&assign.target, Precedence::Test);
+                if parenthesize {
+                    self.write(")");
+                }
+                self.write(": ");
+                self.expression(&assign.annotation, Precedence::Test);
+                if let Some(value) = &assign.value {
+                    self.write(" = ");
+                    self.expression(value, Precedence::Test);
+                }
+            }
+            Statement::AugAssignStatement(assign) => {
+                self.fill("");
+                self.expression(&assign.target, Precedence::Test);
+                self.write(&format!(" {} ", assign.op));
+                self.expression(&assign.value, Precedence::Test);
+            }
+            Statement::Assert(assert) => {
+                self.fill("assert ");
+                self.expression(&assert.test, Precedence::Test);
+                if let Some(msg) = &assert.msg {
+                    self.write(", ");
+                    self.expression(msg, Precedence::Test);
+                }
+            }
+            Statement::Pass(_) => self.fill("pass"),
+            Statement::BreakStmt(_) => self.fill("break"),
+            Statement::ContinueStmt(_) => self.fill("continue"),
+            Statement::Delete(delete) => {
+                self.fill("del ");
+                self.interleave(&delete.targets, |unparser, target| {
+                    unparser.expression(target, Precedence::Test)
+                });
+            }
+            Statement::ReturnStmt(ret) => {
+                self.fill("return");
+                if let Some(value) = &ret.value {
+                    self.write(" ");
+                    self.expression(value, Precedence::Test);
+                }
+            }
+            Statement::Raise(raise) => {
+                self.fill("raise");
+                if let Some(exc) = &raise.exc {
+                    self.write(" ");
+                    self.expression(exc, Precedence::Test);
+                    if let Some(cause) = &raise.cause {
+                        self.write(" from ");
+                        self.expression(cause, Precedence::Test);
+                    }
+                }
+            }
+            Statement::Import(import) => {
+                self.fill("import ");
+                self.interleave(&import.names, Self::alias);
+            }
+            Statement::ImportFrom(import) => {
+                self.fill("from ");
+                self.write(&".".repeat(import.level));
+                self.write(&import.module);
+                self.write(" import ");
+                self.interleave(&import.names, Self::alias);
+            }
+            Statement::Global(global) => {
+                self.fill("global ");
+                self.write(&global.names.join(", "));
+            }
+            Statement::Nonlocal(nonlocal) => {
+                self.fill("nonlocal ");
+                self.write(&nonlocal.names.join(", "));
+            }
+            Statement::IfStatement(if_statement) => {
+                self.fill("if ");
+                self.expression(&if_statement.test, Precedence::Test);
+                self.block(&if_statement.body, None);
+                // An if statement that is the only statement of the else block
+                // is written as elif
+                let mut orelse = &if_statement.orelse;
+                while let [Statement::IfStatement(elif)] = orelse.as_slice() {
+                    self.fill("elif ");
+                    self.expression(&elif.test, Precedence::Test);
+                    self.block(&elif.body, None);
+                    orelse = &elif.orelse;
+                }
+                self.orelse(orelse);
+            }
+            Statement::WhileStatement(while_statement) => {
+                self.fill("while ");
+                self.expression(&while_statement.test, Precedence::Test);
+                self.block(&while_statement.body, None);
+                self.orelse(&while_statement.orelse);
+            }
+            Statement::ForStatement(for_statement) => self.for_statement(
+                "for ",
+                &for_statement.target,
+                &for_statement.iter,
+                &for_statement.body,
+                &for_statement.orelse,
+                &for_statement.type_comment,
+            ),
+            Statement::AsyncForStatement(for_statement) => self.for_statement(
+                "async for ",
+                &for_statement.target,
+                &for_statement.iter,
+                &for_statement.body,
+                &for_statement.orelse,
+                &for_statement.type_comment,
+            ),
+            Statement::WithStatement(with) => {
+                self.with_statement("with ", &with.items, &with.body, &with.type_comment)
+            }
+            Statement::AsyncWithStatement(with) => {
+                self.with_statement("async with ", &with.items, &with.body, &with.type_comment)
+            }
+            Statement::TryStatement(try_statement) => self.try_statement(
+                &try_statement.body,
+                &try_statement.handlers,
+                &try_statement.orelse,
+                &try_statement.finalbody,
+                false,
+            ),
+            Statement::TryStarStatement(try_statement) => self.try_statement(
+                &try_statement.body,
+                &try_statement.handlers,
+                &try_statement.orelse,
+                &try_statement.finalbody,
+                true,
+            ),
+            Statement::FunctionDef(function) => self.function_def(
+                "def",
+                &function.decorator_list,
+                intern_lookup(function.name),
+                &function.type_params,
+                &function.args,
+                &function.returns,
+                &function.type_comment,
+                &function.body,
+            ),
+            Statement::AsyncFunctionDef(function) => self.function_def(
+                "async def",
+                &function.decorator_list,
+                intern_lookup(function.name),
+                &function.type_params,
+                &function.args,
+                &function.returns,
+                &function.type_comment,
+                &function.body,
+            ),
+            Statement::ClassDef(class) => {
+                self.maybe_newline();
+                self.decorators(&class.decorator_list);
+                self.fill("class ");
+                self.write(intern_lookup(class.name));
+                self.type_params(&class.type_params);
+                if !class.bases.is_empty() || !class.keywords.is_empty() {
+                    self.write("(");
+                    self.interleave(&class.bases, |unparser, base| {
+                        unparser.expression(base, Precedence::Test)
+                    });
+                    if !class.bases.is_empty() && !class.keywords.is_empty() {
+                        self.write(", ");
+                    }
+                    self.interleave(&class.keywords, Self::keyword);
+                    self.write(")");
+                }
+                self.write(":");
+                self.indent += 1;
+                self.body_with_docstring(&class.body);
+                self.indent -= 1;
+            }
+            Statement::MatchStmt(match_statement) => {
+                self.fill("match ");
+                self.expression(&match_statement.subject, Precedence::Test);
+                self.write(":");
+                self.indent += 1;
+                for case in &match_statement.cases {
+                    self.fill("case ");
+                    self.pattern(&case.pattern, Precedence::Test);
+                    if let Some(guard) = &case.guard {
+                        self.write(" if ");
+                        self.expression(guard, Precedence::Test);
+                    }
+                    self.block(&case.body, None);
+                }
+                self.indent -= 1;
+            }
+            Statement::TypeAlias(alias) => {
+                self.fill("type ");
+                self.write(&alias.name);
+                self.type_params(&alias.type_params);
+                self.write(" = ");
+                self.expression(&alias.value, Precedence::Test);
+            }
+            // Code that could not be parsed has no source to generate
+            Statement::Invalid(_) => {}
+        }
+    }
+
+    fn orelse(&mut self, orelse: &[Statement]) {
+        if !orelse.is_empty() {
+            self.fill("else");
+            self.block(orelse, None);
+        }
+    }
+
+    fn for_statement(
+        &mut self,
+        keyword: &str,
+        target: &Expression,
+        iter: &Expression,
+        body: &[Statement],
+        orelse: &[Statement],
+        type_comment: &Option<String>,
+    ) {
+        self.fill(keyword);
+        self.expression(target, Precedence::Tuple);
+        self.write(" in ");
+        self.expression(iter, Precedence::Test);
+        self.block(body, Self::type_comment(type_comment).as_deref());
+        self.orelse(orelse);
+    }
+
+    fn with_statement(
+        &mut self,
+        keyword: &str,
+        items: &[WithItem],
+        body: &[Statement],
+        type_comment: &Option<String>,
+    ) {
+        self.fill(keyword);
+        self.interleave(items, |unparser, item| {
+            unparser.expression(&item.context_expr, Precedence::Test);
+            if let Some(vars) = &item.optional_vars {
+                unparser.write(" as ");
+                unparser.expression(vars, Precedence::Test);
+            }
+        });
+        self.block(body, Self::type_comment(type_comment).as_deref());
+    }
+
+    fn try_statement(
+        &mut self,
+        body: &[Statement],
+        handlers: &[ExceptHandler],
+        orelse: &[Statement],
+        finalbody: &[Statement],
+        is_try_star: bool,
+    ) {
+        let in_try_star = std::mem::replace(&mut self.in_try_star, is_try_star);
+        self.fill("try");
+        self.block(body, None);
+        for handler in handlers {
+            self.fill(if self.in_try_star {
+                "except*"
+            } else {
+                "except"
+            });
+            if let Some(typ) = &handler.typ {
+                self.write(" ");
+                self.expression(typ, Precedence::Test);
+            }
+            if let Some(name) = &handler.name {
+                self.write(" as ");
+                self.write(name);
+            }
+            self.block(&handler.body, None);
+        }
+        self.orelse(orelse);
+        if !finalbody.is_empty() {
+            self.fill("finally");
+            self.block(finalbody, None);
+        }
+        self.in_try_star = in_try_star;
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn function_def(
+        &mut self,
+        keyword: &str,
+        decorators: &[Expression],
+        name: &str,
+        type_params: &[TypeParam],
+        args: &Arguments,
+        returns: &Option<Expression>,
+        type_comment: &Option<String>,
+        body: &[Statement],
+    ) {
+        self.maybe_newline();
+        self.decorators(decorators);
+        self.fill(keyword);
+        self.write(" ");
+        self.write(name);
+        self.type_params(type_params);
+        self.write("(");
+        self.arguments(args);
+        self.write(")");
+        if let Some(returns) = returns {
+            self.write(" -> ");
+            self.expression(returns, Precedence::Test);
+        }
+        self.write(":");
+        if let Some(comment) = Self::type_comment(type_comment) {
+            self.write(&comment);
+        }
+        self.indent += 1;
+        self.body_with_docstring(body);
+        self.indent -= 1;
+    }
+
+    fn decorators(&mut self, decorators: &[Expression]) {
+        for decorator in decorators {
+            self.fill("@");
+            self.expression(decorator, Precedence::Test);
+        }
+    }
+
+    fn type_params(&mut self, type_params: &[TypeParam]) {
+        if type_params.is_empty() {
+            return;
+        }
+        self.write("[");
+        self.interleave(type_params, |unparser, param| match param {
+            TypeParam::TypeVar(var) => {
+                unparser.write(&var.name);
+                if let Some(bound) = &var.bound {
+                    unparser.write(": ");
+                    unparser.expression(bound, Precedence::Test);
+                }
+            }
+            TypeParam::ParamSpec(spec) => {
+                unparser.write("**");
+                unparser.write(&spec.name);
+            }
+            TypeParam::TypeVarTuple(tuple) => {
+                unparser.write("*");
+                unparser.write(&tuple.name);
+            }
+        });
+        self.write("]");
+    }
+
+    fn arguments(&mut self, args: &Arguments) {
+        let mut first = true;
+        let mut separator = |unparser: &mut Self| {
+            if !std::mem::take(&mut first) {
+                unparser.write(", ");
+            }
+        };
+        let positional = args.posonlyargs.iter().chain(&args.args);
+        // Defaults belong to the last positional arguments
+        let without_default = args.posonlyargs.len() + args.args.len() - args.defaults.len();
+        for (index, arg) in positional.enumerate() {
+            separator(self);
+            self.arg(arg);
+            if let Some(default) = index
+                .checked_sub(without_default)
+                .and_then(|i| args.defaults.get(i))
+            {
+                self.write("=");
+                self.expression(default, Precedence::Test);
+            }
+            if index + 1 == args.posonlyargs.len() {
+                self.write(", /");
+            }
+        }
+        // A bare `*` separates keyword only arguments when there is no vararg
+        if args.vararg.is_some() || !args.kwonlyargs.is_empty() {
+            separator(self);
+            self.write("*");
+            if let Some(vararg) = &args.vararg {
+                self.arg(vararg);
+            }
+        }
+        for (index, arg) in args.kwonlyargs.iter().enumerate() {
+            self.write(", ");
+            self.arg(arg);
+            if let Some(Some(default)) = args.kw_defaults.get(index) {
+                self.write("=");
+                self.expression(default, Precedence::Test);
+            }
+        }
+        if let Some(kwarg) = &args.kwarg {
+            separator(self);
+            self.write("**");
+            self.arg(kwarg);
+        }
+    }
+
+    fn arg(&mut self, arg: &Arg) {
+        self.write(&arg.arg);
+        if let Some(annotation) = &arg.annotation {
+            self.write(": ");
+            self.expression(annotation, Precedence::Test);
+        }
+    }
+
+    fn alias(&mut self, alias: &Alias) {
+        self.write(&alias.name);
+        if let Some(asname) = &alias.asname {
+            self.write(" as ");
+            self.write(asname);
+        }
+    }
+
+    fn keyword(&mut self, keyword: &Keyword) {
+        match &keyword.arg {
+            Some(arg) => {
+                self.write(arg);
+                self.write("=");
+            }
+            None => self.write("**"),
+        }
+        self.expression(&keyword.value, Precedence::Test);
+    }
+
+    fn interleave<T>(&mut self, items: &[T], mut write_item: impl FnMut(&mut Self, &T)) {
+        for (index, item) in items.iter().enumerate() {
+            if index > 0 {
+                self.write(", ");
+            }
+            write_item(self, item);
+        }
+    }
+
+    /// Writes the elements of a tuple, a single element needs a trailing comma
+    fn items_view(&mut self, elements: &[Expression]) {
+        if let [element] = elements {
+            self.expression(element, Precedence::Test);
+            self.write(",");
+        } else {
+            self.interleave(elements, |unparser, element| {
+                unparser.expression(element, Precedence::Test)
+            });
+        }
+    }
+
+    /// Writes the expression in a place that requires at least `precedence`
+    fn expression(&mut self, expr: &Expression, precedence: Precedence) {
+        match expr {
+            Expression::Constant(constant) => self.constant(constant),
+            Expression::Name(name) => self.write(&name.id),
+            Expression::List(list) => {
+                self.write("[");
+                self.interleave(&list.elements, |unparser, element| {
+                    unparser.expression(element, Precedence::Test)
+                });
+                self.write("]");
+            }
+            Expression::Tuple(tuple) => {
+                let parenthesize = tuple.elements.is_empty() || precedence > Precedence::Tuple;
+                self.parenthesize_if(parenthesize, |unparser| {
+                    unparser.items_view(&tuple.elements)
+                });
+            }
+            Expression::Set(set) => {
+                if set.elements.is_empty() {
+                    // `{}` is an empty dict
+                    self.write("{*()}");
+                } else {
+                    self.write("{");
+                    self.interleave(&set.elements, |unparser, element| {
+                        unparser.expression(element, Precedence::Test)
+                    });
+                    self.write("}");
+                }
+            }
+            Expression::Dict(dict) => {
+                self.write("{");
+                let items = dict.keys.iter().zip(&dict.values).collect::<Vec<_>>();
+                self.interleave(&items, |unparser, (key, value)| match key {
+                    Some(key) => {
+                        unparser.expression(key, Precedence::Test);
+                        unparser.write(": ");
+                        unparser.expression(value, Precedence::Test);
+                    }
+                    None => {
+                        unparser.write("**");
+                        unparser.expression(value, Precedence::Expr);
+                    }
+                });
+                self.write("}");
+            }
+            Expression::BoolOp(bool_op) => {
+                let (operator, mut operator_precedence) = match bool_op.op {
+                    BooleanOperator::And => (" and ", Precedence::And),
+                    BooleanOperator::Or => (" or ", Precedence::Or),
+                };
+                self.parenthesize_if(precedence > operator_precedence, |unparser| {
+                    for (index, value) in bool_op.values.iter().enumerate() {
+                        if index > 0 {
+                            unparser.write(operator);
+                        }
+                        operator_precedence = operator_precedence.next();
+                        unparser.expression(value, operator_precedence);
+                    }
+                });
+            }
+            Expression::UnaryOp(unary) => {
+                let operator_precedence = match unary.op {
+                    UnaryOperator::Not => Precedence::Not,
+                    _ => Precedence::Factor,
+                };
+                self.parenthesize_if(precedence > operator_precedence, |unparser| {
+                    unparser.write(&unary.op.to_string());
+                    // Only `not` is separated from its operand, e.g. `-1`
+                    if operator_precedence != Precedence::Factor {
+                        unparser.write(" ");
+                    }
+                    unparser.expression(&unary.operand, operator_precedence);
+                });
+            }
+            Expression::BinOp(bin_op) => {
+                let operator_precedence = Precedence::of_binary_operator(&bin_op.op);
+                // `**` is right associative, `a ** b ** c` is `a ** (b ** c)`
+                let (left, right) = if bin_op.op == BinaryOperator::Pow {
+                    (operator_precedence.next(), operator_precedence)
+                } else {
+                    (operator_precedence, operator_precedence.next())
+                };
+                self.parenthesize_if(precedence > operator_precedence, |unparser| {
+                    unparser.expression(&bin_op.left, left);
+                    unparser.write(&format!(" {} ", bin_op.op));
+                    unparser.expression(&bin_op.right, right);
+                });
+            }
+            Expression::Compare(compare) => {
+                self.parenthesize_if(precedence > Precedence::Cmp, |unparser| {
+                    unparser.expression(&compare.left, Precedence::Cmp.next());
+                    for (op, comparator) in compare.ops.iter().zip(&compare.comparators) {
+                        unparser.write(&format!(" {op} "));
+                        unparser.expression(comparator, Precedence::Cmp.next());
+                    }
+                });
+            }
+            Expression::NamedExpr(named) => {
+                self.parenthesize_if(precedence > Precedence::NamedExpr, |unparser| {
+                    unparser.expression(&named.target, Precedence::Atom);
+                    unparser.write(" := ");
+                    unparser.expression(&named.value, Precedence::Atom);
+                });
+            }
+            Expression::Yield(yield_expr) => {
+                self.parenthesize_if(precedence > Precedence::Yield, |unparser| {
+                    unparser.write("yield");
+                    if let Some(value) = &yield_expr.value {
+                        unparser.write(" ");
+                        unparser.expression(value, Precedence::Atom);
+                    }
+                });
+            }
+            Expression::YieldFrom(yield_from) => {
+                self.parenthesize_if(precedence > Precedence::Yield, |unparser| {
+                    unparser.write("yield from ");
+                    unparser.expression(&yield_from.value, Precedence::Atom);
+                });
+            }
+            Expression::Await(await_expr) => {
+                self.parenthesize_if(precedence > Precedence::Await, |unparser| {
+                    unparser.write("await ");
+                    unparser.expression(&await_expr.value, Precedence::Atom);
+                });
+            }
+            Expression::Starred(starred) => {
+                self.write("*");
+                self.expression(&starred.value, Precedence::Expr);
+            }
+            Expression::Generator(generator) => {
+                self.write("(");
+                self.expression(&generator.element, Precedence::Test);
+                self.comprehensions(&generator.generators);
+                self.write(")");
+            }
+            Expression::ListComp(comp) => {
+                self.write("[");
+                self.expression(&comp.element, Precedence::Test);
+                self.comprehensions(&comp.generators);
+                self.write("]");
+            }
+            Expression::SetComp(comp) => {
+                self.write("{");
+                self.expression(&comp.element, Precedence::Test);
+                self.comprehensions(&comp.generators);
+                self.write("}");
+            }
+            Expression::DictComp(comp) => {
+                self.write("{");
+                self.expression(&comp.key, Precedence::Test);
+                self.write(": ");
+                self.expression(&comp.value, Precedence::Test);
+                self.comprehensions(&comp.generators);
+                self.write("}");
+            }
+            Expression::Attribute(attribute) => {
+                self.expression(&attribute.value, Precedence::Atom);
+                // `1.real` is read as a float followed by a name
+                if let Expression::Constant(constant) = &attribute.value {
+                    if matches!(constant.value, ConstantValue::Int(_)) {
+                        self.write(" ");
+                    }
+                }
+                self.write(".");
+                self.write(&attribute.attr);
+            }
+            Expression::Subscript(subscript) => {
+                self.expression(&subscript.value, Precedence::Atom);
+                self.write("[");
+                match &subscript.slice {
+                    // The parentheses of the tuple are not needed, e.g. `a[1, 2]`
+                    Expression::Tuple(tuple) if !tuple.elements.is_empty() => {
+                        self.items_view(&tuple.elements)
+                    }
+                    slice => self.expression(slice, Precedence::Test),
+                }
+                self.write("]");
+            }
+            Expression::Slice(slice) => {
+                if let Some(lower) = &slice.lower {
+                    self.expression(lower, Precedence::Test);
+                }
+                self.write(":");
+                if let Some(upper) = &slice.upper {
+                    self.expression(upper, Precedence::Test);
+                }
+                if let Some(step) = &slice.step {
+                    self.write(":");
+                    self.expression(step, Precedence::Test);
+                }
+            }
+            Expression::Call(call) => {
+                self.expression(&call.func, Precedence::Atom);
+                self.write("(");
+                self.interleave(&call.args, |unparser, arg| {
+                    unparser.expression(arg, Precedence::Test)
+                });
+                if !call.args.is_empty() && !call.keywords.is_empty() {
+                    self.write(", ");
+                }
+                self.interleave(&call.keywords, Self::keyword);
+                self.write(")");
+            }
+            Expression::Lambda(lambda) => {
+                self.parenthesize_if(precedence > Precedence::Test, |unparser| {
+                    unparser.write("lambda");
+                    let mut args = Unparser::default();
+                    args.arguments(&lambda.args);
+                    if !args.source.is_empty() {
+                        unparser.write(" ");
+                        unparser.write(&args.source);
+                    }
+                    unparser.write(": ");
+                    unparser.expression(&lambda.body, Precedence::Test);
+                });
+            }
+            Expression::IfExp(if_exp) => {
+                self.parenthesize_if(precedence > Precedence::Test, |unparser| {
+                    unparser.expression(&if_exp.body, Precedence::Test.next());
+                    unparser.write(" if ");
+                    unparser.expression(&if_exp.test, Precedence::Test.next());
+                    unparser.write(" else ");
+                    unparser.expression(&if_exp.orelse, Precedence::Test);
+                });
+            }
+            Expression::JoinedStr(joined_str) => self.joined_str(joined_str),
+            Expression::FormattedValue(value) => {
+                let value = formatted_value(value);
+                self.write(&value);
+            }
+            Expression::Invalid(_) => {}
+        }
+    }
+
+    fn parenthesize_if(&mut self, condition: bool, write: impl FnOnce(&mut Self)) {
+        if condition {
+            self.write("(");
+        }
+        write(self);
+        if condition {
+            self.write(")");
+        }
+    }
+
+    fn comprehensions(&mut self, generators: &[Comprehension]) {
+        for generator in generators {
+            self.write(if generator.is_async {
+                " async for "
+            } else {
+                " for "
+            });
+            self.expression(&generator.target, Precedence::Tuple);
+            self.write(" in ");
+            self.expression(&generator.iter, Precedence::Test.next());
+            for if_clause in &generator.ifs {
+                self.write(" if ");
+                self.expression(if_clause, Precedence::Test.next());
+            }
+        }
+    }
+
+    fn constant(&mut self, constant: &Constant) {
+        match &constant.value {
+            ConstantValue::None => self.write("None"),
+            ConstantValue::Ellipsis => self.write("..."),
+            ConstantValue::Bool(true) => self.write("True"),
+            ConstantValue::Bool(false) => self.write("False"),
+            ConstantValue::Tuple => self.write("()"),
+            ConstantValue::Str(value) => self.write(&str_repr(value)),
+            ConstantValue::Bytes(value) => self.write(&bytes_repr(value)),
+            ConstantValue::Int(value) => self.write(&value.to_string()),
+            ConstantValue::Float(_) | ConstantValue::Complex { .. } => {
+                let value = constant
+                    .get_value()
+                    .replace("inf", INFINITY)
+                    .replace("nan", &format!("({INFINITY}-{INFINITY})"));
+                self.write(&value);
+            }
+        }
+    }
+
+    /// Writes the f-string with quotes that are not used inside of it
+    fn joined_str(&mut self, joined_str: &JoinedStr) {
+        self.write("f");
+        let parts = joined_str
+            .values
+            .iter()
+            .map(|value| (fstring_inner(value, false), value.is_constant()))
+            .collect::<Vec<_>>();
+
+        let mut quote_types = ALL_QUOTES.to_vec();
+        let mut fallback_to_repr = false;
+        let mut new_parts = vec![];
+        for (value, is_constant) in &parts {
+            if *is_constant {
+                let (value, new_quote_types) = str_literal_helper(value, &quote_types, true);
+                if !new_quote_types.iter().any(|q| quote_types.contains(q)) {
+                    fallback_to_repr = true;
+                    break;
+                }
+                quote_types = new_quote_types;
+                new_parts.push(value);
+            } else {
+                if value.contains('\n') {
+                    quote_types.retain(|q| MULTI_QUOTES.contains(q));
+                }
+                // Replacement fields can only contain the quotes of the
+                // f-string since Python 3.12
+                let unused_quotes: Vec<_> = quote_types
+                    .iter()
+                    .copied()
+                    .filter(|q| !value.contains(q))
+                    .collect();
+                if !unused_quotes.is_empty() {
+                    quote_types = unused_quotes;
+                }
+                new_parts.push(value.clone());
+            }
+        }
+        // No quote works for all of the parts, the constants are escaped so
+        // they can be written between triple single quotes
+        if fallback_to_repr {
+            quote_types = vec!["'''"];
+            new_parts = parts
+                .into_iter()
+                .map(|(value, is_constant)| {
+                    if is_constant {
+                        // The double quote forces the repr to use single quotes
+                        let repr = str_repr(&format!("\"{value}"));
+                        repr[2..repr.len() - 1].to_string()
+                    } else {
+                        value
+                    }
+                })
+                .collect();
+        }
+        self.write(quote_types[0]);
+        self.write(&new_parts.concat());
+        self.write(quote_types[0]);
+    }
+}
+
+/// Content of an f-string part without the quotes of the f-string
+fn fstring_inner(expr: &Expression, is_format_spec: bool) -> String {
+    match expr {
+        Expression::JoinedStr(joined_str) => joined_str
+            .values
+            .iter()
+            .map(|value| fstring_inner(value, is_format_spec))
+            .collect(),
+        Expression::Constant(constant) => {
+            let mut value = constant.get_value().replace('{', "{{").replace('}', "}}");
+            if is_format_spec {
+                value = value
+                    .replace('\\', "\\\\")
+                    .replace('\'', "\\'")
+                    .replace('"', "\\\"")
+                    .replace('\n', "\\n");
+            }
+            value
+        }
+        Expression::FormattedValue(value) => formatted_value(value),
+        expr => unparse_expression(expr),
+    }
+}
+
+fn formatted_value(value: &FormattedValue) -> String {
+    let mut unparser = Unparser::default();
+    unparser.expression(&value.value, Precedence::Test.next());
+    let expr = unparser.source;
+    let mut result = String::from("{");
+    // `{{` would be an escaped brace
+    if expr.starts_with('{') {
+        result.push(' ');
+    }
+    result.push_str(&expr);
+    if let Some(conversion) =
+        char::from_u32(value.conversion as u32).filter(|_| value.conversion != -1)
+    {
+        result.push('!');
+        result.push(conversion);
+    }
+    if let Some(format_spec) = &value.format_spec {
+        result.push(':');
+        result.push_str(&fstring_inner(format_spec, true));
+    }
+    result.push('}');
+    result
+}
+
+impl Unparser {
+    fn pattern(&mut self, pattern: &MatchPattern, precedence: Precedence) {
+        match pattern {
+            MatchPattern::MatchValue(value) => self.expression(&value.value, Precedence::Test),
+            MatchPattern::MatchSingleton(value) => self.expression(value, Precedence::Test),
+            MatchPattern::MatchSequence(patterns) => {
+                self.write("[");
+                self.interleave(patterns, |unparser, pattern| {
+                    unparser.pattern(pattern, Precedence::Test)
+                });
+                self.write("]");
+            }
+            MatchPattern::MatchStar(name) => {
+                self.write("*");
+                self.expression(name, Precedence::Test);
+            }
+            MatchPattern::MatchMapping(mapping) => {
+                self.write("{");
+                let items = mapping
+                    .keys
+                    .iter()
+                    .zip(&mapping.patterns)
+                    .collect::<Vec<_>>();
+                self.interleave(&items, |unparser, (key, pattern)| {
+                    unparser.expression(key, Precedence::Test);
+                    unparser.write(": ");
+                    unparser.pattern(pattern, Precedence::Test);
+                });
+                if let Some(rest) = &mapping.rest {
+                    if !mapping.keys.is_empty() {
+                        self.write(", ");
+                    }
+                    self.write("**");
+                    self.write(rest);
+                }
+                self.write("}");
+            }
+            MatchPattern::MatchClass(class) => {
+                self.expression(&class.cls, Precedence::Atom);
+                self.write("(");
+                self.interleave(&class.patterns, |unparser, pattern| {
+                    unparser.pattern(pattern, Precedence::Test)
+                });
+                if !class.patterns.is_empty() && !class.kwd_attrs.is_empty() {
+                    self.write(", ");
+                }
+                let keywords = class
+                    .kwd_attrs
+                    .iter()
+                    .zip(&class.kwd_patterns)
+                    .collect::<Vec<_>>();
+                self.interleave(&keywords, |unparser, (attr, pattern)| {
+                    unparser.write(attr);
+                    unparser.write("=");
+                    unparser.pattern(pattern, Precedence::Test);
+                });
+                self.write(")");
+            }
+            MatchPattern::MatchAs(match_as) => match (&match_as.name, &match_as.pattern) {
+                (None, _) => self.write("_"),
+                (Some(name), None) => self.write(name),
+                (Some(name), Some(pattern)) => {
+                    self.parenthesize_if(precedence > Precedence::Test, |unparser| {
+                        unparser.pattern(pattern, Precedence::BIT_OR);
+                        unparser.write(" as ");
+                        unparser.write(name);
+                    });
+                }
+            },
+            MatchPattern::MatchOr(patterns) => {
+                self.parenthesize_if(precedence > Precedence::BIT_OR, |unparser| {
+                    for (index, pattern) in patterns.iter().enumerate() {
+                        if index > 0 {
+                            unparser.write(" | ");
+                        }
+                        unparser.pattern(pattern, Precedence::BIT_OR.next());
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// Escapes the characters Python does not print as they are, e.g. `\x00`
+fn push_escaped(out: &mut String, c: char) {
+    match c {
+        '\\' => out.push_str("\\\\"),
+        '\n' => out.push_str("\\n"),
+        '\r' => out.push_str("\\r"),
+        '\t' => out.push_str("\\t"),
+        c if (c as u32) < 0x100 => out.push_str(&format!("\\x{:02x}", c as u32)),
+        c if (c as u32) < 0x10000 => out.push_str(&format!("\\u{:04x}", c as u32)),
+        c => out.push_str(&format!("\\U{:08x}", c as u32)),
+    }
+}
+
+/// Approximation of Python's `str.isprintable` for a single character
+fn is_printable(c: char) -> bool {
+    if c == ' ' {
+        return true;
+    }
+    !(c.is_control()
+        || c.is_whitespace()
+        || matches!(
+            c,
+            '\u{ad}'
+                | '\u{200b}'..='\u{200f}'
+                | '\u{202a}'..='\u{202e}'
+                | '\u{2060}'..='\u{2064}'
+                | '\u{feff}'
+        ))
+}
+
+/// The string literal that Python's `repr` gives for the value
+fn str_repr(value: &str) -> String {
+    let quote = if value.contains('\'') && !value.contains('"') {
+        '"'
+    } else {
+        '\''
+    };
+    let mut repr = String::with_capacity(value.len() + 2);
+    repr.push(quote);
+    for c in value.chars() {
+        if c == quote {
+            repr.push('\\');
+            repr.push(c);
+        } else if c == '\\' || !is_printable(c) {
+            push_escaped(&mut repr, c);
+        } else {
+            repr.push(c);
+        }
+    }
+    repr.push(quote);
+    repr
+}
+
+/// The bytes literal that Python's `repr` gives for the value
+fn bytes_repr(value: &[u8]) -> String {
+    let quote = if value.contains(&b'\'') && !value.contains(&b'"') {
+        b'"'
+    } else {
+        b'\''
+    };
+    let mut repr = String::with_capacity(value.len() + 3);
+    repr.push('b');
+    repr.push(quote as char);
+    for &byte in value {
+        match byte {
+            b'\\' => repr.push_str("\\\\"),
+            b'\n' => repr.push_str("\\n"),
+            b'\r' => repr.push_str("\\r"),
+            b'\t' => repr.push_str("\\t"),
+            byte if byte == quote => {
+                repr.push('\\');
+                repr.push(byte as char);
+            }
+            0x20..=0x7e => repr.push(byte as char),
+            byte => repr.push_str(&format!("\\x{byte:02x}")),
+        }
+    }
+    repr.push(quote as char);
+    repr
+}
+
+/// Escapes the string and finds the quotes from `quote_types` that can
+/// surround it, the best quote is first. Falls back to `repr` when none of
+/// the quotes can be used.
+fn str_literal_helper(
+    value: &str,
+    quote_types: &[&'static str],
+    escape_special_whitespace: bool,
+) -> (String, Vec<&'static str>) {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if !escape_special_whitespace && matches!(c, '\n' | '\t') {
+            escaped.push(c);
+        } else if c == '\\' || !is_printable(c) {
+            push_escaped(&mut escaped, c);
+        } else {
+            escaped.push(c);
+        }
+    }
+    let mut possible_quotes = quote_types.to_vec();
+    if escaped.contains('\n') {
+        possible_quotes.retain(|quote| MULTI_QUOTES.contains(quote));
+    }
+    possible_quotes.retain(|quote| !escaped.contains(quote));
+    if possible_quotes.is_empty() {
+        let repr = str_repr(value);
+        let repr_quote = &repr[..1];
+        let quote = quote_types
+            .iter()
+            .find(|quote| quote.contains(repr_quote))
+            .copied()
+            .unwrap_or(if repr_quote == "\"" { "\"" } else { "'" });
+        return (repr[1..repr.len() - 1].to_string(), vec![quote]);
+    }
+    if let Some(last) = escaped.chars().last() {
+        // Prefer the quotes that do not need the last character escaped
+        possible_quotes.sort_by_key(|quote| quote.starts_with(last));
+        if possible_quotes[0].starts_with(last) {
+            escaped.pop();
+            escaped.push('\\');
+            escaped.push(last);
+        }
+    }
+    (escaped, possible_quotes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use insta::glob;
+
+    use super::*;
+    use crate::parser::parser::Parser;
+
+    fn roundtrip(source: &str) -> String {
+        let (module, errors) = Parser::new(source).parse();
+        assert!(errors.is_empty(), "{source}: {errors:?}");
+        unparse(&module)
+    }
+
+    fn assert_unparse(source: &str, expected: &str) {
+        assert_eq!(roundtrip(source), expected, "source: {source}");
+    }
+
+    #[test]
+    fn test_unparse_precedence() {
+        assert_unparse("(a + b) * c", "(a + b) * c");
+        assert_unparse("a + (b * c)", "a + b * c");
+        assert_unparse("a - (b - c)", "a - (b - c)");
+        assert_unparse("(a ** b) ** c", "(a ** b) ** c");
+        assert_unparse("a ** (b ** c)", "a ** b ** c");
+        assert_unparse("-(a ** b)", "-a ** b");
+        assert_unparse("(-a) ** b", "(-a) ** b");
+        assert_unparse("not (a and b) or c", "not (a and b) or c");
+        assert_unparse("(a or b) and c", "(a or b) and c");
+        assert_unparse("(a < b) < c", "(a < b) < c");
+        assert_unparse("(lambda: x)()", "(lambda: x)()");
+        assert_unparse("lambda x, *y, z=1, **k: x", "lambda x, *y, z=1, **k: x");
+        assert_unparse("(a if b else c).d", "(a if b else c).d");
+        assert_unparse("(yield x)", "yield x");
+        assert_unparse("x = (yield)", "x = (yield)");
+        assert_unparse("await (a + b)", "await (a + b)");
+        assert_unparse("(x := 1)", "(x := 1)");
+        assert_unparse("f(*(a or b), **c)", "f(*(a or b), **c)");
+        assert_unparse("1 .real", "1 .real");
+        assert_unparse("x = 1,", "x = (1,)");
+        assert_unparse("for x, y in z: pass", "for x, y in z:\n    pass");
+        assert_unparse("a[1, 2:3]", "a[1, 2:3]");
+        assert_unparse("a[()]", "a[()]");
+        assert_unparse("{**a, 'b': 1}", "{**a, 'b': 1}");
+        assert_unparse(
+            "[x for x in (a if b else c) if (d if e else f)]",
+            "[x for x in (a if b else c) if (d if e else f)]",
+        );
+    }
+
+    #[test]
+    fn test_unparse_strings() {
+        assert_unparse("x = 'a'", "x = 'a'");
+        assert_unparse(r#"x = "it's""#, r#"x = "it's""#);
+        assert_unparse(r#"x = '"\''"#, r#"x = '"\''"#);
+        assert_unparse(r"x = '\n\t\x00\\'", r"x = '\n\t\x00\\'");
+        assert_unparse(r"x = b'\xff\''", r#"x = b"\xff'""#);
+        assert_unparse("'doc\\nstring'", "\"\"\"doc\nstring\"\"\"");
+        assert_unparse(
+            "def f():\n    '''doc'''\n    pass",
+            "def f():\n    \"\"\"doc\"\"\"\n    pass",
+        );
+        assert_unparse("f'{a!r:>10} {b:.2f}'", "f'{a!r:>10} {b:.2f}'");
+        assert_unparse(r#"f"{a['b']}""#, r#"f"{a['b']}""#);
+        assert_unparse("f'{a['b']}'", r#"f"{a['b']}""#);
+        assert_unparse(r#"f"{'a'}""#, r#"f"{'a'}""#);
+        assert_unparse(r#"f"{'a'} {"it's"}""#, r#"f"""{'a'} {"it's"}""""#);
+        assert_unparse("f'{x=}'", "f'x={x!r}'");
+        assert_unparse("f'{x:>{w}}'", "f'{x:>{w}}'");
+        assert_unparse("f'{{a}} {b}'", "f'{{a}} {b}'");
+        assert_unparse(r"rf'\d{x}'", r"f'\\d{x}'");
+        assert_unparse("x = 1e400", "x = 1e309");
+        assert_unparse("x = 2j", "x = 2j");
+    }
+
+    #[test]
+    fn test_unparse_statements() {
+        assert_unparse(
+            "@a.b(c)\n@d\nclass C[T: int, *Ts, **P](B, metaclass=M):\n    x: int = 1",
+            "@a.b(c)\n@d\nclass C[T: int, *Ts, **P](B, metaclass=M):\n    x: int = 1",
+        );
+        assert_unparse(
+            "async def f(a, /, b=1, *, c, d=2, **e) -> int:\n    return await a",
+            "async def f(a, /, b=1, *, c, d=2, **e) -> int:\n    return await a",
+        );
+        assert_unparse(
+            "if a:\n    pass\nelse:\n    if b:\n        pass\n    else:\n        pass",
+            "if a:\n    pass\nelif b:\n    pass\nelse:\n    pass",
+        );
+        assert_unparse(
+            "try:\n    pass\nexcept* E as e:\n    pass\nfinally:\n    pass",
+            "try:\n    pass\nexcept* E as e:\n    pass\nfinally:\n    pass",
+        );
+        assert_unparse("from ..a import b as c, d", "from ..a import b as c, d");
+        assert_unparse("(x): int", "(x): int");
+        assert_unparse("x += 1; del a, b", "x += 1\ndel a, b");
+        assert_unparse("type X[T] = list[T]", "type X[T] = list[T]");
+        assert_unparse("with a as b, c: pass", "with a as b, c:\n    pass");
+    }
+
+    #[test]
+    fn test_unparse_match() {
+        assert_unparse(
+            "match x:\n    case [1, *rest] | {'a': _, **kw}:\n        pass",
+            "match x:\n    case [1, *rest] | {'a': _, **kw}:\n        pass",
+        );
+        assert_unparse(
+            "match x:\n    case Point(1, y=(2 | 3) as z) if z:\n        pass",
+            "match x:\n    case Point(1, y=2 | 3 as z) if z:\n        pass",
+        );
+        assert_unparse(
+            "match x:\n    case ((1 as a) | 2, *_):\n        pass",
+            "match x:\n    case [(1 as a) | 2, *_]:\n        pass",
+        );
+        assert_unparse(
+            "match x:\n    case (None | _):\n        pass",
+            "match x:\n    case None | _:\n        pass",
+        );
+    }
+
+    /// Parsing the generated code gives the same code again
+    #[test]
+    fn test_unparse_roundtrip() {
+        glob!("../test_data", "inputs/*.py", |path| {
+            let source = fs::read_to_string(path).unwrap();
+            let (module, _) = Parser::new(&source).parse();
+            let unparsed = unparse(&module);
+            assert_eq!(roundtrip(&unparsed), unparsed, "{}", path.display());
+        });
+    }
+}
@@ -0,0 +1,875 @@
+//! Traversal of the AST.
+//!
+//! Implement [`Visitor`] and override the methods for the nodes you are
+//! interested in. Every method defaults to the matching `walk_*` function
+//! which visits the children of the node in source order, so an overridden
+//! method calls the `walk_*` function to continue into the children.
+//!
+//! ```
+//! use enderpy_python_parser::{ast::Name, parser::parser::Parser, visitor::Visitor};
+//!
+//! #[derive(Default)]
+//! struct Names(Vec<String>);
+//!
+//! impl Visitor<'_> for Names {
+//!     fn visit_name(&mut self, node: &Name) {
+//!         self.0.push(node.id.clone());
+//!     }
+//! }
+//!
+//! let (module, _) = Parser::new("a = b + c").parse();
+//! let mut names = Names::default();
+//! names.visit_module(&module);
+//! assert_eq!(names.0, ["a", "b", "c"]);
+//! ```
+//!
+//! Use [`Transformer`](crate::transformer::Transformer) to change the tree.
+use crate::ast::*;
+
+/// Visits the nodes of the AST, the default methods visit all the children
+#[allow(unused_variables)]
+pub trait Visitor<'a> {
+    /// Visits the statements of a block, e.g. the body of a function
+    fn visit_body(&mut self, body: &'a [Statement]) {
+        walk_body(self, body);
+    }
+
+    fn visit_stmt(&mut self, stmt: &'a Statement) {
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &'a Expression) {
+        walk_expr(self, expr);
+    }
+
+    fn visit_module(&mut self, node: &'a Module) {
+        walk_module(self, node);
+    }
+
+    fn visit_assign(&mut self, node: &'a Assign) {
+        walk_assign(self, node);
+    }
+
+    fn visit_ann_assign(&mut self, node: &'a AnnAssign) {
+        walk_ann_assign(self, node);
+    }
+
+    fn visit_aug_assign(&mut self, node: &'a AugAssign) {
+        walk_aug_assign(self, node);
+    }
+
+    fn visit_assert(&mut self, node: &'a Assert) {
+        walk_assert(self, node);
+    }
+
+    fn visit_pass(&mut self, node: &'a Pass) {}
+
+    fn visit_delete(&mut self, node: &'a Delete) {
+        walk_delete(self, node);
+    }
+
+    fn visit_return(&mut self, node: &'a Return) {
+        walk_return(self, node);
+    }
+
+    fn visit_raise(&mut self, node: &'a Raise) {
+        walk_raise(self, node);
+    }
+
+    fn visit_break(&mut self, node: &'a Break) {}
+
+    fn visit_continue(&mut self, node: &'a Continue) {}
+
+    fn visit_import(&mut self, node: &'a Import) {
+        walk_import(self, node);
+    }
+
+    fn visit_import_from(&mut self, node: &'a ImportFrom) {
+        walk_import_from(self, node);
+    }
+
+    fn visit_global(&mut self, node: &'a Global) {}
+
+    fn visit_nonlocal(&mut self, node: &'a Nonlocal) {}
+
+    fn visit_if(&mut self, node: &'a If) {
+        walk_if(self, node);
+    }
+
+    fn visit_while(&mut self, node: &'a While) {
+        walk_while(self, node);
+    }
+
+    fn visit_for(&mut self, node: &'a For) {
+        walk_for(self, node);
+    }
+
+    fn visit_async_for(&mut self, node: &'a AsyncFor) {
+        walk_async_for(self, node);
+    }
+
+    fn visit_with(&mut self, node: &'a With) {
+        walk_with(self, node);
+    }
+
+    fn visit_async_with(&mut self, node: &'a AsyncWith) {
+        walk_async_with(self, node);
+    }
+
+    fn visit_try(&mut self, node: &'a Try) {
+        walk_try(self, node);
+    }
+
+    fn visit_try_star(&mut self, node: &'a TryStar) {
+        walk_try_star(self, node);
+    }
+
+    fn visit_function_def(&mut self, node: &'a FunctionDef) {
+        walk_function_def(self, node);
+    }
+
+    fn visit_async_function_def(&mut self, node: &'a AsyncFunctionDef) {
+        walk_async_function_def(self, node);
+    }
+
+    fn visit_class_def(&mut self, node: &'a ClassDef) {
+        walk_class_def(self, node);
+    }
+
+    fn visit_match(&mut self, node: &'a Match) {
+        walk_match(self, node);
+    }
+
+    fn visit_type_alias(&mut self, node: &'a TypeAlias) {
+        walk_type_alias(self, node);
+    }
+
+    fn visit_invalid(&mut self, node: &'a Invalid) {}
+
+    fn visit_constant(&mut self, node: &'a Constant) {}
+
+    fn visit_list(&mut self, node: &'a List) {
+        walk_list(self, node);
+    }
+
+    fn visit_tuple(&mut self, node: &'a Tuple) {
+        walk_tuple(self, node);
+    }
+
+    fn visit_dict(&mut self, node: &'a Dict) {
+        walk_dict(self, node);
+    }
+
+    fn visit_set(&mut self, node: &'a Set) {
+        walk_set(self, node);
+    }
+
+    fn visit_name(&mut self, node: &'a Name) {}
+
+    fn visit_bool_op(&mut self, node: &'a BoolOperation) {
+        walk_bool_op(self, node);
+    }
+
+    fn visit_unary_op(&mut self, node: &'a UnaryOperation) {
+        walk_unary_op(self, node);
+    }
+
+    fn visit_bin_op(&mut self, node: &'a BinOp) {
+        walk_bin_op(self, node);
+    }
+
+    fn visit_named_expr(&mut self, node: &'a NamedExpression) {
+        walk_named_expr(self, node);
+    }
+
+    fn visit_yield(&mut self, node: &'a Yield) {
+        walk_yield(self, node);
+    }
+
+    fn visit_yield_from(&mut self, node: &'a YieldFrom) {
+        walk_yield_from(self, node);
+    }
+
+    fn visit_starred(&mut self, node: &'a Starred) {
+        walk_starred(self, node);
+    }
+
+    fn visit_generator(&mut self, node: &'a Generator) {
+        walk_generator(self, node);
+    }
+
+    fn visit_list_comp(&mut self, node: &'a ListComp) {
+        walk_list_comp(self, node);
+    }
+
+    fn visit_set_comp(&mut self, node: &'a SetComp) {
+        walk_set_comp(self, node);
+    }
+
+    fn visit_dict_comp(&mut self, node: &'a DictComp) {
+        walk_dict_comp(self, node);
+    }
+
+    fn visit_attribute(&mut self, node: &'a Attribute) {
+        walk_attribute(self, node);
+    }
+
+    fn visit_subscript(&mut self, node: &'a Subscript) {
+        walk_subscript(self, node);
+    }
+
+    fn visit_slice(&mut self, node: &'a Slice) {
+        walk_slice(self, node);
+    }
+
+    fn visit_call(&mut self, node: &'a Call) {
+        walk_call(self, node);
+    }
+
+    fn visit_await(&mut self, node: &'a Await) {
+        walk_await(self, node);
+    }
+
+    fn visit_compare(&mut self, node: &'a Compare) {
+        walk_compare(self, node);
+    }
+
+    fn visit_lambda(&mut self, node: &'a Lambda) {
+        walk_lambda(self, node);
+    }
+
+    fn visit_if_exp(&mut self, node: &'a IfExp) {
+        walk_if_exp(self, node);
+    }
+
+    fn visit_joined_str(&mut self, node: &'a JoinedStr) {
+        walk_joined_str(self, node);
+    }
+
+    fn visit_formatted_value(&mut self, node: &'a FormattedValue) {
+        walk_formatted_value(self, node);
+    }
+
+    fn visit_alias(&mut self, node: &'a Alias) {}
+
+    fn visit_arguments(&mut self, node: &'a Arguments) {
+        walk_arguments(self, node);
+    }
+
+    fn visit_arg(&mut self, node: &'a Arg) {
+        walk_arg(self, node);
+    }
+
+    fn visit_keyword(&mut self, node: &'a Keyword) {
+        walk_keyword(self, node);
+    }
+
+    fn visit_comprehension(&mut self, node: &'a Comprehension) {
+        walk_comprehension(self, node);
+    }
+
+    fn visit_with_item(&mut self, node: &'a WithItem) {
+        walk_with_item(self, node);
+    }
+
+    fn visit_except_handler(&mut self, node: &'a ExceptHandler) {
+        walk_except_handler(self, node);
+    }
+
+    fn visit_match_case(&mut self, node: &'a MatchCase) {
+        walk_match_case(self, node);
+    }
+
+    fn visit_match_pattern(&mut self, pattern: &'a MatchPattern) {
+        walk_match_pattern(self, pattern);
+    }
+
+    fn visit_type_param(&mut self, type_param: &'a TypeParam) {
+        walk_type_param(self, type_param);
+    }
+}
+
+pub fn walk_body<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, body: &'a [Statement]) {
+    for stmt in body {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_stmt<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, stmt: &'a Statement) {
+    match stmt {
+        Statement::AssignStatement(node) => visitor.visit_assign(node),
+        Statement::AnnAssignStatement(node) => visitor.visit_ann_assign(node),
+        Statement::AugAssignStatement(node) => visitor.visit_aug_assign(node),
+        Statement::ExpressionStatement(node) => visitor.visit_expr(node),
+        Statement::Assert(node) => visitor.visit_assert(node),
+        Statement::Pass(node) => visitor.visit_pass(node),
+        Statement::Delete(node) => visitor.visit_delete(node),
+        Statement::ReturnStmt(node) => visitor.visit_return(node),
+        Statement::Raise(node) => visitor.visit_raise(node),
+        Statement::BreakStmt(node) => visitor.visit_break(node),
+        Statement::ContinueStmt(node) => visitor.visit_continue(node),
+        Statement::Import(node) => visitor.visit_import(node),
+        Statement::ImportFrom(node) => visitor.visit_import_from(node),
+        Statement::Global(node) => visitor.visit_global(node),
+        Statement::Nonlocal(node) => visitor.visit_nonlocal(node),
+        Statement::IfStatement(node) => visitor.visit_if(node),
+        Statement::WhileStatement(node) => visitor.visit_while(node),
+        Statement::ForStatement(node) => visitor.visit_for(node),
+        Statement::AsyncForStatement(node) => visitor.visit_async_for(node),
+        Statement::WithStatement(node) => visitor.visit_with(node),
+        Statement::AsyncWithStatement(node) => visitor.visit_async_with(node),
+        Statement::TryStatement(node) => visitor.visit_try(node),
+        Statement::TryStarStatement(node) => visitor.visit_try_star(node),
+        Statement::FunctionDef(node) => visitor.visit_function_def(node),
+        Statement::AsyncFunctionDef(node) => visitor.visit_async_function_def(node),
+        Statement::ClassDef(node) => visitor.visit_class_def(node),
+        Statement::MatchStmt(node) => visitor.visit_match(node),
+        Statement::TypeAlias(node) => visitor.visit_type_alias(node),
+        Statement::Invalid(node) => visitor.visit_invalid(node),
+    }
+}
+
+pub fn walk_expr<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, expr: &'a Expression) {
+    match expr {
+        Expression::Constant(node) => visitor.visit_constant(node),
+        Expression::List(node) => visitor.visit_list(node),
+        Expression::Tuple(node) => visitor.visit_tuple(node),
+        Expression::Dict(node) => visitor.visit_dict(node),
+        Expression::Set(node) => visitor.visit_set(node),
+        Expression::Name(node) => visitor.visit_name(node),
+        Expression::BoolOp(node) => visitor.visit_bool_op(node),
+        Expression::UnaryOp(node) => visitor.visit_unary_op(node),
+        Expression::BinOp(node) => visitor.visit_bin_op(node),
+        Expression::NamedExpr(node) => visitor.visit_named_expr(node),
+        Expression::Yield(node) => visitor.visit_yield(node),
+        Expression::YieldFrom(node) => visitor.visit_yield_from(node),
+        Expression::Starred(node) => visitor.visit_starred(node),
+        Expression::Generator(node) => visitor.visit_generator(node),
+        Expression::ListComp(node) => visitor.visit_list_comp(node),
+        Expression::SetComp(node) => visitor.visit_set_comp(node),
+        Expression::DictComp(node) => visitor.visit_dict_comp(node),
+        Expression::Attribute(node) => visitor.visit_attribute(node),
+        Expression::Subscript(node) => visitor.visit_subscript(node),
+        Expression::Slice(node) => visitor.visit_slice(node),
+        Expression::Call(node) => visitor.visit_call(node),
+        Expression::Await(node) => visitor.visit_await(node),
+        Expression::Compare(node) => visitor.visit_compare(node),
+        Expression::Lambda(node) => visitor.visit_lambda(node),
+        Expression::IfExp(node) => visitor.visit_if_exp(node),
+        Expression::JoinedStr(node) => visitor.visit_joined_str(node),
+        Expression::FormattedValue(node) => visitor.visit_formatted_value(node),
+        Expression::Invalid(node) => visitor.visit_invalid(node),
+    }
+}
+
+pub fn walk_module<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a Module) {
+    visitor.visit_body(&node.body);
+}
+
+pub fn walk_assign<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a Assign) {
+    for expr in &node.targets {
+        visitor.visit_expr(expr);
+    }
+    visitor.visit_expr(&node.value);
+}
+
+pub fn walk_ann_assign<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a AnnAssign) {
+    visitor.visit_expr(&node.target);
+    visitor.visit_expr(&node.annotation);
+    if let Some(expr) = &node.value {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_aug_assign<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a AugAssign) {
+    visitor.visit_expr(&node.target);
+    visitor.visit_expr(&node.value);
+}
+
+pub fn walk_assert<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a Assert) {
+    visitor.visit_expr(&node.test);
+    if let Some(expr) = &node.msg {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_delete<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a Delete) {
+    for expr in &node.targets {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_return<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a Return) {
+    if let Some(expr) = &node.value {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_raise<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a Raise) {
+    if let Some(expr) = &node.exc {
+        visitor.visit_expr(expr);
+    }
+    if let Some(expr) = &node.cause {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_import<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a Import) {
+    for alias in &node.names {
+        visitor.visit_alias(alias);
+    }
+}
+
+pub fn walk_import_from<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a ImportFrom) {
+    for alias in &node.names {
+        visitor.visit_alias(alias);
+    }
+}
+
+pub fn walk_if<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a If) {
+    visitor.visit_expr(&node.test);
+    visitor.visit_body(&node.body);
+    visitor.visit_body(&node.orelse);
+}
+
+pub fn walk_while<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a While) {
+    visitor.visit_expr(&node.test);
+    visitor.visit_body(&node.body);
+    visitor.visit_body(&node.orelse);
+}
+
+pub fn walk_for<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a For) {
+    visitor.visit_expr(&node.target);
+    visitor.visit_expr(&node.iter);
+    visitor.visit_body(&node.body);
+    visitor.visit_body(&node.orelse);
+}
+
+pub fn walk_async_for<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a AsyncFor) {
+    visitor.visit_expr(&node.target);
+    visitor.visit_expr(&node.iter);
+    visitor.visit_body(&node.body);
+    visitor.visit_body(&node.orelse);
+}
+
+pub fn walk_with<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a With) {
+    for with_item in &node.items {
+        visitor.visit_with_item(with_item);
+    }
+    visitor.visit_body(&node.body);
+}
+
+pub fn walk_async_with<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a AsyncWith) {
+    for with_item in &node.items {
+        visitor.visit_with_item(with_item);
+    }
+    visitor.visit_body(&node.body);
+}
+
+pub fn walk_try<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a Try) {
+    visitor.visit_body(&node.body);
+    for except_handler in &node.handlers {
+        visitor.visit_except_handler(except_handler);
+    }
+    visitor.visit_body(&node.orelse);
+    visitor.visit_body(&node.finalbody);
+}
+
+pub fn walk_try_star<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a TryStar) {
+    visitor.visit_body(&node.body);
+    for except_handler in &node.handlers {
+        visitor.visit_except_handler(except_handler);
+    }
+    visitor.visit_body(&node.orelse);
+    visitor.visit_body(&node.finalbody);
+}
+
+pub fn walk_function_def<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a FunctionDef) {
+    for expr in &node.decorator_list {
+        visitor.visit_expr(expr);
+    }
+    for type_param in &node.type_params {
+        visitor.visit_type_param(type_param);
+    }
+    visitor.visit_arguments(&node.args);
+    if let Some(expr) = &node.returns {
+        visitor.visit_expr(expr);
+    }
+    visitor.visit_body(&node.body);
+}
+
+pub fn walk_async_function_def<'a, V: Visitor<'a> + ?Sized>(
+    visitor: &mut V,
+    node: &'a AsyncFunctionDef,
+) {
+    for expr in &node.decorator_list {
+        visitor.visit_expr(expr);
+    }
+    for type_param in &node.type_params {
+        visitor.visit_type_param(type_param);
+    }
+    visitor.visit_arguments(&node.args);
+    if let Some(expr) = &node.returns {
+        visitor.visit_expr(expr);
+    }
+    visitor.visit_body(&node.body);
+}
+
+pub fn walk_class_def<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a ClassDef) {
+    for expr in &node.decorator_list {
+        visitor.visit_expr(expr);
+    }
+    for type_param in &node.type_params {
+        visitor.visit_type_param(type_param);
+    }
+    for expr in &node.bases {
+        visitor.visit_expr(expr);
+    }
+    for keyword in &node.keywords {
+        visitor.visit_keyword(keyword);
+    }
+    visitor.visit_body(&node.body);
+}
+
+pub fn walk_match<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a Match) {
+    visitor.visit_expr(&node.subject);
+    for match_case in &node.cases {
+        visitor.visit_match_case(match_case);
+    }
+}
+
+pub fn walk_type_alias<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a TypeAlias) {
+    for type_param in &node.type_params {
+        visitor.visit_type_param(type_param);
+    }
+    visitor.visit_expr(&node.value);
+}
+
+pub fn walk_list<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a List) {
+    for expr in &node.elements {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_tuple<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a Tuple) {
+    for expr in &node.elements {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_dict<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a Dict) {
+    for (key, value) in node.keys.iter().zip(&node.values) {
+        if let Some(key) = key {
+            visitor.visit_expr(key);
+        }
+        visitor.visit_expr(value);
+    }
+}
+
+pub fn walk_set<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a Set) {
+    for expr in &node.elements {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_bool_op<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a BoolOperation) {
+    for expr in &node.values {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_unary_op<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a UnaryOperation) {
+    visitor.visit_expr(&node.operand);
+}
+
+pub fn walk_bin_op<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a BinOp) {
+    visitor.visit_expr(&node.left);
+    visitor.visit_expr(&node.right);
+}
+
+pub fn walk_named_expr<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a NamedExpression) {
+    visitor.visit_expr(&node.target);
+    visitor.visit_expr(&node.value);
+}
+
+pub fn walk_yield<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a Yield) {
+    if let Some(expr) = &node.value {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_yield_from<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a YieldFrom) {
+    visitor.visit_expr(&node.value);
+}
+
+pub fn walk_starred<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a Starred) {
+    visitor.visit_expr(&node.value);
+}
+
+pub fn walk_generator<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a Generator) {
+    visitor.visit_expr(&node.element);
+    for comprehension in &node.generators {
+        visitor.visit_comprehension(comprehension);
+    }
+}
+
+pub fn walk_list_comp<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a ListComp) {
+    visitor.visit_expr(&node.element);
+    for comprehension in &node.generators {
+        visitor.visit_comprehension(comprehension);
+    }
+}
+
+pub fn walk_set_comp<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a SetComp) {
+    visitor.visit_expr(&node.element);
+    for comprehension in &node.generators {
+        visitor.visit_comprehension(comprehension);
+    }
+}
+
+pub fn walk_dict_comp<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a DictComp) {
+    visitor.visit_expr(&node.key);
+    visitor.visit_expr(&node.value);
+    for comprehension in &node.generators {
+        visitor.visit_comprehension(comprehension);
+    }
+}
+
+pub fn walk_attribute<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a Attribute) {
+    visitor.visit_expr(&node.value);
+}
+
+pub fn walk_subscript<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a Subscript) {
+    visitor.visit_expr(&node.value);
+    visitor.visit_expr(&node.slice);
+}
+
+pub fn walk_slice<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a Slice) {
+    if let Some(expr) = &node.lower {
+        visitor.visit_expr(expr);
+    }
+    if let Some(expr) = &node.upper {
+        visitor.visit_expr(expr);
+    }
+    if let Some(expr) = &node.step {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_call<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a Call) {
+    visitor.visit_expr(&node.func);
+    for expr in &node.args {
+        visitor.visit_expr(expr);
+    }
+    for keyword in &node.keywords {
+        visitor.visit_keyword(keyword);
+    }
+}
+
+pub fn walk_await<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a Await) {
+    visitor.visit_expr(&node.value);
+}
+
+pub fn walk_compare<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a Compare) {
+    visitor.visit_expr(&node.left);
+    for expr in &node.comparators {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_lambda<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a Lambda) {
+    visitor.visit_arguments(&node.args);
+    visitor.visit_expr(&node.body);
+}
+
+pub fn walk_if_exp<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a IfExp) {
+    visitor.visit_expr(&node.body);
+    visitor.visit_expr(&node.test);
+    visitor.visit_expr(&node.orelse);
+}
+
+pub fn walk_joined_str<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a JoinedStr) {
+    for expr in &node.values {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_formatted_value<'a, V: Visitor<'a> + ?Sized>(
+    visitor: &mut V,
+    node: &'a FormattedValue,
+) {
+    visitor.visit_expr(&node.value);
+    if let Some(expr) = &node.format_spec {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_arguments<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a Arguments) {
+    // Defaults belong to the last positional arguments
+    let positional = node.posonlyargs.len() + node.args.len();
+    let without_default = positional.saturating_sub(node.defaults.len());
+    let mut defaults = node.defaults.iter();
+    for (index, arg) in node.posonlyargs.iter().chain(node.args.iter()).enumerate() {
+        visitor.visit_arg(arg);
+        if index >= without_default {
+            if let Some(default) = defaults.next() {
+                visitor.visit_expr(default);
+            }
+        }
+    }
+    if let Some(vararg) = &node.vararg {
+        visitor.visit_arg(vararg);
+    }
+    for (arg, default) in node.kwonlyargs.iter().zip(node.kw_defaults.iter()) {
+        visitor.visit_arg(arg);
+        if let Some(default) = default {
+            visitor.visit_expr(default);
+        }
+    }
+    if let Some(kwarg) = &node.kwarg {
+        visitor.visit_arg(kwarg);
+    }
+}
+
+pub fn walk_arg<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a Arg) {
+    if let Some(expr) = &node.annotation {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_keyword<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a Keyword) {
+    visitor.visit_expr(&node.value);
+}
+
+pub fn walk_comprehension<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a Comprehension) {
+    visitor.visit_expr(&node.target);
+    visitor.visit_expr(&node.iter);
+    for expr in &node.ifs {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_with_item<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a WithItem) {
+    visitor.visit_expr(&node.context_expr);
+    if let Some(expr) = &node.optional_vars {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_except_handler<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a ExceptHandler) {
+    if let Some(expr) = &node.typ {
+        visitor.visit_expr(expr);
+    }
+    visitor.visit_body(&node.body);
+}
+
+pub fn walk_match_case<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &'a MatchCase) {
+    visitor.visit_match_pattern(&node.pattern);
+    if let Some(expr) = &node.guard {
+        visitor.visit_expr(expr);
+    }
+    visitor.visit_body(&node.body);
+}
+
+pub fn walk_match_pattern<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, pattern: &'a MatchPattern) {
+    match pattern {
+        MatchPattern::MatchValue(node) => visitor.visit_expr(&node.value),
+        MatchPattern::MatchSingleton(expr) | MatchPattern::MatchStar(expr) => {
+            visitor.visit_expr(expr)
+        }
+        MatchPattern::MatchSequence(patterns) | MatchPattern::MatchOr(patterns) => {
+            for pattern in patterns {
+                visitor.visit_match_pattern(pattern);
+            }
+        }
+        MatchPattern::MatchMapping(node) => {
+            for (key, pattern) in node.keys.iter().zip(&node.patterns) {
+                visitor.visit_expr(key);
+                visitor.visit_match_pattern(pattern);
+            }
+        }
+        MatchPattern::MatchAs(node) => {
+            if let Some(pattern) = &node.pattern {
+                visitor.visit_match_pattern(pattern);
+            }
+        }
+        MatchPattern::MatchClass(node) => {
+            visitor.visit_expr(&node.cls);
+            for pattern in &node.patterns {
+                visitor.visit_match_pattern(pattern);
+            }
+            for pattern in &node.kwd_patterns {
+                visitor.visit_match_pattern(pattern);
+            }
+        }
+    }
+}
+
+pub fn walk_type_param<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, type_param: &'a TypeParam) {
+    match type_param {
+        TypeParam::TypeVar(node) => {
+            if let Some(bound) = &node.bound {
+                visitor.visit_expr(bound);
+            }
+        }
+        TypeParam::ParamSpec(_) | TypeParam::TypeVarTuple(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use insta::glob;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::parser::{compat::AsPythonCompat, parser::Parser};
+
+    #[derive(Default)]
+    struct NameCollector {
+        names: Vec<String>,
+    }
+
+    impl Visitor<'_> for NameCollector {
+        fn visit_name(&mut self, node: &Name) {
+            self.names.push(node.id.clone());
+        }
+    }
+
+    /// The ids of the `Name` nodes in the JSON of the Python `ast` module. The
+    /// name of a type alias is a string and not a node in our tree.
+    fn compat_names(value: &Value, names: &mut Vec<String>) {
+        match value {
+            Value::Object(object) => {
+                let node_type = object.get("_type").and_then(Value::as_str);
+                if node_type == Some("Name") {
+                    names.push(object["id"].as_str().unwrap().to_string());
+                }
+                object
+                    .iter()
+                    .filter(|(key, _)| !(node_type == Some("TypeAlias") && *key == "name"))
+                    .for_each(|(_, v)| compat_names(v, names));
+            }
+            Value::Array(array) => array.iter().for_each(|v| compat_names(v, names)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_visitor_visits_all_names() {
+        glob!("../test_data", "inputs/*.py", |path| {
+            let source = fs::read_to_string(path).unwrap();
+            let mut parser = Parser::new(&source);
+            let (module, _) = parser.parse();
+            let mut collector = NameCollector::default();
+            collector.visit_module(&module);
+            let mut expected = vec![];
+            compat_names(&module.as_python_compat(&parser), &mut expected);
+
+            collector.names.sort();
+            expected.sort();
+            assert_eq!(collector.names, expected, "{}", path.display());
+        });
+    }
+}
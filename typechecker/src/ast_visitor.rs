@@ -40,6 +40,7 @@ pub trait TraversalVisitor {
             Statement::AsyncWithStatement(w) => self.visit_async_with(w),
             Statement::AsyncFunctionDef(f) => self.visit_async_function_def(f),
             Statement::TypeAlias(t) => self.visit_type_alias(t),
+            Statement::Invalid(i) => self.visit_invalid(i),
         }
     }
 
@@ -72,6 +73,7 @@ pub trait TraversalVisitor {
             Expression::IfExp(i) => self.visit_if_exp(i),
             Expression::JoinedStr(j) => self.visit_joined_str(j),
             Expression::FormattedValue(f) => self.visit_formatted_value(f),
+            Expression::Invalid(i) => self.visit_invalid(i),
         }
     }
 
@@ -116,9 +118,8 @@ pub trait TraversalVisitor {
         }
         for with_items in &w.items {
             self.visit_expr(&with_items.context_expr);
-            match &with_items.optional_vars {
-                Some(items) => self.visit_expr(items),
-                None => (),
+            if let Some(items) = &with_items.optional_vars {
+                self.visit_expr(items)
             }
         }
     }
@@ -129,9 +130,8 @@ pub trait TraversalVisitor {
         }
         for with_items in &w.items {
             self.visit_expr(&with_items.context_expr);
-            match &with_items.optional_vars {
-                Some(items) => self.visit_expr(items),
-                None => (),
+            if let Some(items) = &with_items.optional_vars {
+                self.visit_expr(items)
             }
         }
     }
@@ -373,6 +373,9 @@ pub trait TraversalVisitor {
     fn visit_type_alias(&mut self, _t: &TypeAlias) {
         todo!()
     }
+
+    // Placeholders left by parser error recovery have nothing to visit
+    fn visit_invalid(&mut self, _i: &Invalid) {}
 }
 
 #[allow(dead_code)]
@@ -408,6 +411,7 @@ pub trait TraversalVisitorGeneric<T> {
             Statement::AsyncWithStatement(w) => self.visit_async_with(w),
             Statement::AsyncFunctionDef(f) => self.visit_async_function_def(f),
             Statement::TypeAlias(a) => self.visit_type_alias(a),
+            Statement::Invalid(i) => self.visit_invalid(i),
         }
     }
     fn visit_expr(&self, e: &Expression) -> T {
@@ -439,6 +443,7 @@ pub trait TraversalVisitorGeneric<T> {
             Expression::IfExp(i) => self.visit_if_exp(i),
             Expression::JoinedStr(j) => self.visit_joined_str(j),
             Expression::FormattedValue(f) => self.visit_formatted_value(f),
+            Expression::Invalid(i) => self.visit_invalid(i),
         }
     }
     fn visit_import(&self, _i: &Import) -> T {
@@ -634,4 +639,8 @@ pub trait TraversalVisitorGeneric<T> {
     fn visit_type_alias(&self, _a: &TypeAlias) -> T {
         todo!()
     }
+
+    // Placeholders left by parser error recovery can appear in any tree, so
+    // every visitor has to decide what they produce
+    fn visit_invalid(&self, i: &Invalid) -> T;
 }
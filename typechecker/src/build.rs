@@ -7,17 +7,27 @@ use tracing::{span, Level};
 use tracing_subscriber::EnvFilter;
 
 use dashmap::DashMap;
+use enderpy_python_parser::{
+    ast::GetNode,
+    incremental::TextEdit,
+    line_index::{LineCol, LineIndex, PositionEncoding},
+    locate::node_at_offset,
+    python_version::PythonVersion,
+};
 use log::debug;
 
 use crate::{
     checker::TypeChecker,
+    diagnostic::Diagnostic,
     file::{EnderpyFile, ImportKinds},
     ruff_python_import_resolver::{
         self as ruff_python_resolver, execution_environment, import_result::ImportResult,
         module_descriptor::ImportModuleDescriptor, resolver,
     },
     settings::Settings,
+    suppression::Suppressions,
     symbol_table::{Id, SymbolTable},
+    types::ClassType,
 };
 
 #[derive(Debug)]
@@ -61,9 +71,14 @@ impl<'a> BuildManager {
     // this only prepares necessary python files.
     pub fn build(&self, root: &Path) {
         let builtins_file = self.settings.typeshed_path.join("stdlib/builtins.pyi");
-        let builtins = EnderpyFile::new(builtins_file, true);
-        let (imports, mut new_modules) =
-            gather_imports(vec![builtins], root, &self.import_config, &self.host);
+        let builtins = EnderpyFile::new(builtins_file, true, self.settings.target_version);
+        let (imports, mut new_modules) = gather_imports(
+            vec![builtins],
+            root,
+            &self.import_config,
+            &self.host,
+            self.settings.target_version,
+        );
         log::debug!("Imports resolved");
         for mut module in new_modules {
             let sym_table = module.populate_symbol_table(&imports);
@@ -77,9 +92,61 @@ impl<'a> BuildManager {
     // Resolves imports and adds file and its imports to modules
     pub fn build_one(&self, root: &Path, file: &Path) {
         debug!("building {file:?}");
-        let enderpy_file = EnderpyFile::new(file.to_path_buf(), false);
-        let (imports, mut new_modules) =
-            gather_imports(vec![enderpy_file], root, &self.import_config, &self.host);
+        let enderpy_file =
+            EnderpyFile::new(file.to_path_buf(), false, self.settings.target_version);
+        self.add_file(root, enderpy_file);
+    }
+
+    /// Same as `build_one` but uses `source` instead of the contents on disk
+    pub fn build_source(&self, root: &Path, file: &Path, source: String) {
+        debug!("building {file:?} from source");
+        let enderpy_file = EnderpyFile::from_source(
+            file.to_path_buf(),
+            source,
+            false,
+            self.settings.target_version,
+        );
+        self.add_file(root, enderpy_file);
+    }
+
+    /// Applies the edits to a file that was built before and builds it again.
+    /// Only the changed statements of the file are parsed again.
+    pub fn apply_edits(&self, root: &Path, file: &Path, edits: &[TextEdit]) {
+        let Some((_, mut enderpy_file)) = self
+            .paths
+            .get(file)
+            .and_then(|id| self.files.remove(id.value()))
+        else {
+            self.build_one(root, file);
+            return;
+        };
+        debug!("applying {} edits to {file:?}", edits.len());
+        enderpy_file.apply_edits(edits, self.settings.target_version);
+        self.add_file(root, enderpy_file);
+    }
+
+    /// Returns the source of the file as it was last built
+    pub fn get_source(&self, path: &Path) -> Option<String> {
+        let id = self.paths.get(path)?;
+        let file = self.files.get(&id)?;
+        Some(file.source.clone())
+    }
+
+    /// Returns the line index of the file as it was last built
+    pub fn get_line_index(&self, path: &Path) -> Option<LineIndex> {
+        let id = self.paths.get(path)?;
+        let file = self.files.get(&id)?;
+        Some(file.line_index.clone())
+    }
+
+    fn add_file(&self, root: &Path, enderpy_file: EnderpyFile) {
+        let (imports, mut new_modules) = gather_imports(
+            vec![enderpy_file],
+            root,
+            &self.import_config,
+            &self.host,
+            self.settings.target_version,
+        );
         log::debug!("Imports resolved");
         for mut module in new_modules {
             let sym_table = module.populate_symbol_table(&imports);
@@ -105,9 +172,24 @@ impl<'a> BuildManager {
         checker
     }
 
+    /// Returns syntax and type errors of the file sorted by their position
+    pub fn get_diagnostics(&self, path: &Path) -> Vec<Diagnostic> {
+        let Some(id) = self.paths.get(path) else {
+            return vec![];
+        };
+        let file = self.files.get(&id).unwrap();
+        let mut diagnostics = file.syntax_diagnostics();
+        let checker = self.type_check(path, &file);
+        diagnostics.extend(checker.diagnostics);
+        let mut diagnostics = Suppressions::new(&file.source)
+            .filter(diagnostics, self.settings.report_unused_ignores);
+        diagnostics.sort_by(|a, b| a.range.start.cmp(&b.range.start));
+        diagnostics
+    }
+
     pub fn get_symbol_table_by_path(&'a self, path: &Path) -> Arc<SymbolTable> {
         let module_id = self.paths.get(path).expect("incorrect ID");
-        return self.get_symbol_table_by_id(&module_id);
+        self.get_symbol_table_by_id(&module_id)
     }
 
     pub fn get_symbol_table_by_id(&'a self, id: &Id) -> Arc<SymbolTable> {
@@ -118,27 +200,48 @@ impl<'a> BuildManager {
             .clone();
     }
 
-    pub fn get_hover_information(&self, path: &Path, line: u32, column: u32) -> String {
+    /// Line and column start from 0, the column is counted in `encoding`
+    pub fn get_hover_information(
+        &self,
+        path: &Path,
+        position: LineCol,
+        encoding: PositionEncoding,
+    ) -> Option<HoverInformation> {
         let file = self.files.get(&self.paths.get(path).unwrap()).unwrap();
         let checker = self.type_check(path, &file);
         let symbol_table = self.get_symbol_table_by_path(path);
-        let hovered_offset = file.line_starts[line as usize] + column;
+        let hovered_offset = file.line_index.offset(position, encoding);
+        let hovered = node_at_offset(&file.tree, hovered_offset)?.node.get_node();
 
-        let hovered_offset_start = hovered_offset.saturating_sub(1);
-        let type_info = &checker
+        // Types are recorded for expressions and also for names inside of
+        // statements, take the narrowest one under the cursor
+        let inside_hovered = |start: u32, stop: u32| hovered.start <= start && stop <= hovered.end;
+        let type_info = checker
             .types
-            .find(hovered_offset_start, hovered_offset + 1)
-            .last();
-        let type_str = if let Some(type_info) = type_info {
-            &type_info.val
-        } else {
-            return String::new();
-        };
+            .find(hovered_offset, hovered_offset + 1)
+            .filter(|interval| inside_hovered(interval.start, interval.stop))
+            .min_by_key(|interval| interval.stop - interval.start)?;
+        let declared_in = checker
+            .member_origins
+            .find(hovered_offset, hovered_offset + 1)
+            .filter(|interval| inside_hovered(interval.start, interval.stop))
+            .min_by_key(|interval| interval.stop - interval.start)
+            .map(|origin| origin.val.clone());
 
-        format!("{type_str:}")
+        Some(HoverInformation {
+            type_info: type_info.val.to_string(),
+            declared_in,
+        })
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct HoverInformation {
+    pub type_info: String,
+    /// The base class that declares the hovered member when it is inherited
+    pub declared_in: Option<ClassType>,
+}
+
 #[derive(Debug, Clone)]
 pub struct ResolvedImport {
     pub resolved_ids: Vec<Id>,
@@ -147,15 +250,16 @@ pub struct ResolvedImport {
 
 pub type ResolvedImports = HashMap<ImportModuleDescriptor, Arc<ResolvedImport>>;
 
-fn gather_imports<'a>(
+fn gather_imports(
     mut initial_files: Vec<EnderpyFile>,
     root: &Path,
     import_config: &ruff_python_resolver::config::Config,
     host: &ruff_python_resolver::host::StaticHost,
+    target_version: PythonVersion,
 ) -> (ResolvedImports, HashSet<EnderpyFile>) {
     let execution_environment = &execution_environment::ExecutionEnvironment {
         root: root.to_path_buf(),
-        python_version: ruff_python_resolver::python_version::PythonVersion::Py312,
+        python_version: target_version.into(),
         python_platform: ruff_python_resolver::python_platform::PythonPlatform::Darwin,
         extra_paths: vec![],
     };
@@ -163,18 +267,19 @@ fn gather_imports<'a>(
     let mut import_results = HashMap::new();
     let mut seen = HashSet::new();
 
-    while let Some(module) = initial_files.pop() {
+    while let Some(mut module) = initial_files.pop() {
         if seen.contains(&module.path) {
             continue;
         }
         seen.insert(module.path.clone());
-        let resolved_imports = resolve_file_imports(
+        let (resolved_imports, unresolved_imports) = resolve_file_imports(
             &module,
             execution_environment,
             import_config,
             host,
             &import_results,
         );
+        module.unresolved_imports = unresolved_imports;
         new_modules.insert(module);
         for (import_desc, mut resolved) in resolved_imports {
             if !resolved.is_import_found {
@@ -188,7 +293,7 @@ fn gather_imports<'a>(
                 {
                     resolved_ids.push(found.id);
                 } else {
-                    let e = EnderpyFile::new(std::mem::take(resolved_path), true);
+                    let e = EnderpyFile::new(std::mem::take(resolved_path), true, target_version);
                     resolved_ids.push(e.id);
                     initial_files.push(e);
                 }
@@ -205,7 +310,7 @@ fn gather_imports<'a>(
                 {
                     resolved_ids.push(found.id);
                 } else {
-                    let e = EnderpyFile::new(std::mem::take(resolved_path), true);
+                    let e = EnderpyFile::new(std::mem::take(resolved_path), true, target_version);
                     resolved_ids.push(e.id);
                     initial_files.push(e);
                 }
@@ -241,8 +346,12 @@ fn resolve_file_imports(
     import_config: &ruff_python_resolver::config::Config,
     host: &ruff_python_resolver::host::StaticHost,
     resolved_imports: &ResolvedImports,
-) -> HashMap<ImportModuleDescriptor, ImportResult> {
+) -> (
+    HashMap<ImportModuleDescriptor, ImportResult>,
+    HashSet<ImportModuleDescriptor>,
+) {
     let mut imports = HashMap::new();
+    let mut unresolved = HashSet::new();
     debug!("resolving imports for file {:?}", file.path);
     for import in file.get_imports().iter() {
         let import_descriptions = match import {
@@ -275,12 +384,13 @@ fn resolve_file_imports(
             if !resolved.is_import_found {
                 let error = format!("cannot import name '{}'", import_desc.name());
                 log::warn!("{}", error);
+                unresolved.insert(import_desc);
                 continue;
             }
             imports.insert(import_desc, resolved);
         }
     }
-    imports
+    (imports, unresolved)
 }
 
 #[cfg(test)]
@@ -348,4 +458,128 @@ mod tests {
         test_symbols_import_star,
         "test_data/inputs/import_star_test/a.py"
     );
+
+    #[test]
+    fn test_hover_information() {
+        let path = PathBuf::from("test_data/inputs/basic_types.py");
+        let manager = BuildManager::new(Settings::test_settings());
+        let root = &Path::new("");
+        manager.build(root);
+        manager.build_one(root, &path);
+
+        // _ = a + int(b)
+        let hover_at = |col| {
+            let position = LineCol { line: 6, col };
+            manager
+                .get_hover_information(&path, position, PositionEncoding::Utf16)
+                .map(|hover| hover.type_info)
+        };
+        assert_eq!(hover_at(4).as_deref(), Some("(class) int"));
+        assert_eq!(hover_at(6).as_deref(), Some("(class) int"));
+        assert_eq!(hover_at(12).as_deref(), Some("(class) str"));
+        assert_eq!(hover_at(1), None);
+    }
+
+    #[test]
+    fn test_hover_information_non_ascii() {
+        let path = PathBuf::from("test_data/inputs/hover.py");
+        let manager = BuildManager::new(Settings::test_settings());
+        let root = &Path::new("");
+        manager.build(root);
+        manager.build_one(root, &path);
+
+        // a = ["é𝕏", 1]
+        let hover_at = |col, encoding| {
+            let position = LineCol { line: 0, col };
+            manager
+                .get_hover_information(&path, position, encoding)
+                .map(|hover| hover.type_info)
+        };
+        let int = Some("(class) int".to_string());
+        assert_eq!(hover_at(12, PositionEncoding::Utf16), int);
+        assert_eq!(hover_at(11, PositionEncoding::Utf32), int);
+        assert_eq!(hover_at(15, PositionEncoding::Utf8), int);
+        assert_eq!(
+            hover_at(7, PositionEncoding::Utf16).as_deref(),
+            Some("(class) str")
+        );
+    }
+
+    #[test]
+    fn test_hover_information_fstring() {
+        let path = PathBuf::from("test_data/inputs/hover.py");
+        let manager = BuildManager::new(Settings::test_settings());
+        let root = &Path::new("");
+        manager.build(root);
+        manager.build_one(root, &path);
+
+        // b = f"{width!r:>{width}}"
+        let hover_at = |col| {
+            let position = LineCol { line: 2, col };
+            manager
+                .get_hover_information(&path, position, PositionEncoding::Utf16)
+                .map(|hover| hover.type_info)
+        };
+        let int = Some("(class) int".to_string());
+        assert_eq!(hover_at(7), int);
+        // In the nested replacement field of the format specifier
+        assert_eq!(hover_at(17), int);
+    }
+
+    #[test]
+    fn test_target_version() {
+        let path = PathBuf::from("test_data/inputs/target_version.py");
+        let settings = Settings {
+            target_version: PythonVersion::Py39,
+            ..Settings::test_settings()
+        };
+        let manager = BuildManager::new(settings);
+        let root = &Path::new("");
+        manager.build(root);
+        let messages = |manager: &BuildManager| {
+            manager
+                .get_diagnostics(&path)
+                .into_iter()
+                .map(|d| d.body)
+                .collect::<Vec<_>>()
+        };
+
+        manager.build_source(root, &path, "x = 1\n".to_string());
+        assert_eq!(messages(&manager), Vec::<String>::new());
+
+        // The edited statement is parsed for the target version too
+        let edits = [TextEdit::new(
+            6,
+            6,
+            "match x:\n    case 1:\n        pass\n".to_string(),
+        )];
+        manager.apply_edits(root, &path, &edits);
+        assert_eq!(messages(&manager), ["match statement requires Python 3.10"]);
+    }
+
+    #[test]
+    fn test_decode_errors_after_edits() {
+        let path = PathBuf::from("test_data/inputs/diagnostics/encoding_invalid_utf8.py");
+        let manager = BuildManager::new(Settings::test_settings());
+        let root = &Path::new("");
+        manager.build(root);
+        manager.build_one(root, &path);
+        let messages = |manager: &BuildManager| {
+            manager
+                .get_diagnostics(&path)
+                .into_iter()
+                .map(|d| d.body)
+                .collect::<Vec<_>>()
+        };
+        let decode_error = "'utf-8' codec can't decode byte 0xe9 in position 11";
+
+        // The syntax error makes the whole file parsed again
+        manager.apply_edits(root, &path, &[TextEdit::new(27, 27, "y = (\n".to_string())]);
+        let after_syntax_error = messages(&manager);
+        assert_eq!(after_syntax_error.len(), 2);
+        assert_eq!(after_syntax_error[0], decode_error);
+
+        manager.apply_edits(root, &path, &[TextEdit::new(27, 33, String::new())]);
+        assert_eq!(messages(&manager), [decode_error]);
+    }
 }
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use ast::{Expression, Statement};
@@ -5,27 +6,62 @@ use enderpy_python_parser as parser;
 use enderpy_python_parser::ast::{self, *};
 use enderpy_python_parser::parser::parser::intern_lookup;
 
-use super::{type_evaluator::TypeEvaluator, types::PythonType};
+use super::{
+    type_evaluator::{union_members, Narrowing, TypeEvaluator},
+    types::PythonType,
+};
+use crate::ast_visitor::TraversalVisitor;
 use crate::build::BuildManager;
+use crate::diagnostic::{codes, Diagnostic};
+use crate::ruff_python_import_resolver::module_descriptor::ImportModuleDescriptor;
+use crate::semantic_analyzer::type_comment_annotation;
 use crate::symbol_table::Id;
-use crate::types::ModuleRef;
-use crate::{ast_visitor::TraversalVisitor, diagnostic::CharacterSpan};
+use crate::types::{CallableArgs, CallableType, ClassType, FunctionKind, KnownValue, ModuleRef};
 use rust_lapper::{Interval, Lapper};
 
+/// Return annotations of generator functions
+const GENERATOR_TYPES: [&str; 6] = [
+    "Generator",
+    "Iterator",
+    "Iterable",
+    "AsyncGenerator",
+    "AsyncIterator",
+    "AsyncIterable",
+];
+
 #[derive(Clone, Debug)]
 pub struct TypeChecker<'a> {
     pub types: Lapper<u32, PythonType>,
+    /// Classes that declare members which are accessed on a subclass. The
+    /// intervals are the ranges of the member names in attribute expressions.
+    pub member_origins: Lapper<u32, ClassType>,
     id: Id,
     type_evaluator: TypeEvaluator<'a>,
     build_manager: &'a BuildManager,
     current_scope: u32,
     prev_scope: u32,
+    /// Declared return types of the functions being checked, innermost last
+    return_types: Vec<PythonType>,
+    flow: FlowState,
+    pub diagnostics: Vec<Diagnostic>,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct TypeCheckError {
-    pub msg: String,
-    pub span: CharacterSpan,
+/// State of the code flow at the statement being checked
+#[derive(Clone, Debug)]
+struct FlowState {
+    /// Types of names narrowed by conditions
+    narrowed_types: HashMap<String, PythonType>,
+    /// False after return, raise, break and continue
+    reachable: bool,
+}
+
+impl FlowState {
+    fn new() -> Self {
+        FlowState {
+            narrowed_types: HashMap::new(),
+            reachable: true,
+        }
+    }
 }
 
 #[allow(unused)]
@@ -36,8 +72,12 @@ impl<'a> TypeChecker<'a> {
             id,
             build_manager,
             types: Lapper::new(vec![]),
+            member_origins: Lapper::new(vec![]),
             current_scope: 0,
             prev_scope: 0,
+            return_types: vec![],
+            flow: FlowState::new(),
+            diagnostics: vec![],
         }
     }
 
@@ -47,10 +87,12 @@ impl<'a> TypeChecker<'a> {
 
     fn infer_expr_type(&mut self, expr: &Expression) -> PythonType {
         let symbol_table = self.build_manager.get_symbol_table_by_id(&self.id);
-        let t = match self
+        self.set_narrowing(expr.get_node());
+        let result = self
             .type_evaluator
-            .get_type(expr, &symbol_table, self.current_scope)
-        {
+            .get_type(expr, &symbol_table, self.current_scope);
+        self.type_evaluator.set_narrowing(None);
+        let t = match result {
             Ok(t) => t,
             Err(e) => {
                 log::error!("type evaluator error: {} for expr {expr:?}", e);
@@ -80,6 +122,474 @@ impl<'a> TypeChecker<'a> {
         t
     }
 
+    /// Evaluates the type of an expression without recording it
+    fn evaluate_type(&self, expr: &Expression) -> PythonType {
+        let symbol_table = self.build_manager.get_symbol_table_by_id(&self.id);
+        self.set_narrowing(expr.get_node());
+        let t = self
+            .type_evaluator
+            .get_type(expr, &symbol_table, self.current_scope)
+            .unwrap_or(PythonType::Unknown);
+        self.type_evaluator.set_narrowing(None);
+        t
+    }
+
+    fn is_type_expression(&self, expr: &Expression) -> bool {
+        let symbol_table = self.build_manager.get_symbol_table_by_id(&self.id);
+        self.type_evaluator
+            .is_type_expression(expr, &symbol_table, self.current_scope)
+    }
+
+    /// Makes the narrowed types of the current flow visible to the evaluator
+    /// while it evaluates `node`
+    fn set_narrowing(&self, node: Node) {
+        if self.flow.narrowed_types.is_empty() {
+            return;
+        }
+        self.type_evaluator.set_narrowing(Some(Narrowing {
+            symbol_table_id: self.id,
+            scope_id: self.current_scope,
+            node,
+            types: self.flow.narrowed_types.clone(),
+        }));
+    }
+
+    /// Narrowed types of names when `test` evaluates to `positive`
+    fn narrow(&self, test: &Expression, positive: bool) -> HashMap<String, PythonType> {
+        let symbol_table = self.build_manager.get_symbol_table_by_id(&self.id);
+        self.set_narrowing(test.get_node());
+        let narrowed =
+            self.type_evaluator
+                .narrow_condition(test, positive, &symbol_table, self.current_scope);
+        self.type_evaluator.set_narrowing(None);
+        narrowed
+    }
+
+    fn apply_narrowing(&mut self, narrowed: HashMap<String, PythonType>) {
+        self.flow.narrowed_types.extend(narrowed);
+    }
+
+    /// Visits a `try` or `try*` statement. The handlers start from the flow
+    /// before the `try` block because an exception can be raised anywhere in it.
+    fn visit_try_flow(
+        &mut self,
+        body: &[Statement],
+        handlers: &[parser::ast::ExceptHandler],
+        orelse: &[Statement],
+        finalbody: &[Statement],
+    ) {
+        let entry = self.flow.clone();
+        for stmt in body {
+            self.visit_stmt(stmt);
+        }
+        for stmt in orelse {
+            self.visit_stmt(stmt);
+        }
+        let mut flows = vec![self.flow.clone()];
+        for handler in handlers {
+            self.flow = entry.clone();
+            self.forget_assigned_in(body);
+            if let Some(typ) = &handler.typ {
+                self.visit_expr(typ);
+            }
+            for stmt in &handler.body {
+                self.visit_stmt(stmt);
+            }
+            flows.push(self.flow.clone());
+        }
+        self.flow = self.join_flows(flows);
+        for stmt in finalbody {
+            self.visit_stmt(stmt);
+        }
+    }
+
+    /// The flow after branches that continue to the same statement
+    fn join_flows(&self, flows: Vec<FlowState>) -> FlowState {
+        let mut reachable = flows.into_iter().filter(|f| f.reachable);
+        let Some(first) = reachable.next() else {
+            return FlowState {
+                narrowed_types: HashMap::new(),
+                reachable: false,
+            };
+        };
+        reachable.fold(first, |acc, flow| FlowState {
+            narrowed_types: self
+                .type_evaluator
+                .join_narrowing(&acc.narrowed_types, &flow.narrowed_types),
+            reachable: true,
+        })
+    }
+
+    /// Assigning to a name invalidates its narrowed type
+    fn forget_narrowing(&mut self, target: &Expression) {
+        match target {
+            Expression::Name(n) => {
+                self.flow.narrowed_types.remove(&n.id);
+            }
+            Expression::Tuple(t) => t.elements.iter().for_each(|e| self.forget_narrowing(e)),
+            Expression::List(l) => l.elements.iter().for_each(|e| self.forget_narrowing(e)),
+            Expression::Starred(s) => self.forget_narrowing(&s.value),
+            _ => (),
+        }
+    }
+
+    /// Forgets narrowing of names assigned in a loop body or a try block since
+    /// they can be reassigned before the statements are reached again.
+    fn forget_assigned_in(&mut self, stmts: &[Statement]) {
+        for stmt in stmts {
+            match stmt {
+                Statement::AssignStatement(a) => {
+                    a.targets.iter().for_each(|t| self.forget_narrowing(t))
+                }
+                Statement::AnnAssignStatement(a) => self.forget_narrowing(&a.target),
+                Statement::AugAssignStatement(a) => self.forget_narrowing(&a.target),
+                Statement::Delete(d) => d.targets.iter().for_each(|t| self.forget_narrowing(t)),
+                Statement::ForStatement(f) => {
+                    self.forget_narrowing(&f.target);
+                    self.forget_assigned_in(&f.body);
+                    self.forget_assigned_in(&f.orelse);
+                }
+                Statement::AsyncForStatement(f) => {
+                    self.forget_narrowing(&f.target);
+                    self.forget_assigned_in(&f.body);
+                    self.forget_assigned_in(&f.orelse);
+                }
+                Statement::WhileStatement(w) => {
+                    self.forget_assigned_in(&w.body);
+                    self.forget_assigned_in(&w.orelse);
+                }
+                Statement::IfStatement(i) => {
+                    self.forget_assigned_in(&i.body);
+                    self.forget_assigned_in(&i.orelse);
+                }
+                Statement::WithStatement(w) => {
+                    for item in w.items.iter() {
+                        if let Some(vars) = &item.optional_vars {
+                            self.forget_narrowing(vars);
+                        }
+                    }
+                    self.forget_assigned_in(&w.body);
+                }
+                Statement::AsyncWithStatement(w) => {
+                    for item in w.items.iter() {
+                        if let Some(vars) = &item.optional_vars {
+                            self.forget_narrowing(vars);
+                        }
+                    }
+                    self.forget_assigned_in(&w.body);
+                }
+                Statement::TryStatement(t) => {
+                    self.forget_assigned_in(&t.body);
+                    self.forget_assigned_in(&t.orelse);
+                    self.forget_assigned_in(&t.finalbody);
+                    t.handlers
+                        .iter()
+                        .for_each(|h| self.forget_assigned_in(&h.body));
+                }
+                Statement::TryStarStatement(t) => {
+                    self.forget_assigned_in(&t.body);
+                    self.forget_assigned_in(&t.orelse);
+                    self.forget_assigned_in(&t.finalbody);
+                    t.handlers
+                        .iter()
+                        .for_each(|h| self.forget_assigned_in(&h.body));
+                }
+                Statement::MatchStmt(m) => m
+                    .cases
+                    .iter()
+                    .for_each(|c| self.forget_assigned_in(&c.body)),
+                Statement::FunctionDef(f) => {
+                    self.flow.narrowed_types.remove(intern_lookup(f.name));
+                }
+                Statement::AsyncFunctionDef(f) => {
+                    self.flow.narrowed_types.remove(intern_lookup(f.name));
+                }
+                Statement::ClassDef(c) => {
+                    self.flow.narrowed_types.remove(intern_lookup(c.name));
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Visits the body of a function or class with a new flow
+    fn visit_in_new_flow(&mut self, body: &[Statement]) {
+        let outer_flow = std::mem::replace(&mut self.flow, FlowState::new());
+        for stmt in body {
+            self.visit_stmt(stmt);
+        }
+        self.flow = outer_flow;
+    }
+
+    /// Evaluates the type of a value assigned to a target of the declared type.
+    /// Constants are only inferred as literals when a literal is expected.
+    fn evaluate_value_type(&self, value: &Expression, declared: &PythonType) -> PythonType {
+        let expects_literal = union_members(declared).iter().any(|t| t.is_literal_value());
+        if expects_literal && matches!(value, Expression::Constant(_) | Expression::UnaryOp(_)) {
+            let symbol_table = self.build_manager.get_symbol_table_by_id(&self.id);
+            let literal_value = self.type_evaluator.get_literal_value_from_param(
+                value,
+                &symbol_table,
+                self.current_scope,
+            );
+            if let Some(literal_value) = literal_value.into_iter().next() {
+                return PythonType::LiteralValue(KnownValue { literal_value });
+            }
+        }
+        self.evaluate_type(value)
+    }
+
+    fn evaluate_annotation_type(&self, expr: &Expression) -> PythonType {
+        let symbol_table = self.build_manager.get_symbol_table_by_id(&self.id);
+        self.type_evaluator
+            .get_annotation_type(expr, &symbol_table, self.current_scope)
+    }
+
+    /// Checks the value assigned to a variable against its annotation
+    fn check_declared_value(&mut self, annotation: &Expression, value: &Expression) {
+        let declared_type = self.evaluate_annotation_type(annotation);
+        let value_type = self.evaluate_value_type(value, &declared_type);
+        self.check_assignable(
+            &value_type,
+            &declared_type,
+            codes::ASSIGNMENT,
+            |src, dest| format!("Type \"{src}\" is not assignable to declared type \"{dest}\""),
+            value.get_node(),
+        );
+    }
+
+    /// Return type that `return` statements of a function are checked against.
+    /// Generators are not checked since their return annotation describes the
+    /// yielded values.
+    fn declared_return_type(&self, returns: &Option<Expression>) -> PythonType {
+        let Some(returns) = returns else {
+            return PythonType::Unknown;
+        };
+        let declared = self.evaluate_annotation_type(returns);
+        let is_generator = match &declared {
+            PythonType::Class(c) => GENERATOR_TYPES.contains(&c.details.name.as_str()),
+            PythonType::Instance(i) => {
+                GENERATOR_TYPES.contains(&i.class_type.details.name.as_str())
+            }
+            _ => false,
+        };
+        if is_generator {
+            PythonType::Unknown
+        } else {
+            declared
+        }
+    }
+
+    fn check_assignable(
+        &mut self,
+        src: &PythonType,
+        dest: &PythonType,
+        code: &'static str,
+        msg: impl FnOnce(String, String) -> String,
+        node: Node,
+    ) {
+        if !self.type_evaluator.is_assignable(src, dest) {
+            self.add_error(
+                code,
+                msg(src.short_name(), dest.short_name()),
+                node.start,
+                node.end,
+            );
+        }
+    }
+
+    /// Matches the arguments of a call to the parameters of the called function
+    /// and checks them.
+    /// https://docs.python.org/3/reference/expressions.html#calls
+    fn check_call_arguments(&mut self, c: &Call) {
+        let Some(callable) = self.called_callable(c) else {
+            return;
+        };
+        let signature = &callable.signature;
+        let name = &callable.name;
+        let mut matched = vec![false; signature.len()];
+        // Unpacked arguments can fill any number of parameters
+        let mut unpacked_args = false;
+        let mut unpacked_kwargs = false;
+
+        let positional_params: Vec<usize> = signature
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| {
+                matches!(
+                    p,
+                    CallableArgs::PositionalOnly(_) | CallableArgs::Positional(_)
+                )
+            })
+            .map(|(i, _)| i)
+            .collect();
+        let var_args = signature
+            .iter()
+            .position(|p| matches!(p, CallableArgs::Args(_)));
+        let var_kwargs = signature
+            .iter()
+            .position(|p| matches!(p, CallableArgs::KwArgs(_)));
+
+        let mut next_positional = positional_params.iter();
+        for arg in c.args.iter() {
+            if arg.is_starred() {
+                unpacked_args = true;
+                continue;
+            }
+            // After an unpacked argument it is unknown which parameter an argument fills
+            if unpacked_args {
+                continue;
+            }
+            let param_index = match next_positional.next() {
+                Some(index) => *index,
+                None => match var_args {
+                    Some(index) => index,
+                    None => {
+                        let node = arg.get_node();
+                        self.add_error(
+                            codes::CALL_ARG,
+                            format!("Too many positional arguments for \"{name}\""),
+                            node.start,
+                            node.end,
+                        );
+                        break;
+                    }
+                },
+            };
+            matched[param_index] = true;
+            self.check_argument_type(arg, &signature[param_index]);
+        }
+
+        for keyword in c.keywords.iter() {
+            let Some(arg_name) = &keyword.arg else {
+                unpacked_kwargs = true;
+                continue;
+            };
+            let param_index = signature.iter().position(|p| {
+                matches!(p, CallableArgs::Positional(_) | CallableArgs::Keyword(_))
+                    && p.name() == arg_name
+            });
+            match param_index {
+                Some(index) if matched[index] => {
+                    self.add_error(
+                        codes::CALL_ARG,
+                        format!("\"{name}\" gets multiple values for argument \"{arg_name}\""),
+                        keyword.node.start,
+                        keyword.node.end,
+                    );
+                }
+                Some(index) => {
+                    matched[index] = true;
+                    self.check_argument_type(&keyword.value, &signature[index]);
+                }
+                None => match var_kwargs {
+                    Some(index) => self.check_argument_type(&keyword.value, &signature[index]),
+                    None => self.add_error(
+                        codes::CALL_ARG,
+                        format!("Unexpected keyword argument \"{arg_name}\" for \"{name}\""),
+                        keyword.node.start,
+                        keyword.node.end,
+                    ),
+                },
+            }
+        }
+
+        let missing: Vec<&CallableArgs> = signature
+            .iter()
+            .zip(matched.iter())
+            .filter(|(param, matched)| !**matched && param.is_required())
+            .filter(|(param, _)| match param {
+                CallableArgs::PositionalOnly(_) => !unpacked_args,
+                CallableArgs::Positional(_) => !unpacked_args && !unpacked_kwargs,
+                _ => !unpacked_kwargs,
+            })
+            .map(|(param, _)| param)
+            .collect();
+        if !missing.is_empty() {
+            let names = missing
+                .iter()
+                .map(|p| format!("\"{}\"", p.name()))
+                .collect::<Vec<String>>()
+                .join(", ");
+            let plural = if missing.len() > 1 { "s" } else { "" };
+            self.add_error(
+                codes::CALL_ARG,
+                format!("Missing argument{plural} {names} in call to \"{name}\""),
+                c.node.start,
+                c.node.end,
+            );
+        }
+    }
+
+    /// The signature the arguments of `c` are matched to. The parameter bound
+    /// to the receiver of a method is removed and calls of classes are matched
+    /// to `__init__`. Returns `None` when the signature is unknown.
+    fn called_callable(&mut self, c: &Call) -> Option<CallableType> {
+        let (callable, bound) = match self.evaluate_type(&c.func) {
+            PythonType::Callable(callable) => {
+                let bound = match (&c.func, callable.kind) {
+                    (_, FunctionKind::StaticMethod) => false,
+                    (Expression::Attribute(a), kind) => match self.evaluate_type(&a.value) {
+                        PythonType::Module(_) => false,
+                        // Instances are evaluated to their class too
+                        PythonType::Class(_) | PythonType::Instance(_) => {
+                            kind == FunctionKind::ClassMethod || !self.is_type_expression(&a.value)
+                        }
+                        _ => return None,
+                    },
+                    _ => false,
+                };
+                (*callable, bound)
+            }
+            // Classes that create their instances in `__new__` are not checked
+            PythonType::Class(class) => {
+                let init_origin = self
+                    .type_evaluator
+                    .lookup_member_origin(&class, "__init__")?;
+                let new_origin = self
+                    .type_evaluator
+                    .lookup_member_origin(&class, "__new__")?;
+                if init_origin.details.name == "object" || new_origin.details.name != "object" {
+                    return None;
+                }
+                let PythonType::Callable(init) =
+                    self.type_evaluator.lookup_on_class(&class, "__init__")?
+                else {
+                    return None;
+                };
+                let init = CallableType {
+                    name: class.details.name.clone(),
+                    ..*init
+                };
+                (init, true)
+            }
+            _ => return None,
+        };
+        if callable.is_decorated {
+            return None;
+        }
+        let mut callable = callable;
+        if bound && !callable.signature.is_empty() {
+            callable.signature.remove(0);
+        }
+        Some(callable)
+    }
+
+    fn check_argument_type(&mut self, arg: &Expression, param: &CallableArgs) {
+        let arg_type = self.evaluate_type(arg);
+        self.check_assignable(
+            &arg_type,
+            param.get_type(),
+            codes::ARG_TYPE,
+            |src, dest| {
+                format!(
+                    "Argument of type \"{src}\" is not assignable to parameter of type \"{dest}\""
+                )
+            },
+            arg.get_node(),
+        );
+    }
+
     fn infer_name_type(&mut self, name: &str, start: u32, stop: u32) {
         let symbol_table = self.build_manager.get_symbol_table_by_id(&self.id);
         let name_type =
@@ -92,6 +602,66 @@ impl<'a> TypeChecker<'a> {
         });
     }
 
+    fn report_unsupported_operator(
+        &mut self,
+        op: &str,
+        left: &PythonType,
+        right: &PythonType,
+        node: Node,
+    ) {
+        self.add_error(
+            codes::OPERATOR,
+            format!(
+                "Operator \"{op}\" not supported for types \"{}\" and \"{}\"",
+                left.short_name(),
+                right.short_name()
+            ),
+            node.start,
+            node.end,
+        );
+    }
+
+    /// Only protocols decorated with `@runtime_checkable` can be used with
+    /// `isinstance` and `issubclass`
+    fn check_instance_check(&mut self, c: &Call) {
+        let Some(func) = c.func.as_name() else {
+            return;
+        };
+        if !matches!(func.id.as_str(), "isinstance" | "issubclass") {
+            return;
+        }
+        let Some(class_arg) = c.args.get(1) else {
+            return;
+        };
+        let elements = match class_arg {
+            Expression::Tuple(t) => t.elements.iter().collect(),
+            other => vec![other],
+        };
+        for e in elements {
+            if let PythonType::Class(class) = self.evaluate_type(e) {
+                if class.details.is_protocol && !class.details.runtime_checkable {
+                    self.add_error(
+                        codes::MISC,
+                        "Instance or class checks can only be used with @runtime_checkable protocol classes".to_string(),
+                        e.get_node().start,
+                        e.get_node().end,
+                    );
+                }
+            }
+        }
+    }
+
+    fn add_error(&mut self, code: &'static str, body: String, start: u32, end: u32) {
+        let file = self.build_manager.files.get(&self.id).unwrap();
+        let range = file.get_range(start, end);
+        self.diagnostics.push(Diagnostic::error(code, body, range));
+    }
+
+    fn is_import_resolved(&self, import: &ImportModuleDescriptor) -> bool {
+        let file = self.build_manager.files.get(&self.id).unwrap();
+        !file.unresolved_imports.contains(import)
+    }
+
     fn enter_scope(&mut self, pos: u32) {
         let symbol_table = self.build_manager.get_symbol_table_by_id(&self.id);
         self.prev_scope = self.current_scope;
@@ -170,6 +740,7 @@ impl<'a> TraversalVisitor for TypeChecker<'a> {
             Statement::AsyncWithStatement(w) => self.visit_async_with(w),
             Statement::AsyncFunctionDef(f) => self.visit_async_function_def(f),
             Statement::TypeAlias(a) => self.visit_type_alias(a),
+            Statement::Invalid(i) => self.visit_invalid(i),
         }
     }
 
@@ -203,12 +774,21 @@ impl<'a> TraversalVisitor for TypeChecker<'a> {
             Expression::IfExp(i) => self.visit_if_exp(i),
             Expression::JoinedStr(j) => self.visit_joined_str(j),
             Expression::FormattedValue(f) => self.visit_formatted_value(f),
+            Expression::Invalid(i) => self.visit_invalid(i),
         }
     }
 
     fn visit_import(&mut self, _i: &Import) {
         for name in _i.names.iter() {
             self.infer_name_type(&name.name, name.node.start, name.node.end);
+            if !self.is_import_resolved(&ImportModuleDescriptor::from(name)) {
+                self.add_error(
+                    codes::IMPORT_NOT_FOUND,
+                    format!("Import \"{}\" could not be resolved", name.name),
+                    name.node.start,
+                    name.node.start + name.name.len() as u32,
+                );
+            }
         }
     }
 
@@ -220,6 +800,15 @@ impl<'a> TraversalVisitor for TypeChecker<'a> {
         // Just to show type module when modules are hovered in imports.
         let start = _i.node.start + 5;
         let stop = start + _i.module.len() as u32 + 1;
+        if !self.is_import_resolved(&ImportModuleDescriptor::from(_i)) {
+            let module = format!("{}{}", ".".repeat(_i.level), _i.module);
+            self.add_error(
+                codes::IMPORT_NOT_FOUND,
+                format!("Import \"{module}\" could not be resolved"),
+                start,
+                start + module.len() as u32,
+            );
+        }
         self.types.insert(Interval {
             start,
             stop,
@@ -230,19 +819,39 @@ impl<'a> TraversalVisitor for TypeChecker<'a> {
 
     fn visit_if(&mut self, i: &parser::ast::If) {
         self.visit_expr(&i.test);
+        let entry = self.flow.clone();
+        let positive = self.narrow(&i.test, true);
+        let negative = self.narrow(&i.test, false);
+
+        self.apply_narrowing(positive);
         for stmt in &i.body {
             self.visit_stmt(stmt);
         }
+        let body_flow = std::mem::replace(&mut self.flow, entry);
+
+        self.apply_narrowing(negative);
         for stmt in &i.orelse {
             self.visit_stmt(stmt);
         }
+        let else_flow = self.flow.clone();
+        self.flow = self.join_flows(vec![body_flow, else_flow]);
     }
 
     fn visit_while(&mut self, w: &parser::ast::While) {
+        self.forget_assigned_in(&w.body);
         self.visit_expr(&w.test);
+        let entry = self.flow.clone();
+        let positive = self.narrow(&w.test, true);
+        self.apply_narrowing(positive);
         for stmt in &w.body {
             self.visit_stmt(stmt)
         }
+        self.flow = entry;
+        // The loop can only end without a break when the condition is false
+        if !contains_break(&w.body) {
+            let negative = self.narrow(&w.test, false);
+            self.apply_narrowing(negative);
+        }
         for stmt in &w.orelse {
             self.visit_stmt(stmt)
         }
@@ -250,10 +859,14 @@ impl<'a> TraversalVisitor for TypeChecker<'a> {
 
     fn visit_for(&mut self, f: &parser::ast::For) {
         self.visit_expr(&f.iter);
+        self.forget_narrowing(&f.target);
+        self.forget_assigned_in(&f.body);
         self.visit_expr(&f.target);
+        let entry = self.flow.clone();
         for stmt in &f.body {
             self.visit_stmt(stmt);
         }
+        self.flow = entry;
     }
 
     fn visit_with(&mut self, w: &parser::ast::With) {
@@ -262,51 +875,18 @@ impl<'a> TraversalVisitor for TypeChecker<'a> {
         }
         for with_items in &w.items {
             self.visit_expr(&with_items.context_expr);
-            match &with_items.optional_vars {
-                Some(items) => self.visit_expr(items),
-                None => (),
+            if let Some(items) = &with_items.optional_vars {
+                self.visit_expr(items)
             }
         }
     }
 
     fn visit_try(&mut self, t: &parser::ast::Try) {
-        for stmt in &t.body {
-            self.visit_stmt(stmt);
-        }
-        for stmt in &t.orelse {
-            self.visit_stmt(stmt);
-        }
-        for stmt in &t.finalbody {
-            self.visit_stmt(stmt);
-        }
-        for handler in &t.handlers {
-            for stmt in &handler.body {
-                self.visit_stmt(stmt);
-            }
-            if let Some(typ) = &handler.typ {
-                self.visit_expr(typ);
-            }
-        }
+        self.visit_try_flow(&t.body, &t.handlers, &t.orelse, &t.finalbody);
     }
 
     fn visit_try_star(&mut self, t: &parser::ast::TryStar) {
-        for stmt in &t.body {
-            self.visit_stmt(stmt);
-        }
-        for stmt in &t.orelse {
-            self.visit_stmt(stmt);
-        }
-        for stmt in &t.finalbody {
-            self.visit_stmt(stmt);
-        }
-        for handler in &t.handlers {
-            for stmt in &handler.body {
-                self.visit_stmt(stmt);
-            }
-            if let Some(typ) = &handler.typ {
-                self.visit_expr(typ);
-            }
-        }
+        self.visit_try_flow(&t.body, &t.handlers, &t.orelse, &t.finalbody);
     }
 
     fn visit_function_def(&mut self, f: &Arc<parser::ast::FunctionDef>) {
@@ -317,9 +897,10 @@ impl<'a> TraversalVisitor for TypeChecker<'a> {
         if let Some(ret_type) = &f.returns {
             self.visit_expr(ret_type);
         }
-        for stmt in &f.body {
-            self.visit_stmt(stmt);
-        }
+        let return_type = self.declared_return_type(&f.returns);
+        self.return_types.push(return_type);
+        self.visit_in_new_flow(&f.body);
+        self.return_types.pop();
         for (arg, _index) in f.args.args.iter().zip(0..) {
             if let Some(annotation) = &arg.annotation {
                 self.infer_annotation_type(annotation);
@@ -335,16 +916,35 @@ impl<'a> TraversalVisitor for TypeChecker<'a> {
         self.enter_scope(f.node.start);
         let name = intern_lookup(f.name);
         self.infer_name_type(name, f.node.start + 9, f.node.start + 9 + name.len() as u32);
-        for stmt in &f.body {
-            self.visit_stmt(stmt);
-        }
+        let return_type = self.declared_return_type(&f.returns);
+        self.return_types.push(return_type);
+        self.visit_in_new_flow(&f.body);
+        self.return_types.pop();
         self.leave_scope();
     }
 
     fn visit_class_def(&mut self, c: &Arc<parser::ast::ClassDef>) {
         let file = &self.build_manager.files.get(&self.id).unwrap();
         let name = intern_lookup(c.name);
-        self.infer_name_type(name, c.node.start + 6, c.node.start + 6 + name.len() as u32);
+        let name_start = c.node.start + 6;
+        let name_end = name_start + name.len() as u32;
+        self.infer_name_type(name, name_start, name_end);
+        let symbol_table = self.build_manager.get_symbol_table_by_id(&self.id);
+        if let PythonType::Class(class_type) =
+            self.type_evaluator
+                .get_name_type(name, None, &symbol_table, self.current_scope)
+        {
+            if self.type_evaluator.get_mro(&class_type).is_none() {
+                self.add_error(
+                    codes::INCONSISTENT_MRO,
+                    format!(
+                        "Cannot create a consistent method resolution order (MRO) for class \"{name}\""
+                    ),
+                    name_start,
+                    name_end,
+                );
+            }
+        }
 
         self.enter_scope(c.node.start);
         for base in &c.bases {
@@ -353,9 +953,7 @@ impl<'a> TraversalVisitor for TypeChecker<'a> {
         for dec in &c.decorator_list {
             self.visit_expr(dec);
         }
-        for stmt in &c.body {
-            self.visit_stmt(stmt);
-        }
+        self.visit_in_new_flow(&c.body);
         for keyword in &c.keywords {
             self.visit_expr(&keyword.value);
         }
@@ -365,15 +963,62 @@ impl<'a> TraversalVisitor for TypeChecker<'a> {
 
     fn visit_match(&mut self, m: &parser::ast::Match) {
         self.visit_expr(&m.subject);
+        let symbol_table = self.build_manager.get_symbol_table_by_id(&self.id);
+        let entry = self.flow.clone();
+        // Type of the subject that is not matched by the previous cases
+        let subject_name = m.subject.as_name().map(|n| n.id.clone());
+        let mut remaining = self.evaluate_type(&m.subject);
+        let mut flows = vec![];
+        let mut exhaustive = false;
         for case in &m.cases {
-            for stmt in &case.body {
-                self.visit_stmt(stmt);
+            self.flow = entry.clone();
+            if let Some(name) = &subject_name {
+                let matched = self
+                    .type_evaluator
+                    .narrow_pattern(
+                        &remaining,
+                        &case.pattern,
+                        true,
+                        &symbol_table,
+                        self.current_scope,
+                    )
+                    .unwrap_or(remaining.clone());
+                self.flow.narrowed_types.insert(name.clone(), matched);
             }
+            self.visit_match_pattern(&case.pattern);
             if let Some(guard) = &case.guard {
                 self.visit_expr(guard);
             }
-            self.visit_match_pattern(&case.pattern);
+            for stmt in &case.body {
+                self.visit_stmt(stmt);
+            }
+            flows.push(self.flow.clone());
+
+            if case.guard.is_some() {
+                continue;
+            }
+            if is_irrefutable(&case.pattern) {
+                exhaustive = true;
+                break;
+            }
+            if let Some(not_matched) = self.type_evaluator.narrow_pattern(
+                &remaining,
+                &case.pattern,
+                false,
+                &symbol_table,
+                self.current_scope,
+            ) {
+                remaining = not_matched;
+            }
+        }
+        if !exhaustive {
+            let mut fallthrough = entry;
+            if let Some(name) = subject_name {
+                fallthrough.narrowed_types.insert(name, remaining);
+            }
+            flows.push(fallthrough);
         }
+        self.flow = self.join_flows(flows);
     }
 
     fn visit_match_pattern(&mut self, _m: &parser::ast::MatchPattern) {
@@ -428,7 +1073,7 @@ impl<'a> TraversalVisitor for TypeChecker<'a> {
     }
 
     fn visit_dict(&mut self, _d: &Dict) {
-        for key in &_d.keys {
+        for key in _d.keys.iter().flatten() {
             self.visit_expr(key);
         }
 
@@ -453,11 +1098,45 @@ impl<'a> TraversalVisitor for TypeChecker<'a> {
 
     fn visit_unary_op(&mut self, _u: &UnaryOperation) {
         self.visit_expr(&_u.operand);
+        let symbol_table = self.build_manager.get_symbol_table_by_id(&self.id);
+        let operand_type = self.evaluate_type(&_u.operand);
+        if self
+            .type_evaluator
+            .unary_op_result_type(&operand_type, &_u.op, &symbol_table)
+            .is_none()
+        {
+            self.add_error(
+                codes::OPERATOR,
+                format!(
+                    "Operator \"{}\" not supported for type \"{}\"",
+                    _u.op,
+                    operand_type.short_name()
+                ),
+                _u.node.start,
+                _u.node.end,
+            );
+        }
     }
 
     fn visit_bin_op(&mut self, b: &BinOp) {
-        let l_type = self.infer_expr_type(&b.left);
-        let r_type = self.infer_expr_type(&b.right);
+        self.visit_expr(&b.left);
+        self.visit_expr(&b.right);
+        let symbol_table = self.build_manager.get_symbol_table_by_id(&self.id);
+        if self
+            .type_evaluator
+            .is_union_type_expression(b, &symbol_table, self.current_scope)
+        {
+            return;
+        }
+        let l_type = self.evaluate_type(&b.left);
+        let r_type = self.evaluate_type(&b.right);
+        if self
+            .type_evaluator
+            .bin_op_result_type(&l_type, &r_type, &b.op, &symbol_table)
+            .is_none()
+        {
+            self.report_unsupported_operator(&b.op.to_string(), &l_type, &r_type, b.node);
+        }
     }
 
     fn visit_named_expr(&mut self, _n: &NamedExpression) {
@@ -521,7 +1200,24 @@ impl<'a> TraversalVisitor for TypeChecker<'a> {
     }
 
     fn visit_attribute(&mut self, a: &Attribute) {
-        self.infer_expr_type(&a.value);
+        let value_type = self.infer_expr_type(&a.value);
+        let class_type = match &value_type {
+            PythonType::Class(c) => c,
+            PythonType::Instance(i) => &i.class_type,
+            _ => return,
+        };
+        if let Some(origin) = self
+            .type_evaluator
+            .lookup_member_origin(class_type, &a.attr)
+        {
+            if origin.details.declaration_path != class_type.details.declaration_path {
+                self.member_origins.insert(Interval {
+                    start: a.node.end - a.attr.len() as u32,
+                    stop: a.node.end,
+                    val: origin,
+                });
+            }
+        }
     }
 
     fn visit_subscript(&mut self, _s: &Subscript) {
@@ -543,6 +1239,8 @@ impl<'a> TraversalVisitor for TypeChecker<'a> {
 
     fn visit_call(&mut self, c: &Call) {
         self.infer_expr_type(&c.func);
+        self.check_call_arguments(c);
+        self.check_instance_check(c);
         for arg in &c.args {
             self.visit_expr(arg);
         }
@@ -560,6 +1258,20 @@ impl<'a> TraversalVisitor for TypeChecker<'a> {
         for comprators in &_c.comparators {
             self.visit_expr(comprators);
         }
+        let symbol_table = self.build_manager.get_symbol_table_by_id(&self.id);
+        let mut left = &_c.left;
+        for (op, right) in _c.ops.iter().zip(_c.comparators.iter()) {
+            let l_type = self.evaluate_type(left);
+            let r_type = self.evaluate_type(right);
+            if self
+                .type_evaluator
+                .compare_result_type(&l_type, &r_type, op, &symbol_table)
+                .is_none()
+            {
+                self.report_unsupported_operator(&op.to_string(), &l_type, &r_type, _c.node);
+            }
+            left = right;
+        }
     }
 
     fn visit_lambda(&mut self, _l: &Lambda) {
@@ -581,25 +1293,56 @@ impl<'a> TraversalVisitor for TypeChecker<'a> {
 
     fn visit_formatted_value(&mut self, _f: &FormattedValue) {
         self.visit_expr(&_f.value);
+        if let Some(format_spec) = &_f.format_spec {
+            self.visit_expr(format_spec);
+        }
     }
 
     fn visit_alias(&mut self, _a: &Alias) {}
 
     fn visit_assign(&mut self, a: &Assign) {
         self.visit_expr(&a.value);
+        if let Some(type_comment) = &a.type_comment {
+            let annotation = type_comment_annotation(type_comment, a.node);
+            self.check_declared_value(&annotation, &a.value);
+        }
         for target in &a.targets {
+            self.forget_narrowing(target);
             self.visit_expr(target);
         }
     }
     fn visit_ann_assign(&mut self, _a: &AnnAssign) {
         if let Some(value) = &_a.value {
             self.visit_expr(value);
+            self.check_declared_value(&_a.annotation, value);
         }
+        self.forget_narrowing(&_a.target);
         self.infer_expr_type(&_a.target);
     }
 
     fn visit_aug_assign(&mut self, _a: &AugAssign) {
         self.visit_expr(&_a.value);
+        let symbol_table = self.build_manager.get_symbol_table_by_id(&self.id);
+        let target_type = self.evaluate_type(&_a.target);
+        let value_type = self.evaluate_type(&_a.value);
+        if self
+            .type_evaluator
+            .aug_assign_result_type(
+                &target_type,
+                &value_type,
+                &BinaryOperator::from(&_a.op),
+                &symbol_table,
+            )
+            .is_none()
+        {
+            self.report_unsupported_operator(
+                &_a.op.to_string(),
+                &target_type,
+                &value_type,
+                _a.node,
+            );
+        }
+        self.forget_narrowing(&_a.target);
         self.visit_expr(&_a.target);
     }
 
@@ -608,6 +1351,8 @@ impl<'a> TraversalVisitor for TypeChecker<'a> {
         if let Some(msg) = &_a.msg {
             self.visit_expr(msg);
         }
+        let narrowed = self.narrow(&_a.test, true);
+        self.apply_narrowing(narrowed);
     }
 
     fn visit_pass(&mut self, _p: &Pass) {}
@@ -622,6 +1367,21 @@ impl<'a> TraversalVisitor for TypeChecker<'a> {
         if let Some(value) = &_r.value {
             self.visit_expr(value);
         }
+        let Some(return_type) = self.return_types.last().cloned() else {
+            return;
+        };
+        let (value_type, node) = match &_r.value {
+            Some(value) => (self.evaluate_type(value), value.get_node()),
+            None => (PythonType::None, _r.node),
+        };
+        self.check_assignable(
+            &value_type,
+            &return_type,
+            codes::RETURN_VALUE,
+            |src, dest| format!("Type \"{src}\" is not assignable to return type \"{dest}\""),
+            node,
+        );
+        self.flow.reachable = false;
     }
 
     fn visit_raise(&mut self, _r: &Raise) {
@@ -631,11 +1391,16 @@ impl<'a> TraversalVisitor for TypeChecker<'a> {
         if let Some(cause) = &_r.cause {
             self.visit_expr(cause);
         }
+        self.flow.reachable = false;
     }
 
-    fn visit_break(&mut self, _b: &Break) {}
+    fn visit_break(&mut self, _b: &Break) {
+        self.flow.reachable = false;
+    }
 
-    fn visit_continue(&mut self, _c: &Continue) {}
+    fn visit_continue(&mut self, _c: &Continue) {
+        self.flow.reachable = false;
+    }
 
     fn visit_global(&mut self, _g: &Global) {
         let mut cur_offset = _g.node.start + 6;
@@ -648,6 +1413,39 @@ impl<'a> TraversalVisitor for TypeChecker<'a> {
     fn visit_nonlocal(&mut self, _n: &Nonlocal) {}
 }
 
+/// Whether a loop body can be left with a break statement
+fn contains_break(body: &[Statement]) -> bool {
+    body.iter().any(|stmt| match stmt {
+        Statement::BreakStmt(_) => true,
+        Statement::IfStatement(i) => contains_break(&i.body) || contains_break(&i.orelse),
+        Statement::WithStatement(w) => contains_break(&w.body),
+        Statement::AsyncWithStatement(w) => contains_break(&w.body),
+        Statement::TryStatement(t) => {
+            contains_break(&t.body)
+                || contains_break(&t.orelse)
+                || contains_break(&t.finalbody)
+                || t.handlers.iter().any(|h| contains_break(&h.body))
+        }
+        Statement::TryStarStatement(t) => {
+            contains_break(&t.body)
+                || contains_break(&t.orelse)
+                || contains_break(&t.finalbody)
+                || t.handlers.iter().any(|h| contains_break(&h.body))
+        }
+        Statement::MatchStmt(m) => m.cases.iter().any(|c| contains_break(&c.body)),
+        _ => false,
+    })
+}
+
+/// Patterns that match any subject
+fn is_irrefutable(pattern: &MatchPattern) -> bool {
+    match pattern {
+        MatchPattern::MatchAs(m) => m.pattern.as_ref().is_none_or(is_irrefutable),
+        MatchPattern::MatchOr(patterns) => patterns.iter().any(is_irrefutable),
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::{fs, path::PathBuf};
@@ -692,7 +1490,49 @@ mod tests {
         };
     }
 
+    fn snapshot_diagnostics(path: PathBuf) -> String {
+        let settings = Settings::test_settings();
+        let manager = BuildManager::new(settings);
+        let root = &PathBuf::from("");
+        manager.build(root);
+        manager.build_one(root, &path);
+        manager
+            .get_diagnostics(&path)
+            .iter()
+            .map(|d| format!("{d}\n"))
+            .collect()
+    }
+
+    macro_rules! diagnostics_test {
+        ($test_name:ident, $test_file:expr) => {
+            #[test]
+            fn $test_name() {
+                let path = PathBuf::from($test_file);
+                let contents = String::from_utf8_lossy(&fs::read(&path).unwrap()).into_owned();
+                let result = snapshot_diagnostics(path);
+
+                let mut content_with_line_numbers = String::new();
+                for (i, line) in contents.lines().enumerate() {
+                    content_with_line_numbers.push_str(&format!("{}: {}\n", i + 1, line));
+                }
+
+                let mut settings = insta::Settings::clone_current();
+                settings.set_snapshot_path("../test_data/output/");
+                settings.set_description(content_with_line_numbers);
+                settings.bind(|| {
+                    insta::assert_snapshot!(result);
+                });
+            }
+        };
+    }
+
     type_eval_test!(basic_types, "test_data/inputs/basic_types.py");
+    type_eval_test!(narrowing, "test_data/inputs/narrowing.py");
+    type_eval_test!(operators, "test_data/inputs/operators.py");
+    type_eval_test!(class_hierarchy, "test_data/inputs/class_hierarchy.py");
+    type_eval_test!(protocols, "test_data/inputs/protocols.py");
+    type_eval_test!(literals, "test_data/inputs/literals.py");
+    type_eval_test!(type_comments, "test_data/inputs/type_comments.py");
     type_eval_test!(
         generics_basic,
         "test_data/inputs/conformance_tests/generics_basic.py"
@@ -710,4 +1550,50 @@ mod tests {
         annotations_forward_refs,
         "test_data/inputs/conformance_tests/annotations_forward_refs.py"
     );
+
+    diagnostics_test!(
+        diagnostics_syntax_and_imports,
+        "test_data/inputs/diagnostics/syntax_and_imports.py"
+    );
+    diagnostics_test!(
+        diagnostics_assignability,
+        "test_data/inputs/diagnostics/assignability.py"
+    );
+    diagnostics_test!(
+        diagnostics_call_arguments,
+        "test_data/inputs/diagnostics/call_arguments.py"
+    );
+    diagnostics_test!(
+        diagnostics_type_comments,
+        "test_data/inputs/diagnostics/type_comments.py"
+    );
+    diagnostics_test!(
+        diagnostics_operators,
+        "test_data/inputs/diagnostics/operators.py"
+    );
+    diagnostics_test!(diagnostics_mro, "test_data/inputs/diagnostics/mro.py");
+    diagnostics_test!(
+        diagnostics_protocols,
+        "test_data/inputs/diagnostics/protocols.py"
+    );
+    diagnostics_test!(
+        diagnostics_literals,
+        "test_data/inputs/diagnostics/literals.py"
+    );
+    diagnostics_test!(
+        diagnostics_suppressions,
+        "test_data/inputs/diagnostics/suppressions.py"
+    );
+    diagnostics_test!(
+        diagnostics_encoding_latin1,
+        "test_data/inputs/diagnostics/encoding_latin1.py"
+    );
+    diagnostics_test!(
+        diagnostics_encoding_invalid_utf8,
+        "test_data/inputs/diagnostics/encoding_invalid_utf8.py"
+    );
+    diagnostics_test!(
+        diagnostics_encoding_bom,
+        "test_data/inputs/diagnostics/encoding_bom.py"
+    );
 }
@@ -1,5 +1,20 @@
 use std::fmt::Display;
 
+/// Rule codes attached to diagnostics. They are shown to the user and can be
+/// used to refer to a specific kind of error.
+pub mod codes {
+    pub const SYNTAX: &str = "syntax";
+    pub const IMPORT_NOT_FOUND: &str = "import-not-found";
+    pub const ASSIGNMENT: &str = "assignment";
+    pub const ARG_TYPE: &str = "arg-type";
+    pub const CALL_ARG: &str = "call-arg";
+    pub const RETURN_VALUE: &str = "return-value";
+    pub const OPERATOR: &str = "operator";
+    pub const INCONSISTENT_MRO: &str = "inconsistent-mro";
+    pub const MISC: &str = "misc";
+    pub const UNUSED_IGNORE: &str = "unused-ignore";
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct Diagnostic {
     /// The message body to display to the user, to explain the diagnostic.
@@ -7,6 +22,51 @@ pub struct Diagnostic {
     /// The message to display to the user, to explain the suggested fix.
     pub suggestion: Option<String>,
     pub range: Range,
+    pub severity: Severity,
+    /// The rule that produced this diagnostic, one of `codes`.
+    pub code: &'static str,
+}
+
+impl Diagnostic {
+    pub fn error(code: &'static str, body: String, range: Range) -> Self {
+        Diagnostic {
+            body,
+            suggestion: None,
+            range,
+            severity: Severity::Error,
+            code,
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}: {} [{}]",
+            self.range.start.line, self.range.start.character, self.severity, self.body, self.code
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Information => "info",
+            Severity::Hint => "hint",
+        };
+        write!(f, "{s}")
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
@@ -15,7 +75,9 @@ pub struct Range {
     pub end: Position,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+/// Line numbers start from 1 and characters from 0. Characters are counted in
+/// bytes, see `LineIndex` to convert them to other encodings.
+#[derive(Debug, PartialEq, Eq, Clone, Hash, PartialOrd, Ord)]
 pub struct Position {
     pub line: u32,
     pub character: u32,
@@ -1,18 +1,34 @@
 use core::panic;
+use std::collections::HashSet;
 use std::panic::{catch_unwind, AssertUnwindSafe};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::AtomicUsize;
 use std::sync::Arc;
 
 use crate::ast_visitor::TraversalVisitor;
 use enderpy_python_parser as parser;
 use enderpy_python_parser::ast::*;
-use parser::{ast, get_row_col_position, parser::parser::Parser};
+use parser::{
+    ast,
+    encoding::decode_source,
+    error::ParsingError,
+    get_row_col_position,
+    incremental::{reparse_with_options, TextEdit},
+    line_index::LineIndex,
+    parser::parser::{Parser, ParserOptions},
+    python_version::PythonVersion,
+    semantic_errors::semantic_errors,
+};
 use std::sync::atomic::Ordering;
 
 use crate::build::ResolvedImports;
+use crate::ruff_python_import_resolver::module_descriptor::ImportModuleDescriptor;
 use crate::symbol_table;
-use crate::{diagnostic::Position, semantic_analyzer::SemanticAnalyzer, symbol_table::SymbolTable};
+use crate::{
+    diagnostic::{codes, Diagnostic, Position, Range},
+    semantic_analyzer::SemanticAnalyzer,
+    symbol_table::SymbolTable,
+};
 
 #[derive(Clone, Debug)]
 pub enum ImportKinds<'a> {
@@ -29,47 +45,77 @@ pub struct EnderpyFile {
     pub followed: bool,
     pub path: Arc<PathBuf>,
     pub source: String,
-    pub line_starts: Vec<u32>,
+    pub line_index: LineIndex,
     pub tree: ast::Module,
+    /// Syntax errors found while parsing the file
+    pub parse_errors: Vec<ParsingError>,
+    /// Errors of decoding the file from disk, they are kept when the file is
+    /// parsed again after edits
+    pub decode_errors: Vec<ParsingError>,
+    /// Imports of this file that could not be resolved
+    pub unresolved_imports: HashSet<ImportModuleDescriptor>,
 }
 
-impl<'a> Eq for EnderpyFile {}
+impl Eq for EnderpyFile {}
 
-impl<'a> PartialEq for EnderpyFile {
+impl PartialEq for EnderpyFile {
     fn eq(&self, other: &Self) -> bool {
         self.id == other.id && self.path == other.path
     }
 }
 
-impl<'a> std::hash::Hash for EnderpyFile {
+impl std::hash::Hash for EnderpyFile {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.id.hash(state);
         self.path.hash(state);
     }
 }
 
+/// Parsing is not expected to panic since the parser recovers from syntax
+/// errors, but a bug should not stop the analysis of the rest of the project.
+/// Keep an empty module instead.
+fn panicked_parse(path: &Path, source: &str) -> (Module, Vec<ParsingError>) {
+    log::error!("panic occurred during parsing {path:?}");
+    let module = Module::new(Node::new(0, source.len() as u32), vec![]);
+    let error = ParsingError::InvalidSyntax {
+        msg: "invalid token".to_string(),
+        advice: String::new(),
+        span: (0, 0),
+    };
+    (module, vec![error])
+}
+
 fn get_id() -> u32 {
     static COUNTER: AtomicUsize = AtomicUsize::new(1);
     COUNTER.fetch_add(1, Ordering::SeqCst) as u32
 }
 
-impl<'a> EnderpyFile {
-    pub fn new(path: PathBuf, followed: bool) -> Self {
-        let source =
-            std::fs::read_to_string(&path).unwrap_or_else(|_| panic!("cannot read file {path:?}"));
+impl EnderpyFile {
+    /// Reads the file in the encoding it declares, bytes that cannot be
+    /// decoded are reported as syntax errors
+    pub fn new(path: PathBuf, followed: bool, target_version: PythonVersion) -> Self {
+        let bytes = std::fs::read(&path).unwrap_or_else(|_| panic!("cannot read file {path:?}"));
+        let decoded = decode_source(&bytes);
+        let mut file = Self::from_source(path, decoded.text, followed, target_version);
+        file.decode_errors = decoded.errors;
+        file
+    }
 
-        let mut parser = Parser::new(&source);
+    /// Creates the file from the given source instead of reading it from disk,
+    /// e.g. from the contents of an editor
+    pub fn from_source(
+        path: PathBuf,
+        source: String,
+        followed: bool,
+        target_version: PythonVersion,
+    ) -> Self {
+        let mut parser = Parser::with_options(&source, ParserOptions { target_version });
         let parse_result = catch_unwind(AssertUnwindSafe(|| parser.parse()));
-        let tree = match parse_result {
-            Ok(Ok(module)) => module,
-            Ok(Err(_)) => {
-                panic!("Cannot parse file : {path:?}");
-            }
-            Err(_) => {
-                panic!("Cannot parse file : {path:?}");
-            }
+        let (tree, parse_errors) = match parse_result {
+            Ok(result) => result,
+            Err(_) => panicked_parse(&path, &source),
         };
-        let line_starts = parser.lexer.line_starts;
+        let line_index = LineIndex::new(&source);
 
         let id = if path.ends_with("builtins.pyi") {
             symbol_table::Id(0)
@@ -80,13 +126,39 @@ impl<'a> EnderpyFile {
         Self {
             id,
             source,
-            line_starts,
+            line_index,
             followed,
             tree,
+            parse_errors,
+            decode_errors: vec![],
+            unresolved_imports: HashSet::new(),
             path: Arc::new(path),
         }
     }
 
+    /// Applies the edits to the source and parses again only the statements
+    /// that changed. `target_version` must be the one the file was parsed for.
+    pub fn apply_edits(&mut self, edits: &[TextEdit], target_version: PythonVersion) {
+        let tree = std::mem::replace(&mut self.tree, Module::new(Node::default(), vec![]));
+        let errors = std::mem::take(&mut self.parse_errors);
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            let options = ParserOptions { target_version };
+            reparse_with_options(&self.source, tree, errors, edits, options)
+        }));
+        (self.source, self.tree, self.parse_errors) = match result {
+            Ok(result) => result,
+            Err(_) => {
+                let mut source = std::mem::take(&mut self.source);
+                for edit in edits {
+                    edit.apply(&mut source);
+                }
+                let (tree, errors) = panicked_parse(&self.path, &source);
+                (source, tree, errors)
+            }
+        };
+        self.line_index = LineIndex::new(&self.source);
+    }
+
     pub fn path(&self) -> PathBuf {
         self.path.to_path_buf()
     }
@@ -97,7 +169,7 @@ impl<'a> EnderpyFile {
 
     /// Return source of the line number
     pub fn get_line_content(&self, line: usize) -> String {
-        let line_starts = &self.line_starts;
+        let line_starts = self.line_index.line_starts();
         let line_start_offset = line_starts[line - 1] as usize;
         let line_end_offset = if line == line_starts.len() {
             self.source.len()
@@ -107,7 +179,7 @@ impl<'a> EnderpyFile {
         self.source[line_start_offset..line_end_offset].to_string()
     }
 
-    pub fn get_imports(&self) -> Vec<ImportKinds> {
+    pub fn get_imports(&self) -> Vec<ImportKinds<'_>> {
         let imports = vec![];
         let mut import_collector = ImportCollector { imports };
         for s in self.tree.body.iter() {
@@ -118,13 +190,44 @@ impl<'a> EnderpyFile {
 
     pub fn get_position(&self, start: u32, end: u32) -> Position {
         let (start_line_num, start_line_column, _end_line_num, _end_line_column) =
-            get_row_col_position(start, end, &self.line_starts);
+            get_row_col_position(start, end, self.line_index.line_starts());
         Position {
             line: start_line_num,
             character: (end - start_line_column),
         }
     }
 
+    pub fn get_range(&self, start: u32, end: u32) -> Range {
+        let (start_line, start_character, end_line, end_character) =
+            get_row_col_position(start, end, self.line_index.line_starts());
+        Range {
+            start: Position {
+                line: start_line,
+                character: start_character,
+            },
+            end: Position {
+                line: end_line,
+                character: end_character,
+            },
+        }
+    }
+
+    /// Syntax errors of the file as diagnostics, including the ones CPython
+    /// reports when it compiles the file
+    pub fn syntax_diagnostics(&self) -> Vec<Diagnostic> {
+        self.decode_errors
+            .iter()
+            .chain(self.parse_errors.iter())
+            .chain(semantic_errors(&self.tree).iter())
+            .map(|err| {
+                let ParsingError::InvalidSyntax { msg, span, .. } = err;
+                let start = span.0 as u32;
+                let end = start + span.1 as u32;
+                Diagnostic::error(codes::SYNTAX, msg.clone(), self.get_range(start, end))
+            })
+            .collect()
+    }
+
     /// entry point to fill up the symbol table from the global definitions
     pub fn populate_symbol_table(&mut self, imports: &ResolvedImports) -> SymbolTable {
         let mut sem_anal = SemanticAnalyzer::new(self, imports);
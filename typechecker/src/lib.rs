@@ -3,6 +3,7 @@ use std::path::Path;
 mod ast_visitor;
 mod file;
 mod ruff_python_import_resolver;
+mod suppression;
 mod symbol_table;
 
 pub mod build;
@@ -23,3 +23,17 @@ impl PythonVersion {
         }
     }
 }
+
+impl From<enderpy_python_parser::python_version::PythonVersion> for PythonVersion {
+    fn from(version: enderpy_python_parser::python_version::PythonVersion) -> Self {
+        use enderpy_python_parser::python_version::PythonVersion as ParserVersion;
+        match version {
+            ParserVersion::Py37 => PythonVersion::Py37,
+            ParserVersion::Py38 => PythonVersion::Py38,
+            ParserVersion::Py39 => PythonVersion::Py39,
+            ParserVersion::Py310 => PythonVersion::Py310,
+            ParserVersion::Py311 => PythonVersion::Py311,
+            ParserVersion::Py312 => PythonVersion::Py312,
+        }
+    }
+}
@@ -2,9 +2,10 @@ use std::sync::Arc;
 
 use enderpy_python_parser::ast::Expression;
 use enderpy_python_parser::parser::parser::intern_lookup;
+use enderpy_python_parser::parser::split_signature_type_comment;
 use enderpy_python_parser::{self as parser};
 
-use parser::ast::{self, GetNode, Name, Statement};
+use parser::ast::{self, GetNode, Name, Node, Statement};
 
 use crate::{
     ast_visitor::TraversalVisitor,
@@ -39,10 +40,57 @@ pub struct FunctionInformation {
     pub yield_statements: Vec<ast::Yield>,
 }
 
+/// Type comments are used as string annotations, they are never evaluated
+/// at runtime just like forward references
+pub(crate) fn type_comment_annotation(type_comment: &str, node: Node) -> Expression {
+    Expression::Constant(Box::new(ast::Constant {
+        node,
+        value: ast::ConstantValue::Str(type_comment.to_string()),
+    }))
+}
+
+/// Fills the missing parameter and return annotations of a function from its
+/// signature type comment, e.g. `# type: (int, *str) -> bool`
+fn annotate_from_type_comment(
+    args: &mut ast::Arguments,
+    returns: &mut Option<Expression>,
+    type_comment: &str,
+    node: Node,
+    is_method: bool,
+) {
+    let Some((arg_types, return_type)) = split_signature_type_comment(type_comment) else {
+        return;
+    };
+    if returns.is_none() {
+        *returns = Some(type_comment_annotation(return_type, node));
+    }
+    if arg_types == ["..."] {
+        return;
+    }
+    let mut params: Vec<&mut ast::Arg> = args
+        .posonlyargs
+        .iter_mut()
+        .chain(args.args.iter_mut())
+        .chain(args.vararg.iter_mut())
+        .chain(args.kwonlyargs.iter_mut())
+        .chain(args.kwarg.iter_mut())
+        .collect();
+    // The type of self or cls can be left out
+    if is_method && arg_types.len() + 1 == params.len() {
+        params.remove(0);
+    }
+    for (param, typ) in params.into_iter().zip(arg_types) {
+        if param.annotation.is_none() {
+            let typ = typ.trim_start_matches('*').trim_start();
+            param.annotation = Some(type_comment_annotation(typ, node));
+        }
+    }
+}
+
 #[allow(unused)]
 impl<'a> SemanticAnalyzer<'a> {
     pub fn new(file: &'a EnderpyFile, imports: &'a ResolvedImports) -> Self {
-        let symbols = SymbolTable::new(&file.path, file.id);
+        let symbols = SymbolTable::new(&file.path, file.id, file.source.len() as u32);
         SemanticAnalyzer {
             file,
             symbol_table: symbols,
@@ -148,6 +196,26 @@ impl<'a> SemanticAnalyzer<'a> {
         }
     }
 
+    /// Declares the targets of the items of a `with` statement. The type
+    /// comment annotates the target when there is a single item.
+    fn declare_with_items(
+        &mut self,
+        items: &[parser::ast::WithItem],
+        type_comment: &Option<String>,
+        node: Node,
+    ) {
+        for item in items {
+            self.visit_expr(&item.context_expr);
+            if let Some(target) = &item.optional_vars {
+                let type_annotation = type_comment
+                    .as_ref()
+                    .filter(|_| items.len() == 1)
+                    .map(|typ| type_comment_annotation(typ, node));
+                self.create_variable_declaration_symbol(target, None, type_annotation, None);
+            }
+        }
+    }
+
     fn add_arguments_definitions(&mut self, args: &parser::ast::Arguments) {
         let defaults_len = args.defaults.len();
         for (pos_only, index) in args.posonlyargs.iter().zip(0..args.posonlyargs.len()) {
@@ -294,6 +362,7 @@ impl<'a> TraversalVisitor for SemanticAnalyzer<'a> {
             Statement::AsyncWithStatement(w) => self.visit_async_with(w),
             Statement::AsyncFunctionDef(f) => self.visit_async_function_def(f),
             Statement::TypeAlias(a) => self.visit_type_alias(a),
+            Statement::Invalid(i) => self.visit_invalid(i),
         }
     }
 
@@ -326,6 +395,7 @@ impl<'a> TraversalVisitor for SemanticAnalyzer<'a> {
             parser::ast::Expression::IfExp(i) => self.visit_if_exp(i),
             parser::ast::Expression::JoinedStr(j) => self.visit_joined_str(j),
             parser::ast::Expression::FormattedValue(f) => self.visit_formatted_value(f),
+            parser::ast::Expression::Invalid(i) => self.visit_invalid(i),
         }
     }
 
@@ -401,42 +471,39 @@ impl<'a> TraversalVisitor for SemanticAnalyzer<'a> {
     }
 
     fn visit_for(&mut self, f: &parser::ast::For) {
-        self.create_variable_declaration_symbol(&f.target, None, None, Some(f.clone()));
+        let type_annotation = f
+            .type_comment
+            .as_ref()
+            .map(|typ| type_comment_annotation(typ, f.node));
+        self.create_variable_declaration_symbol(&f.target, None, type_annotation, Some(f.clone()));
         for stmt in &f.body {
             self.visit_stmt(stmt);
         }
     }
 
     fn visit_async_for(&mut self, f: &parser::ast::AsyncFor) {
+        let type_annotation = f
+            .type_comment
+            .as_ref()
+            .map(|typ| type_comment_annotation(typ, f.node));
+        self.create_variable_declaration_symbol(&f.target, None, type_annotation, None);
         for stmt in &f.body {
             self.visit_stmt(stmt);
         }
     }
 
     fn visit_with(&mut self, w: &parser::ast::With) {
+        self.declare_with_items(&w.items, &w.type_comment, w.node);
         for stmt in &w.body {
             self.visit_stmt(stmt);
         }
-        for with_items in &w.items {
-            self.visit_expr(&with_items.context_expr);
-            match &with_items.optional_vars {
-                Some(items) => self.visit_expr(items),
-                None => (),
-            }
-        }
     }
 
     fn visit_async_with(&mut self, w: &parser::ast::AsyncWith) {
+        self.declare_with_items(&w.items, &w.type_comment, w.node);
         for stmt in &w.body {
             self.visit_stmt(stmt);
         }
-        for with_items in &w.items {
-            self.visit_expr(&with_items.context_expr);
-            match &with_items.optional_vars {
-                Some(items) => self.visit_expr(items),
-                None => (),
-            }
-        }
     }
 
     fn visit_try(&mut self, t: &parser::ast::Try) {
@@ -478,6 +545,21 @@ impl<'a> TraversalVisitor for SemanticAnalyzer<'a> {
     }
 
     fn visit_function_def(&mut self, f: &Arc<parser::ast::FunctionDef>) {
+        let f = &match &f.type_comment {
+            Some(type_comment) => {
+                let mut def = parser::ast::FunctionDef::clone(f);
+                let is_method = self.is_inside_class();
+                annotate_from_type_comment(
+                    &mut def.args,
+                    &mut def.returns,
+                    type_comment,
+                    def.node,
+                    is_method,
+                );
+                Arc::new(def)
+            }
+            None => Arc::clone(f),
+        };
         let declaration_path = DeclarationPath::new(
             self.symbol_table.id,
             f.node,
@@ -560,6 +642,21 @@ impl<'a> TraversalVisitor for SemanticAnalyzer<'a> {
     }
 
     fn visit_async_function_def(&mut self, f: &Arc<parser::ast::AsyncFunctionDef>) {
+        let f = &match &f.type_comment {
+            Some(type_comment) => {
+                let mut def = parser::ast::AsyncFunctionDef::clone(f);
+                let is_method = self.is_inside_class();
+                annotate_from_type_comment(
+                    &mut def.args,
+                    &mut def.returns,
+                    type_comment,
+                    def.node,
+                    is_method,
+                );
+                Arc::new(def)
+            }
+            None => Arc::clone(f),
+        };
         let declaration_path = DeclarationPath::new(
             self.symbol_table.id,
             f.node,
@@ -779,7 +876,11 @@ impl<'a> TraversalVisitor for SemanticAnalyzer<'a> {
             .targets
             .last()
             .expect("Assignment has at least one target");
-        self.create_variable_declaration_symbol(target, Some(value.clone()), None, None);
+        let type_annotation = assign
+            .type_comment
+            .as_ref()
+            .map(|typ| type_comment_annotation(typ, assign.node));
+        self.create_variable_declaration_symbol(target, Some(value.clone()), type_annotation, None);
 
         self.visit_expr(&assign.value);
     }
@@ -1,13 +1,28 @@
 use std::{env, path::PathBuf};
 
 use config::{Config, ConfigError, File};
-use serde::Deserialize;
+use enderpy_python_parser::python_version::PythonVersion;
+use serde::{Deserialize, Deserializer};
 
 #[derive(Debug, Deserialize)]
 #[allow(unused)]
 pub struct Settings {
     pub typeshed_path: PathBuf,
     pub python_executable: Option<PathBuf>,
+    /// Report `# type: ignore` comments that do not suppress any diagnostic
+    #[serde(default)]
+    pub report_unused_ignores: bool,
+    /// Python version the files are parsed and imports are resolved for,
+    /// written like `3.10`
+    #[serde(default, deserialize_with = "deserialize_python_version")]
+    pub target_version: PythonVersion,
+}
+
+fn deserialize_python_version<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<PythonVersion, D::Error> {
+    let version = String::deserialize(deserializer)?;
+    version.parse().map_err(serde::de::Error::custom)
 }
 
 impl Settings {
@@ -25,6 +40,8 @@ impl Settings {
         Settings {
             typeshed_path,
             python_executable: None,
+            report_unused_ignores: false,
+            target_version: PythonVersion::default(),
         }
     }
 
@@ -33,6 +50,8 @@ impl Settings {
         Settings {
             typeshed_path: file_dir.parent().unwrap().join("typeshed"),
             python_executable: None,
+            report_unused_ignores: false,
+            target_version: PythonVersion::default(),
         }
     }
 }
@@ -0,0 +1,281 @@
+use std::{
+    collections::{HashMap, HashSet},
+    panic::{catch_unwind, AssertUnwindSafe},
+};
+
+use enderpy_python_parser::{get_row_col_position, token::Kind, Lexer};
+
+use crate::diagnostic::{codes, Diagnostic, Position, Range, Severity};
+
+/// Comments that silence diagnostics, e.g. `# type: ignore[assignment]` or
+/// `# enderpy: ignore`. A comment suppresses the diagnostics that start on
+/// its line. Comments before the first statement of a module suppress the
+/// diagnostics of the whole file.
+#[derive(Debug, Default)]
+pub struct Suppressions {
+    file: Vec<Suppression>,
+    /// Suppressions by the line number of their comment
+    lines: HashMap<u32, Suppression>,
+}
+
+#[derive(Debug)]
+struct Suppression {
+    /// `type` or `enderpy`
+    tool: &'static str,
+    /// Codes of the suppressed rules, all rules are suppressed when empty
+    codes: Vec<String>,
+    range: Range,
+}
+
+impl Suppression {
+    fn suppresses(&self, code: &str) -> bool {
+        self.codes.is_empty() || self.codes.iter().any(|c| c == code)
+    }
+
+    fn unused_diagnostic(&self, codes: &[&str]) -> Diagnostic {
+        let suffix = if codes.is_empty() {
+            String::new()
+        } else {
+            format!("[{}]", codes.join(", "))
+        };
+        Diagnostic {
+            body: format!("Unused \"{}: ignore{suffix}\" comment", self.tool),
+            suggestion: None,
+            range: self.range.clone(),
+            severity: Severity::Warning,
+            code: codes::UNUSED_IGNORE,
+        }
+    }
+}
+
+impl Suppressions {
+    pub fn new(source: &str) -> Self {
+        catch_unwind(AssertUnwindSafe(|| Self::from_tokens(source))).unwrap_or_default()
+    }
+
+    fn from_tokens(source: &str) -> Self {
+        let mut lexer = Lexer::new(source);
+        let tokens = lexer.lex();
+        let mut suppressions = Suppressions::default();
+        let mut in_header = true;
+        for token in tokens {
+            match token.kind {
+                Kind::Comment => {}
+                Kind::NL | Kind::NewLine | Kind::WhiteSpace => continue,
+                _ => {
+                    in_header = false;
+                    continue;
+                }
+            }
+            let Some((tool, codes)) = parse_suppression(token.as_str(source)) else {
+                continue;
+            };
+            let (start_line, start_character, end_line, end_character) =
+                get_row_col_position(token.start, token.end, &lexer.line_starts);
+            let suppression = Suppression {
+                tool,
+                codes,
+                range: Range {
+                    start: Position {
+                        line: start_line,
+                        character: start_character,
+                    },
+                    end: Position {
+                        line: end_line,
+                        character: end_character,
+                    },
+                },
+            };
+            if in_header {
+                suppressions.file.push(suppression);
+            } else {
+                suppressions.lines.insert(start_line, suppression);
+            }
+        }
+        suppressions
+    }
+
+    /// Removes the suppressed diagnostics. Syntax errors cannot be suppressed.
+    /// With `report_unused` the comments that did not suppress anything are
+    /// reported as well.
+    pub fn filter(&self, diagnostics: Vec<Diagnostic>, report_unused: bool) -> Vec<Diagnostic> {
+        // The line of the comment and the codes it suppressed
+        let mut used: HashMap<u32, HashSet<&str>> = HashMap::new();
+        let mut result: Vec<Diagnostic> = diagnostics
+            .into_iter()
+            .filter(|diagnostic| {
+                if diagnostic.code == codes::SYNTAX {
+                    return true;
+                }
+                let suppression = self
+                    .lines
+                    .get(&diagnostic.range.start.line)
+                    .filter(|s| s.suppresses(diagnostic.code))
+                    .or_else(|| self.file.iter().find(|s| s.suppresses(diagnostic.code)));
+                match suppression {
+                    Some(suppression) => {
+                        used.entry(suppression.range.start.line)
+                            .or_default()
+                            .insert(diagnostic.code);
+                        false
+                    }
+                    None => true,
+                }
+            })
+            .collect();
+
+        if report_unused {
+            let mut lines: Vec<&Suppression> = self.lines.values().collect();
+            lines.sort_by_key(|s| s.range.start.line);
+            for suppression in self.file.iter().chain(lines) {
+                let used = used.get(&suppression.range.start.line);
+                if suppression.codes.is_empty() {
+                    if used.is_none() {
+                        result.push(suppression.unused_diagnostic(&[]));
+                    }
+                    continue;
+                }
+                let unused_codes: Vec<&str> = suppression
+                    .codes
+                    .iter()
+                    .map(String::as_str)
+                    .filter(|code| !used.is_some_and(|used| used.contains(code)))
+                    .collect();
+                if !unused_codes.is_empty() {
+                    result.push(suppression.unused_diagnostic(&unused_codes));
+                }
+            }
+        }
+        result
+    }
+}
+
+/// Parses `# type: ignore[code, ...]` or `# enderpy: ignore[code, ...]`.
+/// The suppression can follow other comments, e.g. `# noqa # type: ignore`.
+fn parse_suppression(comment: &str) -> Option<(&'static str, Vec<String>)> {
+    for segment in comment.split('#').skip(1) {
+        let segment = segment.trim_start();
+        let (tool, rest) = if let Some(rest) = segment.strip_prefix("type:") {
+            ("type", rest)
+        } else if let Some(rest) = segment.strip_prefix("enderpy:") {
+            ("enderpy", rest)
+        } else {
+            continue;
+        };
+        let Some(rest) = rest.trim_start().strip_prefix("ignore") else {
+            continue;
+        };
+        if let Some(rest) = rest.strip_prefix('[') {
+            let Some((codes, rest)) = rest.split_once(']') else {
+                continue;
+            };
+            if !rest.trim().is_empty() {
+                continue;
+            }
+            let codes = codes
+                .split(',')
+                .map(str::trim)
+                .filter(|code| !code.is_empty())
+                .map(String::from)
+                .collect();
+            return Some((tool, codes));
+        }
+        if rest.trim().is_empty() {
+            return Some((tool, vec![]));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostic(line: u32, code: &'static str) -> Diagnostic {
+        let position = Position { line, character: 0 };
+        let range = Range {
+            start: position.clone(),
+            end: position,
+        };
+        Diagnostic::error(code, String::new(), range)
+    }
+
+    fn filter(source: &str, diagnostics: Vec<Diagnostic>) -> Vec<String> {
+        Suppressions::new(source)
+            .filter(diagnostics, true)
+            .iter()
+            .map(|d| d.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_suppression() {
+        for (comment, expected) in [
+            ("# type: ignore", Some(("type", vec![]))),
+            ("#type:ignore", Some(("type", vec![]))),
+            (
+                "# type: ignore[assignment, arg-type]",
+                Some(("type", vec!["assignment", "arg-type"])),
+            ),
+            ("# enderpy: ignore[misc]", Some(("enderpy", vec!["misc"]))),
+            ("# noqa # type: ignore", Some(("type", vec![]))),
+            ("# type: ignore # because", Some(("type", vec![]))),
+            ("# type: int", None),
+            ("# type: ignored", None),
+            ("# ignore", None),
+        ] {
+            let expected = expected.map(|(tool, codes)| {
+                (
+                    tool,
+                    codes.into_iter().map(String::from).collect::<Vec<_>>(),
+                )
+            });
+            assert_eq!(parse_suppression(comment), expected, "{comment}");
+        }
+    }
+
+    #[test]
+    fn test_filter() {
+        let source = "\
+a = 1  # type: ignore
+b = 1  # type: ignore[assignment]
+c = 1  # enderpy: ignore[arg-type, call-arg]
+d = 1  # type: ignore
+e = '# type: ignore'
+f = (
+";
+        let diagnostics = vec![
+            diagnostic(1, codes::ASSIGNMENT),
+            diagnostic(2, codes::ARG_TYPE),
+            diagnostic(3, codes::ARG_TYPE),
+            diagnostic(5, codes::ASSIGNMENT),
+            diagnostic(6, codes::SYNTAX),
+        ];
+        assert_eq!(
+            filter(source, diagnostics),
+            [
+                "2:0: error:  [arg-type]",
+                "5:0: error:  [assignment]",
+                "6:0: error:  [syntax]",
+                "2:7: warning: Unused \"type: ignore[assignment]\" comment [unused-ignore]",
+                "3:7: warning: Unused \"enderpy: ignore[call-arg]\" comment [unused-ignore]",
+                "4:7: warning: Unused \"type: ignore\" comment [unused-ignore]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_file_level_suppression() {
+        let source = "\
+#!/usr/bin/env python
+# type: ignore[assignment]
+
+a = 1  # type: ignore
+";
+        let diagnostics = vec![
+            diagnostic(4, codes::ASSIGNMENT),
+            diagnostic(10, codes::ASSIGNMENT),
+        ];
+        assert_eq!(filter(source, diagnostics), Vec::<String>::new());
+    }
+}
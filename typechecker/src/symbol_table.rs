@@ -1,7 +1,6 @@
 use bitflags::bitflags;
 use rust_lapper::{Interval, Lapper};
 
-use std::fs;
 use std::path::Path;
 use std::sync::Arc;
 use std::{collections::HashMap, fmt::Display, path::PathBuf};
@@ -30,10 +29,7 @@ pub struct SymbolTable {
 }
 
 impl SymbolTable {
-    pub fn new(file_path: &Path, id: Id) -> Self {
-        let file_len = fs::read_to_string(file_path)
-            .expect("Could not read the file")
-            .len() as u32;
+    pub fn new(file_path: &Path, id: Id, file_len: u32) -> Self {
         let global_scope_interval = Interval {
             start: 0,
             stop: file_len,
@@ -55,14 +51,13 @@ impl SymbolTable {
     }
 
     pub fn get_file_name(&self) -> String {
-        return self
-            .file_path
+        self.file_path
             .to_str()
             .expect("cannot get file path string")
             .split("/")
             .last()
             .expect("last part of the file")
-            .to_string();
+            .to_string()
     }
 
     /// Do not use for lookup operations
@@ -70,8 +65,7 @@ impl SymbolTable {
         if let Some(scope) = self
             .scopes
             .iter()
-            .filter(|scope| scope.id == self.current_scope_id)
-            .last()
+            .rfind(|scope| scope.id == self.current_scope_id)
         {
             scope
         } else {
@@ -100,24 +94,23 @@ impl SymbolTable {
     /// Returns the parent scope of the current scope
     pub fn parent_scope(&self, scope: &SymbolTableScope) -> Option<&SymbolTableScope> {
         let parent_id = scope.parent?;
-        return Some(
+        Some(
             self.scopes
                 .iter()
-                .filter(|scope| scope.id == parent_id)
-                .last()
+                .rfind(|scope| scope.id == parent_id)
                 .expect("parent scope id not found in scopes"),
-        );
+        )
     }
 
     pub fn parent_scope_mut(&mut self, scope: &SymbolTableScope) -> Option<&mut SymbolTableScope> {
         let parent_id = scope.parent?;
-        return Some(
+        Some(
             self.scopes
                 .iter_mut()
                 .filter(|scope| scope.id == parent_id)
                 .last()
                 .expect("parent scope id not found in scopes"),
-        );
+        )
     }
 
     pub fn current_scope_mut(&mut self) -> &mut SymbolTableScope {
@@ -129,7 +122,7 @@ impl SymbolTable {
     }
 
     pub fn get_scope_by_id(&self, id: u32) -> Option<&SymbolTableScope> {
-        self.scopes.iter().filter(|scope| scope.id == id).last()
+        self.scopes.iter().rfind(|scope| scope.id == id)
     }
 
     pub fn get_scope_mut_by_id(&mut self, id: u32) -> Option<&mut SymbolTableScope> {
@@ -167,7 +160,7 @@ impl SymbolTable {
     }
 
     pub fn current_scope_type(&self) -> &SymbolTableType {
-        return &self.current_scope().kind;
+        &self.current_scope().kind
     }
 
     /// search for symbol in that scope
@@ -233,8 +226,7 @@ impl SymbolTable {
     pub fn global_scope(&self) -> &SymbolTableScope {
         self.scopes
             .iter()
-            .filter(|scope| scope.id == 0)
-            .last()
+            .rfind(|scope| scope.id == 0)
             .expect("no global scope")
     }
 
@@ -251,7 +243,7 @@ impl SymbolTable {
             .expect("no parent scope. Exiting global scope is not allowed");
     }
 
-    pub fn add_symbol(&mut self, mut symbol_node: SymbolTableNode) {
+    pub fn add_symbol(&mut self, symbol_node: SymbolTableNode) {
         let scope = if symbol_node.flags.contains(SymbolFlags::CLASS_MEMBER)
             || symbol_node.flags.contains(SymbolFlags::INSTANCE_MEMBER)
         {
@@ -267,10 +259,10 @@ impl SymbolTable {
             self.current_scope_mut()
         };
 
-        if let Some(existing_symbol) = scope.symbols.get(&symbol_node.name) {
-            symbol_node
+        if let Some(existing_symbol) = scope.symbols.get_mut(&symbol_node.name) {
+            existing_symbol
                 .declarations
-                .extend(existing_symbol.declarations.clone());
+                .extend(symbol_node.declarations);
         } else {
             scope.symbols.insert(symbol_node.name.clone(), symbol_node);
         }
@@ -282,7 +274,7 @@ impl SymbolTable {
         let scope = self
             .get_scope_by_id(scope_id)
             .expect("scope is in the symbol table");
-        return scope.symbols.get(attr);
+        scope.symbols.get(attr)
     }
 }
 
@@ -303,6 +295,10 @@ fn get_id() -> u32 {
 }
 
 impl SymbolTableScope {
+    pub fn symbols(&self) -> impl Iterator<Item = &SymbolTableNode> {
+        self.symbols.values()
+    }
+
     pub fn new(
         symbol_table_type: SymbolTableType,
         name: String,
@@ -372,7 +368,7 @@ impl Display for SymbolTableNode {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct DeclarationPath {
     pub symbol_table_id: Id,
     pub node: Node,
@@ -480,6 +476,11 @@ pub struct Class {
     pub class_node: Option<Arc<ClassDef>>,
     pub class_scope_id: u32,
     pub qual_name: String,
+    /// Protocol classes are compared structurally. A class is a protocol if
+    /// `Protocol` is one of its bases.
+    pub is_protocol: bool,
+    /// Protocols decorated with `@runtime_checkable` can be used with `isinstance`
+    pub runtime_checkable: bool,
 }
 
 impl Class {
@@ -493,6 +494,14 @@ impl Class {
     ) -> Self {
         module_name.push('.');
         let qual_name = module_name + name;
+        let is_protocol = class_node
+            .bases
+            .iter()
+            .any(|base| is_typing_name(base, "Protocol"));
+        let runtime_checkable = class_node
+            .decorator_list
+            .iter()
+            .any(|decorator| is_typing_name(decorator, "runtime_checkable"));
         Class {
             name: name.to_string(),
             declaration_path,
@@ -500,6 +509,8 @@ impl Class {
             qual_name,
             class_node: Some(class_node),
             class_scope_id,
+            is_protocol,
+            runtime_checkable,
         }
     }
 
@@ -518,10 +529,23 @@ impl Class {
             class_node: None,
             class_scope_id,
             qual_name,
+            is_protocol: false,
+            runtime_checkable: false,
         }
     }
 }
 
+/// Whether the expression refers to `name` from typing, e.g. `Protocol`,
+/// `Protocol[T]` or `typing.Protocol`. Names are not resolved at this point.
+fn is_typing_name(expr: &ast::Expression, name: &str) -> bool {
+    match expr {
+        ast::Expression::Name(n) => n.id == name,
+        ast::Expression::Attribute(a) => a.attr == name,
+        ast::Expression::Subscript(s) => is_typing_name(&s.value, name),
+        _ => false,
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Parameter {
     pub declaration_path: DeclarationPath,
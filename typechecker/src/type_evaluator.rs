@@ -6,7 +6,8 @@ use enderpy_python_parser::{self as parser, parser::parser::intern_lookup};
 use parser::ast;
 use parser::parser::parser::Parser;
 use std::{
-    cell::Cell,
+    cell::{Cell, RefCell},
+    collections::HashMap,
     panic::{catch_unwind, AssertUnwindSafe},
     sync::Arc,
 };
@@ -18,14 +19,15 @@ use parser::ast::Expression;
 use super::{
     builtins,
     types::{
-        self, CallableType, ClassType, InstanceType, LiteralValue, ModuleRef, PythonType, TypeVar,
+        self, CallableType, ClassType, FunctionKind, InstanceType, LiteralValue, ModuleRef,
+        PythonType, TypeVar,
     },
 };
 use crate::{
     build::BuildManager,
     semantic_analyzer::get_member_access_info,
     symbol_table::{self, Class, Declaration, DeclarationPath, Id, SymbolTable, SymbolTableNode},
-    types::CallableArgs,
+    types::{CallableArgs, Parameter},
 };
 
 const LITERAL_TYPE_PARAMETER_MSG: &str = "Type arguments for 'Literal' must be None, a literal value (int, bool, str, or bytes), or an enum value";
@@ -34,10 +36,44 @@ const LITERAL_TYPE_PARAMETER_MSG: &str = "Type arguments for 'Literal' must be N
 const UNION_TYPE_PARAMETER_MSG: &str = "Type arguments for 'Union' must be names or literal values";
 
 const SPECIAL_FORM: &str = "_SpecialForm";
+/// Members that are not part of the interface of a protocol
+/// https://github.com/python/cpython/blob/3.12/Lib/typing.py
+const PROTOCOL_EXCLUDED_MEMBERS: &[&str] = &[
+    "__abstractmethods__",
+    "__annotations__",
+    "__class_getitem__",
+    "__dict__",
+    "__doc__",
+    "__init__",
+    "__init_subclass__",
+    "__module__",
+    "__new__",
+    "__orig_bases__",
+    "__parameters__",
+    "__slots__",
+    "__subclasshook__",
+    "__weakref__",
+];
 #[derive(Clone, Debug)]
 pub struct TypeEvaluator<'a> {
     build_manager: &'a BuildManager,
     flags: Cell<GetTypeFlags>,
+    narrowing: RefCell<Option<Narrowing>>,
+    /// Method resolution order of classes, None for inconsistent hierarchies
+    mro_cache: RefCell<HashMap<DeclarationPath, Option<Vec<ClassType>>>>,
+    /// Pairs of classes and protocols that are being compared
+    protocol_assumptions: RefCell<Vec<(DeclarationPath, DeclarationPath)>>,
+}
+
+/// Types of names narrowed by the code flow at the expression that is being
+/// evaluated. Names outside of `node` are not narrowed because the narrowing only
+/// holds at that position.
+#[derive(Clone, Debug)]
+pub struct Narrowing {
+    pub symbol_table_id: Id,
+    pub scope_id: u32,
+    pub node: ast::Node,
+    pub types: HashMap<String, PythonType>,
 }
 
 bitflags::bitflags! {
@@ -65,6 +101,9 @@ impl<'a> TypeEvaluator<'a> {
         TypeEvaluator {
             build_manager,
             flags: Cell::new(GetTypeFlags::empty()),
+            narrowing: RefCell::new(None),
+            mro_cache: RefCell::new(HashMap::new()),
+            protocol_assumptions: RefCell::new(vec![]),
         }
     }
     /// Entry point function to get type of an expression. The expression passed
@@ -84,16 +123,16 @@ impl<'a> TypeEvaluator<'a> {
                 let typ = match &c.value {
                     // Constants are not literals unless they are explicitly
                     // typing.readthedocs.io/en/latest/spec/literal.html#backwards-compatibility
-                    ast::ConstantValue::Int => self.get_builtin_type("int"),
-                    ast::ConstantValue::Float => self.get_builtin_type("float"),
+                    ast::ConstantValue::Int(_) => self.get_builtin_type("int"),
+                    ast::ConstantValue::Float(_) => self.get_builtin_type("float"),
                     ast::ConstantValue::Str(_) => self.get_builtin_type("str"),
                     ast::ConstantValue::Bool(_) => self.get_builtin_type("bool"),
                     ast::ConstantValue::None => Some(PythonType::None),
-                    ast::ConstantValue::Bytes => self.get_builtin_type("bytes"),
+                    ast::ConstantValue::Bytes(_) => self.get_builtin_type("bytes"),
                     ast::ConstantValue::Ellipsis => Some(PythonType::Any),
                     // TODO: implement
                     ast::ConstantValue::Tuple => Some(PythonType::Unknown),
-                    ast::ConstantValue::Complex => Some(PythonType::Unknown),
+                    ast::ConstantValue::Complex { .. } => self.get_builtin_type("complex"),
                 };
                 Ok(match typ {
                     Some(t) => t,
@@ -104,6 +143,9 @@ impl<'a> TypeEvaluator<'a> {
                 if n.id == "PathLike" {
                     dbg!(expr);
                 }
+                if let Some(narrowed) = self.get_narrowed_type(n, symbol_table, scope_id) {
+                    return Ok(narrowed);
+                }
                 Ok(self.get_name_type(&n.id, Some(n.node.start), symbol_table, scope_id))
             }
             ast::Expression::Call(call) => {
@@ -131,15 +173,7 @@ impl<'a> TypeEvaluator<'a> {
                             let type_name = match first_arg {
                                 ast::Expression::Constant(ref str_const) => {
                                     match &str_const.value {
-                                        ast::ConstantValue::Str(_) => {
-                                            let decl_id = t.decl_id;
-                                            let file = &self
-                                                .build_manager
-                                                .files
-                                                .get(&symbol_table.id)
-                                                .unwrap();
-                                            str_const.get_value(&file.source).to_string()
-                                        }
+                                        ast::ConstantValue::Str(value) => value.clone(),
                                         _ => panic!("TypeVar first arg must be a string"),
                                     }
                                 }
@@ -220,8 +254,11 @@ impl<'a> TypeEvaluator<'a> {
                 Ok(PythonType::Instance(InstanceType::new(c, vec![elm_type])))
             }
             ast::Expression::Dict(d) => {
-                let key_type =
-                    self.get_sequence_type_from_elements(&d.keys, symbol_table, scope_id);
+                let key_type = self.get_sequence_type_from_elements(
+                    d.keys.iter().flatten(),
+                    symbol_table,
+                    scope_id,
+                );
                 let value_type =
                     self.get_sequence_type_from_elements(&d.values, symbol_table, scope_id);
                 let class_type = self
@@ -251,12 +288,12 @@ impl<'a> TypeEvaluator<'a> {
                 )))
             }
             ast::Expression::BoolOp(_) => Ok(self.get_builtin_type("bool").expect("typeshed")),
-            ast::Expression::UnaryOp(u) => match u.op {
-                ast::UnaryOperator::Not => Ok(self.get_builtin_type("bool").expect("typeshed")),
-                ast::UnaryOperator::UAdd
-                | ast::UnaryOperator::USub
-                | ast::UnaryOperator::Invert => Ok(PythonType::Unknown),
-            },
+            ast::Expression::UnaryOp(u) => {
+                let operand_type = self.get_type(&u.operand, symbol_table, scope_id)?;
+                Ok(self
+                    .unary_op_result_type(&operand_type, &u.op, symbol_table)
+                    .unwrap_or(PythonType::Unknown))
+            }
             ast::Expression::NamedExpr(e) => self.get_type(&e.value, symbol_table, scope_id),
             ast::Expression::Yield(a) => {
                 let yield_type = match a.value {
@@ -351,13 +388,21 @@ impl<'a> TypeEvaluator<'a> {
                 };
                 match value_type {
                     PythonType::Class(ref c) => {
-                        let attribute_on_c = self.lookup_on_class(symbol_table, c, &a.attr);
+                        if let Some(literal_value) = self.enum_member_literal(c, &a.attr) {
+                            return Ok(PythonType::LiteralValue(types::KnownValue {
+                                literal_value,
+                            }));
+                        }
+                        let attribute_on_c = self.lookup_on_class(c, &a.attr);
                         if let Some(attribute_on_c) = attribute_on_c {
                             Ok(attribute_on_c)
                         } else {
                             Ok(PythonType::Unknown)
                         }
                     }
+                    PythonType::Instance(ref i) => Ok(self
+                        .lookup_on_class(&i.class_type, &a.attr)
+                        .unwrap_or(PythonType::Unknown)),
                     PythonType::Module(module) => {
                         let module_sym_table = self.get_symbol_table(&module.module_id);
                         Ok(self.get_name_type(&a.attr, None, &module_sym_table, 0))
@@ -370,8 +415,7 @@ impl<'a> TypeEvaluator<'a> {
                             .expect("object is a builtin");
                         let object_class = get_builtin_type.as_class().expect("object is a class");
 
-                        let attribute_on_c =
-                            self.lookup_on_class(symbol_table, object_class, &a.attr);
+                        let attribute_on_c = self.lookup_on_class(object_class, &a.attr);
                         if let Some(attribute_on_c) = attribute_on_c {
                             Ok(attribute_on_c)
                         } else {
@@ -380,11 +424,17 @@ impl<'a> TypeEvaluator<'a> {
                     }
                 }
             }
-            ast::Expression::BinOp(b) => Ok(self.bin_op_result_type(
-                &self.get_type(&b.left, symbol_table, scope_id)?,
-                &self.get_type(&b.right, symbol_table, scope_id)?,
-                &b.op,
-            )),
+            ast::Expression::BinOp(b) => {
+                // `int | None` outside of an annotation creates a union type
+                if self.is_union_type_expression(b, symbol_table, scope_id) {
+                    return Ok(self.get_annotation_type(expr, symbol_table, scope_id));
+                }
+                let left_type = self.get_type(&b.left, symbol_table, scope_id)?;
+                let right_type = self.get_type(&b.right, symbol_table, scope_id)?;
+                Ok(self
+                    .bin_op_result_type(&left_type, &right_type, &b.op, symbol_table)
+                    .unwrap_or(PythonType::Unknown))
+            }
             ast::Expression::Subscript(s) => {
                 let value_type = self.get_type(&s.value, symbol_table, scope_id)?;
                 let span = span!(Level::TRACE, "Subscript", value_type = display(&value_type),);
@@ -432,82 +482,11 @@ impl<'a> TypeEvaluator<'a> {
                             // If type parameters are set then this is accessing an attribute on
                             // class.
                         } else {
-                            let lookup_on_class =
-                                self.lookup_on_class(symbol_table, c, "__getitem__");
-                            match lookup_on_class {
-                                Some(PythonType::Callable(callable)) => {
-                                    let ret_type = callable.return_type;
-
-                                    match ret_type {
-                                        PythonType::TypeVar(ref tv) => {
-                                            // 1. Get the index of this type var in the class type
-                                            //    parameters
-                                            // 2. Get the value for this index in the
-                                            //    initialized_type_parameters or Unknown if not initialized
-                                            let Some(type_var_index) =
-                                                c.type_parameters.iter().position(|tp| {
-                                                    let Some(type_var) = tp.as_type_var() else {
-                                                        return false;
-                                                    };
-                                                    type_var.name == tv.name
-                                                })
-                                            else {
-                                                error!("did not find type var name {tv:?} in original type vars {:?}", c.type_parameters);
-                                                return Ok(PythonType::Unknown);
-                                            };
-
-                                            let type_var_value = c
-                                                .specialized
-                                                .get(type_var_index)
-                                                .unwrap_or(&PythonType::Any);
-                                            type_var_value.clone()
-                                        }
-                                        _ => ret_type,
-                                    }
-                                }
-                                _ => PythonType::Unknown,
-                            }
+                            self.get_item_type(&value_type, &s.slice, symbol_table, scope_id)?
                         }
                     }
-                    PythonType::Instance(ref c) => {
-                        let lookup_on_class =
-                            self.lookup_on_class(symbol_table, &c.class_type, "__getitem__");
-
-                        match lookup_on_class {
-                            Some(PythonType::Callable(callable)) => {
-                                let ret_type = self.resolve_generics(
-                                    &callable.return_type,
-                                    &c.class_type.type_parameters,
-                                    &c.specialized_type_parameters,
-                                );
-                                match ret_type {
-                                    PythonType::TypeVar(ref tv) => {
-                                        // 1. Get the index of this type var in the class type
-                                        //    parameters
-                                        // 2. Get the value for this index in the
-                                        //    initialized_type_parameters or Unknown if not initialized
-                                        let Some(type_var_index) =
-                                            c.class_type.type_parameters.iter().position(|tp| {
-                                                let Some(type_var) = tp.as_type_var() else {
-                                                    return false;
-                                                };
-                                                type_var.name == tv.name
-                                            })
-                                        else {
-                                            return Ok(PythonType::Unknown);
-                                        };
-
-                                        let type_var_value = c
-                                            .specialized_type_parameters
-                                            .get(type_var_index)
-                                            .unwrap_or(&PythonType::Any);
-                                        type_var_value.clone()
-                                    }
-                                    _ => return Ok(ret_type),
-                                }
-                            }
-                            _ => PythonType::Unknown,
-                        }
+                    PythonType::Instance(_) => {
+                        self.get_item_type(&value_type, &s.slice, symbol_table, scope_id)?
                     }
                     _ => PythonType::Unknown,
                 };
@@ -517,7 +496,9 @@ impl<'a> TypeEvaluator<'a> {
                 // }
                 return Ok(typ);
             }
-            ast::Expression::Slice(_) => Ok(PythonType::Unknown),
+            ast::Expression::Slice(_) => Ok(self
+                .get_builtin_type("slice")
+                .unwrap_or(PythonType::Unknown)),
             ast::Expression::Await(a) => {
                 let awaited_type = self.get_type(&a.value, symbol_table, scope_id)?;
                 let typ = match awaited_type {
@@ -527,11 +508,25 @@ impl<'a> TypeEvaluator<'a> {
 
                 Ok(typ)
             }
-            ast::Expression::Compare(_) => Ok(PythonType::Unknown),
+            ast::Expression::Compare(c) => {
+                // `a < b < c` is evaluated as `a < b and b < c`
+                let mut left_type = self.get_type(&c.left, symbol_table, scope_id)?;
+                let mut results = Vec::with_capacity(c.ops.len());
+                for (op, comparator) in c.ops.iter().zip(c.comparators.iter()) {
+                    let right_type = self.get_type(comparator, symbol_table, scope_id)?;
+                    results.push(
+                        self.compare_result_type(&left_type, &right_type, op, symbol_table)
+                            .unwrap_or(PythonType::Unknown),
+                    );
+                    left_type = right_type;
+                }
+                Ok(union_of(results).unwrap_or(PythonType::Unknown))
+            }
             ast::Expression::Lambda(_) => Ok(PythonType::Unknown),
             ast::Expression::IfExp(_) => Ok(PythonType::Unknown),
             ast::Expression::JoinedStr(_) => Ok(self.get_builtin_type("str").expect("typeshed")),
             ast::Expression::FormattedValue(f) => self.get_type(&f.value, symbol_table, scope_id),
+            ast::Expression::Invalid(_) => Ok(PythonType::Unknown),
         };
 
         tracing::debug!("get type result: {:?}", r);
@@ -561,16 +556,14 @@ impl<'a> TypeEvaluator<'a> {
                 // 2. Module is preferred over local scope so we first check module scope and
                 //    then local scope.
                 //    https://peps.python.org/pep-0563/#backwards-compatibility
-                ast::ConstantValue::Str(_) => {
-                    let source = self.build_manager.files.get(&symbol_table.id).unwrap();
-                    let value = c.get_value(&source.source);
-                    let mut parser = Parser::new(&value);
+                ast::ConstantValue::Str(ref value) => {
+                    let mut parser = Parser::new(value);
                     // Wrap the parsing logic inside a `catch_unwind` block
                     let parse_result = catch_unwind(AssertUnwindSafe(|| parser.parse()));
 
                     let module = match parse_result {
-                        Ok(Ok(module)) => module,
-                        Ok(Err(_)) => {
+                        Ok((module, errors)) if errors.is_empty() => module,
+                        Ok(_) => {
                             trace!("parsing annotation failed");
                             return PythonType::Unknown;
                         }
@@ -602,28 +595,29 @@ impl<'a> TypeEvaluator<'a> {
                 // This is a generic type
                 let typ = self
                     .get_type(&s.value, symbol_table, scope_id)
-                    .unwrap_or_else(|_| PythonType::Unknown);
+                    .unwrap_or(PythonType::Unknown);
                 let Some(class_type) = typ.as_class() else {
                     error!("subscript value is unknown {}", typ,);
                     return PythonType::Unknown;
                 };
                 if class_type.details.special {
                     return match class_type.details.name.as_str() {
-                        "Literal" => self.handle_literal_type(s),
+                        "Literal" => self.handle_literal_type(s, symbol_table, scope_id),
                         "Union" => {
                             // try to convert subscript value into tuple and send the tuple
                             // items as parameters to union type
                             let union_parameters = match &s.slice {
-                                Expression::Tuple(t) => &t.elements,
-                                _ => todo!(),
+                                Expression::Tuple(t) => t.elements.clone(),
+                                other => vec![other.clone()],
                             };
-                            self.handle_union_type(union_parameters.to_vec())
+                            self.handle_union_type(union_parameters, symbol_table, scope_id)
                         }
                         "Optional" => {
                             let inner_value =
                                 self.get_annotation_type(&s.slice, symbol_table, scope_id);
                             PythonType::Optional(Box::new(inner_value))
                         }
+                        "Callable" => self.handle_callable_type(s, symbol_table, scope_id),
                         _ => PythonType::Any,
                     };
                 }
@@ -671,7 +665,7 @@ impl<'a> TypeEvaluator<'a> {
                     ast::BinaryOperator::BitOr => {
                         // flatten the bit or expression if the left and right are also bit or
                         let union_parameters = self.flatten_bit_or(b);
-                        self.handle_union_type(union_parameters)
+                        self.handle_union_type(union_parameters, symbol_table, scope_id)
                     }
                     // TODO: check if other binary operators are allowed
                     _ => todo!(),
@@ -777,6 +771,7 @@ impl<'a> TypeEvaluator<'a> {
             };
             decl
         };
+        let decl = self.first_overload(symbol, decl);
         let decl_scope = decl.declaration_path().scope_id;
         let symbol_table = self.get_dec_symbol_table(decl.declaration_path());
         let result = match decl {
@@ -833,11 +828,9 @@ impl<'a> TypeEvaluator<'a> {
                         .unwrap_or_else(|_| panic!("iterating over unknown {:?}", for_stmt));
                     match iter_type {
                         PythonType::Instance(instance_type) => {
-                            let iter_method = match self.lookup_on_class(
-                                &symbol_table,
-                                &instance_type.class_type,
-                                "__iter__",
-                            ) {
+                            let iter_method = match self
+                                .lookup_on_class(&instance_type.class_type, "__iter__")
+                            {
                                 Some(PythonType::Callable(c)) => c,
                                 Some(other) => panic!("iter method was not callable: {}", other),
                                 None => panic!("next method not found"),
@@ -854,11 +847,9 @@ impl<'a> TypeEvaluator<'a> {
                                 panic!("iter method return type is not class");
                             };
 
-                            let next_method = match self.lookup_on_class(
-                                &symbol_table,
-                                &iter_method_type,
-                                "__next__",
-                            ) {
+                            let next_method = match self
+                                .lookup_on_class(&iter_method_type, "__next__")
+                            {
                                 Some(PythonType::Callable(c)) => c,
                                 Some(other) => panic!("next method was not callable: {}", other),
                                 None => panic!("next method not found"),
@@ -929,8 +920,10 @@ impl<'a> TypeEvaluator<'a> {
                 match &a.symbol_name {
                     Some(name) => {
                         trace!("finding alias with name {name:?}");
-                        let import_result =
-                            a.import_result.clone().expect("import result not found");
+                        // Unresolved imports are reported by the checker
+                        let Some(import_result) = a.import_result.clone() else {
+                            return PythonType::Unknown;
+                        };
                         trace!("import result {:?}", import_result);
                         for id in import_result.resolved_ids.iter() {
                             trace!("checking path {:?}", id);
@@ -1041,35 +1034,35 @@ impl<'a> TypeEvaluator<'a> {
             }));
         }
         let mut bases = vec![];
-        match &class_symbol.class_node {
-            Some(ref b) => {
-                for base in b.bases.iter() {
-                    bases.push(base);
-                }
+        if let Some(ref b) = &class_symbol.class_node {
+            for base in b.bases.iter() {
+                bases.push(base);
             }
-            None => {}
         };
+        // Keywords only add type parameters and are not part of the class hierarchy
+        let base_count = bases.len();
         // Bases can also add generic type parameters to the class
         // For example: class A(metaclass=Generic)
-        match &class_symbol.class_node {
-            Some(ref k) => {
-                for keyword in k.keywords.iter() {
-                    bases.push(&keyword.value);
-                }
+        if let Some(ref k) = &class_symbol.class_node {
+            for keyword in k.keywords.iter() {
+                bases.push(&keyword.value);
             }
-            None => {}
         };
 
         let mut class_def_type_parameters = vec![];
         let mut base_classes = vec![];
         let mut specialized_type_parameters = vec![];
-        for base_class in bases {
+        for (index, base_class) in bases.into_iter().enumerate() {
             let base_type = self.get_type(base_class, symbol_table, class_decl_scope);
             let Ok(PythonType::Class(c)) = base_type else {
                 continue;
             };
+            let is_base = index < base_count;
             let Some(possible_type_parameter) = base_class.as_subscript() else {
-                class_def_type_parameters.extend(c.type_parameters);
+                class_def_type_parameters.extend(c.type_parameters.clone());
+                if is_base {
+                    base_classes.push(c);
+                }
                 continue;
             };
             match &possible_type_parameter.slice {
@@ -1122,7 +1115,9 @@ impl<'a> TypeEvaluator<'a> {
                     }
                 }
             };
-            base_classes.push(c);
+            if is_base {
+                base_classes.push(c);
+            }
         }
 
         Ok(PythonType::Class(ClassType::new(
@@ -1133,9 +1128,9 @@ impl<'a> TypeEvaluator<'a> {
         )))
     }
 
-    fn get_sequence_type_from_elements(
+    fn get_sequence_type_from_elements<'e>(
         &self,
-        elements: &Vec<ast::Expression>,
+        elements: impl IntoIterator<Item = &'e ast::Expression>,
         symbol_table: &SymbolTable,
         scope_id: u32,
     ) -> PythonType {
@@ -1164,7 +1159,7 @@ impl<'a> TypeEvaluator<'a> {
         }
         let builtins_symbol_table = self.get_symbol_table(&Id(0));
         let builtin_symbol = builtins_symbol_table.lookup_in_scope(name, 0)?;
-        let decl = builtin_symbol.last_declaration();
+        let decl = self.first_overload(builtin_symbol, builtin_symbol.last_declaration());
         let found_declaration = match decl {
             Declaration::Class(c) => {
                 let decl_scope = decl.declaration_path().scope_id;
@@ -1186,6 +1181,26 @@ impl<'a> TypeEvaluator<'a> {
         Some(found_declaration)
     }
 
+    // TODO: Overloads are not resolved for calls yet. Stubs declare the most
+    // specific overload first so that one is used.
+    fn first_overload<'s>(
+        &self,
+        symbol: &'s SymbolTableNode,
+        decl: &'s Declaration,
+    ) -> &'s Declaration {
+        match symbol.declarations.first() {
+            Some(first @ Declaration::Function(_))
+                if decl.is_function()
+                    && self
+                        .get_dec_symbol_table(decl.declaration_path())
+                        .is_pyi_file() =>
+            {
+                first
+            }
+            _ => decl,
+        }
+    }
+
     /// This function flattens a chain of bit or expressions
     /// For example: a | b | c | d
     /// will be flattened to [a, b, c, d]
@@ -1225,8 +1240,17 @@ impl<'a> TypeEvaluator<'a> {
     /// expressions are the parameters of the union type
     /// in case of t1 | t2 | t3, expressions are [t1, t2, t3]
     /// and in case of Union[t1, t2, t3], expressions are [t1, t2, t3]
-    fn handle_union_type(&self, expressions: Vec<Expression>) -> PythonType {
-        PythonType::Unknown
+    fn handle_union_type(
+        &self,
+        expressions: Vec<Expression>,
+        symbol_table: &SymbolTable,
+        scope_id: u32,
+    ) -> PythonType {
+        let members = expressions
+            .iter()
+            .map(|e| self.get_annotation_type(e, symbol_table, scope_id))
+            .collect();
+        union_of(members).unwrap_or(PythonType::Unknown)
     }
 
     /// TODO: Need to complete this when types are more complete
@@ -1236,116 +1260,1192 @@ impl<'a> TypeEvaluator<'a> {
     }
 
     // https://peps.python.org/pep-0586
-    fn handle_literal_type(&self, s: &ast::Subscript) -> PythonType {
+    fn handle_literal_type(
+        &self,
+        s: &ast::Subscript,
+        symbol_table: &SymbolTable,
+        scope_id: u32,
+    ) -> PythonType {
         // Only simple parameters are allowed for literal type:
         // https://peps.python.org/pep-0586/#legal-and-illegal-parameterizations
-        let value = self.get_literal_value_from_param(&s.slice.clone());
-        if value.len() > 1 {
-            todo!("MultiValue literal type is not supported yet")
-        }
+        let values = self.get_literal_value_from_param(&s.slice, symbol_table, scope_id);
+        let members = values
+            .into_iter()
+            .map(|literal_value| match literal_value {
+                LiteralValue::None => PythonType::None,
+                literal_value => {
+                    PythonType::LiteralValue(super::types::KnownValue { literal_value })
+                }
+            })
+            .collect();
+        union_of(members).unwrap_or(PythonType::Unknown)
+    }
 
-        PythonType::LiteralValue(super::types::KnownValue {
-            literal_value: value.last().unwrap().clone(),
-        })
+    /// https://docs.python.org/3/library/typing.html#annotating-callable-objects
+    /// `Callable[[t1, t2], r]` takes positional only parameters of types t1 and t2
+    /// and `Callable[..., r]` takes any arguments
+    fn handle_callable_type(
+        &self,
+        s: &ast::Subscript,
+        symbol_table: &SymbolTable,
+        scope_id: u32,
+    ) -> PythonType {
+        let Expression::Tuple(t) = &s.slice else {
+            return PythonType::Unknown;
+        };
+        let [parameters, return_annotation] = t.elements.as_slice() else {
+            return PythonType::Unknown;
+        };
+        let signature = match parameters {
+            Expression::List(l) => l
+                .elements
+                .iter()
+                .map(|e| {
+                    let typ = self.get_annotation_type(e, symbol_table, scope_id);
+                    CallableArgs::PositionalOnly(Parameter::new(String::new(), typ, false))
+                })
+                .collect(),
+            Expression::Constant(c) if matches!(c.value, ast::ConstantValue::Ellipsis) => vec![
+                CallableArgs::Args(Parameter::new(String::new(), PythonType::Any, false)),
+                CallableArgs::KwArgs(Parameter::new(String::new(), PythonType::Any, false)),
+            ],
+            _ => return PythonType::Unknown,
+        };
+        let return_type = self.get_annotation_type(return_annotation, symbol_table, scope_id);
+        PythonType::Callable(Box::new(CallableType::new(
+            "Callable".to_string(),
+            signature,
+            return_type,
+            false,
+            false,
+            FunctionKind::Function,
+        )))
     }
 
-    /// Write a function that takes in an expression which is a parameter to a
-    /// literal type and returns the LiteralValue of the parameter.
-    /// Literal values might contain a tuple, that's why the return type is a
-    /// vector.
-    pub fn get_literal_value_from_param(&self, expr: &Expression) -> Vec<LiteralValue> {
+    /// Takes an expression which is a parameter to a literal type and returns
+    /// the values of the parameter. `Literal[1, 2]` and nested literals like
+    /// `Literal[Literal[1], 2]` have more than one value.
+    /// Illegal parameters have no values.
+    pub fn get_literal_value_from_param(
+        &self,
+        expr: &Expression,
+        symbol_table: &SymbolTable,
+        scope_id: u32,
+    ) -> Vec<LiteralValue> {
         let val = match expr {
-            Expression::Constant(c) => {
-                match c.value.clone() {
-                    ast::ConstantValue::Bool(_) => LiteralValue::Bool,
-                    ast::ConstantValue::Int => LiteralValue::Int,
-                    ast::ConstantValue::Float => LiteralValue::Float,
-                    ast::ConstantValue::Str(_) => LiteralValue::Str,
-                    ast::ConstantValue::Bytes => LiteralValue::Bytes,
-                    ast::ConstantValue::None => LiteralValue::None,
-                    // Tuple is illegal if it has parentheses, otherwise it's allowed and the output
-                    // a multiValued type Currently even mypy does not support
-                    // this, who am I to do it? https://mypy-play.net/?mypy=latest&python=3.10&gist=0df0421d5c85f3b75f65a51cae8616ce
-                    ast::ConstantValue::Tuple => {
-                        LiteralValue::Int
-                        // if t.len() == 1 {
-                        //     match t[0].value.clone() {
-                        //         ast::ConstantValue::Bool => LiteralValue::Bool,
-                        //         ast::ConstantValue::Int => LiteralValue::Int,
-                        //         ast::ConstantValue::Float => LiteralValue::Float,
-                        //         ast::ConstantValue::Str => LiteralValue::Str,
-                        //         ast::ConstantValue::Bytes => LiteralValue::Bytes,
-                        //         ast::ConstantValue::None => LiteralValue::None,
-                        //         _ => panic!("Tuple type with illegal parameter"),
-                        //     }
-                        // } else {
-                        //     let literal_values = t
-                        //         .iter()
-                        //         .map(|c| match c.value {
-                        //             ast::ConstantValue::Bool => LiteralValue::Bool,
-                        //             ast::ConstantValue::Int => LiteralValue::Int,
-                        //             ast::ConstantValue::Float => LiteralValue::Float,
-                        //             ast::ConstantValue::Str => LiteralValue::Str,
-                        //             ast::ConstantValue::Bytes => LiteralValue::Bytes,
-                        //             ast::ConstantValue::None => LiteralValue::None,
-                        //             _ => panic!("Tuple type with illegal parameter"),
-                        //         })
-                        //         .collect();
-                        //     return literal_values;
-                        // }
-                    }
-                    // Illegal parameter
-                    ast::ConstantValue::Ellipsis => {
-                        panic!("Literal type with ellipsis value is not supported")
-                    }
-                    ast::ConstantValue::Complex => {
-                        panic!("Literal type with complex value is not supported")
-                    }
+            Expression::Constant(c) => match &c.value {
+                ast::ConstantValue::Bool(b) => LiteralValue::Bool(*b),
+                ast::ConstantValue::Int(i) => LiteralValue::Int(i.clone()),
+                ast::ConstantValue::Str(s) => LiteralValue::Str(s.clone()),
+                ast::ConstantValue::Bytes(b) => LiteralValue::Bytes(b.clone()),
+                ast::ConstantValue::None => LiteralValue::None,
+                ast::ConstantValue::Float(_)
+                | ast::ConstantValue::Complex { .. }
+                | ast::ConstantValue::Ellipsis
+                | ast::ConstantValue::Tuple => return vec![],
+            },
+            // Negative integers are allowed
+            Expression::UnaryOp(u) if matches!(u.op, ast::UnaryOperator::USub) => {
+                match &u.operand {
+                    Expression::Constant(c) => match &c.value {
+                        ast::ConstantValue::Int(i) => LiteralValue::Int(-i.clone()),
+                        _ => return vec![],
+                    },
+                    _ => return vec![],
                 }
             }
-            // Only can be enum values
-            Expression::Attribute(a) => {
-                let value = match &a.value {
-                    Expression::Name(n) => &n.id,
-                    _ => panic!("Literal type with attribute value can only be a name"),
-                };
-                LiteralValue::Str
+            Expression::Tuple(t) => {
+                return t
+                    .elements
+                    .iter()
+                    .flat_map(|e| self.get_literal_value_from_param(e, symbol_table, scope_id))
+                    .collect();
             }
+            // Only can be enum values
+            Expression::Attribute(a) => match self.get_type(expr, symbol_table, scope_id) {
+                Ok(PythonType::LiteralValue(v))
+                    if matches!(v.literal_value, LiteralValue::EnumMember(..)) =>
+                {
+                    v.literal_value
+                }
+                _ => return vec![],
+            },
             Expression::Subscript(s) => {
                 match &s.value {
-                    Expression::Name(n) => {
-                        if !self.is_literal(n.id.clone()) {
-                            panic!("{}", LITERAL_TYPE_PARAMETER_MSG)
-                        }
+                    Expression::Name(n) if self.is_literal(n.id.clone()) => {
                         // When there is a literal inside a literal we flatten it
-                        return self.get_literal_value_from_param(&s.slice);
+                        return self.get_literal_value_from_param(&s.slice, symbol_table, scope_id);
                     }
-                    _ => panic!("{}", LITERAL_TYPE_PARAMETER_MSG),
+                    _ => return vec![],
                 };
             }
             // Illegal parameter
-            _ => {
-                panic!("Literal type with illegal parameter, can only be a constant value or enum")
-            }
+            _ => return vec![],
         };
 
         vec![val]
     }
 
+    /// The literal type of an enum member accessed on its class, e.g. `Color.RED`.
+    /// Members are the variables assigned in the body of a subclass of `Enum`.
+    fn enum_member_literal(&self, c: &ClassType, name: &str) -> Option<LiteralValue> {
+        if name.starts_with('_')
+            || c.details.qual_name == "enum.Enum"
+            || !self
+                .lookup_order(c)
+                .iter()
+                .any(|base| base.details.qual_name == "enum.Enum")
+        {
+            return None;
+        }
+        let class_symbol_table = self.get_dec_symbol_table(&c.details.declaration_path);
+        let scope = class_symbol_table.get_scope_by_id(c.details.class_scope_id)?;
+        let symbol = scope.symbols().find(|s| s.name == name)?;
+        match symbol.last_declaration() {
+            Declaration::Variable(_) => Some(LiteralValue::EnumMember(c.clone(), name.to_string())),
+            _ => None,
+        }
+    }
+
+    /// The type of `left op right`. The dunder method of the operator is looked up on
+    /// the left operand and the reflected method on the right operand is used when
+    /// the left operand does not support the right one.
+    /// Returns None when the operands do not support the operator.
     pub fn bin_op_result_type(
         &self,
-        t1: &PythonType,
-        t2: &PythonType,
+        left: &PythonType,
+        right: &PythonType,
         op: &ast::BinaryOperator,
-    ) -> PythonType {
-        // Dummy
-        t1.clone()
+        symbol_table: &SymbolTable,
+    ) -> Option<PythonType> {
+        let (method, reflected) = binary_operator_methods(op);
+        self.operation_result_type(left, right, method, reflected, symbol_table)
+    }
+
+    /// The type of `left op= right`. The in-place dunder method is tried before
+    /// falling back to the binary operator.
+    pub fn aug_assign_result_type(
+        &self,
+        left: &PythonType,
+        right: &PythonType,
+        op: &ast::BinaryOperator,
+        symbol_table: &SymbolTable,
+    ) -> Option<PythonType> {
+        let (method, _) = binary_operator_methods(op);
+        let inplace_method = format!("__i{}", &method[2..]);
+        let mut results = vec![];
+        for l in union_members(left) {
+            for r in union_members(right) {
+                let result = match self.call_operator_method(&l, &inplace_method, &r, symbol_table)
+                {
+                    Some(t) => t,
+                    None => self.bin_op_result_type(&l, &r, op, symbol_table)?,
+                };
+                results.push(result);
+            }
+        }
+        union_of(results)
+    }
+
+    /// The type of `left op right` for a single comparison of a chain.
+    /// Returns None when the operands do not support the operator.
+    pub fn compare_result_type(
+        &self,
+        left: &PythonType,
+        right: &PythonType,
+        op: &ast::ComparisonOperator,
+        symbol_table: &SymbolTable,
+    ) -> Option<PythonType> {
+        let bool_type = || self.get_builtin_type("bool");
+        let (method, reflected) = match op {
+            ast::ComparisonOperator::Is | ast::ComparisonOperator::IsNot => return bool_type(),
+            // Membership tests fall back to iterating over the container
+            ast::ComparisonOperator::In | ast::ComparisonOperator::NotIn => {
+                let supported = union_members(right).iter().all(|container| {
+                    if matches!(container, PythonType::Any | PythonType::Unknown) {
+                        return true;
+                    }
+                    let Some(class) = self.operand_class(container) else {
+                        return false;
+                    };
+                    ["__contains__", "__iter__", "__getitem__"]
+                        .iter()
+                        .any(|m| self.lookup_on_class(&class, m).is_some())
+                });
+                return if supported { bool_type() } else { None };
+            }
+            ast::ComparisonOperator::Eq => ("__eq__", "__eq__"),
+            ast::ComparisonOperator::NotEq => ("__ne__", "__ne__"),
+            ast::ComparisonOperator::Lt => ("__lt__", "__gt__"),
+            ast::ComparisonOperator::LtE => ("__le__", "__ge__"),
+            ast::ComparisonOperator::Gt => ("__gt__", "__lt__"),
+            ast::ComparisonOperator::GtE => ("__ge__", "__le__"),
+        };
+        self.operation_result_type(left, right, method, reflected, symbol_table)
+    }
+
+    /// The type of `op operand`. Returns None when the operand does not support the operator.
+    pub fn unary_op_result_type(
+        &self,
+        operand: &PythonType,
+        op: &ast::UnaryOperator,
+        symbol_table: &SymbolTable,
+    ) -> Option<PythonType> {
+        let method = match op {
+            ast::UnaryOperator::Not => return self.get_builtin_type("bool"),
+            ast::UnaryOperator::Invert => "__invert__",
+            ast::UnaryOperator::UAdd => "__pos__",
+            ast::UnaryOperator::USub => "__neg__",
+        };
+        let mut results = vec![];
+        for member in union_members(operand) {
+            if matches!(member, PythonType::Any | PythonType::Unknown) {
+                results.push(PythonType::Unknown);
+                continue;
+            }
+            let class = self.operand_class(&member)?;
+            let method_type = self.lookup_on_class(&class, method)?;
+            let result = match method_type {
+                PythonType::Callable(c) => self.specialize_member_type(&c.return_type, &member),
+                _ => PythonType::Unknown,
+            };
+            results.push(result);
+        }
+        union_of(results)
+    }
+
+    /// The type of `value[slice]` from the `__getitem__` overload that accepts the slice
+    fn get_item_type(
+        &self,
+        value_type: &PythonType,
+        slice: &Expression,
+        symbol_table: &SymbolTable,
+        scope_id: u32,
+    ) -> Result<PythonType> {
+        let slice_type = self.get_type(slice, symbol_table, scope_id)?;
+        Ok(self
+            .call_operator_method(value_type, "__getitem__", &slice_type, symbol_table)
+            .unwrap_or(PythonType::Unknown))
+    }
+
+    /// Distributes the operation over the members of union operands
+    fn operation_result_type(
+        &self,
+        left: &PythonType,
+        right: &PythonType,
+        method: &str,
+        reflected: &str,
+        symbol_table: &SymbolTable,
+    ) -> Option<PythonType> {
+        let mut results = vec![];
+        for l in union_members(left) {
+            for r in union_members(right) {
+                if matches!(l, PythonType::Any | PythonType::Unknown)
+                    || matches!(r, PythonType::Any | PythonType::Unknown)
+                {
+                    results.push(PythonType::Unknown);
+                    continue;
+                }
+                // `x + y` where both are `AnyStr` keeps the type var when every
+                // constraint supports the operator
+                if let (PythonType::TypeVar(t1), PythonType::TypeVar(t2)) = (&l, &r) {
+                    if t1.name == t2.name && !t1.bounds.is_empty() {
+                        for bound in &t1.bounds {
+                            self.operation_result_type(
+                                bound,
+                                bound,
+                                method,
+                                reflected,
+                                symbol_table,
+                            )?;
+                        }
+                        results.push(l.clone());
+                        continue;
+                    }
+                }
+                let result = self
+                    .call_operator_method(&l, method, &r, symbol_table)
+                    .or_else(|| self.call_operator_method(&r, reflected, &l, symbol_table))?;
+                results.push(result);
+            }
+        }
+        union_of(results)
+    }
+
+    /// Calls the dunder method `method` of `receiver` with `argument` and returns the
+    /// return type. Returns None when the method does not exist or does not accept the
+    /// argument.
+    fn call_operator_method(
+        &self,
+        receiver: &PythonType,
+        method: &str,
+        argument: &PythonType,
+        symbol_table: &SymbolTable,
+    ) -> Option<PythonType> {
+        let class = self.operand_class(receiver)?;
+        // The first overload that accepts the argument is used
+        self.lookup_overloads_on_class(&class, method)
+            .into_iter()
+            .find_map(|overload| {
+                // e.g. `__eq__ = None` disables the operator
+                let PythonType::Callable(callable) = overload else {
+                    return None;
+                };
+                // The first parameter is self, overloads can restrict its type
+                let self_type = callable.signature.first()?.get_type();
+                if !self.is_assignable(receiver, &self.specialize_member_type(self_type, receiver))
+                {
+                    return None;
+                }
+                let parameter = callable.signature.iter().skip(1).find(|arg| {
+                    matches!(
+                        arg,
+                        CallableArgs::PositionalOnly(_)
+                            | CallableArgs::Positional(_)
+                            | CallableArgs::Args(_)
+                    )
+                })?;
+                let parameter_type = self.specialize_member_type(parameter.get_type(), receiver);
+                self.is_assignable(argument, &parameter_type)
+                    .then(|| self.specialize_member_type(&callable.return_type, receiver))
+            })
+    }
+
+    /// The class to look up the dunder methods of an operand on
+    fn operand_class(&self, t: &PythonType) -> Option<ClassType> {
+        match t {
+            PythonType::Class(c) => Some(c.clone()),
+            PythonType::Instance(i) => Some(i.class_type.clone()),
+            PythonType::LiteralValue(v) => match self.literal_class(&v.literal_value)? {
+                PythonType::Class(c) => Some(c),
+                _ => self.object_class(),
+            },
+            PythonType::TypeVar(t) if !t.bounds.is_empty() => self.operand_class(&t.bounds[0]),
+            PythonType::None
+            | PythonType::TypeVar(_)
+            | PythonType::Callable(_)
+            | PythonType::Coroutine(_)
+            | PythonType::Module(_) => self.object_class(),
+            PythonType::Any
+            | PythonType::Unknown
+            | PythonType::MultiValue(_)
+            | PythonType::Optional(_) => None,
+        }
+    }
+
+    fn object_class(&self) -> Option<ClassType> {
+        match self.get_builtin_type("object")? {
+            PythonType::Class(c) => Some(c),
+            _ => None,
+        }
+    }
+
+    /// Replaces the type parameters of the receiver class in the type of a member
+    /// with the type arguments of the receiver. e.g. `list[_T]` becomes `list[int]`
+    /// for a receiver of type `list[int]`.
+    fn specialize_member_type(&self, t: &PythonType, receiver: &PythonType) -> PythonType {
+        let Some(class) = self.nominal_class(receiver) else {
+            return t.clone();
+        };
+        let arguments = self.type_arguments(receiver);
+        match t {
+            PythonType::TypeVar(type_var) => class
+                .type_parameters
+                .iter()
+                .position(|p| matches!(p, PythonType::TypeVar(p) if p.name == type_var.name))
+                .and_then(|index| arguments.get(index).cloned())
+                .unwrap_or(PythonType::Unknown),
+            PythonType::Class(c) if !c.specialized.is_empty() => {
+                let mut specialized_class = c.clone();
+                specialized_class.specialized = c
+                    .specialized
+                    .iter()
+                    .map(|arg| self.specialize_member_type(arg, receiver))
+                    .collect();
+                PythonType::Class(specialized_class)
+            }
+            PythonType::Instance(i) => {
+                let mut specialized_instance = i.clone();
+                specialized_instance.specialized_type_parameters = i
+                    .specialized_type_parameters
+                    .iter()
+                    .map(|arg| self.specialize_member_type(arg, receiver))
+                    .collect();
+                PythonType::Instance(specialized_instance)
+            }
+            PythonType::MultiValue(members) => union_of(
+                members
+                    .iter()
+                    .map(|m| self.specialize_member_type(m, receiver))
+                    .collect(),
+            )
+            .unwrap_or(PythonType::Unknown),
+            PythonType::Optional(inner) => {
+                PythonType::Optional(Box::new(self.specialize_member_type(inner, receiver)))
+            }
+            PythonType::Callable(c) => {
+                let mut callable = c.clone();
+                for arg in callable.signature.iter_mut() {
+                    let parameter = arg.parameter_mut();
+                    parameter.typ = self.specialize_member_type(&parameter.typ, receiver);
+                }
+                callable.return_type = self.specialize_member_type(&c.return_type, receiver);
+                PythonType::Callable(callable)
+            }
+            _ => t.clone(),
+        }
+    }
+
+    /// Whether the `|` creates a union type, e.g. `int | None`, instead of applying
+    /// the operator to values
+    pub fn is_union_type_expression(
+        &self,
+        b: &ast::BinOp,
+        symbol_table: &SymbolTable,
+        scope_id: u32,
+    ) -> bool {
+        matches!(b.op, ast::BinaryOperator::BitOr)
+            && (self.is_type_expression(&b.left, symbol_table, scope_id)
+                || self.is_type_expression(&b.right, symbol_table, scope_id))
+    }
+
+    /// Whether the expression refers to a class rather than a value. Values of
+    /// literals are evaluated to their class so the declaration has to be checked.
+    pub fn is_type_expression(
+        &self,
+        expr: &Expression,
+        symbol_table: &SymbolTable,
+        scope_id: u32,
+    ) -> bool {
+        match expr {
+            Expression::Constant(c) => matches!(c.value, ast::ConstantValue::None),
+            Expression::Subscript(s) => self.is_type_expression(&s.value, symbol_table, scope_id),
+            Expression::BinOp(b) => self.is_union_type_expression(b, symbol_table, scope_id),
+            Expression::Name(n) => {
+                let symbol = symbol_table.lookup_in_scope(&n.id, scope_id);
+                match symbol.map(|s| s.last_declaration()) {
+                    Some(
+                        Declaration::Class(_)
+                        | Declaration::TypeAlias(_)
+                        | Declaration::TypeParameter(_),
+                    ) => true,
+                    Some(Declaration::Alias(_)) => matches!(
+                        self.get_type(expr, symbol_table, scope_id),
+                        Ok(PythonType::Class(_))
+                    ),
+                    Some(_) => false,
+                    None => self.get_builtin_type(&n.id).is_some_and(|t| t.is_class()),
+                }
+            }
+            Expression::Attribute(a) => {
+                matches!(
+                    self.get_type(&a.value, symbol_table, scope_id),
+                    Ok(PythonType::Module(_))
+                ) && matches!(
+                    self.get_type(expr, symbol_table, scope_id),
+                    Ok(PythonType::Class(_))
+                )
+            }
+            _ => false,
+        }
     }
 
     pub fn is_literal(&self, name: String) -> bool {
         name.as_str() == "Literal"
     }
 
+    /// Sets the types narrowed by the code flow, see [`Narrowing`]
+    pub fn set_narrowing(&self, narrowing: Option<Narrowing>) {
+        self.narrowing.replace(narrowing);
+    }
+
+    fn get_narrowed_type(
+        &self,
+        name: &ast::Name,
+        symbol_table: &SymbolTable,
+        scope_id: u32,
+    ) -> Option<PythonType> {
+        let narrowing = self.narrowing.borrow();
+        let narrowing = narrowing.as_ref()?;
+        if narrowing.symbol_table_id != symbol_table.id
+            || narrowing.scope_id != scope_id
+            || name.node.start < narrowing.node.start
+            || name.node.end > narrowing.node.end
+        {
+            return None;
+        }
+        narrowing.types.get(&name.id).cloned()
+    }
+
+    /// Returns the narrowed types of names when `test` evaluates to `positive`.
+    /// https://typing.readthedocs.io/en/latest/spec/narrowing.html
+    pub fn narrow_condition(
+        &self,
+        test: &Expression,
+        positive: bool,
+        symbol_table: &SymbolTable,
+        scope_id: u32,
+    ) -> HashMap<String, PythonType> {
+        let mut narrowed = HashMap::new();
+        match test {
+            Expression::UnaryOp(u) if matches!(u.op, ast::UnaryOperator::Not) => {
+                return self.narrow_condition(&u.operand, !positive, symbol_table, scope_id);
+            }
+            Expression::BoolOp(b) => {
+                let all_hold = matches!(b.op, ast::BooleanOperator::And) == positive;
+                let mut branches = b
+                    .values
+                    .iter()
+                    .map(|v| self.narrow_condition(v, positive, symbol_table, scope_id));
+                let first = branches.next().unwrap_or_default();
+                return branches.fold(first, |acc, branch| {
+                    if all_hold {
+                        self.intersect_narrowing(acc, branch)
+                    } else {
+                        self.join_narrowing(&acc, &branch)
+                    }
+                });
+            }
+            // Truthiness
+            Expression::Name(n) if positive => {
+                let declared = self.get_type(test, symbol_table, scope_id).ok();
+                if let Some(t) = declared.and_then(|d| self.remove_none(&d)) {
+                    narrowed.insert(n.id.clone(), t);
+                }
+            }
+            Expression::Compare(c) => {
+                let (Some(name), [op], [comparator]) =
+                    (c.left.as_name(), c.ops.as_slice(), c.comparators.as_slice())
+                else {
+                    return narrowed;
+                };
+                let Ok(declared) = self.get_type(&c.left, symbol_table, scope_id) else {
+                    return narrowed;
+                };
+                let is_none = comparator
+                    .as_constant()
+                    .is_some_and(|c| matches!(c.value, ast::ConstantValue::None));
+                let t = match op {
+                    ast::ComparisonOperator::Is | ast::ComparisonOperator::IsNot if is_none => {
+                        if matches!(op, ast::ComparisonOperator::Is) == positive {
+                            union_members(&declared)
+                                .iter()
+                                .any(|m| m.is_none() || m.is_any() || m.is_unknown())
+                                .then_some(PythonType::None)
+                        } else {
+                            self.remove_none(&declared)
+                        }
+                    }
+                    ast::ComparisonOperator::Eq | ast::ComparisonOperator::NotEq
+                        if matches!(op, ast::ComparisonOperator::Eq) == positive =>
+                    {
+                        self.literal_type(comparator, symbol_table, scope_id)
+                            .and_then(|literal| {
+                                self.filter_members(&declared, |m| self.is_assignable(&literal, m))
+                            })
+                    }
+                    // Only the literal member is excluded, other members can still be equal
+                    ast::ComparisonOperator::Eq | ast::ComparisonOperator::NotEq => self
+                        .literal_type(comparator, symbol_table, scope_id)
+                        .filter(|literal| literal.is_literal_value())
+                        .and_then(|literal| {
+                            self.filter_members(&declared, |m| !m.type_equal(&literal))
+                        }),
+                    ast::ComparisonOperator::In | ast::ComparisonOperator::NotIn
+                        if matches!(op, ast::ComparisonOperator::In) == positive =>
+                    {
+                        let container = self
+                            .get_type(comparator, symbol_table, scope_id)
+                            .unwrap_or(PythonType::Unknown);
+                        match self.type_arguments(&container).first() {
+                            Some(element) if !element.is_unknown() => {
+                                self.filter_members(&declared, |m| self.is_assignable(element, m))
+                            }
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                };
+                if let Some(t) = t {
+                    narrowed.insert(name.id.clone(), t);
+                }
+            }
+            Expression::Call(call) => {
+                let (Some(func), Some(Expression::Name(n))) =
+                    (call.func.as_name(), call.args.first())
+                else {
+                    return narrowed;
+                };
+                let first_arg = &call.args[0];
+                let Ok(declared) = self.get_type(first_arg, symbol_table, scope_id) else {
+                    return narrowed;
+                };
+                let t = match (func.id.as_str(), call.args.get(1)) {
+                    ("isinstance", Some(class_arg)) => {
+                        let classes =
+                            self.get_isinstance_classes(class_arg, symbol_table, scope_id);
+                        if classes.is_empty() {
+                            None
+                        } else {
+                            self.narrow_to_classes(&declared, &classes, positive)
+                        }
+                    }
+                    ("callable", None) => self.filter_members(&declared, |m| {
+                        let is_callable =
+                            matches!(m, PythonType::Callable(_) | PythonType::Class(_));
+                        if positive {
+                            is_callable || m.is_any() || m.is_unknown()
+                        } else {
+                            !matches!(m, PythonType::Callable(_))
+                        }
+                    }),
+                    _ => None,
+                };
+                if let Some(t) = t {
+                    narrowed.insert(n.id.clone(), t);
+                }
+            }
+            _ => (),
+        }
+        narrowed
+    }
+
+    /// Narrowed types after a `case` pattern matched (or did not match) the
+    /// subject type.
+    pub fn narrow_pattern(
+        &self,
+        subject: &PythonType,
+        pattern: &ast::MatchPattern,
+        positive: bool,
+        symbol_table: &SymbolTable,
+        scope_id: u32,
+    ) -> Option<PythonType> {
+        match pattern {
+            ast::MatchPattern::MatchSingleton(e)
+            | ast::MatchPattern::MatchValue(ast::MatchValue { value: e, .. }) => {
+                let literal = self.literal_type(e, symbol_table, scope_id)?;
+                if literal.is_none() {
+                    if positive {
+                        Some(PythonType::None)
+                    } else {
+                        self.remove_none(subject)
+                    }
+                } else if positive {
+                    self.filter_members(subject, |m| self.is_assignable(&literal, m))
+                } else {
+                    None
+                }
+            }
+            // Only patterns without sub patterns match every instance of the class
+            ast::MatchPattern::MatchClass(c)
+                if positive || (c.patterns.is_empty() && c.kwd_patterns.is_empty()) =>
+            {
+                let classes = self.get_isinstance_classes(&c.cls, symbol_table, scope_id);
+                self.narrow_to_classes(subject, &classes, positive)
+            }
+            ast::MatchPattern::MatchAs(m) => match &m.pattern {
+                Some(p) => self.narrow_pattern(subject, p, positive, symbol_table, scope_id),
+                None => None,
+            },
+            ast::MatchPattern::MatchOr(patterns) if positive => {
+                let mut members = vec![];
+                for p in patterns.iter() {
+                    let t = self.narrow_pattern(subject, p, positive, symbol_table, scope_id)?;
+                    members.push(t);
+                }
+                union_of(members)
+            }
+            _ => None,
+        }
+    }
+
+    /// Classes of the second argument of isinstance
+    fn get_isinstance_classes(
+        &self,
+        expr: &Expression,
+        symbol_table: &SymbolTable,
+        scope_id: u32,
+    ) -> Vec<ClassType> {
+        let elements = match expr {
+            Expression::Tuple(t) => t.elements.iter().collect(),
+            other => vec![other],
+        };
+        let mut classes = vec![];
+        for e in elements {
+            match self.get_type(e, symbol_table, scope_id) {
+                Ok(PythonType::Class(c)) => classes.push(c),
+                // Unknown classes cannot be narrowed to
+                _ => return vec![],
+            }
+        }
+        classes
+    }
+
+    fn narrow_to_classes(
+        &self,
+        declared: &PythonType,
+        classes: &[ClassType],
+        positive: bool,
+    ) -> Option<PythonType> {
+        let instances: Vec<PythonType> = classes
+            .iter()
+            .map(|c| PythonType::Instance(InstanceType::new(c.clone(), vec![])))
+            .collect();
+        let mut members = vec![];
+        for member in union_members(declared) {
+            if member.is_any() || member.is_unknown() {
+                if positive {
+                    members.extend(instances.iter().cloned());
+                } else {
+                    members.push(member);
+                }
+                continue;
+            }
+            let is_instance = instances.iter().any(|i| self.is_assignable(&member, i));
+            if !positive {
+                if !is_instance {
+                    members.push(member);
+                }
+            } else if is_instance {
+                members.push(member);
+            } else {
+                // The value can be an instance of a subclass of the member
+                members.extend(
+                    instances
+                        .iter()
+                        .filter(|i| self.is_assignable(i, &member))
+                        .cloned(),
+                );
+            }
+        }
+        union_of(members)
+    }
+
+    fn filter_members(
+        &self,
+        declared: &PythonType,
+        f: impl Fn(&PythonType) -> bool,
+    ) -> Option<PythonType> {
+        union_of(
+            union_members(declared)
+                .into_iter()
+                .filter(|m| f(m))
+                .collect(),
+        )
+    }
+
+    fn remove_none(&self, declared: &PythonType) -> Option<PythonType> {
+        self.filter_members(declared, |m| !m.is_none())
+    }
+
+    fn literal_type(
+        &self,
+        expr: &Expression,
+        symbol_table: &SymbolTable,
+        scope_id: u32,
+    ) -> Option<PythonType> {
+        if expr.is_tuple() || expr.is_subscript() {
+            return None;
+        }
+        match self
+            .get_literal_value_from_param(expr, symbol_table, scope_id)
+            .pop()?
+        {
+            LiteralValue::None => Some(PythonType::None),
+            literal_value => Some(PythonType::LiteralValue(types::KnownValue {
+                literal_value,
+            })),
+        }
+    }
+
+    /// Narrowing when both maps hold. For names narrowed by both the narrower
+    /// type is used.
+    pub fn intersect_narrowing(
+        &self,
+        mut first: HashMap<String, PythonType>,
+        second: HashMap<String, PythonType>,
+    ) -> HashMap<String, PythonType> {
+        for (name, t) in second {
+            match first.get(&name) {
+                Some(existing) if self.is_assignable(existing, &t) => {}
+                _ => {
+                    first.insert(name, t);
+                }
+            }
+        }
+        first
+    }
+
+    /// Narrowing when either of the maps holds. Only names narrowed by both are
+    /// kept.
+    pub fn join_narrowing(
+        &self,
+        first: &HashMap<String, PythonType>,
+        second: &HashMap<String, PythonType>,
+    ) -> HashMap<String, PythonType> {
+        first
+            .iter()
+            .filter_map(|(name, t1)| {
+                let t2 = second.get(name)?;
+                union_of(vec![t1.clone(), t2.clone()]).map(|t| (name.clone(), t))
+            })
+            .collect()
+    }
+
+    /// Checks whether a value of type `src` can be assigned to a target declared
+    /// with type `dest`.
+    /// https://typing.readthedocs.io/en/latest/spec/concepts.html#assignable-to
+    pub fn is_assignable(&self, src: &PythonType, dest: &PythonType) -> bool {
+        match (src, dest) {
+            // Any and Unknown are assignable in both directions
+            (PythonType::Any | PythonType::Unknown, _) => true,
+            (_, PythonType::Any | PythonType::Unknown) => true,
+            // A union is assignable if every member of it is
+            (PythonType::MultiValue(members), _) => {
+                members.iter().all(|m| self.is_assignable(m, dest))
+            }
+            (PythonType::Optional(inner), _) => {
+                self.is_assignable(&PythonType::None, dest) && self.is_assignable(inner, dest)
+            }
+            (_, PythonType::MultiValue(members)) => {
+                members.iter().any(|m| self.is_assignable(src, m))
+            }
+            (_, PythonType::Optional(inner)) => src.is_none() || self.is_assignable(src, inner),
+            (PythonType::TypeVar(t1), PythonType::TypeVar(t2)) if t1.name == t2.name => true,
+            // A type var is only known to be one of its bounds
+            (PythonType::TypeVar(t), _) => {
+                if t.bounds.is_empty() {
+                    self.is_object(dest)
+                } else {
+                    t.bounds.iter().all(|b| self.is_assignable(b, dest))
+                }
+            }
+            (_, PythonType::TypeVar(t)) => {
+                t.bounds.is_empty() || t.bounds.iter().any(|b| self.is_assignable(src, b))
+            }
+            (PythonType::LiteralValue(v1), PythonType::LiteralValue(v2)) => v1 == v2,
+            (PythonType::LiteralValue(v), PythonType::Class(c))
+                if c.details.special && c.details.name == "LiteralString" =>
+            {
+                matches!(v.literal_value, LiteralValue::Str(_))
+            }
+            // Literals widen to the class of their value
+            (PythonType::LiteralValue(v), _) => match self.literal_class(&v.literal_value) {
+                Some(widened) => self.is_assignable(&widened, dest),
+                None => false,
+            },
+            (PythonType::None, PythonType::None) => true,
+            (PythonType::None, _) => self.is_object(dest),
+            (PythonType::Module(m1), PythonType::Module(m2)) => m1 == m2,
+            (PythonType::Callable(c1), PythonType::Callable(c2)) => {
+                self.is_callable_assignable(c1, c2)
+            }
+            (PythonType::Coroutine(c1), PythonType::Coroutine(c2)) => {
+                self.is_assignable(&c1.return_type, &c2.return_type)
+            }
+            _ => {
+                if self.is_object(dest) {
+                    return true;
+                }
+                // The evaluator does not yet separate class objects from instances for all
+                // values (e.g. constants), so both are compared nominally.
+                match (self.nominal_class(src), self.nominal_class(dest)) {
+                    (Some(src_class), Some(dest_class)) => self.is_class_assignable(
+                        (src_class, self.type_arguments(src)),
+                        (dest_class, self.type_arguments(dest)),
+                    ),
+                    _ => false,
+                }
+            }
+        }
+    }
+
+    /// Classes are passed with their type arguments
+    fn is_class_assignable(
+        &self,
+        (src, src_args): (&ClassType, &[PythonType]),
+        (dest, dest_args): (&ClassType, &[PythonType]),
+    ) -> bool {
+        // Only literal strings are assignable to LiteralString
+        if dest.details.special && dest.details.name == "LiteralString" {
+            return src.details.special && src.details.name == "LiteralString";
+        }
+        // Newer typeshed versions declare `Any` as a class
+        if dest.details.qual_name == "typing.Any" || src.details.qual_name == "typing.Any" {
+            return true;
+        }
+        // Special forms are defined by PEPs and are not checked yet
+        if dest.details.special || src.details.special {
+            return true;
+        }
+        // https://typing.readthedocs.io/en/latest/spec/special-types.html#special-cases-for-float-and-complex
+        if let ("builtins.int", "builtins.float" | "builtins.complex")
+        | ("builtins.float", "builtins.complex") = (
+            src.details.qual_name.as_str(),
+            dest.details.qual_name.as_str(),
+        ) {
+            return true;
+        }
+        let Some(base) = self.find_base_class(src, dest) else {
+            return dest.details.is_protocol
+                && self.is_protocol_assignable(
+                    &PythonType::Instance(InstanceType::new(src.clone(), src_args.to_vec())),
+                    &PythonType::Instance(InstanceType::new(dest.clone(), dest_args.to_vec())),
+                );
+        };
+        // TODO: type arguments of a base class can refer to type parameters of the
+        // subclass and need to be resolved before they can be compared.
+        if base.details.declaration_path != src.details.declaration_path {
+            // Members of the subclass are specialized with its own type arguments,
+            // so generic protocols are compared structurally instead
+            return dest_args.is_empty()
+                || !dest.details.is_protocol
+                || self.is_protocol_assignable(
+                    &PythonType::Instance(InstanceType::new(src.clone(), src_args.to_vec())),
+                    &PythonType::Instance(InstanceType::new(dest.clone(), dest_args.to_vec())),
+                );
+        }
+        // Type parameters are invariant and an unspecialized parameter is Any
+        src_args
+            .iter()
+            .zip(dest_args.iter())
+            .all(|(t1, t2)| self.is_assignable(t1, t2) && self.is_assignable(t2, t1))
+    }
+
+    /// Protocols are assignable from classes that have compatible members.
+    /// https://typing.readthedocs.io/en/latest/spec/protocol.html#protocol-types
+    fn is_protocol_assignable(&self, src: &PythonType, protocol: &PythonType) -> bool {
+        let (Some(src_class), Some(protocol_class)) =
+            (self.nominal_class(src), self.nominal_class(protocol))
+        else {
+            return false;
+        };
+        // Protocols can refer to themselves in their members, e.g. `Iterator.__iter__`.
+        // Comparing the same classes again assumes they are compatible.
+        let key = (
+            src_class.details.declaration_path.clone(),
+            protocol_class.details.declaration_path.clone(),
+        );
+        if self.protocol_assumptions.borrow().contains(&key) {
+            return true;
+        }
+        self.protocol_assumptions.borrow_mut().push(key);
+        let result = self
+            .protocol_members(protocol_class)
+            .iter()
+            .all(|member| self.is_protocol_member_compatible(src, protocol, member));
+        self.protocol_assumptions.borrow_mut().pop();
+        result
+    }
+
+    /// Names of the members declared by the protocol and the protocols it extends
+    pub fn protocol_members(&self, protocol: &ClassType) -> Vec<String> {
+        let mut members = vec![];
+        for class in self.lookup_order(protocol) {
+            if !class.details.is_protocol {
+                continue;
+            }
+            let symbol_table = self.get_dec_symbol_table(&class.details.declaration_path);
+            let Some(scope) = symbol_table.get_scope_by_id(class.details.class_scope_id) else {
+                continue;
+            };
+            for symbol in scope.symbols() {
+                if !PROTOCOL_EXCLUDED_MEMBERS.contains(&symbol.name.as_str())
+                    && !members.contains(&symbol.name)
+                {
+                    members.push(symbol.name.clone());
+                }
+            }
+        }
+        members.sort();
+        members
+    }
+
+    fn is_protocol_member_compatible(
+        &self,
+        src: &PythonType,
+        protocol: &PythonType,
+        member: &str,
+    ) -> bool {
+        let (Some(src_class), Some(protocol_class)) =
+            (self.nominal_class(src), self.nominal_class(protocol))
+        else {
+            return false;
+        };
+        let src_types = self.lookup_overloads_on_class(src_class, member);
+        if src_types.is_empty() {
+            return false;
+        }
+        // Every overload of the protocol has to be implemented by one of the overloads
+        self.lookup_overloads_on_class(protocol_class, member)
+            .iter()
+            .all(|protocol_type| {
+                let protocol_type = self.bind_member_type(protocol_type, protocol);
+                src_types.iter().any(|src_type| {
+                    let src_type = self.bind_member_type(src_type, src);
+                    match (&src_type, &protocol_type) {
+                        (PythonType::Callable(_), PythonType::Callable(_)) => {
+                            self.is_assignable(&src_type, &protocol_type)
+                        }
+                        // A property of the protocol can be implemented with an attribute
+                        (_, PythonType::Callable(p)) if p.is_decorated => {
+                            self.is_assignable(&src_type, &p.return_type)
+                        }
+                        (_, PythonType::Callable(_)) | (PythonType::Callable(_), _) => false,
+                        _ => self.is_assignable(&src_type, &protocol_type),
+                    }
+                })
+            })
+    }
+
+    /// The type of a member accessed on `receiver`. Type parameters of the class are
+    /// replaced by the type arguments of the receiver and methods lose their self parameter.
+    fn bind_member_type(&self, member_type: &PythonType, receiver: &PythonType) -> PythonType {
+        match self.specialize_member_type(member_type, receiver) {
+            PythonType::Callable(mut callable) if !callable.signature.is_empty() => {
+                callable.signature.remove(0);
+                PythonType::Callable(callable)
+            }
+            other => other,
+        }
+    }
+
+    /// Finds the class `target` in the class hierarchy of `c`
+    fn find_base_class(&self, c: &ClassType, target: &ClassType) -> Option<ClassType> {
+        self.lookup_order(c)
+            .into_iter()
+            .find(|base| is_same_class(base, target))
+    }
+
+    /// Callables are assignable if `src` accepts every call `dest` accepts.
+    /// Parameters are paired by kind: positional parameters by position and
+    /// keyword parameters by name, `*args` and `**kwargs` receive the arguments
+    /// left over. Parameter types are contravariant and return types covariant.
+    fn is_callable_assignable(&self, src: &CallableType, dest: &CallableType) -> bool {
+        if !self.is_assignable(&src.return_type, &dest.return_type) {
+            return false;
+        }
+        // `(*args: Any, **kwargs: Any)` is the signature of `Callable[..., R]`
+        // and accepts every signature
+        if let [CallableArgs::Args(args), CallableArgs::KwArgs(kwargs)] = dest.signature.as_slice()
+        {
+            if args.typ.is_any() && kwargs.typ.is_any() {
+                return true;
+            }
+        }
+        let src_positional: Vec<usize> = (0..src.signature.len())
+            .filter(|i| {
+                matches!(
+                    src.signature[*i],
+                    CallableArgs::PositionalOnly(_) | CallableArgs::Positional(_)
+                )
+            })
+            .collect();
+        let src_args = src
+            .signature
+            .iter()
+            .position(|p| matches!(p, CallableArgs::Args(_)));
+        let src_kwargs = src
+            .signature
+            .iter()
+            .position(|p| matches!(p, CallableArgs::KwArgs(_)));
+        // An argument of `dest_param` has to be accepted by `src_param` and
+        // calls that leave out `dest_param` have to be valid for `src`
+        let accepts = |dest_param: &CallableArgs, src_param: &CallableArgs| {
+            self.is_assignable(dest_param.get_type(), src_param.get_type())
+                && (dest_param.is_required() || !src_param.is_required())
+        };
+
+        let mut matched = vec![false; src.signature.len()];
+        let mut position = 0;
+        for dest_param in dest.signature.iter() {
+            let src_index = match dest_param {
+                CallableArgs::PositionalOnly(_) | CallableArgs::Positional(_) => {
+                    let src_index = match src_positional.get(position) {
+                        Some(index) => *index,
+                        None => match src_args {
+                            Some(index) => index,
+                            None => return false,
+                        },
+                    };
+                    position += 1;
+                    // The argument can also be passed by its name
+                    if matches!(dest_param, CallableArgs::Positional(_))
+                        && src_kwargs.is_none()
+                        && !matches!(&src.signature[src_index], CallableArgs::Positional(p) if p.name == dest_param.name())
+                    {
+                        return false;
+                    }
+                    src_index
+                }
+                CallableArgs::Keyword(_) => {
+                    let by_name = src.signature.iter().position(|p| {
+                        matches!(p, CallableArgs::Positional(_) | CallableArgs::Keyword(_))
+                            && p.name() == dest_param.name()
+                    });
+                    match by_name.or(src_kwargs) {
+                        Some(index) => index,
+                        None => return false,
+                    }
+                }
+                CallableArgs::Args(_) => match src_args {
+                    Some(index) => index,
+                    None => return false,
+                },
+                CallableArgs::KwArgs(_) => match src_kwargs {
+                    Some(index) => index,
+                    None => return false,
+                },
+            };
+            matched[src_index] = true;
+            if !accepts(dest_param, &src.signature[src_index]) {
+                return false;
+            }
+        }
+        // Parameters of `src` that calls of `dest` do not pass must be optional
+        src.signature
+            .iter()
+            .zip(matched)
+            .all(|(param, matched)| matched || !param.is_required())
+    }
+
+    fn nominal_class<'b>(&self, t: &'b PythonType) -> Option<&'b ClassType> {
+        match t {
+            PythonType::Class(c) => Some(c),
+            PythonType::Instance(i) => Some(&i.class_type),
+            _ => None,
+        }
+    }
+
+    fn type_arguments<'b>(&self, t: &'b PythonType) -> &'b [PythonType] {
+        match t {
+            PythonType::Class(c) => &c.specialized,
+            PythonType::Instance(i) if i.specialized_type_parameters.is_empty() => {
+                &i.class_type.specialized
+            }
+            PythonType::Instance(i) => &i.specialized_type_parameters,
+            _ => &[],
+        }
+    }
+
+    fn is_object(&self, t: &PythonType) -> bool {
+        self.nominal_class(t)
+            .is_some_and(|c| c.details.qual_name == "builtins.object")
+    }
+
+    /// The class a literal value is an instance of
+    fn literal_class(&self, value: &LiteralValue) -> Option<PythonType> {
+        let name = match value {
+            LiteralValue::Bool(_) => "bool",
+            LiteralValue::Int(_) => "int",
+            LiteralValue::Str(_) => "str",
+            LiteralValue::Bytes(_) => "bytes",
+            LiteralValue::None => return Some(PythonType::None),
+            LiteralValue::EnumMember(c, _) => return Some(PythonType::Class(c.clone())),
+        };
+        self.get_builtin_type(name)
+    }
+
     // TODO: If the return type of type parameter then use the passed args to determine it's type
     // TODO: The any type can be assumed for the type parameters that are not set but we are not
     // correctly checking if it's set or not.
@@ -1383,14 +2483,17 @@ impl<'a> TypeEvaluator<'a> {
                         .get_type(passed_arg, symbol_table, scope_id)
                         .expect("cannot get type for parameter");
                     match values_matching_type_param {
+                        // https://github.com/python/typing/blob/main/conformance/tests/generics_basic.py#L68
                         Some(ref v) => {
-                            // TODO: This should not be an equality check but assignable check
-                            // Also the condition in block below.
-                            // https://github.com/python/typing/blob/main/conformance/tests/generics_basic.py#L68
-                            if *v != passed_arg_type {
+                            if self.is_assignable(&passed_arg_type, v) {
+                                continue;
+                            }
+                            if !self.is_assignable(v, &passed_arg_type) {
                                 error!("Two different types were passed for one type parameter first: {v} second: {passed_arg_type}");
                                 return PythonType::Unknown;
                             }
+                            // Widen to the more general type
+                            values_matching_type_param = Some(passed_arg_type);
                         }
                         None => values_matching_type_param = Some(passed_arg_type),
                     };
@@ -1485,36 +2588,129 @@ impl<'a> TypeEvaluator<'a> {
         ret_type
     }
 
-    fn lookup_on_class(
-        &self,
-        symbol_table: &SymbolTable,
-        c: &ClassType,
-        method_name: &str,
-    ) -> Option<PythonType> {
-        let class_symbol_table = self.get_dec_symbol_table(&c.details.declaration_path);
-        let class_scope = c.details.class_scope_id;
-        // TODO: Probably should implement mro here
-        // Try to find on the class and it's base classes.
-        let bases = self.get_base_classes(c);
-        let symbol = class_symbol_table.lookup_attribute(method_name, class_scope);
-        if symbol.is_none() {
-            for base in bases {
-                let base_class = base.expect_class();
-                let class_symbol_table =
-                    self.get_dec_symbol_table(&base_class.details.declaration_path);
-                if let Some(attribute_on_base) = class_symbol_table
-                    .lookup_attribute(method_name, base_class.details.class_scope_id)
-                {
-                    return Some(self.get_symbol_type(
-                        attribute_on_base,
-                        &class_symbol_table,
-                        None,
-                    ));
+    /// Looks up an attribute on the class following its method resolution order
+    pub fn lookup_on_class(&self, c: &ClassType, method_name: &str) -> Option<PythonType> {
+        let origin = self.lookup_member_origin(c, method_name)?;
+        let class_symbol_table = self.get_dec_symbol_table(&origin.details.declaration_path);
+        let symbol =
+            class_symbol_table.lookup_attribute(method_name, origin.details.class_scope_id)?;
+        Some(self.get_symbol_type(symbol, &class_symbol_table, None))
+    }
+
+    /// Types of every declaration of a method on the class or its base classes.
+    /// Overloaded methods have one declaration for each overload.
+    fn lookup_overloads_on_class(&self, c: &ClassType, method_name: &str) -> Vec<PythonType> {
+        let Some(origin) = self.lookup_member_origin(c, method_name) else {
+            return vec![];
+        };
+        let class_symbol_table = self.get_dec_symbol_table(&origin.details.declaration_path);
+        let Some(symbol) =
+            class_symbol_table.lookup_attribute(method_name, origin.details.class_scope_id)
+        else {
+            return vec![];
+        };
+        symbol
+            .declarations
+            .iter()
+            .map(|decl| match decl {
+                Declaration::Function(f) => self.get_function_type(
+                    &class_symbol_table,
+                    f,
+                    class_symbol_table.get_scope(f.function_node.node.start),
+                ),
+                _ => self.get_symbol_type(symbol, &class_symbol_table, None),
+            })
+            .collect()
+    }
+
+    /// Finds the class that declares the member `name` of `c`. This is the first
+    /// class in the method resolution order of `c` that has the member.
+    pub fn lookup_member_origin(&self, c: &ClassType, name: &str) -> Option<ClassType> {
+        self.lookup_order(c).into_iter().find(|base| {
+            // Special forms do not have a class scope
+            !base.details.special
+                && self
+                    .get_dec_symbol_table(&base.details.declaration_path)
+                    .lookup_attribute(name, base.details.class_scope_id)
+                    .is_some()
+        })
+    }
+
+    /// The method resolution order of the class using C3 linearization.
+    /// https://docs.python.org/3/howto/mro.html
+    /// Returns None when the bases of the class cannot be linearized. For example
+    /// `class C(A, B)` when `A` is a subclass of `B`.
+    pub fn get_mro(&self, c: &ClassType) -> Option<Vec<ClassType>> {
+        let path = &c.details.declaration_path;
+        if let Some(mro) = self.mro_cache.borrow().get(path) {
+            return mro.clone();
+        }
+        // A class that inherits from itself is inconsistent. Mark the class as
+        // inconsistent while its bases are linearized to detect that.
+        self.mro_cache.borrow_mut().insert(path.clone(), None);
+        let mro = self.linearize(c);
+        self.mro_cache
+            .borrow_mut()
+            .insert(path.clone(), mro.clone());
+        mro
+    }
+
+    fn linearize(&self, c: &ClassType) -> Option<Vec<ClassType>> {
+        let mut bases = c.base_classes.clone();
+        if bases.is_empty() {
+            match self.object_class() {
+                Some(object) if !is_same_class(&object, c) => bases.push(object),
+                _ => return Some(vec![c.clone()]),
+            }
+        }
+        let mut sequences = bases
+            .iter()
+            .map(|base| self.get_mro(base))
+            .collect::<Option<Vec<_>>>()?;
+        sequences.push(bases);
+
+        let mut mro = vec![c.clone()];
+        loop {
+            sequences.retain(|sequence| !sequence.is_empty());
+            if sequences.is_empty() {
+                return Some(mro);
+            }
+            // The next class is the first head that is not in the tail of any sequence
+            let next = sequences
+                .iter()
+                .map(|sequence| &sequence[0])
+                .find(|head| {
+                    !sequences
+                        .iter()
+                        .any(|sequence| sequence[1..].iter().any(|t| is_same_class(t, head)))
+                })?
+                .clone();
+            for sequence in sequences.iter_mut() {
+                if is_same_class(&sequence[0], &next) {
+                    sequence.remove(0);
                 }
             }
+            mro.push(next);
         }
+    }
 
-        symbol.map(|node| self.get_symbol_type(node, symbol_table, None))
+    /// The order to look up members in. When the hierarchy is inconsistent the
+    /// bases are searched depth first so that members can still be found.
+    fn lookup_order(&self, c: &ClassType) -> Vec<ClassType> {
+        if let Some(mro) = self.get_mro(c) {
+            return mro;
+        }
+        let mut order: Vec<ClassType> = vec![];
+        let mut stack = vec![c.clone()];
+        while let Some(class) = stack.pop() {
+            if order.iter().any(|o| is_same_class(o, &class)) {
+                continue;
+            }
+            stack.extend(class.base_classes.iter().rev().cloned());
+            order.push(class);
+        }
+        order.extend(self.object_class());
+        order
     }
 
     fn get_function_signature(
@@ -1524,76 +2720,79 @@ impl<'a> TypeEvaluator<'a> {
         scope_id: u32,
     ) -> Vec<CallableArgs> {
         let mut signature = Vec::with_capacity(arguments.len());
-        for argument in arguments.posonlyargs.iter() {
-            if let Some(type_annotation) = &argument.annotation {
-                signature.push(CallableArgs::PositionalOnly(self.get_annotation_type(
-                    type_annotation,
-                    symbol_table,
-                    scope_id,
-                )));
+        // Defaults belong to the last positional parameters
+        let positional_count = arguments.posonlyargs.len() + arguments.args.len();
+        let first_default = positional_count.saturating_sub(arguments.defaults.len());
+        let positional = arguments
+            .posonlyargs
+            .iter()
+            .map(|arg| (arg, true))
+            .chain(arguments.args.iter().map(|arg| (arg, false)));
+        for (index, (argument, positional_only)) in positional.enumerate() {
+            let typ = if let Some(type_annotation) = &argument.annotation {
+                self.get_annotation_type(type_annotation, symbol_table, scope_id)
             } else {
-                signature.push(CallableArgs::PositionalOnly(self.get_name_type(
+                self.get_name_type(
                     &argument.arg,
                     Some(argument.node.end),
                     symbol_table,
                     scope_id,
-                )));
-            }
-        }
-        for positional in arguments.args.iter() {
-            if let Some(type_annotation) = &positional.annotation {
-                signature.push(CallableArgs::Positional(self.get_annotation_type(
-                    type_annotation,
-                    symbol_table,
-                    scope_id,
-                )));
+                )
+            };
+            let parameter = Parameter::new(argument.arg.clone(), typ, index >= first_default);
+            if positional_only {
+                signature.push(CallableArgs::PositionalOnly(parameter));
             } else {
-                signature.push(CallableArgs::Positional(self.get_name_type(
-                    &positional.arg,
-                    Some(positional.node.end),
-                    symbol_table,
-                    scope_id,
-                )));
+                signature.push(CallableArgs::Positional(parameter));
             }
         }
-        for argument in arguments.kwonlyargs.iter() {
-            if let Some(type_annotation) = &argument.annotation {
-                signature.push(CallableArgs::Keyword(self.get_annotation_type(
-                    type_annotation,
-                    symbol_table,
-                    scope_id,
-                )));
-            } else {
-                signature.push(CallableArgs::Keyword(PythonType::Unknown));
-            }
+        for (index, argument) in arguments.kwonlyargs.iter().enumerate() {
+            let typ = self.get_parameter_annotation_type(argument, symbol_table, scope_id);
+            let has_default = arguments
+                .kw_defaults
+                .get(index)
+                .is_some_and(|default| default.is_some());
+            signature.push(CallableArgs::Keyword(Parameter::new(
+                argument.arg.clone(),
+                typ,
+                has_default,
+            )));
         }
 
         if let Some(vararg) = &arguments.vararg {
-            if let Some(type_annotation) = &vararg.annotation {
-                signature.push(CallableArgs::Args(self.get_annotation_type(
-                    type_annotation,
-                    symbol_table,
-                    scope_id,
-                )));
-            } else {
-                signature.push(CallableArgs::Args(PythonType::Unknown));
-            }
+            let typ = self.get_parameter_annotation_type(vararg, symbol_table, scope_id);
+            signature.push(CallableArgs::Args(Parameter::new(
+                vararg.arg.clone(),
+                typ,
+                false,
+            )));
         }
         if let Some(kwarg) = &arguments.kwarg {
-            if let Some(type_annotation) = &kwarg.annotation {
-                signature.push(CallableArgs::KwArgs(self.get_annotation_type(
-                    type_annotation,
-                    symbol_table,
-                    scope_id,
-                )));
-            } else {
-                signature.push(CallableArgs::KwArgs(PythonType::Unknown));
-            }
+            let typ = self.get_parameter_annotation_type(kwarg, symbol_table, scope_id);
+            signature.push(CallableArgs::KwArgs(Parameter::new(
+                kwarg.arg.clone(),
+                typ,
+                false,
+            )));
         }
 
         signature
     }
 
+    fn get_parameter_annotation_type(
+        &self,
+        argument: &ast::Arg,
+        symbol_table: &symbol_table::SymbolTable,
+        scope_id: u32,
+    ) -> PythonType {
+        match &argument.annotation {
+            Some(type_annotation) => {
+                self.get_annotation_type(type_annotation, symbol_table, scope_id)
+            }
+            None => PythonType::Unknown,
+        }
+    }
+
     // TODO(coroutine_annotation): These two are very similar. Maybe should be presented in another
     // way. Async version only needs the return type to be a coroutine.
     fn get_function_type(
@@ -1602,8 +2801,6 @@ impl<'a> TypeEvaluator<'a> {
         f: &symbol_table::Function,
         arguments_scope_id: u32,
     ) -> PythonType {
-        // TODO: handle default values
-
         let name = f.function_node.name;
         let signature =
             self.get_function_signature(&f.function_node.args, symbol_table, arguments_scope_id);
@@ -1624,6 +2821,8 @@ impl<'a> TypeEvaluator<'a> {
             signature,
             return_type,
             false,
+            has_unknown_decorator(&f.function_node.decorator_list),
+            function_kind(&f.function_node.decorator_list),
         )))
     }
 
@@ -1658,22 +2857,11 @@ impl<'a> TypeEvaluator<'a> {
                 yield_type: PythonType::Any,
             })),
             true,
+            has_unknown_decorator(&f.function_node.decorator_list),
+            function_kind(&f.function_node.decorator_list),
         )))
     }
 
-    fn get_base_classes(&self, c: &ClassType) -> Vec<PythonType> {
-        let mut super_classes = vec![];
-        super_classes.push(PythonType::Class(c.clone()));
-        for super_class in &c.base_classes {
-            super_classes.push(PythonType::Class(super_class.clone()));
-        }
-        let object_class = self.get_builtin_type("object").expect("object not found");
-
-        super_classes.push(object_class.clone());
-
-        super_classes
-    }
-
     // Resolving all type parameters in a python type based on the given type parameters dict
     fn resolve_generics(
         &self,
@@ -1728,10 +2916,106 @@ impl<'a> TypeEvaluator<'a> {
 
     fn get_dec_symbol_table(&self, decl_path: &DeclarationPath) -> Arc<SymbolTable> {
         let table_id = decl_path.symbol_table_id;
-        return self.build_manager.get_symbol_table_by_id(&table_id);
+        self.build_manager.get_symbol_table_by_id(&table_id)
     }
 
     fn get_symbol_table(&self, id: &Id) -> Arc<SymbolTable> {
-        return self.build_manager.get_symbol_table_by_id(id);
+        self.build_manager.get_symbol_table_by_id(id)
+    }
+}
+
+/// Classes are the same if they come from the same declaration
+fn is_same_class(c1: &ClassType, c2: &ClassType) -> bool {
+    c1.details.declaration_path == c2.details.declaration_path
+}
+
+/// The dunder method implementing a binary operator and its reflected form
+fn binary_operator_methods(op: &ast::BinaryOperator) -> (&'static str, &'static str) {
+    match op {
+        ast::BinaryOperator::Add => ("__add__", "__radd__"),
+        ast::BinaryOperator::Sub => ("__sub__", "__rsub__"),
+        ast::BinaryOperator::Mult => ("__mul__", "__rmul__"),
+        ast::BinaryOperator::MatMult => ("__matmul__", "__rmatmul__"),
+        ast::BinaryOperator::Div => ("__truediv__", "__rtruediv__"),
+        ast::BinaryOperator::Mod => ("__mod__", "__rmod__"),
+        ast::BinaryOperator::Pow => ("__pow__", "__rpow__"),
+        ast::BinaryOperator::LShift => ("__lshift__", "__rlshift__"),
+        ast::BinaryOperator::RShift => ("__rshift__", "__rrshift__"),
+        ast::BinaryOperator::BitOr => ("__or__", "__ror__"),
+        ast::BinaryOperator::BitXor => ("__xor__", "__rxor__"),
+        ast::BinaryOperator::BitAnd => ("__and__", "__rand__"),
+        ast::BinaryOperator::FloorDiv => ("__floordiv__", "__rfloordiv__"),
+    }
+}
+
+fn is_decorator_name(decorator: &ast::Expression, name: &str) -> bool {
+    matches!(decorator, ast::Expression::Name(n) if n.id == name)
+}
+
+/// `functools.wraps(f)` only copies the metadata of `f` to the decorated function
+fn is_wraps_decorator(decorator: &ast::Expression) -> bool {
+    let ast::Expression::Call(call) = decorator else {
+        return false;
+    };
+    match &call.func {
+        ast::Expression::Name(n) => n.id == "wraps",
+        ast::Expression::Attribute(a) => {
+            a.attr == "wraps" && a.value.as_name().is_some_and(|n| n.id == "functools")
+        }
+        _ => false,
+    }
+}
+
+/// Whether one of the decorators changes the signature of the function in an
+/// unknown way
+fn has_unknown_decorator(decorators: &[ast::Expression]) -> bool {
+    decorators.iter().any(|decorator| {
+        !is_decorator_name(decorator, "staticmethod")
+            && !is_decorator_name(decorator, "classmethod")
+            && !is_wraps_decorator(decorator)
+    })
+}
+
+fn function_kind(decorators: &[ast::Expression]) -> FunctionKind {
+    if decorators
+        .iter()
+        .any(|decorator| is_decorator_name(decorator, "staticmethod"))
+    {
+        FunctionKind::StaticMethod
+    } else if decorators
+        .iter()
+        .any(|decorator| is_decorator_name(decorator, "classmethod"))
+    {
+        FunctionKind::ClassMethod
+    } else {
+        FunctionKind::Function
+    }
+}
+
+/// Members of a union type. Other types are a union of one member.
+pub fn union_members(t: &PythonType) -> Vec<PythonType> {
+    match t {
+        PythonType::MultiValue(members) => members.iter().flat_map(union_members).collect(),
+        PythonType::Optional(inner) => {
+            let mut members = union_members(inner);
+            members.push(PythonType::None);
+            members
+        }
+        _ => vec![t.clone()],
+    }
+}
+
+/// Creates the union of the types. Returns None when there are no members.
+pub fn union_of(types: Vec<PythonType>) -> Option<PythonType> {
+    let mut members: Vec<PythonType> = vec![];
+    for t in types.iter().flat_map(union_members) {
+        if !members.iter().any(|m| m.type_equal(&t)) {
+            members.push(t);
+        }
+    }
+    match members.len() {
+        0 => None,
+        1 => members.pop(),
+        _ => Some(PythonType::MultiValue(members)),
     }
 }
@@ -1,5 +1,6 @@
 use crate::symbol_table::{self, Id};
 use is_macro::Is;
+use num_bigint::BigInt;
 use std::fmt::{self, Display};
 
 #[derive(Debug, Clone, PartialEq, Eq, Is)]
@@ -52,9 +53,88 @@ impl PythonType {
             }
             (PythonType::Callable(c1), PythonType::Callable(c2)) => c1.type_equal(c2),
             (PythonType::Class(c1), PythonType::Class(c2)) => c1.type_equal(c2),
+            (PythonType::Instance(i1), PythonType::Instance(i2)) => {
+                i1.class_type.type_equal(&i2.class_type)
+                    && i1.specialized_type_parameters.len() == i2.specialized_type_parameters.len()
+                    && i1
+                        .specialized_type_parameters
+                        .iter()
+                        .zip(i2.specialized_type_parameters.iter())
+                        .all(|(t1, t2)| t1.type_equal(t2))
+            }
+            (PythonType::Optional(o1), PythonType::Optional(o2)) => o1.type_equal(o2),
+            (PythonType::TypeVar(t1), PythonType::TypeVar(t2)) => t1 == t2,
+            (PythonType::Module(m1), PythonType::Module(m2)) => m1 == m2,
             _ => false,
         }
     }
+
+    /// Short name of the type as it would be written in an annotation.
+    /// Used in diagnostic messages.
+    pub fn short_name(&self) -> String {
+        fn join(types: &[PythonType], sep: &str) -> String {
+            types
+                .iter()
+                .map(|t| t.short_name())
+                .collect::<Vec<String>>()
+                .join(sep)
+        }
+        fn with_args(name: &str, args: &[PythonType]) -> String {
+            if args.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}[{}]", name, join(args, ", "))
+            }
+        }
+
+        match self {
+            PythonType::None => "None".to_string(),
+            PythonType::Unknown => "Unknown".to_string(),
+            PythonType::Any => "Any".to_string(),
+            PythonType::LiteralValue(value) => format!("Literal[{}]", value.literal_value),
+            PythonType::Module(_) => "Module".to_string(),
+            PythonType::MultiValue(m) => {
+                // `Literal[1] | Literal[2]` is written as `Literal[1, 2]`
+                let literals: Vec<String> = m
+                    .iter()
+                    .filter_map(|t| t.as_literal_value())
+                    .map(|v| v.literal_value.to_string())
+                    .collect();
+                let mut parts = vec![];
+                let mut literals_written = false;
+                for t in m {
+                    if !t.is_literal_value() {
+                        parts.push(t.short_name());
+                    } else if !literals_written {
+                        parts.push(format!("Literal[{}]", literals.join(", ")));
+                        literals_written = true;
+                    }
+                }
+                parts.join(" | ")
+            }
+            PythonType::Callable(c) => {
+                let params = c
+                    .signature
+                    .iter()
+                    .map(|arg| arg.get_type().short_name())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                format!("({}) -> {}", params, c.return_type.short_name())
+            }
+            PythonType::Coroutine(c) => format!(
+                "Coroutine[{}, {}, {}]",
+                c.yield_type.short_name(),
+                c.send_type.short_name(),
+                c.return_type.short_name()
+            ),
+            PythonType::Class(c) => with_args(&c.details.name, &c.specialized),
+            PythonType::Instance(i) => {
+                with_args(&i.class_type.details.name, &i.specialized_type_parameters)
+            }
+            PythonType::Optional(inner) => format!("{} | None", inner.short_name()),
+            PythonType::TypeVar(t) => t.name.clone(),
+        }
+    }
 }
 
 #[allow(unused)]
@@ -66,14 +146,6 @@ pub enum TypeFlags {
     Instantiable,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct NoneType {}
-
-#[derive(Debug, Clone, PartialEq)]
-pub struct Any {
-    pub source: AnySource,
-}
-
 /// Describes the source of Any
 #[allow(unused)]
 #[derive(PartialEq, Clone, Debug)]
@@ -82,6 +154,16 @@ pub enum AnySource {
     Explicit,
 }
 
+/// How a function declared in a class body is bound when it is accessed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FunctionKind {
+    Function,
+    /// Decorated with `@staticmethod`, never bound
+    StaticMethod,
+    /// Decorated with `@classmethod`, bound to the class
+    ClassMethod,
+}
+
 #[allow(unused)]
 #[derive(Debug, Clone)]
 pub struct CallableType {
@@ -89,6 +171,10 @@ pub struct CallableType {
     pub signature: Vec<CallableArgs>,
     pub return_type: PythonType,
     pub is_async: bool,
+    /// Decorators (including overloads) can change the signature of a function.
+    /// Only set for decorators whose effect on the signature is unknown.
+    pub is_decorated: bool,
+    pub kind: FunctionKind,
 }
 
 impl Display for CallableType {
@@ -107,38 +193,80 @@ impl Display for CallableType {
     }
 }
 
+/// A parameter of a callable
+#[derive(Debug, Clone)]
+pub struct Parameter {
+    pub name: String,
+    pub typ: PythonType,
+    /// Parameters with a default value can be omitted in calls
+    pub has_default: bool,
+}
+
+impl Parameter {
+    pub fn new(name: String, typ: PythonType, has_default: bool) -> Self {
+        Self {
+            name,
+            typ,
+            has_default,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum CallableArgs {
-    PositionalOnly(PythonType),
-    Positional(PythonType),
-    Keyword(PythonType),
-    Args(PythonType),
-    KwArgs(PythonType),
-    WithDefault(PythonType),
+    PositionalOnly(Parameter),
+    Positional(Parameter),
+    Keyword(Parameter),
+    Args(Parameter),
+    KwArgs(Parameter),
 }
 
 impl fmt::Display for CallableArgs {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            CallableArgs::PositionalOnly(ty) => write!(f, "pos_only: {}", ty),
-            CallableArgs::Positional(ty) => write!(f, "pos: {}", ty),
-            CallableArgs::Keyword(ty) => write!(f, "kw_only: {}", ty),
-            CallableArgs::Args(ty) => write!(f, "*args: {}", ty),
-            CallableArgs::KwArgs(ty) => write!(f, "**kwargs: {}", ty),
-            CallableArgs::WithDefault(ty) => write!(f, "kw_default: {} = ...", ty),
+            CallableArgs::PositionalOnly(p) => write!(f, "pos_only: {}", p.typ),
+            CallableArgs::Positional(p) => write!(f, "pos: {}", p.typ),
+            CallableArgs::Keyword(p) => write!(f, "kw_only: {}", p.typ),
+            CallableArgs::Args(p) => write!(f, "*args: {}", p.typ),
+            CallableArgs::KwArgs(p) => write!(f, "**kwargs: {}", p.typ),
         }
     }
 }
 
 impl CallableArgs {
-    pub fn get_type(&self) -> &PythonType {
+    pub fn parameter(&self) -> &Parameter {
         match &self {
-            CallableArgs::Args(python_type) => python_type,
-            CallableArgs::PositionalOnly(python_type) => python_type,
-            CallableArgs::Positional(python_type) => python_type,
-            CallableArgs::Keyword(python_type) => python_type,
-            CallableArgs::KwArgs(python_type) => python_type,
-            CallableArgs::WithDefault(python_type) => python_type,
+            CallableArgs::Args(parameter) => parameter,
+            CallableArgs::PositionalOnly(parameter) => parameter,
+            CallableArgs::Positional(parameter) => parameter,
+            CallableArgs::Keyword(parameter) => parameter,
+            CallableArgs::KwArgs(parameter) => parameter,
+        }
+    }
+
+    pub fn parameter_mut(&mut self) -> &mut Parameter {
+        match self {
+            CallableArgs::Args(parameter) => parameter,
+            CallableArgs::PositionalOnly(parameter) => parameter,
+            CallableArgs::Positional(parameter) => parameter,
+            CallableArgs::Keyword(parameter) => parameter,
+            CallableArgs::KwArgs(parameter) => parameter,
+        }
+    }
+
+    pub fn get_type(&self) -> &PythonType {
+        &self.parameter().typ
+    }
+
+    pub fn name(&self) -> &str {
+        &self.parameter().name
+    }
+
+    /// Whether a call must pass a value for this parameter
+    pub fn is_required(&self) -> bool {
+        match self {
+            CallableArgs::Args(_) | CallableArgs::KwArgs(_) => false,
+            _ => !self.parameter().has_default,
         }
     }
 }
@@ -151,12 +279,16 @@ impl CallableType {
         signature: Vec<CallableArgs>,
         return_type: PythonType,
         is_async: bool,
+        is_decorated: bool,
+        kind: FunctionKind,
     ) -> Self {
         CallableType {
             name,
             signature,
             return_type,
             is_async,
+            is_decorated,
+            kind,
         }
     }
     pub fn type_equal(&self, other: &Self) -> bool {
@@ -318,18 +450,84 @@ pub struct KnownValue {
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum LiteralValue {
-    Bool,
-    Int,
-    Float,
-    Str,
+    Bool(bool),
+    Int(BigInt),
+    Str(String),
+    Bytes(Vec<u8>),
     None,
-    Bytes,
+    /// A member of an enum class, e.g. `Color.RED`
+    EnumMember(ClassType, String),
 }
 
 impl Display for LiteralValue {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "")
+        match self {
+            LiteralValue::Bool(true) => write!(f, "True"),
+            LiteralValue::Bool(false) => write!(f, "False"),
+            LiteralValue::Int(value) => write!(f, "{value}"),
+            LiteralValue::Str(value) => write!(f, "{}", str_repr(value)),
+            LiteralValue::Bytes(value) => write!(f, "b{}", bytes_repr(value)),
+            LiteralValue::None => write!(f, "None"),
+            LiteralValue::EnumMember(class, member) => {
+                write!(f, "{}.{}", class.details.name, member)
+            }
+        }
+    }
+}
+
+/// Quotes the string the way Python's `repr` does
+fn str_repr(value: &str) -> String {
+    let quote = if value.contains('\'') && !value.contains('"') {
+        '"'
+    } else {
+        '\''
+    };
+    let mut result = String::from(quote);
+    for c in value.chars() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if c == quote => {
+                result.push('\\');
+                result.push(c);
+            }
+            c if (c as u32) < 0x20 || c as u32 == 0x7f => {
+                result.push_str(&format!("\\x{:02x}", c as u32))
+            }
+            c => result.push(c),
+        }
+    }
+    result.push(quote);
+    result
+}
+
+/// Quotes the bytes the way Python's `repr` does
+fn bytes_repr(value: &[u8]) -> String {
+    let contains_single = value.contains(&b'\'');
+    let quote = if contains_single && !value.contains(&b'"') {
+        b'"'
+    } else {
+        b'\''
+    };
+    let mut result = String::from(quote as char);
+    for &b in value {
+        match b {
+            b'\\' => result.push_str("\\\\"),
+            b'\n' => result.push_str("\\n"),
+            b'\r' => result.push_str("\\r"),
+            b'\t' => result.push_str("\\t"),
+            b if b == quote => {
+                result.push('\\');
+                result.push(b as char);
+            }
+            0x20..=0x7e => result.push(b as char),
+            b => result.push_str(&format!("\\x{b:02x}")),
+        }
     }
+    result.push(quote as char);
+    result
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -0,0 +1,25 @@
+class A:
+    def method(self) -> int:
+        return 0
+
+    def only_in_a(self) -> bytes:
+        return b""
+
+
+class B(A):
+    pass
+
+
+class C(A):
+    def method(self) -> str:
+        return ""
+
+
+class D(B, C):
+    pass
+
+
+d = D()
+d.method()
+d.only_in_a()
+D.method
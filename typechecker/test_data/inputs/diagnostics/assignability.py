@@ -0,0 +1,124 @@
+from typing import Callable, List, Literal, Optional, Protocol, TypeVar, Union
+
+
+class Animal:
+    pass
+
+
+class Dog(Animal):
+    pass
+
+
+a: int = 1
+b: int = "str"
+c: float = 1
+d: Optional[int] = None
+e: int = None
+f: Animal = Dog()
+g: Dog = Animal()
+h: object = Dog()
+i: List[int] = [1, 2]
+j: List[str] = [1, 2]
+k: Literal[1] = 1
+l: int = k
+m: str = k
+
+
+def takes_int(x: int) -> int:
+    return x
+
+
+def returns_str() -> str:
+    return 1
+
+
+def returns_none() -> None:
+    return
+
+
+def returns_optional(flag: bool) -> Optional[Dog]:
+    if flag:
+        return None
+    return Dog()
+
+
+takes_int(1)
+takes_int("a")
+takes_int(True)
+
+T = TypeVar("T", int, str)
+
+
+def constrained(x: T) -> T:
+    return x
+
+
+constrained(1)
+constrained(1.0)
+
+
+class SupportsLog(Protocol):
+    def log(self, message: str, level: int) -> None: ...
+
+
+class SupportsKeywordLog(Protocol):
+    def log(self, message: str, *, level: int) -> None: ...
+
+
+class Logger:
+    def log(self, message: str, level: int) -> None: ...
+
+
+class VarArgsLogger:
+    def log(self, *args: str) -> None: ...
+
+
+class AnyArgsLogger:
+    def log(self, *args: object, **kwargs: object) -> None: ...
+
+
+class KwArgsLogger:
+    def log(self, **kwargs: object) -> None: ...
+
+
+class RenamedLogger:
+    def log(self, message: str, severity: int) -> None: ...
+
+
+class KeywordLogger:
+    def log(self, message: str, *, level: str) -> None: ...
+
+
+class OptionalLogger:
+    def log(self, message: str, level: int, *, flush: bool = False) -> None: ...
+
+
+class RequiredKeywordLogger:
+    def log(self, message: str, level: int, *, flush: bool) -> None: ...
+
+
+n: SupportsLog = Logger()
+o: SupportsLog = VarArgsLogger()
+p: SupportsLog = AnyArgsLogger()
+q: SupportsLog = KwArgsLogger()
+r: SupportsLog = RenamedLogger()
+s: SupportsLog = OptionalLogger()
+t: SupportsLog = RequiredKeywordLogger()
+u: SupportsKeywordLog = Logger()
+v: SupportsKeywordLog = AnyArgsLogger()
+w: SupportsKeywordLog = RenamedLogger()
+x: SupportsKeywordLog = KeywordLogger()
+
+
+def accepts_int(x: int) -> None: ...
+
+
+def takes_optional(x: int, y: str = "") -> None: ...
+
+
+y: Callable[[int], None] = accepts_int
+z: Callable[[str], None] = accepts_int
+aa: Callable[[int], None] = takes_optional
+ab: Callable[[int, str, str], None] = takes_optional
+ac: Callable[..., None] = takes_optional
+ad: Callable[[int], int] = accepts_int
@@ -0,0 +1,104 @@
+import functools
+
+
+def f(a: int, b: str, c: int = 0) -> None:
+    pass
+
+
+def pos_only(a: int, /, b: int) -> None:
+    pass
+
+
+def kw_only(a: int, *, key: str, flag: bool = False) -> None:
+    pass
+
+
+def var_args(*args: int, **kwargs: str) -> None:
+    pass
+
+
+f(1, "a")
+f(1, "a", 2)
+f(1, b="a", c=3)
+f(1)
+f()
+f(1, "a", 2, 3)
+f(1, "a", d=1)
+f(1, "a", a=1)
+f("a", 1)
+
+pos_only(1, 2)
+pos_only(a=1, b=2)
+
+kw_only(1, key="k")
+kw_only(1, "k")
+kw_only(1, key=1)
+
+var_args(1, 2, 3, x="a", y="b")
+var_args(1, "a", x=1)
+
+args = [1, 2]
+kwargs = {"b": "a"}
+f(*args)
+f(1, **kwargs)
+
+
+class A:
+    def __init__(self, x: int) -> None:
+        self.x = x
+
+    def method(self, a: int) -> None:
+        pass
+
+    @staticmethod
+    def static(a: int) -> None:
+        pass
+
+    @classmethod
+    def create(cls, a: int) -> None:
+        pass
+
+    def call_self(self) -> None:
+        self.method(1)
+        self.method()
+
+
+a = A(1)
+A()
+A("a")
+a.method(1)
+a.method()
+a.method(1, 2)
+A.method(a, 1)
+A.method(a)
+A.static(1)
+a.static(1, 2)
+A.create(1)
+a.create("a")
+
+
+class B:
+    pass
+
+
+B()
+
+
+def decorator(func):
+    return func
+
+
+@decorator
+def unknown_decorator(a: int) -> None:
+    pass
+
+
+unknown_decorator()
+
+
+@functools.wraps(f)
+def wrapped(a: int) -> None:
+    pass
+
+
+wrapped()
@@ -0,0 +1 @@
+﻿x: int = "a"
@@ -0,0 +1,2 @@
+name = "caf"
+x: int = 1
@@ -0,0 +1,21 @@
+from enum import Enum
+from typing import Literal
+
+
+class Color(Enum):
+    RED = 1
+    GREEN = 2
+
+
+a: Literal[3] = 4
+b: Literal["a", "b"] = "c"
+c: Literal[True] = 1
+d: Literal[1] = True
+e: Literal[b"x"] = "x"
+f: Literal[Color.RED] = Color.GREEN
+g: Literal[Color.RED, Color.GREEN] = Color.GREEN
+h: Literal[1, 2] = 2
+i: int = h
+j: Literal[1, 2, 3] = h
+k: Literal[1] = h
+l: Color = Color.RED
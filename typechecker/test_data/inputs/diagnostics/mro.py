@@ -0,0 +1,13 @@
+class A: ...
+
+
+class B(A): ...
+
+
+class C(A, B): ...
+
+
+class D(B, A): ...
+
+
+class E(D, C): ...
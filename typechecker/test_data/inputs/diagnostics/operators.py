@@ -0,0 +1,48 @@
+from typing import Optional
+
+
+class Vector:
+    def __add__(self, other: "Vector") -> "Vector":
+        return self
+
+    def __neg__(self) -> "Vector":
+        return self
+
+    def __lt__(self, other: "Vector") -> bool:
+        return True
+
+
+class Scalar:
+    def __rmul__(self, other: Vector) -> Vector:
+        return other
+
+
+def operators(a: int, b: float, s: str, v: Vector, k: Scalar, o: Optional[int]) -> None:
+    i = a + a
+    f = a + b
+    t = s * a
+    l = [1] + [2]
+    w = v + v
+    r = v * k
+    n = -v
+    m = not s
+    c = a < b < a
+    e = v == s
+    p = a in [1, 2]
+
+    s + a
+    v - v
+    -s
+    ~b
+    v < a
+    a in a
+    o + 1
+    s += a
+
+
+def unions(u: int | str) -> None:
+    u + 1
+    u * 2
+
+
+IntOrStr = int | str
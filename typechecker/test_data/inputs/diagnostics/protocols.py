@@ -0,0 +1,48 @@
+from typing import Iterable, Protocol, Sized, SupportsInt, runtime_checkable
+
+
+class SupportsClose(Protocol):
+    def close(self) -> None: ...
+
+
+class HasName(Protocol):
+    name: str
+
+
+@runtime_checkable
+class SupportsRead(Protocol):
+    def read(self, size: int) -> bytes: ...
+
+
+class File:
+    name: str
+
+    def close(self) -> None: ...
+
+    def read(self, size: int) -> bytes: ...
+
+
+class Socket:
+    name: int
+
+    def close(self, force: bool) -> None: ...
+
+
+a: Iterable[int] = [1, 2]
+b: Iterable[str] = [1, 2]
+c: Iterable[int] = 1
+d: Sized = "abc"
+e: SupportsInt = 1.5
+f: SupportsClose = File()
+g: SupportsClose = Socket()
+h: HasName = File()
+i: HasName = Socket()
+
+
+def check(x: object) -> None:
+    if isinstance(x, SupportsRead):
+        pass
+    if isinstance(x, SupportsClose):
+        pass
+    if isinstance(x, (int, HasName)):
+        pass
@@ -0,0 +1,11 @@
+def takes_int(x: int) -> int:
+    return x
+
+
+a: int = "a"  # type: ignore
+b: int = "b"  # type: ignore[assignment]
+c: int = "c"  # type: ignore[arg-type]
+d: int = "d"  # enderpy: ignore[assignment]
+e: int = "e"  # noqa # type: ignore
+takes_int("f")  # type: ignore[arg-type, call-arg]
+takes_int("g")
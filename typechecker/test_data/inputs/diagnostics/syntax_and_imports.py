@@ -0,0 +1,11 @@
+import os
+import not_a_real_module
+from typing import List
+from missing.package import thing
+from .sibling_that_does_not_exist import other
+
+x: List[int] = [1, 2
+def f(a, b):
+    return a +
+
+y = 1 2
@@ -0,0 +1,8 @@
+from typing import List, Optional
+
+a = 1  # type: int
+b = "a"  # type: int
+c = []  # type: List[int]
+d = [1, 2]  # type: List[str]
+e = None  # type: Optional[str]
+f = None  # type: str
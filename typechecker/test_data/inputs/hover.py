@@ -0,0 +1,3 @@
+a = ["é𝕏", 1]
+width = 3
+b = f"{width!r:>{width}}"
@@ -0,0 +1,24 @@
+from enum import Enum
+from typing import Literal
+
+
+class Color(Enum):
+    RED = 1
+    GREEN = 2
+
+
+a: Literal[3] = 3
+b: Literal["a", "b"] = "a"
+c: Literal[True] = True
+d: Literal[b"x"] = b"x"
+e: Literal[-1, None] = -1
+f: Literal[Color.RED] = Color.RED
+g: Literal[Literal[1, 2], 3] = 2
+h = 0x10
+
+
+def check(x: Literal["a", "b", 1]):
+    if x == "a":
+        x
+    else:
+        x
@@ -0,0 +1,122 @@
+from typing import Optional, Union
+
+
+class Animal:
+    pass
+
+
+class Dog(Animal):
+    pass
+
+
+def is_none(x: Optional[int]):
+    if x is None:
+        x
+    else:
+        x
+
+
+def is_not_none(x: Optional[int]) -> int:
+    if x is not None:
+        return x
+    return 0
+
+
+def early_return(x: Optional[str]):
+    if x is None:
+        return
+    x
+
+
+def truthiness(x: Optional[str]):
+    if x:
+        x
+    if not x:
+        x
+    else:
+        x
+
+
+def instance(x: Union[int, str, Animal]):
+    if isinstance(x, int):
+        x
+    elif isinstance(x, (str, Dog)):
+        x
+    else:
+        x
+
+
+def raise_on_none(x: Union[int, None]):
+    if x is None:
+        raise ValueError()
+    x
+
+
+def literal_equal(x: Union[int, None]):
+    if x == 1:
+        x
+
+
+def in_list(x: Optional[int]):
+    if x in [1, 2]:
+        x
+
+
+def is_callable(x: Union[int, None]):
+    if callable(x):
+        x
+
+
+def and_or(x: Optional[int], y: Optional[str]):
+    if x is not None and y is not None:
+        x
+        y
+    if x is None or y is None:
+        return
+    x
+    y
+
+
+def loops(x: Optional[int]):
+    while x is None:
+        x = 1
+    x
+
+
+def reassigned(x: Optional[int]):
+    if x is not None:
+        x = None
+        x
+
+
+def asserted(x: Optional[int]):
+    assert x is not None
+    x
+
+
+def in_try(x: Optional[int]):
+    if x is None:
+        return
+    try:
+        x
+    except ValueError:
+        x
+
+
+def matched(x: Union[int, str, None]):
+    match x:
+        case None:
+            x
+        case int():
+            x
+        case _:
+            x
+
+
+def in_try_star(x: Optional[int]):
+    try:
+        if x is None:
+            return
+        x
+    except* ValueError:
+        x
@@ -0,0 +1,42 @@
+from typing import AnyStr
+
+
+class Vector:
+    def __add__(self, other: "Vector") -> "Vector":
+        return self
+
+    def __neg__(self) -> "Vector":
+        return self
+
+
+class Scalar:
+    def __rmul__(self, other: Vector) -> Vector:
+        return other
+
+
+def f(a: int, b: float, s: str, v: Vector, k: Scalar, l: list[int], u: int | float) -> None:
+    a + a
+    a + b
+    a / a
+    s * a
+    s % a
+    l + l
+    l[0]
+    l[1:]
+    v + v
+    v * k
+    -v
+    ~a
+    not s
+    a < b
+    a < b <= a
+    a == s
+    a in l
+    u + a
+
+
+def concat(x: AnyStr, y: AnyStr) -> AnyStr:
+    return x + y
+
+
+IntOrNone = int | None
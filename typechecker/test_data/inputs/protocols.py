@@ -0,0 +1,21 @@
+from typing import Protocol, runtime_checkable
+
+
+@runtime_checkable
+class SupportsClose(Protocol):
+    def close(self) -> None: ...
+
+
+class File:
+    def close(self) -> None: ...
+
+
+class Number:
+    value: int
+
+
+def check(x: File | Number) -> None:
+    if isinstance(x, SupportsClose):
+        x
+    else:
+        x
@@ -0,0 +1,38 @@
+from typing import List
+
+a = []  # type: List[int]
+b = None  # type: str
+
+
+def add(x, y):
+    # type: (int, int) -> int
+    return x + y
+
+
+def greet(name, *names):  # type: (str, *str) -> str
+    return name
+
+
+class Greeter:
+    def greet(self, name):  # type: (str) -> str
+        return name
+
+
+for item in []:  # type: int
+    item
+
+with open("a") as file:  # type: Greeter
+    file
+
+
+async def read_lines():
+    async for line in []:  # type: str
+        line
+    async with open("a") as f:  # type: int
+        f
+
+a
+b
+add(1, 2)
+greet("a")
+Greeter().greet("a")